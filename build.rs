@@ -0,0 +1,12 @@
+fn main() {
+    // With the `ffi` feature, keep include/coinwagon.h in sync with src/ffi.rs
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        cbindgen::generate(&dir)
+            .expect("failed to generate C header")
+            .write_to_file(format!("{}/include/coinwagon.h", dir));
+    }
+}
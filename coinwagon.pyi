@@ -0,0 +1,196 @@
+# This file is automatically generated by pyo3_stub_gen
+# ruff: noqa: E501, F401, F403, F405
+
+import builtins
+import datetime
+import pyarrow
+import typing
+__all__ = [
+    "ApiError",
+    "Balance",
+    "CoinWagon",
+    "CoinWagonError",
+    "Global",
+    "InvalidInput",
+    "NetworkError",
+    "NotFound",
+    "Price",
+    "ProviderUnavailable",
+    "RateLimited",
+    "Wallet",
+    "WalletEntry",
+    "get_address_balance",
+    "get_address_balance_async",
+    "get_price",
+    "get_price_async",
+    "get_wallet_balance",
+    "get_wallet_balance_arrow",
+    "get_wallet_balance_async",
+    "query",
+    "run_command",
+]
+
+class ApiError(CoinWagonError):
+    r"""
+    A provider failed or returned an unusable response.
+    """
+    ...
+
+@typing.final
+class Balance:
+    @property
+    def crypto(self) -> builtins.str: ...
+    @property
+    def address(self) -> builtins.str: ...
+    @property
+    def balance(self) -> builtins.float: ...
+    @property
+    def timestamp(self) -> datetime.datetime: ...
+    @property
+    def source(self) -> builtins.str: ...
+    @property
+    def cached(self) -> builtins.bool: ...
+    def to_dict(self) -> dict: ...
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class CoinWagon:
+    def __new__(cls, config: typing.Optional[builtins.str] = None, *, offline: builtins.bool = False) -> CoinWagon: ...
+    def get_price(self, crypto: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False) -> Price: ...
+    def get_address_balance(self, crypto: builtins.str, address: builtins.str, *, verbose: builtins.bool = False) -> Balance: ...
+    def get_wallet_balance(self, path: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False) -> Wallet: ...
+    def get_price_async(self, crypto: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False) -> typing.Awaitable[Price]: ...
+    def get_address_balance_async(self, crypto: builtins.str, address: builtins.str, *, verbose: builtins.bool = False) -> typing.Awaitable[Balance]: ...
+    def get_wallet_balance_async(self, path: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False) -> typing.Awaitable[Wallet]: ...
+    def save_cache(self) -> None: ...
+    def __repr__(self) -> builtins.str: ...
+
+class CoinWagonError(builtins.RuntimeError):
+    r"""
+    Base class for coinwagon errors.
+    """
+    ...
+
+@typing.final
+class Global:
+    @property
+    def fiat(self) -> builtins.str: ...
+    @property
+    def total_market_cap(self) -> builtins.float: ...
+    @property
+    def total_volume_24h(self) -> builtins.float: ...
+    @property
+    def btc_dominance(self) -> builtins.float: ...
+    @property
+    def eth_dominance(self) -> builtins.float: ...
+    @property
+    def active_coins(self) -> builtins.int: ...
+    @property
+    def timestamp(self) -> datetime.datetime: ...
+    @property
+    def source(self) -> builtins.str: ...
+    def to_dict(self) -> dict: ...
+    def __repr__(self) -> builtins.str: ...
+
+class InvalidInput(CoinWagonError):
+    r"""
+    Bad arguments, wallet file or missing offline data.
+    """
+    ...
+
+class NetworkError(CoinWagonError):
+    r"""
+    The HTTP request itself failed.
+    """
+    ...
+
+class NotFound(ApiError):
+    r"""
+    A provider doesn't know the coin, fiat or address.
+    """
+    ...
+
+@typing.final
+class Price:
+    @property
+    def crypto(self) -> builtins.str: ...
+    @property
+    def fiat(self) -> builtins.str: ...
+    @property
+    def price(self) -> builtins.float: ...
+    @property
+    def timestamp(self) -> datetime.datetime: ...
+    @property
+    def source(self) -> builtins.str: ...
+    @property
+    def cached(self) -> builtins.bool: ...
+    def to_dict(self) -> dict: ...
+    def __repr__(self) -> builtins.str: ...
+
+class ProviderUnavailable(ApiError):
+    r"""
+    A provider is down or failing on its side.
+    """
+    ...
+
+class RateLimited(ApiError):
+    r"""
+    A provider is throttling requests.
+    """
+    ...
+
+@typing.final
+class Wallet:
+    @property
+    def fiat(self) -> builtins.str: ...
+    @property
+    def entries(self) -> builtins.list[WalletEntry]: ...
+    @property
+    def total(self) -> builtins.float: ...
+    def to_dict(self) -> dict: ...
+    def __len__(self) -> builtins.int: ...
+    def __repr__(self) -> builtins.str: ...
+
+@typing.final
+class WalletEntry:
+    @property
+    def crypto(self) -> builtins.str: ...
+    @property
+    def address(self) -> builtins.str: ...
+    @property
+    def name(self) -> typing.Optional[builtins.str]: ...
+    @property
+    def balance(self) -> builtins.float: ...
+    @property
+    def price(self) -> builtins.float: ...
+    @property
+    def fiat_value(self) -> builtins.float: ...
+    @property
+    def fiat(self) -> builtins.str: ...
+    @property
+    def timestamp(self) -> datetime.datetime: ...
+    @property
+    def source(self) -> builtins.str: ...
+    @property
+    def price_source(self) -> builtins.str: ...
+    def to_dict(self) -> dict: ...
+    def __repr__(self) -> builtins.str: ...
+
+def get_address_balance(crypto: builtins.str, address: builtins.str, *, verbose: builtins.bool = False, offline: builtins.bool = False, config: typing.Optional[builtins.str] = None) -> Balance: ...
+
+def get_address_balance_async(crypto: builtins.str, address: builtins.str, *, verbose: builtins.bool = False, offline: builtins.bool = False, config: typing.Optional[builtins.str] = None) -> typing.Awaitable[Balance]: ...
+
+def get_price(crypto: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False, offline: builtins.bool = False, config: typing.Optional[builtins.str] = None) -> Price: ...
+
+def get_price_async(crypto: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False, offline: builtins.bool = False, config: typing.Optional[builtins.str] = None) -> typing.Awaitable[Price]: ...
+
+def get_wallet_balance(path: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False, offline: builtins.bool = False, config: typing.Optional[builtins.str] = None) -> Wallet: ...
+
+def get_wallet_balance_arrow(path: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False, offline: builtins.bool = False, config: typing.Optional[builtins.str] = None) -> pyarrow.RecordBatch: ...
+
+def get_wallet_balance_async(path: builtins.str, fiat: builtins.str = 'usd', *, verbose: builtins.bool = False, offline: builtins.bool = False, config: typing.Optional[builtins.str] = None) -> typing.Awaitable[Wallet]: ...
+
+def query(command: builtins.str, args: typing.Sequence[builtins.str]) -> typing.Union[Price, Balance, Wallet, Global, bool, str]: ...
+
+def run_command(command: builtins.str, args: typing.Sequence[builtins.str]) -> builtins.str: ...
+
@@ -0,0 +1,56 @@
+#ifndef COINWAGON_H
+#define COINWAGON_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Current price of `crypto` in `fiat`, written to `out`.
+// Returns 0 on success, -1 on failure (see `coinwagon_last_error`).
+//
+// # Safety
+// `crypto` and `fiat` must be NUL-terminated strings and `out` must point to a writable double.
+int coinwagon_get_price(const char *crypto, const char *fiat, double *out);
+
+// Balance of `address` on the `crypto` chain, written to `out`.
+// Returns 0 on success, -1 on failure (see `coinwagon_last_error`).
+//
+// # Safety
+// `crypto` and `address` must be NUL-terminated strings and `out` must point to a writable double.
+int coinwagon_get_balance(const char *crypto, const char *address, double *out);
+
+// Total value in `fiat` of the wallet file at `path`, written to `out`.
+// Returns 0 on success, -1 on failure (see `coinwagon_last_error`).
+//
+// # Safety
+// `path` and `fiat` must be NUL-terminated strings and `out` must point to a writable double.
+int coinwagon_get_wallet_value(const char *path, const char *fiat, double *out);
+
+// Runs a CLI command, e.g. `command = "current-price"`, `args = {"bitcoin", "usd"}`, and
+// returns its output. Free the result with `coinwagon_string_free`. Returns NULL on failure
+// (see `coinwagon_last_error`).
+//
+// # Safety
+// `command` must be a NUL-terminated string and `args` must point to `nargs` of them
+// (`args` may be NULL when `nargs` is 0).
+char *coinwagon_run_command(const char *command, const char *const *args, size_t nargs);
+
+// Frees a string returned by `coinwagon_run_command`. NULL is ignored.
+//
+// # Safety
+// `s` must come from `coinwagon_run_command` and not have been freed already.
+void coinwagon_string_free(char *s);
+
+// Message for the most recent failure on this thread, or NULL. The pointer stays valid
+// until the next failing coinwagon call on the same thread.
+const char *coinwagon_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* COINWAGON_H */
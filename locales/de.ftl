@@ -0,0 +1,24 @@
+error = Fehler
+stale-as-of = { $result } (veraltet, Stand { $time })
+
+## Wallet and market totals
+
+wallet-total = Gesamt: { $amount } { $fiat }
+global-market-cap = Gesamte Marktkapitalisierung: { $amount } { $fiat }
+global-volume = 24-h-Volumen: { $amount } { $fiat }
+global-btc-dominance = BTC-Dominanz: { $percent } %
+global-eth-dominance = ETH-Dominanz: { $percent } %
+global-active-coins = Aktive Coins: { $count }
+
+## Errors
+
+error-http = HTTP-Anfrage fehlgeschlagen: { $message }
+error-invalid-input = Ungültige Eingabe: { $message }
+error-api = API-Fehler von { $provider }: { $message }
+error-rate-limited = Anfragelimit von { $provider } erreicht
+error-rate-limited-retry = Anfragelimit von { $provider } erreicht, erneut versuchen in { $seconds } s
+error-not-found = Nicht gefunden bei { $provider }: { $what }
+error-unavailable = { $provider } ist nicht erreichbar: { $reason }
+error-system-time = Fehler der Systemzeit: { $message }
+error-config = Konfigurationsfehler: { $message }
+error-runtime = Laufzeitfehler: { $message }
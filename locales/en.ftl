@@ -0,0 +1,28 @@
+# User-facing strings, in Fluent syntax (https://projectfluent.org), read with fluent-bundle.
+# Every locale has the same message ids; any a translation leaves out are shown in English.
+# Variables arrive as text, already formatted, so selectors match on their exact value.
+
+error = Error
+stale-as-of = { $result } (stale as of { $time })
+
+## Wallet and market totals
+
+wallet-total = Total: { $amount } { $fiat }
+global-market-cap = Total market cap: { $amount } { $fiat }
+global-volume = 24h volume: { $amount } { $fiat }
+global-btc-dominance = BTC dominance: { $percent }%
+global-eth-dominance = ETH dominance: { $percent }%
+global-active-coins = Active coins: { $count }
+
+## Errors
+
+error-http = HTTP request failed: { $message }
+error-invalid-input = Invalid input: { $message }
+error-api = API error from { $provider }: { $message }
+error-rate-limited = Rate limited by { $provider }
+error-rate-limited-retry = Rate limited by { $provider }, retry after { $seconds }s
+error-not-found = Not found: { $provider } has no { $what }
+error-unavailable = { $provider } is unavailable: { $reason }
+error-system-time = System time error: { $message }
+error-config = Config error: { $message }
+error-runtime = Runtime error: { $message }
@@ -0,0 +1,24 @@
+error = Error
+stale-as-of = { $result } (desactualizado, a fecha de { $time })
+
+## Wallet and market totals
+
+wallet-total = Total: { $amount } { $fiat }
+global-market-cap = Capitalización de mercado total: { $amount } { $fiat }
+global-volume = Volumen en 24 h: { $amount } { $fiat }
+global-btc-dominance = Dominancia de BTC: { $percent } %
+global-eth-dominance = Dominancia de ETH: { $percent } %
+global-active-coins = Monedas activas: { $count }
+
+## Errors
+
+error-http = Falló la solicitud HTTP: { $message }
+error-invalid-input = Entrada no válida: { $message }
+error-api = Error de la API de { $provider }: { $message }
+error-rate-limited = { $provider } ha limitado las solicitudes
+error-rate-limited-retry = { $provider } ha limitado las solicitudes, reintentar en { $seconds } s
+error-not-found = No encontrado en { $provider }: { $what }
+error-unavailable = { $provider } no está disponible: { $reason }
+error-system-time = Error de la hora del sistema: { $message }
+error-config = Error de configuración: { $message }
+error-runtime = Error de ejecución: { $message }
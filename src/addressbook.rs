@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::CryptoError;
+use crate::config::AddressBookConfig;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    // Coin id of the chain, e.g. bitcoin
+    pub chain: String,
+    pub address: String,
+}
+
+// Names for addresses, used as @name in place of them, in a JSON file under the data directory
+pub struct AddressBook {
+    path: PathBuf,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl AddressBook {
+    // Defaults to ~/.local/share/coinwagon/addressbook.json
+    pub fn path(config: &AddressBookConfig) -> Option<PathBuf> {
+        match &config.path {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::data_dir().map(|dir| dir.join("coinwagon").join("addressbook.json")),
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self, CryptoError> {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| CryptoError::RuntimeError(format!("Address book {} is corrupt: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(CryptoError::RuntimeError(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        Ok(AddressBook { path: path.to_path_buf(), entries })
+    }
+
+    pub fn entries(&self) -> &BTreeMap<String, Entry> {
+        &self.entries
+    }
+
+    // `name` with or without its @
+    pub fn get(&self, name: &str) -> Result<&Entry, CryptoError> {
+        let name = name.strip_prefix('@').unwrap_or(name);
+        self.entries.get(name).ok_or_else(|| {
+            CryptoError::InvalidInput(format!("No @{} in the address book; add it with `addressbook add {} <chain> <address>`", name, name))
+        })
+    }
+
+    // The name of the address on the chain, if it has one
+    pub fn name_of(&self, chain: &str, address: &str) -> Option<&str> {
+        self.entries.iter().find(|(_, entry)| entry.chain == chain && entry.address == address).map(|(name, _)| name.as_str())
+    }
+
+    // Returns whether the name was already taken, by this or another address
+    pub fn add(&mut self, name: &str, entry: Entry) -> bool {
+        self.entries.insert(name.strip_prefix('@').unwrap_or(name).to_string(), entry).is_some()
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.entries.remove(name.strip_prefix('@').unwrap_or(name)).is_some()
+    }
+
+    pub fn save(&self) -> Result<(), CryptoError> {
+        let json = serde_json::to_string_pretty(&self.entries).map_err(|e| CryptoError::RuntimeError(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| CryptoError::RuntimeError(e.to_string()))?;
+        }
+        std::fs::write(&self.path, json)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
@@ -0,0 +1,488 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::commands::WalletTokens;
+use crate::config::{AlertRule, AlertsConfig, Config, CrossDirection};
+use crate::health::{self, Health};
+use crate::indicators::{self, Indicator};
+use crate::providers::{DailyVolume, MempoolTx};
+use crate::watch::Mempool;
+use crate::{CryptoError, CryptoTool, coins, daemon, dca, tidy};
+
+// The [alerts] wallet's value coin by coin at one poll, and a line of the snapshots file
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub time: DateTime<Utc>,
+    pub fiat: String,
+    pub total: f64,
+    pub holdings: BTreeMap<String, f64>,
+}
+
+impl Snapshot {
+    async fn take(tool: &CryptoTool, wallet: &str, fiat: &str, verbose: bool) -> Result<Self, CryptoError> {
+        let mut holdings = BTreeMap::new();
+        for line in tool.wallet_lines(wallet, fiat, WalletTokens::None, verbose).await? {
+            *holdings.entry(line.crypto.clone()).or_insert(0.0) += line.fiat_value();
+        }
+        Ok(Snapshot { time: Utc::now(), fiat: fiat.to_string(), total: holdings.values().sum(), holdings })
+    }
+
+    // Share of the total, as a fraction
+    fn weight(&self, coin: &str) -> f64 {
+        match self.total > 0.0 {
+            true => self.holdings.get(coin).copied().unwrap_or(0.0) / self.total,
+            false => 0.0,
+        }
+    }
+}
+
+// A rule from the config, checked and ready to test
+enum Check {
+    Drift { target: Vec<(String, f64)>, points: f64 },
+    Value { below: f64 },
+    Drawdown { percent: f64, days: u32 },
+    Move { coin: String, percent: f64, minutes: u32 },
+    Volume { coin: String, multiple: f64, days: u32 },
+    Cross { coin: String, indicator: Indicator, above: bool },
+    Whale { coin: String, min: f64 },
+}
+
+// What the polls so far have seen, for the rules to test
+#[derive(Default)]
+struct Observed {
+    snapshot: Option<Snapshot>,
+    // Totals of the snapshots as far back as a rule looks, this poll's included, oldest first
+    history: Vec<(DateTime<Utc>, f64)>,
+    // Each `move` coin's prices as far back as its rules look, with when they were fetched
+    prices: HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+    // Each `volume` coin's daily volumes, as far back as its rules look
+    volumes: HashMap<String, Vec<DailyVolume>>,
+    // Each `cross` coin's daily closes, enough for its rules' indicators
+    closes: HashMap<String, Vec<f64>>,
+    // Each `whale` coin's mempool, and the transactions new to it this poll
+    mempools: HashMap<String, (Mempool, Vec<MempoolTx>)>,
+}
+
+impl Check {
+    fn new(rule: &AlertRule) -> Result<Self, CryptoError> {
+        match rule {
+            AlertRule::Drift { target, points } => {
+                if !points.is_finite() || *points < 0.0 {
+                    return Err(CryptoError::ConfigError(format!("Drift alert: points must be 0 or more, not {}", points)));
+                }
+                Ok(Check::Drift { target: dca::parse_allocation(target)?, points: *points })
+            }
+            AlertRule::Value { below } if !below.is_finite() || *below <= 0.0 => {
+                Err(CryptoError::ConfigError(format!("Value alert: below must be a positive amount, not {}", below)))
+            }
+            AlertRule::Value { below } => Ok(Check::Value { below: *below }),
+            AlertRule::Drawdown { percent, .. } if !percent.is_finite() || *percent <= 0.0 || *percent > 100.0 => {
+                Err(CryptoError::ConfigError(format!("Drawdown alert: percent must be over 0 and at most 100, not {}", percent)))
+            }
+            AlertRule::Drawdown { days: 0, .. } => Err(CryptoError::ConfigError("Drawdown alert: days must be 1 or more".to_string())),
+            AlertRule::Drawdown { percent, days } => Ok(Check::Drawdown { percent: *percent, days: *days }),
+            AlertRule::Move { percent, .. } if !percent.is_finite() || *percent <= 0.0 => {
+                Err(CryptoError::ConfigError(format!("Move alert: percent must be over 0, not {}", percent)))
+            }
+            AlertRule::Move { minutes: 0, .. } => Err(CryptoError::ConfigError("Move alert: minutes must be 1 or more".to_string())),
+            AlertRule::Move { coin, percent, minutes } => Ok(Check::Move { coin: coins::resolve_symbol(coin), percent: *percent, minutes: *minutes }),
+            AlertRule::Volume { multiple, .. } if !multiple.is_finite() || *multiple <= 0.0 => {
+                Err(CryptoError::ConfigError(format!("Volume alert: multiple must be over 0, not {}", multiple)))
+            }
+            AlertRule::Volume { days: 0, .. } => Err(CryptoError::ConfigError("Volume alert: days must be 1 or more".to_string())),
+            AlertRule::Volume { coin, multiple, days } => Ok(Check::Volume { coin: coins::resolve_symbol(coin), multiple: *multiple, days: *days }),
+            AlertRule::Cross { coin, indicator, direction } => Ok(Check::Cross {
+                coin: coins::resolve_symbol(coin),
+                indicator: indicator.parse().map_err(|e| match e {
+                    CryptoError::InvalidInput(message) => CryptoError::ConfigError(format!("Cross alert: {}", message)),
+                    e => e,
+                })?,
+                above: *direction == CrossDirection::Above,
+            }),
+            AlertRule::Whale { min, .. } if !min.is_finite() || *min <= 0.0 => {
+                Err(CryptoError::ConfigError(format!("Whale alert: min must be over 0, not {}", min)))
+            }
+            AlertRule::Whale { coin, min } => {
+                let coin = coins::resolve_symbol(coin);
+                Mempool::new(&coin).map_err(|e| match e {
+                    CryptoError::InvalidInput(message) => CryptoError::ConfigError(format!("Whale alert: {}", message)),
+                    e => e,
+                })?;
+                Ok(Check::Whale { coin, min: *min })
+            }
+        }
+    }
+
+    // How far back the snapshots it reads go
+    fn days(&self) -> u32 {
+        match self {
+            Check::Drawdown { days, .. } => *days,
+            _ => 0,
+        }
+    }
+
+    fn needs_snapshot(&self) -> bool {
+        match self {
+            Check::Drift { .. } | Check::Value { .. } | Check::Drawdown { .. } => true,
+            Check::Move { .. } | Check::Volume { .. } | Check::Cross { .. } | Check::Whale { .. } => false,
+        }
+    }
+
+    // Whether it fires on what happened since the last poll rather than on how things stand, so
+    // it fires each time instead of once while it holds
+    fn is_event(&self) -> bool {
+        matches!(self, Check::Whale { .. })
+    }
+
+    // What to say when the rule holds, None while it doesn't
+    fn test(&self, wallet: &str, fiat: &str, observed: &Observed) -> Option<String> {
+        match self {
+            Check::Drift { target, points } => {
+                let snapshot = observed.snapshot.as_ref()?;
+                let coins: BTreeSet<&str> = target.iter().map(|(coin, _)| coin.as_str()).chain(snapshot.holdings.keys().map(String::as_str)).collect();
+                let drifted: Vec<String> = coins
+                    .into_iter()
+                    .filter_map(|coin| {
+                        let goal = target.iter().find(|(c, _)| c == coin).map_or(0.0, |(_, share)| *share) * 100.0;
+                        let weight = snapshot.weight(coin) * 100.0;
+                        ((weight - goal).abs() > *points).then(|| format!("{} {:.2}% (target {:.2}%, {:+.2} points)", coin, weight, goal, weight - goal))
+                    })
+                    .collect();
+                (!drifted.is_empty()).then(|| format!("{} drifted more than {} points: {}", wallet, points, drifted.join(", ")))
+            }
+            Check::Value { below } => {
+                let snapshot = observed.snapshot.as_ref()?;
+                let fiat = snapshot.fiat.to_uppercase();
+                (snapshot.total < *below).then(|| format!("{} is worth {} {}, below {} {}", wallet, tidy(snapshot.total), fiat, tidy(*below), fiat))
+            }
+            Check::Drawdown { percent, days } => {
+                let snapshot = observed.snapshot.as_ref()?;
+                let since = snapshot.time - chrono::Duration::days(*days as i64);
+                let high = observed.history.iter().filter(|(time, _)| *time >= since).map(|(_, total)| *total).fold(snapshot.total, f64::max);
+                let drop = if high > 0.0 { (high - snapshot.total) / high * 100.0 } else { 0.0 };
+                let fiat = snapshot.fiat.to_uppercase();
+                (drop >= *percent).then(|| {
+                    format!("{} is {:.2}% below its {}-day high of {} {}, at {} {}", wallet, drop, days, tidy(high), fiat, tidy(snapshot.total), fiat)
+                })
+            }
+            // The biggest move from any price in the window to the latest
+            Check::Move { coin, percent, minutes } => {
+                let prices = observed.prices.get(coin)?;
+                let &(now, latest) = prices.last()?;
+                let since = now - chrono::Duration::minutes(*minutes as i64);
+                let (from, change) = prices
+                    .iter()
+                    .filter(|(time, price)| *time >= since && *price > 0.0)
+                    .map(|(_, price)| (*price, (latest / price - 1.0) * 100.0))
+                    .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))?;
+                (change.abs() >= *percent).then(|| {
+                    let unit = if *minutes == 1 { "minute" } else { "minutes" };
+                    format!("{} moved {:+.2}% within {} {}: {} -> {} {}", coin, change, minutes, unit, tidy(from), tidy(latest), fiat.to_uppercase())
+                })
+            }
+            // The last 24 hours against the `days` full days before them
+            Check::Volume { coin, multiple, days } => {
+                let (latest, before) = observed.volumes.get(coin)?.split_last()?;
+                let since = latest.date - chrono::Duration::days(*days as i64);
+                let before: Vec<f64> = before.iter().filter(|day| day.date >= since).map(|day| day.volume).collect();
+                let average = before.iter().sum::<f64>() / before.len() as f64;
+                (average > 0.0 && latest.volume >= average * multiple).then(|| {
+                    let fiat = fiat.to_uppercase();
+                    format!(
+                        "{}'s 24h volume is {} {}, {:.2}x its {}-day average of {} {}",
+                        coin,
+                        tidy(latest.volume),
+                        fiat,
+                        latest.volume / average,
+                        before.len(),
+                        tidy(average),
+                        fiat
+                    )
+                })
+            }
+            Check::Cross { coin, indicator, above } => {
+                let closes = observed.closes.get(coin)?;
+                if indicators::crossing(*indicator, closes)? != *above {
+                    return None;
+                }
+                let direction = if *above { "above" } else { "below" };
+                let latest = closes.last()?;
+                let fiat = fiat.to_uppercase();
+                Some(match indicator {
+                    Indicator::Sma(period) | Indicator::Ema(period) => {
+                        let average = match indicator {
+                            Indicator::Sma(_) => indicators::sma(closes, *period),
+                            _ => indicators::ema(closes, *period),
+                        };
+                        format!("{} crossed {} its {}: {} {} against {} {}", coin, direction, indicator, tidy(*latest), fiat, tidy(*average.last()?), fiat)
+                    }
+                    Indicator::Macd => format!("{}'s MACD crossed {} its signal line", coin, direction),
+                    Indicator::Rsi(period) => {
+                        let level = if *above { 70 } else { 30 };
+                        format!("{}'s {} crossed {} {}, at {:.2}", coin, indicator, direction, level, indicators::rsi(closes, *period).last()?)
+                    }
+                })
+            }
+            Check::Whale { coin, min } => {
+                let (mempool, fresh) = observed.mempools.get(coin)?;
+                let whales: Vec<String> = fresh.iter().filter(|tx| mempool.amount(tx) >= *min).map(|tx| mempool.describe(tx)).collect();
+                (!whales.is_empty()).then(|| format!("Whale in the {} mempool: {}", coin, whales.join("; ")))
+            }
+        }
+    }
+}
+
+struct Daemon {
+    // As configured, to tell which rules a reload left alone
+    rules: Vec<AlertRule>,
+    checks: Vec<Check>,
+    // Whether each rule held at the last poll, so it fires once until it stops holding
+    firing: Vec<bool>,
+    wallet: Option<String>,
+    fiat: String,
+    snapshots: Option<String>,
+    observed: Observed,
+}
+
+impl Daemon {
+    async fn poll(&mut self, tool: &CryptoTool, verbose: bool) -> Result<(), CryptoError> {
+        if let Some(wallet) = &self.wallet {
+            let snapshot = Snapshot::take(tool, wallet, &self.fiat, verbose).await?;
+            if let Some(path) = &self.snapshots {
+                append(path, &snapshot)?;
+            }
+            let days = self.checks.iter().map(Check::days).max().unwrap_or(0);
+            let since = snapshot.time - chrono::Duration::days(days as i64);
+            self.observed.history.retain(|(time, _)| *time >= since);
+            self.observed.history.push((snapshot.time, snapshot.total));
+            self.observed.snapshot = Some(snapshot);
+        }
+        for check in &self.checks {
+            let Check::Move { coin, minutes, .. } = check else { continue };
+            // Past the cache, which would hand back one price per TTL however often this polls
+            let price = tool.live_price(coin, &self.fiat, verbose).await?;
+            let fetched_at: DateTime<Utc> = price.fetched_at.into();
+            let longest = self.checks.iter().filter_map(|c| match c {
+                Check::Move { coin: other, minutes, .. } if other == coin => Some(*minutes),
+                _ => None,
+            });
+            let since = fetched_at - chrono::Duration::minutes(longest.max().unwrap_or(*minutes) as i64);
+            let prices = self.observed.prices.entry(coin.clone()).or_default();
+            prices.retain(|(time, _)| *time >= since);
+            prices.push((fetched_at, price.value));
+        }
+        let mut fetched: Vec<&str> = Vec::new();
+        for check in &self.checks {
+            let Check::Volume { coin, .. } = check else { continue };
+            if fetched.contains(&coin.as_str()) {
+                continue;
+            }
+            let days = self.checks.iter().filter_map(|c| match c {
+                Check::Volume { coin: other, days, .. } if other == coin => Some(*days),
+                _ => None,
+            });
+            let (volumes, _) = tool.volumes(coin, &self.fiat, days.max().unwrap_or(1), verbose).await?;
+            self.observed.volumes.insert(coin.clone(), volumes);
+            fetched.push(coin);
+        }
+        let mut fetched: Vec<&str> = Vec::new();
+        for check in &self.checks {
+            let Check::Cross { coin, .. } = check else { continue };
+            if fetched.contains(&coin.as_str()) {
+                continue;
+            }
+            let needed = self.checks.iter().filter_map(|c| match c {
+                Check::Cross { coin: other, indicator, .. } if other == coin => Some(indicator.closes_needed()),
+                _ => None,
+            });
+            let (closes, _) = tool.closes(coin, &self.fiat, needed.max().unwrap_or(1) as u32, verbose).await?;
+            self.observed.closes.insert(coin.clone(), closes.iter().map(|close| close.price).collect());
+            fetched.push(coin);
+        }
+        let mut fetched: Vec<&str> = Vec::new();
+        for check in &self.checks {
+            let Check::Whale { coin, .. } = check else { continue };
+            if fetched.contains(&coin.as_str()) {
+                continue;
+            }
+            let (recent, _) = tool.recent_transactions(coin, verbose).await?;
+            let mut mempool = match self.observed.mempools.remove(coin) {
+                Some((mempool, _)) => mempool,
+                None => Mempool::new(coin)?,
+            };
+            // Every new transaction; each rule picks its own out by `min`
+            let fresh = mempool.whales(recent, 0.0);
+            self.observed.mempools.insert(coin.clone(), (mempool, fresh));
+            fetched.push(coin);
+        }
+        let wallet = self.wallet.as_deref().unwrap_or_default();
+        for (check, firing) in self.checks.iter().zip(self.firing.iter_mut()) {
+            let message = check.test(wallet, &self.fiat, &self.observed);
+            if let Some(message) = &message
+                && (!*firing || check.is_event())
+            {
+                println!("Alert: {}", message);
+            }
+            *firing = message.is_some();
+        }
+        Ok(())
+    }
+}
+
+// The snapshots already in the file, which needn't exist yet
+pub fn snapshots(path: &str) -> Result<Vec<Snapshot>, CryptoError> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(CryptoError::RuntimeError(format!("Failed to read {}: {}", path, e))),
+    };
+    let mut snapshots = Vec::new();
+    for (i, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let snapshot: Snapshot = serde_json::from_str(line)
+            .map_err(|e| CryptoError::InvalidInput(format!("Line {} of {} isn't a snapshot: {}", i + 1, path, e)))?;
+        snapshots.push(snapshot);
+    }
+    Ok(snapshots)
+}
+
+// Totals in `fiat` of the snapshots already in the file
+fn saved(path: &str, fiat: &str) -> Result<Vec<(DateTime<Utc>, f64)>, CryptoError> {
+    Ok(snapshots(path)?.into_iter().filter(|snapshot| snapshot.fiat == fiat).map(|snapshot| (snapshot.time, snapshot.total)).collect())
+}
+
+fn append(path: &str, snapshot: &Snapshot) -> Result<(), CryptoError> {
+    let line = serde_json::to_string(snapshot).map_err(|e| CryptoError::RuntimeError(format!("Failed to serialize snapshot: {}", e)))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| CryptoError::ConfigError(format!("Can't open snapshots file {}: {}", path, e)))?;
+    writeln!(file, "{}", line).map_err(|e| CryptoError::RuntimeError(format!("Failed to write to {}: {}", path, e)))
+}
+
+impl Daemon {
+    fn new(config: &AlertsConfig) -> Result<Self, CryptoError> {
+        let checks = config.rules.iter().map(Check::new).collect::<Result<Vec<_>, _>>()?;
+        if checks.is_empty() {
+            return Err(CryptoError::InvalidInput("No alert rules; add them as [[alerts.rules]] in the config".to_string()));
+        }
+        let wallet = match checks.iter().any(Check::needs_snapshot) {
+            true => Some(config.wallet.clone().ok_or_else(|| {
+                CryptoError::ConfigError("Alerts on the portfolio need [alerts] wallet set to a wallet file".to_string())
+            })?),
+            false => None,
+        };
+        let fiat = config.fiat.to_lowercase();
+        let history = match &config.snapshots {
+            Some(path) => saved(path, &fiat)?,
+            None => Vec::new(),
+        };
+        Ok(Daemon {
+            rules: config.rules.clone(),
+            firing: vec![false; checks.len()],
+            checks,
+            wallet,
+            fiat,
+            snapshots: config.snapshots.clone(),
+            observed: Observed { history, ..Observed::default() },
+        })
+    }
+
+    // The daemon for the re-read [alerts], carrying over which unchanged rules are firing and,
+    // for the same wallet and fiat, the prices and totals seen so far
+    fn reload(&mut self, config: &AlertsConfig) -> Result<(), CryptoError> {
+        let mut reloaded = Daemon::new(config)?;
+        for (rule, firing) in reloaded.rules.iter().zip(reloaded.firing.iter_mut()) {
+            *firing = self.rules.iter().zip(&self.firing).any(|(old, was)| old == rule && *was);
+        }
+        if reloaded.wallet == self.wallet && reloaded.fiat == self.fiat && reloaded.snapshots == self.snapshots {
+            reloaded.observed = std::mem::take(&mut self.observed);
+        }
+        *self = reloaded;
+        Ok(())
+    }
+
+    fn describe(&self, interval: Duration) -> String {
+        let count = self.checks.len();
+        format!("Watching {} alert rule{} every {}s", count, if count == 1 { "" } else { "s" }, interval.as_secs())
+    }
+}
+
+// The alert daemon: polls every `interval`, snapshotting the [alerts] wallet when a rule needs
+// it, and prints each rule as it starts to hold. A failed poll after the first is reported and
+// the next one tries again. SIGTERM or Ctrl-C stops it between polls; SIGHUP re-reads the
+// config file's [alerts], keeping the old rules if that fails. With `listen`, health checks are
+// answered there from before the first poll.
+pub async fn run(
+    tool: &CryptoTool,
+    config: &AlertsConfig,
+    interval: Duration,
+    polls: Option<u32>,
+    listen: Option<&str>,
+    verbose: bool,
+) -> Result<String, CryptoError> {
+    let mut watcher = Daemon::new(config)?;
+    let mut signals = daemon::Signals::catch()?;
+    let health = Health::default();
+    let listener = match listen {
+        Some(address) => Some(health::bind(address).await?),
+        None => None,
+    };
+    println!("{}", watcher.describe(interval));
+    if let Some(listener) = &listener
+        && let Ok(address) = listener.local_addr()
+    {
+        println!("Health checks on http://{}/healthz and /readyz", address);
+    }
+    let server = async {
+        match listener {
+            Some(listener) => health::serve(listener, tool, &health).await,
+            None => std::future::pending().await,
+        }
+    };
+    let watching = async {
+        let first = watcher.poll(tool, verbose).await;
+        health.polled(&first);
+        first?;
+        daemon::notify("READY=1");
+        let mut done = 1;
+        let mut next = tokio::time::Instant::now() + interval;
+        while polls.is_none_or(|polls| done < polls) {
+            tokio::select! {
+                _ = tokio::time::sleep_until(next) => {
+                    next += interval;
+                    done += 1;
+                    let result = watcher.poll(tool, verbose).await;
+                    health.polled(&result);
+                    if let Err(e) = result {
+                        eprintln!("Warning: poll failed: {}", e);
+                    }
+                }
+                signal = signals.next() => match signal {
+                    daemon::Signal::Stop => {
+                        daemon::notify("STOPPING=1");
+                        break;
+                    }
+                    daemon::Signal::Reload => {
+                        daemon::notify("RELOADING=1");
+                        let path = tool.config_path.as_deref().and_then(|path| path.to_str());
+                        match Config::load(path).and_then(|config| watcher.reload(&config.alerts)) {
+                            Ok(()) => println!("Reloaded: {}", watcher.describe(interval)),
+                            Err(e) => eprintln!("Warning: reload failed, keeping the old rules: {}", e),
+                        }
+                        daemon::notify("READY=1");
+                    }
+                }
+            }
+        }
+        Ok(String::new())
+    };
+    tokio::select! {
+        result = watching => result,
+        () = server => Ok(String::new()),
+    }
+}
@@ -0,0 +1,51 @@
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::WalletLine;
+
+// One row per wallet line, same columns as the Python WalletEntry
+pub fn wallet_record_batch(lines: &[WalletLine], fiat: &str) -> Result<RecordBatch, ArrowError> {
+    let utc: Arc<str> = "UTC".into();
+    let schema = Schema::new(vec![
+        Field::new("crypto", DataType::Utf8, false),
+        Field::new("address", DataType::Utf8, false),
+        Field::new("balance", DataType::Float64, false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("fiat_value", DataType::Float64, false),
+        Field::new("fiat", DataType::Utf8, false),
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Microsecond, Some(utc.clone())), false),
+        Field::new("source", DataType::Utf8, false),
+        Field::new("price_source", DataType::Utf8, false),
+    ]);
+
+    let strings = |f: fn(&WalletLine) -> &str| -> ArrayRef {
+        Arc::new(lines.iter().map(|line| Some(f(line))).collect::<StringArray>())
+    };
+    let floats = |f: fn(&WalletLine) -> f64| -> ArrayRef { Arc::new(lines.iter().map(f).collect::<Float64Array>()) };
+    let timestamps: TimestampMicrosecondArray = lines
+        .iter()
+        .map(|line| micros(line.balance.fetched_at.min(line.price.fetched_at)))
+        .collect::<Vec<_>>()
+        .into();
+
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            strings(|line| &line.crypto),
+            strings(|line| &line.address),
+            floats(|line| line.balance.value),
+            floats(|line| line.price.value),
+            floats(WalletLine::fiat_value),
+            Arc::new(StringArray::from(vec![fiat; lines.len()])),
+            Arc::new(timestamps.with_timezone(utc)),
+            strings(|line| &line.balance.source),
+            strings(|line| &line.price.source),
+        ],
+    )
+}
+
+fn micros(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_micros() as i64).unwrap_or_default()
+}
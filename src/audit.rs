@@ -0,0 +1,81 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::{RequestBuilder, Response};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+use crate::CryptoError;
+use crate::keys::redact;
+
+// One provider call, as a line of the audit log
+#[derive(Serialize)]
+struct Entry<'a> {
+    timestamp: String,
+    provider: &'a str,
+    method: &'a str,
+    host: &'a str,
+    // Path and query, secrets replaced with REDACTED
+    path: String,
+    // Null when no response came back, with the reason in error
+    status: Option<u16>,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// audit_log / --audit-log: every provider call appended to a JSON Lines file as it finishes,
+// retries and fallbacks included, so where each figure came from can be shown later. Only
+// who was asked what is kept, never the responses; answers served from the cache made no call.
+pub struct AuditLog {
+    file: Mutex<File>,
+    secrets: Vec<String>,
+}
+
+impl AuditLog {
+    pub fn open(path: &str, secrets: Vec<String>) -> Result<Self, CryptoError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| CryptoError::ConfigError(format!("Can't open audit log {}: {}", path, e)))?;
+        Ok(AuditLog { file: Mutex::new(file), secrets })
+    }
+
+    // Sends `request` with `send` and appends a line for it; a call that can't be logged fails
+    pub async fn capture<F: Future<Output = Result<Response, CryptoError>>>(
+        &self,
+        provider: &str,
+        request: RequestBuilder,
+        send: impl FnOnce(RequestBuilder) -> F,
+    ) -> Result<Response, CryptoError> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let method = request.method().to_string();
+        let url = request.url().clone();
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+
+        let started: DateTime<Utc> = SystemTime::now().into();
+        let timer = Instant::now();
+        let result = send(RequestBuilder::from_parts(client, request)).await;
+        let entry = Entry {
+            timestamp: started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            provider,
+            method: &method,
+            host: url.host_str().unwrap_or_default(),
+            path: redact(&self.secrets, &path),
+            status: result.as_ref().ok().map(|resp| resp.status().as_u16()),
+            latency_ms: (timer.elapsed().as_secs_f64() * 1000.0 * 10.0).round() / 10.0,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        let line = serde_json::to_string(&entry)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to serialize audit entry: {}", e)))?;
+        writeln!(self.file.lock().unwrap(), "{}", line)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to write to the audit log: {}", e)))?;
+        result
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::providers::DailyClose;
+use crate::{CryptoError, Lookup, chart, coins, tidy};
+
+// A [baskets] entry: one unit holds `amount` of each coin, so it's priced like a single asset
+pub struct Basket {
+    pub name: String,
+    // Coin ids in order, each once
+    pub coins: Vec<(String, f64)>,
+}
+
+impl Basket {
+    pub fn from_config(name: &str, coins: &HashMap<String, f64>) -> Result<Self, CryptoError> {
+        let mut resolved: Vec<(String, f64)> = Vec::new();
+        for (coin, &amount) in coins {
+            if !amount.is_finite() || amount <= 0.0 {
+                return Err(CryptoError::ConfigError(format!("Basket {}: {} needs a positive amount, not {}", name, coin, amount)));
+            }
+            let coin = coins::resolve_symbol(coin);
+            if resolved.iter().any(|(seen, _)| *seen == coin) {
+                return Err(CryptoError::ConfigError(format!("Basket {}: {} is in it twice", name, coin)));
+            }
+            resolved.push((coin, amount));
+        }
+        if resolved.is_empty() {
+            return Err(CryptoError::ConfigError(format!("Basket {} has no coins", name)));
+        }
+        resolved.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(Basket { name: name.to_string(), coins: resolved })
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.coins.iter().map(|(coin, _)| coin.clone()).collect()
+    }
+
+    // `basket list`'s line for the basket
+    pub fn describe(&self) -> String {
+        let coins: Vec<String> = self.coins.iter().map(|(coin, amount)| format!("{} {}", tidy(*amount), coin)).collect();
+        format!("{}: {}", self.name, coins.join(" + "))
+    }
+
+    // `prices` are of `coins`, in order; each coin's line shows its share of the value
+    pub fn render_quote(&self, fiat: &str, prices: &[Lookup]) -> String {
+        let fiat = fiat.to_uppercase();
+        let values: Vec<f64> = self.coins.iter().zip(prices).map(|((_, amount), price)| amount * price.value).collect();
+        let total: f64 = values.iter().sum();
+        let mut sources: Vec<&str> = Vec::new();
+        for price in prices {
+            if !sources.contains(&price.source.as_str()) {
+                sources.push(&price.source);
+            }
+        }
+        let mut lines = vec![format!("{}: {} {} ({})", self.name, tidy(total), fiat, sources.join(", "))];
+        for (((coin, amount), price), value) in self.coins.iter().zip(prices).zip(&values) {
+            let share = if total > 0.0 { value / total * 100.0 } else { 0.0 };
+            lines.push(format!("  {} {} × {} = {} {} ({:.2}%)", tidy(*amount), coin, tidy(price.value), tidy(*value), fiat, share));
+        }
+        lines.join("\n")
+    }
+
+    // The basket's close on every date all its coins have one; `histories` are of `coins`, in order
+    pub fn closes(&self, histories: &[Vec<DailyClose>]) -> Vec<DailyClose> {
+        let prices: Vec<HashMap<NaiveDate, f64>> =
+            histories.iter().map(|closes| closes.iter().map(|close| (close.date, close.price)).collect()).collect();
+        let mut dates: BTreeSet<NaiveDate> = histories.first().map(|closes| closes.iter().map(|close| close.date).collect()).unwrap_or_default();
+        dates.retain(|date| prices.iter().all(|closes| closes.contains_key(date)));
+        dates
+            .into_iter()
+            .map(|date| DailyClose { date, price: self.coins.iter().zip(&prices).map(|((_, amount), closes)| amount * closes[&date]).sum() })
+            .collect()
+    }
+
+    pub fn render_history(&self, fiat: &str, closes: &[DailyClose], source: &str, chart: bool) -> String {
+        let fiat = fiat.to_uppercase();
+        let (first, last) = (&closes[0], &closes[closes.len() - 1]);
+        let high = closes.iter().fold(first, |high, close| if close.price > high.price { close } else { high });
+        let low = closes.iter().fold(first, |low, close| if close.price < low.price { close } else { low });
+        let change = if first.price > 0.0 { format!(" ({:+.2}%)", (last.price / first.price - 1.0) * 100.0) } else { String::new() };
+        let mut lines = vec![
+            format!("{} in {} over {} daily closes, {} to {} ({}):", self.name, fiat, closes.len(), first.date, last.date, source),
+            format!("  First: {} {}", tidy(first.price), fiat),
+            format!("  Last: {} {}{}", tidy(last.price), fiat, change),
+            format!("  High: {} {} on {}", tidy(high.price), fiat, high.date),
+            format!("  Low: {} {} on {}", tidy(low.price), fiat, low.date),
+        ];
+        if chart {
+            let dates: Vec<NaiveDate> = closes.iter().map(|close| close.date).collect();
+            let prices: Vec<f64> = closes.iter().map(|close| close.price).collect();
+            lines.push(String::new());
+            lines.extend(chart::draw(&dates, &[(&self.name, '*', &prices)]));
+        }
+        lines.join("\n")
+    }
+}
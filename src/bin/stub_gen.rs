@@ -0,0 +1,13 @@
+// Regenerates coinwagon.pyi from the Python bindings: `cargo run --bin stub_gen --features arrow`
+fn main() -> pyo3_stub_gen::Result<()> {
+    coinwagon::stub_info()?.generate()?;
+
+    // pyo3-stub-gen writes every exception base as `builtins.<Name>`, which is wrong for
+    // the ones defined in this module
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/coinwagon.pyi");
+    let stub = std::fs::read_to_string(path)?
+        .replace("(builtins.CoinWagonError)", "(CoinWagonError)")
+        .replace("(builtins.ApiError)", "(ApiError)");
+    std::fs::write(path, stub)?;
+    Ok(())
+}
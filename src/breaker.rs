@@ -0,0 +1,148 @@
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+
+use crate::CryptoError;
+use crate::config::BreakerConfig;
+
+#[derive(Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    // The one call let through after the cooldown is out; everyone else is still skipped
+    trial_in_flight: bool,
+}
+
+// Per-provider circuit breaker. After `failures` consecutive failures a provider is skipped for
+// the cooldown and lookups go straight to the fallback; once it has passed, the next call is a
+// trial that closes the circuit on success or reopens it on failure. Calls arriving while the
+// trial runs are skipped as before. A trial that never reports back, say because its lookup was
+// cancelled, gives way to another after a further cooldown.
+pub struct Breakers {
+    circuits: DashMap<String, Circuit>,
+    threshold: u32,
+    cooldown: Duration,
+}
+
+impl Breakers {
+    pub fn new(config: &BreakerConfig) -> Self {
+        Breakers {
+            circuits: DashMap::new(),
+            threshold: config.failures,
+            cooldown: Duration::from_secs(config.cooldown_secs),
+        }
+    }
+
+    // Whether `provider` may be called now
+    pub fn allow(&self, provider: &str) -> bool {
+        let Some(mut circuit) = self.circuits.get_mut(provider) else {
+            return true;
+        };
+        match circuit.open_until {
+            // Cooling down, or a trial call is out
+            Some(until) if Instant::now() < until => false,
+            Some(_) => {
+                circuit.open_until = Some(Instant::now() + self.cooldown);
+                circuit.trial_in_flight = true;
+                true
+            }
+            None => true,
+        }
+    }
+
+    // Whether `provider` is being skipped, without starting a trial call like `allow`. True
+    // while a trial is out too, since every other call is skipped meanwhile.
+    pub fn is_open(&self, provider: &str) -> bool {
+        self.circuits.get(provider).and_then(|circuit| circuit.open_until).is_some_and(|until| Instant::now() < until)
+    }
+
+    // Only failures that say the provider is down or throttling count; a provider that answers
+    // "no such address" is working fine
+    pub fn record(&self, provider: &str, error: Option<&CryptoError>) {
+        match error {
+            Some(e) if e.is_retryable() => self.failed(provider),
+            _ => {
+                self.circuits.remove(provider);
+            }
+        }
+    }
+
+    fn failed(&self, provider: &str) {
+        if self.threshold == 0 {
+            return;
+        }
+        let mut circuit = self.circuits.entry(provider.to_string()).or_default();
+        circuit.consecutive_failures += 1;
+        // A failed trial reopens the circuit straight away
+        if circuit.trial_in_flight || circuit.consecutive_failures >= self.threshold {
+            circuit.open_until = Some(Instant::now() + self.cooldown);
+            circuit.trial_in_flight = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Breakers;
+    use crate::CryptoError;
+    use dashmap::DashMap;
+    use std::time::Duration;
+
+    fn breakers() -> Breakers {
+        Breakers { circuits: DashMap::new(), threshold: 2, cooldown: Duration::from_millis(50) }
+    }
+
+    fn down() -> CryptoError {
+        CryptoError::ProviderUnavailable { provider: "CoinGecko".to_string(), reason: "HTTP 503".to_string() }
+    }
+
+    #[test]
+    fn opens_skips_lets_one_trial_through_and_closes() {
+        let breakers = breakers();
+        breakers.record("CoinGecko", Some(&down()));
+        assert!(breakers.allow("CoinGecko"));
+        breakers.record("CoinGecko", Some(&down()));
+
+        // Open: skipped until the cooldown passes
+        assert!(breakers.is_open("CoinGecko"));
+        assert!(!breakers.allow("CoinGecko"));
+        std::thread::sleep(Duration::from_millis(60));
+
+        // Half-open: only the first caller gets through
+        assert!(breakers.allow("CoinGecko"));
+        assert!(!breakers.allow("CoinGecko"));
+        assert!(!breakers.allow("CoinGecko"));
+
+        // The trial succeeded, so everyone is let through again
+        breakers.record("CoinGecko", None);
+        assert!(!breakers.is_open("CoinGecko"));
+        assert!(breakers.allow("CoinGecko"));
+        assert!(breakers.allow("CoinGecko"));
+    }
+
+    #[test]
+    fn a_failed_trial_reopens_the_circuit() {
+        let breakers = breakers();
+        breakers.record("CoinGecko", Some(&down()));
+        breakers.record("CoinGecko", Some(&down()));
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(breakers.allow("CoinGecko"));
+        breakers.record("CoinGecko", Some(&down()));
+        assert!(!breakers.allow("CoinGecko"));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breakers.allow("CoinGecko"));
+    }
+
+    #[test]
+    fn an_abandoned_trial_gives_way_after_another_cooldown() {
+        let breakers = breakers();
+        breakers.record("CoinGecko", Some(&down()));
+        breakers.record("CoinGecko", Some(&down()));
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(breakers.allow("CoinGecko"));
+        assert!(!breakers.allow("CoinGecko"));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breakers.allow("CoinGecko"));
+    }
+}
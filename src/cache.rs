@@ -0,0 +1,334 @@
+use dashmap::DashMap;
+use reqwest::RequestBuilder;
+use reqwest::header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crate::CryptoError;
+use crate::config::CacheConfig;
+#[cfg(feature = "redis")]
+use crate::redis_cache::RedisCache;
+
+// HTTP validators a provider sent with a response, replayed on the next conditional request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    pub fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub value: f64,
+    pub fetched_at: SystemTime,
+    pub validators: Validators,
+    // Provider that produced the value
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+// Calls made to one provider, summed over every run since the cache was cleared
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestCounts {
+    pub requests: u64,
+    // No response, or a 5xx
+    pub errors: u64,
+    // 429s
+    pub rate_limited: u64,
+    // Of every request, for the average
+    pub total_latency_ms: f64,
+}
+
+impl RequestCounts {
+    pub fn add(&mut self, other: &RequestCounts) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.rate_limited += other.rate_limited;
+        self.total_latency_ms += other.total_latency_ms;
+    }
+}
+
+// On-disk layout of the persistent cache
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    entries: HashMap<String, CacheEntry>,
+    hits: u64,
+    misses: u64,
+    // By provider name
+    #[serde(default)]
+    requests: HashMap<String, RequestCounts>,
+}
+
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    // Entry counts aged <5m, 5m-1h, 1h-24h and older
+    pub age_buckets: [usize; 4],
+}
+
+impl CacheStats {
+    pub fn hit_ratio(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+}
+
+pub struct Cache {
+    entries: DashMap<String, CacheEntry>,
+    ttl: Duration,
+    // Backing file for the persistent cache; None keeps everything in memory
+    path: Option<PathBuf>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    requests: DashMap<String, RequestCounts>,
+    #[cfg(feature = "redis")]
+    shared: Option<RedisCache>,
+}
+
+impl Cache {
+    pub fn from_config(config: &CacheConfig) -> Self {
+        #[allow(unused_mut)]
+        let mut cache = Self::load(config.resolved_path(), Duration::from_secs(config.ttl_secs));
+        if let Some(url) = &config.redis_url {
+            #[cfg(feature = "redis")]
+            match RedisCache::connect(url, &config.redis_prefix) {
+                Ok(shared) => cache.shared = Some(shared),
+                Err(e) => eprintln!("Warning: could not connect to Redis at {}, using local cache only: {}", url, e),
+            }
+            #[cfg(not(feature = "redis"))]
+            eprintln!("Warning: cache.redis_url = {} ignored, coinwagon was built without the `redis` feature", url);
+        }
+        cache
+    }
+
+    pub fn price_key(crypto: &str, fiat: &str) -> String {
+        format!("price:{}:{}", crypto, fiat)
+    }
+
+    // One `from` in `to`, both fiat
+    pub fn fx_key(from: &str, to: &str) -> String {
+        format!("fx:{}:{}", from, to)
+    }
+
+    pub fn balance_key(crypto: &str, address: &str) -> String {
+        format!("balance:{}:{}", crypto, address)
+    }
+
+    // The Fear & Greed index `days_ago` days back
+    pub fn sentiment_key(days_ago: usize) -> String {
+        format!("sentiment:fear-greed:{}", days_ago)
+    }
+
+    // Keys are "<kind>:<coin>:<rest>"
+    pub fn coin_of(key: &str) -> Option<&str> {
+        key.split(':').nth(1)
+    }
+
+    // A missing or unreadable cache file just means starting cold
+    pub fn load(path: Option<PathBuf>, ttl: Duration) -> Self {
+        let mut saved = CacheFile::default();
+        if let Some(path) = &path
+            && let Ok(contents) = std::fs::read_to_string(path)
+        {
+            match serde_json::from_str::<CacheFile>(&contents) {
+                Ok(file) => saved = file,
+                Err(e) => eprintln!("Warning: ignoring corrupt cache file {}: {}", path.display(), e),
+            }
+        }
+        Cache {
+            entries: saved.entries.into_iter().collect(),
+            ttl,
+            path,
+            hits: AtomicU64::new(saved.hits),
+            misses: AtomicU64::new(saved.misses),
+            requests: saved.requests.into_iter().collect(),
+            #[cfg(feature = "redis")]
+            shared: None,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), CryptoError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let snapshot = CacheFile {
+            entries: self.entries.iter().map(|e| (e.key().clone(), e.value().clone())).collect(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            requests: self.requests.iter().map(|e| (e.key().clone(), e.value().clone())).collect(),
+        };
+        let json = serde_json::to_string(&snapshot)
+            .map_err(|e| CryptoError::InvalidInput(format!("Failed to serialize cache: {}", e)))?;
+
+        // Write-then-rename so a concurrent reader never sees a half-written file
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+            std::fs::write(&tmp, json)?;
+            std::fs::rename(&tmp, path)
+        };
+        write().map_err(|e| CryptoError::InvalidInput(format!("Failed to write cache file {}: {}", path.display(), e)))
+    }
+
+    // Returns the value only while it's within the TTL; counts towards the hit ratio
+    pub fn get_fresh(&self, key: &str) -> Result<Option<f64>, CryptoError> {
+        let fresh = match self.get(key) {
+            Some(entry) if SystemTime::now().duration_since(entry.fetched_at)? < self.ttl => Some(entry.value),
+            _ => None,
+        };
+        let counter = if fresh.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(fresh)
+    }
+
+    // A lookup that went to a provider without asking get_fresh first, like a batched one
+    pub fn count_miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // The entry while it's within the TTL, without counting towards the hit ratio
+    pub fn peek_fresh(&self, key: &str) -> Option<CacheEntry> {
+        self.get(key).filter(|entry| entry.fetched_at.elapsed().is_ok_and(|age| age < self.ttl))
+    }
+
+    // Any entry, fresh or stale, e.g. to revalidate with the provider
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        let local = self.entries.get(key).map(|entry| entry.clone());
+        #[cfg(feature = "redis")]
+        if let Some(shared) = &self.shared
+            && let Some(entry) = shared.get(key)
+            && local.as_ref().is_none_or(|l| entry.fetched_at > l.fetched_at)
+        {
+            self.entries.insert(key.to_string(), entry.clone());
+            return Some(entry);
+        }
+        local
+    }
+
+    pub fn insert(&self, key: String, value: f64, source: &str, validators: Validators) -> CacheEntry {
+        let entry = CacheEntry {
+            value,
+            fetched_at: SystemTime::now(),
+            validators,
+            source: Some(source.to_string()),
+        };
+        #[cfg(feature = "redis")]
+        if let Some(shared) = &self.shared {
+            shared.set(&key, &entry);
+        }
+        self.entries.insert(key, entry.clone());
+        entry
+    }
+
+    // Provider answered 304 Not Modified: keep the value, restart its TTL
+    pub fn refresh(&self, key: &str) {
+        if let Some(mut entry) = self.entries.get_mut(key) {
+            entry.fetched_at = SystemTime::now();
+            #[cfg(feature = "redis")]
+            if let Some(shared) = &self.shared {
+                shared.set(key, &entry);
+            }
+        }
+    }
+
+    // With Redis attached, the shared store is the authoritative view
+    pub fn list(&self) -> Vec<(String, CacheEntry)> {
+        #[cfg(feature = "redis")]
+        let mut entries: Vec<_> = match &self.shared {
+            Some(shared) => shared.list(),
+            None => self.entries.iter().map(|e| (e.key().clone(), e.value().clone())).collect(),
+        };
+        #[cfg(not(feature = "redis"))]
+        let mut entries: Vec<_> = self.entries.iter().map(|e| (e.key().clone(), e.value().clone())).collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    // Removes every entry, or only those for one coin; returns how many were dropped
+    pub fn clear(&self, coin: Option<&str>) -> usize {
+        let before = self.entries.len();
+        match coin {
+            Some(coin) => self.entries.retain(|key, _| Self::coin_of(key) != Some(coin)),
+            None => {
+                self.entries.clear();
+                self.hits.store(0, Ordering::Relaxed);
+                self.misses.store(0, Ordering::Relaxed);
+                self.requests.clear();
+            }
+        }
+        #[allow(unused_mut)]
+        let mut removed = before - self.entries.len();
+        #[cfg(feature = "redis")]
+        if let Some(shared) = &self.shared {
+            removed = removed.max(shared.clear(coin));
+        }
+        removed
+    }
+
+    // A run's calls, added to the totals the cache file keeps
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_requests(&self, counts: Vec<(String, RequestCounts)>) {
+        for (provider, counts) in counts {
+            self.requests.entry(provider).or_default().add(&counts);
+        }
+    }
+
+    // By provider, in name order
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn requests(&self) -> Vec<(String, RequestCounts)> {
+        let mut requests: Vec<_> = self.requests.iter().map(|e| (e.key().clone(), e.value().clone())).collect();
+        requests.sort_by(|a, b| a.0.cmp(&b.0));
+        requests
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let now = SystemTime::now();
+        let entries = self.list();
+        let mut age_buckets = [0; 4];
+        for (_, entry) in &entries {
+            let age = now.duration_since(entry.fetched_at).unwrap_or_default().as_secs();
+            let bucket = match age {
+                0..300 => 0,
+                300..3_600 => 1,
+                3_600..86_400 => 2,
+                _ => 3,
+            };
+            age_buckets[bucket] += 1;
+        }
+        CacheStats {
+            entries: entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            age_buckets,
+        }
+    }
+}
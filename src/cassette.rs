@@ -0,0 +1,130 @@
+use reqwest::{RequestBuilder, Response, ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::CryptoError;
+use crate::keys::redact;
+
+// One provider request and the response it got
+#[derive(Clone, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    // POST bodies, like JSON-RPC calls, which share one URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_body: Option<String>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Interaction {
+    fn into_response(self) -> Result<Response, CryptoError> {
+        rebuild_response(&self.url, self.status, &self.headers, self.body)
+    }
+}
+
+// A response that was read into memory, turned back into one providers can consume
+pub fn rebuild_response(url: &str, status: u16, headers: &[(String, String)], body: String) -> Result<Response, CryptoError> {
+    let url = Url::parse(url).map_err(|e| CryptoError::InvalidInput(format!("Bad URL in recorded response: {}", e)))?;
+    let mut builder = http::Response::builder().status(status).url(url);
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    builder
+        .body(body)
+        .map(Response::from)
+        .map_err(|e| CryptoError::InvalidInput(format!("Bad recorded response: {}", e)))
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Tape {
+    interactions: Vec<Interaction>,
+}
+
+// VCR-style capture of provider HTTP traffic. Recording passes requests through and keeps
+// every response; replaying answers each request from the cassette without touching the
+// network, matching on method, URL and body and handing out repeated requests in recorded order.
+// API keys are stored and matched as REDACTED, so cassettes can be shared.
+pub struct Cassette {
+    path: PathBuf,
+    replaying: bool,
+    tape: Mutex<Tape>,
+    secrets: Vec<String>,
+}
+
+impl Cassette {
+    pub fn record(path: &str, secrets: Vec<String>) -> Self {
+        Cassette {
+            path: PathBuf::from(path),
+            replaying: false,
+            tape: Mutex::new(Tape::default()),
+            secrets,
+        }
+    }
+
+    pub fn replay(path: &str, secrets: Vec<String>) -> Result<Self, CryptoError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| CryptoError::ConfigError(format!("Failed to read cassette {}: {}", path, e)))?;
+        let tape = serde_json::from_str(&contents)
+            .map_err(|e| CryptoError::ConfigError(format!("Failed to parse cassette {}: {}", path, e)))?;
+        Ok(Cassette {
+            path: PathBuf::from(path),
+            replaying: true,
+            tape: Mutex::new(tape),
+            secrets,
+        })
+    }
+
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, CryptoError> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let method = request.method().to_string();
+        let url = redact(&self.secrets, request.url().as_str());
+        let request_body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| redact(&self.secrets, &String::from_utf8_lossy(body)));
+        if self.replaying {
+            let mut tape = self.tape.lock().unwrap();
+            let position = tape
+                .interactions
+                .iter()
+                .position(|i| i.method == method && i.url == url && i.request_body == request_body)
+                .ok_or_else(|| {
+                    CryptoError::InvalidInput(format!(
+                        "Cassette {} has no recorded response for {} {}",
+                        self.path.display(),
+                        method,
+                        url
+                    ))
+                })?;
+            return tape.interactions.remove(position).into_response();
+        }
+
+        let resp = client.execute(request).await?;
+        let status = resp.status().as_u16();
+        let headers = resp
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = resp.text().await?;
+        let interaction = Interaction { method, url, request_body, status, headers, body };
+        self.tape.lock().unwrap().interactions.push(interaction.clone());
+        interaction.into_response()
+    }
+
+    // Writes out what was recorded so far; replay cassettes are left alone
+    pub fn save(&self) -> Result<(), CryptoError> {
+        if self.replaying {
+            return Ok(());
+        }
+        let path = &self.path;
+        let json = serde_json::to_string_pretty(&*self.tape.lock().unwrap())
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to serialize cassette: {}", e)))?;
+        std::fs::write(path, json)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to write cassette {}: {}", path.display(), e)))
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::NaiveDate;
+
+// Rows and most columns of a chart
+const HEIGHT: usize = 10;
+const WIDTH: usize = 60;
+
+// Series over the same `dates` on one ASCII grid, each drawn with its own mark and `#` where
+// two meet, then a legend. Longer histories are sampled down to WIDTH columns, first and
+// last date kept.
+pub fn draw(dates: &[NaiveDate], series: &[(&str, char, &[f64])]) -> Vec<String> {
+    let width = dates.len().min(WIDTH);
+    let sample = |column: usize| if width == 1 { 0 } else { column * (dates.len() - 1) / (width - 1) };
+    let (low, high) = series.iter().flat_map(|(_, _, values)| values.iter()).fold((f64::MAX, f64::MIN), |(low, high), &v| (low.min(v), high.max(v)));
+    let row = |value: f64| match high > low {
+        true => ((high - value) / (high - low) * (HEIGHT - 1) as f64).round() as usize,
+        false => HEIGHT / 2,
+    };
+    let marks: Vec<Vec<usize>> = (0..width).map(|column| series.iter().map(|(_, _, values)| row(values[sample(column)])).collect()).collect();
+    let label = |value: f64| format!("{:>7.1}", value);
+    let mut lines: Vec<String> = (0..HEIGHT)
+        .map(|i| {
+            let axis = match i {
+                0 => label(high),
+                i if i == HEIGHT - 1 => label(low),
+                _ => " ".repeat(7),
+            };
+            let cells: String = marks
+                .iter()
+                .map(|rows| {
+                    let mut here = series.iter().zip(rows).filter(|(_, row)| **row == i).map(|((_, mark, _), _)| *mark);
+                    match (here.next(), here.next()) {
+                        (None, _) => ' ',
+                        (Some(mark), None) => mark,
+                        (Some(_), Some(_)) => '#',
+                    }
+                })
+                .collect();
+            format!("{} |{}", axis, cells).trim_end().to_string()
+        })
+        .collect();
+    lines.push(format!("{} +{}", " ".repeat(7), "-".repeat(width)));
+    let (first, last) = (dates[0].to_string(), dates[dates.len() - 1].to_string());
+    let gap = (width + 1).saturating_sub(first.len() + last.len()).max(1);
+    lines.push(format!("{}  {}{}{}", " ".repeat(7), first, " ".repeat(gap), last));
+    let legend: Vec<String> = series.iter().map(|(name, mark, _)| format!("{} {}", mark, name)).collect();
+    lines.push(format!("{}  {}", " ".repeat(7), legend.join("  ")));
+    lines
+}
@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::config::CoinListConfig;
+use crate::{CryptoError, suggest};
+use crate::providers::CoinListing;
+
+// The price provider's list of every coin, saved to disk and downloaded again once
+// `refresh_hours` old. Coin arguments are checked against it and symbols it alone knows are
+// resolved through it; without a saved copy (and nowhere to keep one) coins go to the
+// providers as given.
+pub struct CoinList {
+    path: Option<PathBuf>,
+    max_age: Duration,
+}
+
+impl CoinList {
+    pub fn new(config: &CoinListConfig, cache_path: Option<PathBuf>) -> Self {
+        let path = match &config.path {
+            Some(path) => Some(PathBuf::from(path)),
+            None => cache_path.and_then(|path| Some(path.parent()?.join("coin-list.json"))),
+        };
+        CoinList { path, max_age: Duration::from_secs(config.refresh_hours * 3600) }
+    }
+
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    // The saved copy whatever its age, or None when there isn't a readable one
+    pub fn saved(&self) -> Option<Vec<CoinListing>> {
+        let body = std::fs::read_to_string(self.path.as_ref()?).ok()?;
+        serde_json::from_str(&body).ok()
+    }
+
+    // When the saved copy was written
+    pub fn saved_at(&self) -> Option<SystemTime> {
+        std::fs::metadata(self.path.as_ref()?).and_then(|m| m.modified()).ok()
+    }
+
+    // Whether the next lookup downloads the list first
+    pub fn is_stale(&self) -> bool {
+        let age = self.saved_at().map(|saved| SystemTime::now().duration_since(saved).unwrap_or_default());
+        self.path.is_some() && age.is_none_or(|age| age >= self.max_age || self.saved().is_none())
+    }
+
+    pub fn save(&self, listings: &[CoinListing]) -> Result<(), CryptoError> {
+        let Some(path) = &self.path else {
+            return Err(CryptoError::ConfigError(
+                "Nowhere to keep the coin list; set path under [coin_list] or turn on the persistent cache".to_string(),
+            ));
+        };
+        let json = serde_json::to_string(listings).map_err(|e| CryptoError::RuntimeError(e.to_string()))?;
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path, json))
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
+
+// The coin id `coin` names in `listings`: an id as is, else the one coin with that symbol, else
+// the one with that name
+pub fn resolve(listings: &[CoinListing], coin: &str) -> Result<String, CryptoError> {
+    let coin = coin.to_lowercase();
+    if listings.iter().any(|listing| listing.id == coin) {
+        return Ok(coin);
+    }
+    for matches in [
+        listings.iter().filter(|listing| listing.symbol.to_lowercase() == coin).collect::<Vec<_>>(),
+        listings.iter().filter(|listing| listing.name.to_lowercase() == coin).collect(),
+    ] {
+        match matches.as_slice() {
+            [] => continue,
+            [listing] => return Ok(listing.id.clone()),
+            several => {
+                let ids: Vec<&str> = several.iter().take(5).map(|listing| listing.id.as_str()).collect();
+                let more = if several.len() > ids.len() { format!(" and {} more", several.len() - ids.len()) } else { String::new() };
+                return Err(CryptoError::InvalidInput(format!(
+                    "'{}' could be any of {}{}; give the coin id",
+                    coin,
+                    ids.join(", "),
+                    more
+                )));
+            }
+        }
+    }
+    let names = listings.iter().flat_map(|listing| [listing.id.as_str(), listing.symbol.as_str()]);
+    Err(CryptoError::InvalidInput(format!(
+        "Unknown coin '{}': not an id, symbol or name in the coin list{}",
+        coin,
+        suggest::did_you_mean(&suggest::nearest(&coin, names))
+    )))
+}
+
+// Coins whose id, symbol or name contains `query`, exact symbol matches first
+pub fn search<'a>(listings: &'a [CoinListing], query: &str) -> Vec<&'a CoinListing> {
+    let query = query.to_lowercase();
+    let mut found: Vec<&CoinListing> = listings
+        .iter()
+        .filter(|listing| [&listing.id, &listing.symbol, &listing.name].iter().any(|field| field.to_lowercase().contains(&query)))
+        .collect();
+    found.sort_by_key(|listing| (listing.symbol.to_lowercase() != query, listing.id.len()));
+    found
+}
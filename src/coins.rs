@@ -0,0 +1,224 @@
+use crate::cache::Cache;
+
+// Fiat codes accepted by CoinGecko's vs_currencies (ISO 4217 lowercase)
+pub const FIAT_CODES: &[&str] = &[
+    "aed", "ars", "aud", "bdt", "bhd", "bmd", "brl", "cad", "chf", "clp", "cny", "czk", "dkk", "eur", "gbp",
+    "gel", "hkd", "huf", "idr", "ils", "inr", "jpy", "krw", "kwd", "lkr", "mmk", "mxn", "myr", "ngn", "nok",
+    "nzd", "php", "pkr", "pln", "rub", "sar", "sek", "sgd", "thb", "try", "twd", "uah", "usd", "vef", "vnd",
+    "zar",
+];
+
+// A fiat or one of the metals `--vs` takes; any other quote is taken for a coin
+pub fn is_currency(code: &str) -> bool {
+    FIAT_CODES.contains(&code) || matches!(code, "xau" | "xag")
+}
+
+const DEFAULT_COINS: &[&str] = &["bitcoin", "ethereum", "litecoin", "dogecoin", "bitcoin-cash"];
+
+// Coin ids the user has looked up before (from the cache), plus a few defaults
+pub fn known_coins(cache: &Cache) -> Vec<String> {
+    let mut coins: Vec<String> = DEFAULT_COINS.iter().map(|c| c.to_string()).collect();
+    coins.extend(
+        cache
+            .list()
+            .into_iter()
+            .filter(|(key, _)| key.starts_with("price:") || key.starts_with("balance:"))
+            .filter_map(|(key, _)| Cache::coin_of(&key).map(str::to_string)),
+    );
+    coins.sort();
+    coins.dedup();
+    coins
+}
+
+// Common ticker symbols -> CoinGecko ids, so `price btc` works
+const SYMBOLS: &[(&str, &str)] = &[
+    ("btc", "bitcoin"),
+    ("eth", "ethereum"),
+    ("ltc", "litecoin"),
+    ("doge", "dogecoin"),
+    ("bch", "bitcoin-cash"),
+    ("dash", "dash"),
+    ("sol", "solana"),
+    ("ada", "cardano"),
+    ("xrp", "ripple"),
+    ("bnb", "binancecoin"),
+    ("avax", "avalanche-2"),
+    ("dot", "polkadot"),
+    ("link", "chainlink"),
+    ("usdt", "tether"),
+    ("usdc", "usd-coin"),
+    ("tusd", "true-usd"),
+    ("pyusd", "paypal-usd"),
+    ("fdusd", "first-digital-usd"),
+    ("eurc", "euro-coin"),
+    ("eurs", "stasis-eurs"),
+];
+
+// Stablecoins -> the fiat each is pegged 1:1 to
+const PEGS: &[(&str, &str)] = &[
+    ("tether", "usd"),
+    ("usd-coin", "usd"),
+    ("dai", "usd"),
+    ("true-usd", "usd"),
+    ("paypal-usd", "usd"),
+    ("first-digital-usd", "usd"),
+    ("euro-coin", "eur"),
+    ("stasis-eurs", "eur"),
+];
+
+// What `peg` checks when given no coins
+pub const DEFAULT_PEGGED: &[&str] = &["tether", "usd-coin", "dai"];
+
+pub fn peg_fiat(coin: &str) -> Option<&'static str> {
+    PEGS.iter().find(|(id, _)| *id == coin).map(|(_, fiat)| *fiat)
+}
+
+// Every ticker and id in the table above, to suggest from when a coin isn't found
+pub fn symbol_names<'a>() -> impl Iterator<Item = &'a str> {
+    SYMBOLS.iter().flat_map(|(symbol, id)| [*symbol, *id])
+}
+
+pub fn resolve_symbol(coin: &str) -> String {
+    let coin = coin.to_lowercase();
+    SYMBOLS
+        .iter()
+        .find(|(symbol, _)| *symbol == coin)
+        .map(|(_, id)| id.to_string())
+        .unwrap_or(coin)
+}
+
+// The other way: CoinGecko id -> uppercase ticker, as exchanges name markets
+pub fn ticker(coin: &str) -> String {
+    let coin = resolve_symbol(coin);
+    SYMBOLS
+        .iter()
+        .find(|(_, id)| *id == coin)
+        .map_or(coin.clone(), |(symbol, _)| symbol.to_string())
+        .to_uppercase()
+}
+
+// Best-effort chain detection from the address prefix
+pub fn infer_chain(address: &str) -> Option<&'static str> {
+    let lower = address.to_lowercase();
+    if lower.starts_with("bc1") || address.starts_with('1') || address.starts_with('3') {
+        Some("bitcoin")
+    } else if lower.starts_with("0x") {
+        Some("ethereum")
+    } else if lower.starts_with("ltc1") || address.starts_with('L') || address.starts_with('M') {
+        Some("litecoin")
+    } else if address.starts_with('D') {
+        Some("dogecoin")
+    } else {
+        None
+    }
+}
+
+// Pay-to-taproot: witness version 1 on bitcoin's mainnet, testnet or regtest
+pub fn is_taproot(address: &str) -> bool {
+    let lower = address.to_lowercase();
+    ["bc1p", "tb1p", "bcrt1p"].iter().any(|prefix| lower.starts_with(prefix))
+}
+
+const BECH32_CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+// What a bech32m checksum leaves the polymod at (BIP 350); plain bech32 leaves 1
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ *value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+// A taproot address with a bech32m checksum and a 32-byte witness program. Taproot addresses
+// checksummed the old bech32 way (as BIP 173 wallets made them) send to an unspendable output.
+pub fn is_valid_taproot(address: &str) -> bool {
+    if address.contains(|c: char| c.is_ascii_uppercase()) && address.contains(|c: char| c.is_ascii_lowercase()) {
+        return false;
+    }
+    let address = address.to_lowercase();
+    let Some((hrp, data)) = address.rsplit_once('1') else {
+        return false;
+    };
+    let Some(data) = data.chars().map(|c| BECH32_CHARSET.find(c).map(|i| i as u8)).collect::<Option<Vec<u8>>>() else {
+        return false;
+    };
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend(&data);
+    // The version, 52 five-bit groups for the 32 bytes and 6 of checksum
+    data.len() == 59 && data[0] == 1 && bech32_polymod(&values) == BECH32M_CONST
+}
+
+// Blocks between the halvings of the chains whose rewards halve, and their first block reward
+const HALVINGS: &[(&str, u64, f64)] = &[("bitcoin", 210_000, 50.0), ("bitcoin-cash", 210_000, 50.0), ("litecoin", 840_000, 50.0)];
+
+pub fn halving_schedule(coin: &str) -> Option<(u64, f64)> {
+    HALVINGS.iter().find(|(id, ..)| *id == coin).map(|(_, interval, reward)| (*interval, *reward))
+}
+
+// Denominations of each coin, as powers of ten of its smallest unit; the ticker is the coin itself
+const UNITS: &[(&str, &str, u32)] = &[
+    ("BTC", "bitcoin", 8),
+    ("mBTC", "bitcoin", 5),
+    ("bits", "bitcoin", 2),
+    ("sat", "bitcoin", 0),
+    ("ETH", "ethereum", 18),
+    ("gwei", "ethereum", 9),
+    ("wei", "ethereum", 0),
+    ("LTC", "litecoin", 8),
+    ("litoshi", "litecoin", 0),
+    ("DOGE", "dogecoin", 8),
+    ("koinu", "dogecoin", 0),
+    ("BCH", "bitcoin-cash", 8),
+    ("DASH", "dash", 8),
+    ("duff", "dash", 0),
+    ("SOL", "solana", 9),
+    ("lamport", "solana", 0),
+    ("ADA", "cardano", 6),
+    ("lovelace", "cardano", 0),
+    ("XRP", "ripple", 6),
+    ("drop", "ripple", 0),
+    ("DOT", "polkadot", 10),
+    ("planck", "polkadot", 0),
+];
+
+// A unit's name as written above, its coin, and its power of ten. Plurals ("sats") and coin
+// ids ("bitcoin") work too.
+pub fn unit(name: &str) -> Option<(&'static str, &'static str, u32)> {
+    let find = |name: &str| UNITS.iter().find(|(unit, ..)| unit.eq_ignore_ascii_case(name)).copied();
+    find(name).or_else(|| name.strip_suffix('s').and_then(find)).or_else(|| find(&ticker(name)))
+}
+
+// EIP-155 chain ids of the EVM chains, by the CoinGecko id of their gas coin, as token lists
+// key tokens by chain id
+const CHAIN_IDS: &[(&str, u64)] = &[
+    ("ethereum", 1),
+    ("binancecoin", 56),
+    ("matic-network", 137),
+    ("polygon-ecosystem-token", 137),
+    ("avalanche-2", 43114),
+    ("fantom", 250),
+    ("xdai", 100),
+    ("celo", 42220),
+    ("moonbeam", 1284),
+    ("crypto-com-chain", 25),
+];
+
+pub fn chain_id(coin: &str) -> Option<u64> {
+    CHAIN_IDS.iter().find(|(id, _)| *id == coin).map(|(_, chain_id)| *chain_id)
+}
+
+// Whether `token` names `chain`'s own gas coin, by id or ticker symbol (ethereum, ETH)
+pub fn is_gas_coin(chain: &str, token: &str) -> bool {
+    token.eq_ignore_ascii_case(chain) || resolve_symbol(token) == chain
+}
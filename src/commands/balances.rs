@@ -0,0 +1,183 @@
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::time::SystemTime;
+
+use crate::cache::{Cache, Validators};
+use crate::providers::{AsOf, BalanceProvider, Token};
+use crate::{CryptoError, CryptoTool, Lookup, descriptor, tidy, timezone};
+use super::wallet::WALLET_CONCURRENCY;
+
+// Unused addresses in a row that end the scan of a descriptor's branch, as BIP 44 wallets stop
+pub(crate) const GAP_LIMIT: u32 = 20;
+
+impl CryptoTool {
+    pub async fn get_address_balance(&self, crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError> {
+        Ok(self.lookup_balance(crypto, address, verbose).await?.value)
+    }
+
+    pub async fn lookup_balance(&self, crypto: &str, address: &str, verbose: bool) -> Result<Lookup, CryptoError> {
+        self.check_address(crypto, address)?;
+        // Balances are only read back from the cache offline; online lookups always hit the provider
+        let key = Cache::balance_key(crypto, address);
+        if self.offline {
+            return self.read_offline_cache(&key, &format!("balance for {}", address));
+        }
+        let fetch = async {
+            let (balance, source) = self.fetch_address_balance(crypto, address, verbose).await?;
+            Ok(Lookup::fetched(self.cache.insert(key.clone(), balance, &source, Validators::default())))
+        };
+        self.in_flight.run(&key, fetch).await
+    }
+
+    async fn fetch_address_balance(&self, crypto: &str, address: &str, verbose: bool) -> Result<(f64, String), CryptoError> {
+        let client = self.client_for_address(address)?;
+
+        let providers: Vec<&dyn BalanceProvider> =
+            self.providers.balance.iter().filter(|p| p.supports_address(crypto, address)).map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No balance provider supports {}", crypto));
+        let (balance, provider) =
+            self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.balance(&client, crypto, address, verbose)).await?;
+        Ok((balance, provider.name().to_string()))
+    }
+
+    pub async fn get_token_balance(&self, crypto: &str, address: &str, token: &str, verbose: bool) -> Result<(f64, Token), CryptoError> {
+        self.check_address(crypto, address)?;
+        let token = self.tokens.resolve(&self.client, crypto, self.chain_id(crypto)?, token, verbose).await?;
+        let client = self.client_for_address(address)?;
+        let providers: Vec<&dyn BalanceProvider> =
+            self.providers.balance.iter().filter(|p| p.supports_tokens(crypto)).map(|p| p.as_ref()).collect();
+        let none = || {
+            CryptoError::InvalidInput(format!(
+                "No provider reads token balances on {}; configure an [rpc] endpoint or a Covalent key",
+                crypto
+            ))
+        };
+        let (balance, _) =
+            self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.token_balance(&client, crypto, address, &token, verbose)).await?;
+        Ok((balance, token))
+    }
+
+    // `address-balance`'s coin and address, either of which may be an @name
+    pub fn balance_target(&self, crypto: &str, address: Option<&str>) -> Result<(String, String), CryptoError> {
+        match (crypto.starts_with('@'), address) {
+            (true, None) => {
+                let entry = self.address_book()?.get(crypto)?.clone();
+                Ok((entry.chain, entry.address))
+            }
+            (false, Some(name)) if name.starts_with('@') => {
+                let entry = self.address_book()?.get(name)?.clone();
+                if entry.chain != crypto {
+                    return Err(CryptoError::InvalidInput(format!("{} is a {} address, not {}", name, entry.chain, crypto)));
+                }
+                Ok((entry.chain, entry.address))
+            }
+            (false, Some(address)) => Ok((crypto.to_string(), descriptor_address(address)?)),
+            (true, Some(_)) => Err(CryptoError::InvalidInput(format!("{} names the coin too; drop the address after it", crypto))),
+            (false, None) => Err(CryptoError::InvalidInput("address-balance needs an address after the coin, or an @name".to_string())),
+        }
+    }
+
+    // Read straight from the first provider with the chain's past state; never cached
+    pub async fn past_balance(&self, crypto: &str, address: &str, at: &AsOf, verbose: bool) -> Result<Lookup, CryptoError> {
+        self.check_address(crypto, address)?;
+        let client = self.client_for_address(address)?;
+        let providers: Vec<_> = self.providers.past.iter().filter(|p| p.supports(crypto)).map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No provider reads past balances of {} addresses", crypto));
+        let (balance, provider) =
+            self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.balance_at(&client, crypto, address, at, verbose)).await?;
+        if verbose {
+            eprintln!("Fetched balance as of {} from {}: {}", at, provider.name(), balance);
+        }
+        Ok(Lookup { value: balance, source: provider.name().to_string(), fetched_at: SystemTime::now(), cached: false })
+    }
+
+    // The used addresses of a descriptor that `descriptor_address` left to scan: each branch's
+    // from index 0, until GAP_LIMIT in a row have no transactions. Without /* it's just each
+    // branch's address.
+    pub async fn descriptor_addresses(&self, crypto: &str, text: &str, verbose: bool) -> Result<Vec<String>, CryptoError> {
+        if crypto != "bitcoin" {
+            return Err(CryptoError::InvalidInput(format!("Descriptors are only derived for bitcoin, not {}", crypto)));
+        }
+        let descriptor = descriptor::Descriptor::parse(text)?;
+        if !descriptor.is_ranged() {
+            return (0..descriptor.branches()).map(|branch| descriptor.address(branch, 0)).collect();
+        }
+        if self.offline {
+            return Err(CryptoError::InvalidInput("Scanning a descriptor's addresses needs the network; drop --offline".to_string()));
+        }
+        let (mut used, mut scanned) = (Vec::new(), 0);
+        for branch in 0..descriptor.branches() {
+            let (mut start, mut unused) = (0, 0);
+            while unused < GAP_LIMIT {
+                let addresses = (start..start + GAP_LIMIT).map(|index| descriptor.address(branch, index)).collect::<Result<Vec<_>, _>>()?;
+                let seen: Vec<bool> = stream::iter(addresses.clone())
+                    .map(async |address| Ok::<_, CryptoError>(!self.address_history(crypto, &address, verbose).await?.0.transactions.is_empty()))
+                    .buffered(WALLET_CONCURRENCY)
+                    .try_collect()
+                    .await?;
+                for (address, seen) in addresses.into_iter().zip(seen) {
+                    match seen {
+                        true => {
+                            used.push(address);
+                            unused = 0;
+                        }
+                        false => unused += 1,
+                    }
+                }
+                start += GAP_LIMIT;
+                scanned += GAP_LIMIT;
+            }
+        }
+        if verbose {
+            eprintln!("Scanned {} addresses of {}: {} used", scanned, text, used.len());
+        }
+        Ok(used)
+    }
+
+    // A descriptor's used addresses' balances added up, as one lookup
+    pub async fn descriptor_balance(&self, crypto: &str, text: &str, verbose: bool) -> Result<Lookup, CryptoError> {
+        let addresses = self.descriptor_addresses(crypto, text, verbose).await?;
+        let balances: Vec<Lookup> =
+            stream::iter(addresses).map(async |address| self.lookup_balance(crypto, &address, verbose).await).buffered(WALLET_CONCURRENCY).try_collect().await?;
+        let mut sources: Vec<&str> = balances.iter().map(|lookup| lookup.source.as_str()).collect();
+        sources.dedup();
+        Ok(Lookup {
+            value: tidy(balances.iter().map(|lookup| lookup.value).sum()),
+            source: if sources.is_empty() { "none".to_string() } else { sources.join(", ") },
+            fetched_at: balances.iter().map(|lookup| lookup.fetched_at).min().unwrap_or_else(SystemTime::now),
+            cached: balances.iter().all(|lookup| lookup.cached),
+        })
+    }
+}
+
+// An address given as itself or as an output descriptor, with or without its checksum. A
+// descriptor of one address is derived here; one of a run of addresses (ending in /*, or with
+// <a;b> alternatives) comes back as it is, for `descriptor_addresses` to scan.
+pub fn descriptor_address(address: &str) -> Result<String, CryptoError> {
+    let address = address.trim();
+    if !descriptor::is_descriptor(address) {
+        return Ok(address.to_string());
+    }
+    let descriptor = descriptor::Descriptor::parse(address)?;
+    match descriptor.is_ranged() || descriptor.branches() > 1 {
+        true => Ok(address.to_string()),
+        false => descriptor.address(0, 0),
+    }
+}
+
+// --at YYYY-MM-DD means the end of that day in --timezone, so a year-end balance counts
+// December 31st
+pub fn parse_as_of(at: Option<&str>, height: Option<u64>) -> Result<Option<AsOf>, CryptoError> {
+    match (at, height) {
+        (Some(date), _) => {
+            let date = chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|_| CryptoError::InvalidInput(format!("--at takes a date like 2023-12-31, got '{}'", date)))?;
+            let end = timezone::end_of_day(date);
+            if end > chrono::Utc::now() {
+                return Err(CryptoError::InvalidInput(format!("{} hasn't ended yet", date)));
+            }
+            Ok(Some(AsOf::Time(end)))
+        }
+        (None, Some(height)) => Ok(Some(AsOf::Height(height))),
+        (None, None) => Ok(None),
+    }
+}
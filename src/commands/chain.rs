@@ -0,0 +1,261 @@
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+use crate::providers::{AddressHistory, Block, BlockId, ChainProvider, ChainTip, FeeProvider, Fees, MempoolTx, NetworkStats};
+use crate::{CryptoError, CryptoTool, coins, descriptor, providers, tidy, timezone, utxo};
+use super::balances::descriptor_address;
+use super::wallet::WALLET_CONCURRENCY;
+
+// Blocks between bitcoin's difficulty adjustments
+const DIFFICULTY_PERIOD: u64 = 2016;
+
+// Blocks looked back over for the average block time, as many as a difficulty period
+pub(crate) const BLOCK_TIME_SAMPLE: u64 = DIFFICULTY_PERIOD;
+
+pub struct Halving {
+    crypto: String,
+    tip: ChainTip,
+    interval: u64,
+    first_reward: f64,
+    // Seconds per block over the blocks before the tip
+    average: f64,
+    window: u64,
+    source: String,
+}
+
+impl CryptoTool {
+    // The address's latest transactions from the first provider that lists them, and its name
+    pub async fn address_history(&self, crypto: &str, address: &str, verbose: bool) -> Result<(AddressHistory, &str), CryptoError> {
+        self.check_address(crypto, address)?;
+        let client = self.client_for_address(address)?;
+        let providers: Vec<_> = self.providers.history.iter().filter(|p| p.supports(crypto)).map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No provider lists the transactions of {} addresses", crypto));
+        let (history, provider) =
+            self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.history(&client, crypto, address, verbose)).await?;
+        Ok((history, provider.name()))
+    }
+
+    // The first answer from the providers that read `crypto`'s blocks
+    async fn chain_answer<'p, T, F: Future<Output = Result<T, CryptoError>>>(
+        &'p self,
+        crypto: &str,
+        verbose: bool,
+        fetch: impl Fn(&'p dyn ChainProvider) -> F,
+    ) -> Result<(T, &'p dyn ChainProvider), CryptoError> {
+        let providers: Vec<_> = self.providers.chain.iter().filter(|p| p.supports(crypto)).map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No provider reads {} blocks", crypto));
+        self.first_answer(&providers, |p| p.name(), verbose, none, fetch).await
+    }
+
+    pub async fn chain_tip(&self, crypto: &str, verbose: bool) -> Result<String, CryptoError> {
+        let (tip, provider) = self.chain_answer(crypto, verbose, |p| p.tip(&self.client, crypto, verbose)).await?;
+        Ok(render_tip(crypto, &tip, provider.name()))
+    }
+
+    pub async fn fees(&self, crypto: &str, verbose: bool) -> Result<(Fees, String), CryptoError> {
+        let providers: Vec<&dyn FeeProvider> =
+            self.providers.fees.iter().filter(|p| p.supports(crypto)).map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No fee provider supports {}", crypto));
+        let (fees, provider) = self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.fees(&self.client, crypto, verbose)).await?;
+        Ok((fees, provider.name().to_string()))
+    }
+
+    pub async fn network_stats(&self, crypto: &str, verbose: bool) -> Result<String, CryptoError> {
+        let (stats, provider) = self.chain_answer(crypto, verbose, |p| p.network(&self.client, crypto, verbose)).await?;
+        Ok(render_network(crypto, &stats, provider.name()))
+    }
+
+    pub async fn chain_block(&self, crypto: &str, block: &str, verbose: bool) -> Result<String, CryptoError> {
+        let block = parse_block_id(block)?;
+        let (block, provider) = self.chain_answer(crypto, verbose, |p| p.block(&self.client, crypto, &block, verbose)).await?;
+        Ok(render_block(crypto, &block, provider.name()))
+    }
+
+    pub async fn halving(&self, crypto: &str, verbose: bool) -> Result<Halving, CryptoError> {
+        let (interval, first_reward) = coins::halving_schedule(crypto)
+            .ok_or_else(|| CryptoError::InvalidInput(format!("{} has no halving schedule; try bitcoin, bitcoin-cash or litecoin", crypto)))?;
+        // Both blocks from one provider, so the times are measured on the same chain view
+        let ((tip, earlier, window), provider) = self
+            .chain_answer(crypto, verbose, |p| async move {
+                let tip = p.tip(&self.client, crypto, verbose).await?;
+                let window = BLOCK_TIME_SAMPLE.min(tip.height);
+                let earlier = p.block(&self.client, crypto, &BlockId::Height(tip.height - window), verbose).await?;
+                Ok((tip, earlier, window))
+            })
+            .await?;
+        if window == 0 {
+            return Err(CryptoError::InvalidInput(format!("{} has no blocks to average", crypto)));
+        }
+        let average = (tip.time - earlier.time).num_seconds() as f64 / window as f64;
+        Ok(Halving { crypto: crypto.to_string(), tip, interval, first_reward, average, window, source: provider.name().to_string() })
+    }
+
+    // The transactions that just reached the chain's mempool, from the first provider that lists them
+    pub async fn recent_transactions(&self, crypto: &str, verbose: bool) -> Result<(Vec<MempoolTx>, &str), CryptoError> {
+        let providers: Vec<_> = self.providers.history.iter().filter(|p| p.supports(crypto)).map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No provider lists {} mempool transactions", crypto));
+        let (recent, provider) = self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.recent(&self.client, crypto, verbose)).await?;
+        Ok((recent, provider.name()))
+    }
+
+    // The coin and address of a utxo-report; a descriptor or account key comes back as it is,
+    // for `descriptor_addresses` to scan
+    pub fn utxo_target(&self, target: &str) -> Result<(String, String), CryptoError> {
+        let (crypto, address) = match target.starts_with('@') {
+            true => self.balance_target(target, None)?,
+            false => ("bitcoin".to_string(), descriptor_address(target)?),
+        };
+        if !descriptor::is_descriptor(&address) {
+            self.check_address(&crypto, &address)?;
+        }
+        Ok((crypto, address))
+    }
+
+    pub async fn utxo_report(&self, target: &str, fee_rate: Option<f64>, verbose: bool) -> Result<String, CryptoError> {
+        if let Some(rate) = fee_rate
+            && !(rate.is_finite() && rate > 0.0)
+        {
+            return Err(CryptoError::InvalidInput(format!("{} isn't a fee rate; give sat/vB above 0", rate)));
+        }
+        let (crypto, target) = self.utxo_target(target)?;
+        // One provider for the whole scan, deliberately: the addresses' UTXOs and the tip their
+        // confirmations count from then come from the same view of the chain
+        let provider = self.providers.utxo.iter().find(|p| p.supports(&crypto) && self.breakers.allow(p.name())).ok_or_else(|| {
+            CryptoError::InvalidInput(format!("No provider lists the UTXOs of {} addresses", crypto))
+        })?;
+        let addresses = match descriptor::is_descriptor(&target) {
+            true => self.descriptor_addresses(&crypto, &target, verbose).await?,
+            false => vec![target.clone()],
+        };
+        let first = addresses.first().cloned().unwrap_or_default();
+        let sets: Vec<providers::UtxoSet> = stream::iter(addresses)
+            .map(async |address| {
+                let result = provider.utxos(&self.client_for_address(&address)?, &crypto, &address, verbose).await;
+                self.breakers.record(provider.name(), result.as_ref().err());
+                result
+            })
+            .buffered(WALLET_CONCURRENCY)
+            .try_collect()
+            .await?;
+        // A descriptor's addresses count as one pile of outputs, spent together
+        let Some(set) = sets.into_iter().reduce(|mut all, set| {
+            all.utxos.extend(set.utxos);
+            all.total += set.total;
+            all.tip = all.tip.max(set.tip);
+            all
+        }) else {
+            return Ok(format!("UTXOs of {}: none of its addresses has been used", target));
+        };
+        Ok(utxo::render(&target, &first, &set, fee_rate.unwrap_or(set.fee_rate), provider.name()))
+    }
+}
+
+// A block height, or a hash as 64 hex digits
+pub fn parse_block_id(block: &str) -> Result<BlockId, CryptoError> {
+    let block = block.trim();
+    if let Ok(height) = block.parse::<u64>() {
+        return Ok(BlockId::Height(height));
+    }
+    match block.len() == 64 && block.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Ok(BlockId::Hash(block.to_lowercase())),
+        false => Err(CryptoError::InvalidInput(format!("'{}' is neither a block height nor a block hash", block))),
+    }
+}
+
+fn render_tip(crypto: &str, tip: &ChainTip, source: &str) -> String {
+    [
+        format!("Tip of {} ({}):", crypto, source),
+        format!("  Height: {}", tip.height),
+        format!("  Hash: {}", tip.hash),
+        format!("  Time: {}", timezone::iso(tip.time)),
+    ]
+    .join("\n")
+}
+
+fn render_block(crypto: &str, block: &Block, source: &str) -> String {
+    [
+        format!("Block {} of {} ({}):", block.height, crypto, source),
+        format!("  Hash: {}", block.hash),
+        format!("  Time: {}", timezone::iso(block.time)),
+        format!("  Size: {} bytes", block.size),
+        format!("  Transactions: {}", block.transactions),
+        format!("  Fees: {} {}", tidy(block.fees), crypto.to_uppercase()),
+    ]
+    .join("\n")
+}
+
+// `value` scaled to a metric prefix, e.g. 612.35 EH/s for 6.1235e20 and "H/s"
+fn metric(value: f64, unit: &str) -> String {
+    const PREFIXES: &[&str] = &["", "K", "M", "G", "T", "P", "E", "Z", "Y"];
+    let mut scaled = value;
+    let mut prefix = 0;
+    while scaled.abs() >= 1000.0 && prefix < PREFIXES.len() - 1 {
+        scaled /= 1000.0;
+        prefix += 1;
+    }
+    format!("{:.2} {}{}", scaled, PREFIXES[prefix], unit).trim_end().to_string()
+}
+
+fn render_network(crypto: &str, stats: &NetworkStats, source: &str) -> String {
+    let mut lines = vec![
+        format!("{} network at block {} ({}):", crypto, stats.height, source),
+        format!("  Difficulty: {}", metric(stats.difficulty, "")),
+        format!("  Hashrate (24h): {}", metric(stats.hashrate, "H/s")),
+    ];
+    if let Some(next) = stats.next_difficulty {
+        let mut line = format!("  Next adjustment: {:+.2}% to {}", (next / stats.difficulty - 1.0) * 100.0, metric(next, ""));
+        if let Some(time) = stats.next_retarget {
+            line.push_str(&format!(" around {}", timezone::iso(time)));
+        }
+        // Bitcoin's period
+        if crypto == "bitcoin" {
+            let left = DIFFICULTY_PERIOD - stats.height % DIFFICULTY_PERIOD;
+            line.push_str(&format!(", in {} blocks", left));
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+impl Halving {
+    pub fn render(&self) -> String {
+        let next = (self.tip.height / self.interval + 1) * self.interval;
+        let remaining = next - self.tip.height;
+        let reward = self.first_reward / 2f64.powi((next / self.interval) as i32 - 1);
+        let eta = self.tip.time + chrono::Duration::seconds((remaining as f64 * self.average).round() as i64);
+        let days = (eta - self.tip.time).num_days();
+        [
+            format!("Next {} halving ({}):", self.crypto, self.source),
+            format!("  At block {}, {} blocks after the tip at {}", next, remaining, self.tip.height),
+            format!("  Block reward: {} to {} {}", tidy(reward), tidy(reward / 2.0), self.crypto.to_uppercase()),
+            format!("  Average block time over the last {} blocks: {:.1} minutes", self.window, self.average / 60.0),
+            format!("  Estimated: {} (in about {} days)", timezone::iso(eta), days),
+        ]
+        .join("\n")
+    }
+}
+
+// Each tier on a line, with its confirmation target where the provider gives one
+pub fn render_fees(crypto: &str, fees: &Fees, source: &str) -> String {
+    let mut lines = vec![format!("Fees on {} ({}):", crypto, source)];
+    for tier in &fees.tiers {
+        let mut line = format!("  {}: {} {}", tier.name, tier.rate, fees.unit);
+        match tier.blocks {
+            Some(1) => line.push_str(" (next block)"),
+            Some(blocks) => line.push_str(&format!(" (within {} blocks)", blocks)),
+            None => {}
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+// `fees --json`: the same shape on every chain, with rates in `unit`
+pub fn fees_json(crypto: &str, fees: &Fees, source: &str) -> Result<String, CryptoError> {
+    let json = serde_json::json!({
+        "chain": crypto,
+        "unit": fees.unit,
+        "tiers": fees.tiers,
+        "source": source,
+    });
+    serde_json::to_string_pretty(&json).map_err(|e| CryptoError::RuntimeError(e.to_string()))
+}
@@ -0,0 +1,86 @@
+use crate::providers::CoinListing;
+use crate::{CryptoError, CryptoTool, coinlist, coins, format_timestamp};
+
+// Most matches `coin-list` prints for a search
+const COIN_SEARCH_LIMIT: usize = 20;
+
+impl CryptoTool {
+    // Downloads the coin list and saves it, returning it and where it came from
+    async fn refresh_coin_list(&self, verbose: bool) -> Result<(Vec<CoinListing>, String), CryptoError> {
+        if verbose {
+            eprintln!("Downloading the coin list");
+        }
+        let (listings, source) = self
+            .market_data("a coin list", |p| p.plan_coin_list(&self.client).is_some(), async |p| p.coin_list(&self.client, verbose).await)
+            .await?;
+        self.coin_list.save(&listings)?;
+        Ok((listings, source))
+    }
+
+    // The coin list, downloaded first when the saved copy is missing or stale. A failed
+    // download falls back on the old copy; None when there's none.
+    async fn coin_listings(&self, verbose: bool) -> Option<&[CoinListing]> {
+        let load = async || {
+            if self.offline || !self.coin_list.is_stale() {
+                return self.coin_list.saved();
+            }
+            match self.refresh_coin_list(verbose).await {
+                Ok((listings, _)) => Some(listings),
+                Err(e) => {
+                    let saved = self.coin_list.saved();
+                    let fallback = if saved.is_some() { "using the saved copy" } else { "coins aren't checked" };
+                    eprintln!("Warning: couldn't refresh the coin list ({}), {}", e, fallback);
+                    saved
+                }
+            }
+        };
+        self.coin_listings.get_or_init(load).await.as_deref()
+    }
+
+    // The coin id `coin` stands for: the built-in tickers first, then the coin list, which
+    // also rejects coins no provider would know. Coins a plugin handles are taken as given.
+    pub async fn coin_id(&self, coin: &str, verbose: bool) -> Result<String, CryptoError> {
+        let resolved = coins::resolve_symbol(coin);
+        if resolved != coin.to_lowercase()
+            || self.providers.price.iter().any(|p| p.plan_coin_list(&self.client).is_none() && p.supports(coin))
+        {
+            return Ok(resolved);
+        }
+        match self.coin_listings(verbose).await {
+            Some(listings) => coinlist::resolve(listings, coin),
+            None => Ok(coin.to_string()),
+        }
+    }
+
+    // `coin-list`: a search of the saved list, or how big and old it is
+    pub async fn coin_list_command(&self, query: Option<&str>, refresh: bool, verbose: bool) -> Result<String, CryptoError> {
+        if refresh {
+            if self.offline {
+                return Err(CryptoError::InvalidInput("coin-list --refresh needs the network; drop --offline".to_string()));
+            }
+            let (listings, source) = self.refresh_coin_list(verbose).await?;
+            return Ok(format!("Saved {} coins from {}", listings.len(), source));
+        }
+        let listings = self.coin_listings(verbose).await.ok_or_else(|| match self.coin_list.path() {
+            Some(path) => CryptoError::InvalidInput(format!("No coin list at {}; run coin-list --refresh", path.display())),
+            None => CryptoError::ConfigError(
+                "Nowhere to keep the coin list; set path under [coin_list] or turn on the persistent cache".to_string(),
+            ),
+        })?;
+        let Some(query) = query else {
+            let path = self.coin_list.path().map_or(String::new(), |path| path.display().to_string());
+            let saved = self.coin_list.saved_at().map_or("at an unknown time".to_string(), format_timestamp);
+            return Ok(format!("{} coins in {}, saved {}", listings.len(), path, saved));
+        };
+        let found = coinlist::search(listings, query);
+        if found.is_empty() {
+            return Ok(format!("No coins match '{}'", query));
+        }
+        let mut lines: Vec<String> =
+            found.iter().take(COIN_SEARCH_LIMIT).map(|listing| format!("{} ({}) {}", listing.id, listing.symbol.to_uppercase(), listing.name)).collect();
+        if found.len() > COIN_SEARCH_LIMIT {
+            lines.push(format!("... and {} more", found.len() - COIN_SEARCH_LIMIT));
+        }
+        Ok(lines.join("\n"))
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{CryptoError, CryptoTool, ExportDataset, alerts, coins, export, query};
+
+impl CryptoTool {
+    pub async fn export(&self, dataset: ExportDataset, format: &str, output: Option<&str>) -> Result<String, CryptoError> {
+        let table = match dataset {
+            ExportDataset::Transactions { exchange } => {
+                let store = self.transaction_store()?;
+                let transactions: Vec<_> = store.all().iter().filter(|t| exchange.as_deref().is_none_or(|name| t.source == name)).cloned().collect();
+                export::Table::transactions(&transactions)
+            }
+            ExportDataset::Snapshots { file } => {
+                let path = file.or_else(|| self.alerts.snapshots.clone()).ok_or_else(|| {
+                    CryptoError::ConfigError("No snapshots file to export; set [alerts] snapshots or pass --file".to_string())
+                })?;
+                export::Table::snapshots(&alerts::snapshots(&path)?)
+            }
+            ExportDataset::History { .. } if self.offline => {
+                return Err(CryptoError::InvalidInput("export history needs the network; drop --offline".to_string()));
+            }
+            ExportDataset::History { crypto, fiat, days, verbose } => {
+                let (crypto, fiat) = (coins::resolve_symbol(&crypto), fiat.to_lowercase());
+                let (closes, source) = self.closes(&crypto, &fiat, days, verbose).await?;
+                export::Table::history(&crypto, &fiat, &closes, &source)
+            }
+        };
+        table.write(format, output)
+    }
+
+    // Tables: transactions, prices from the price log and snapshots from the alerts daemon
+    pub fn query(&self, sql: &str, format: &str) -> Result<String, CryptoError> {
+        let prices = self
+            .price_log
+            .as_ref()
+            .ok_or_else(|| CryptoError::ConfigError("No price log to query; set log_prices or pass --log-prices".to_string()))
+            .and_then(|log| Ok(export::Table::prices(&log.read()?)));
+        let snapshots = self
+            .alerts
+            .snapshots
+            .as_ref()
+            .ok_or_else(|| CryptoError::ConfigError("No snapshots file to query; set [alerts] snapshots".to_string()))
+            .and_then(|path| Ok(export::Table::snapshots(&alerts::snapshots(path)?)));
+        let tables = vec![
+            ("transactions", self.transaction_store().map(|store| export::Table::transactions(store.all()))),
+            ("prices", prices),
+            ("snapshots", snapshots),
+        ];
+        let table = query::run(sql, tables)?;
+        match format {
+            "csv" => Ok(table.csv()),
+            "json" => table.json(),
+            _ => Ok(table.render()),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use futures::stream::{self, StreamExt};
+use std::time::SystemTime;
+
+use crate::providers::ExchangeProvider;
+use crate::{CryptoError, CryptoTool, Lookup, WalletLine, coins, transactions};
+use super::wallet::WALLET_CONCURRENCY;
+
+impl CryptoTool {
+    pub fn exchange(&self, name: &str) -> Result<&dyn ExchangeProvider, CryptoError> {
+        self.providers
+            .exchanges
+            .iter()
+            .find(|e| e.name().eq_ignore_ascii_case(name))
+            .map(|e| e.as_ref())
+            .ok_or_else(|| {
+                CryptoError::InvalidInput(format!(
+                    "No credentials for {}; add an [exchanges.{}] table with a read-only API key",
+                    name,
+                    name.to_lowercase()
+                ))
+            })
+    }
+
+    // An exchange account's holdings valued in `fiat`, as wallet lines with the exchange as
+    // the address. Assets without a price are counted at 0.
+    pub async fn exchange_lines(&self, name: &str, fiat: &str, verbose: bool) -> Result<Vec<WalletLine>, CryptoError> {
+        if self.offline {
+            return Err(CryptoError::InvalidInput(format!("{} balances need the network; drop --offline", name)));
+        }
+        let exchange = self.exchange(name)?;
+        let result = exchange.holdings(&self.client, verbose).await;
+        self.breakers.record(exchange.name(), result.as_ref().err());
+        let now = SystemTime::now();
+        let lines = stream::iter(result?)
+            .map(async |(asset, amount)| {
+                let crypto = coins::resolve_symbol(&asset);
+                let price = self.asset_price(&crypto, fiat, verbose).await.unwrap_or_else(|e| {
+                    eprintln!("Warning: no {} price for {} on {}, counted as 0: {}", fiat, asset, exchange.name(), e);
+                    Lookup { value: 0.0, source: "unpriced".to_string(), fetched_at: now, cached: false }
+                });
+                WalletLine {
+                    crypto,
+                    address: name.to_lowercase(),
+                    name: None,
+                    balance: Lookup { value: amount, source: exchange.name().to_string(), fetched_at: now, cached: false },
+                    price,
+                }
+            })
+            .buffered(WALLET_CONCURRENCY)
+            .collect()
+            .await;
+        Ok(lines)
+    }
+
+    pub fn transaction_store(&self) -> Result<transactions::TransactionStore, CryptoError> {
+        let path = self.transactions_path.as_ref().ok_or_else(|| {
+            CryptoError::ConfigError("No data directory for the transaction store; set [transactions] path".to_string())
+        })?;
+        transactions::TransactionStore::open(path)
+    }
+
+    // Fetches an exchange account's history and stores what's new; importing again is safe
+    pub async fn import_history(&self, name: &str, verbose: bool) -> Result<String, CryptoError> {
+        let mut store = self.transaction_store()?;
+        let exchange = self.exchange(name)?;
+        let result = exchange.history(&self.client, verbose).await;
+        self.breakers.record(exchange.name(), result.as_ref().err());
+        let added = store.add(result?);
+        store.save()?;
+        Ok(format!("Imported {} new transactions from {} ({} stored)", added, exchange.name(), store.all().len()))
+    }
+
+    pub fn list_transactions(&self, exchange: Option<&str>) -> Result<String, CryptoError> {
+        let store = self.transaction_store()?;
+        let lines: Vec<String> = store
+            .all()
+            .iter()
+            .filter(|t| exchange.is_none_or(|name| t.source == name))
+            .map(|t| t.render())
+            .collect();
+        match lines.is_empty() {
+            true => Ok("No transactions imported; run import-history first".to_string()),
+            false => Ok(lines.join("\n")),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use futures::future::try_join;
+use std::collections::HashMap;
+
+use crate::providers::{DailyClose, DailyVolume};
+use crate::{CryptoError, CryptoTool, coins, compare, dca, indicators, returns};
+
+impl CryptoTool {
+    // The last `days` daily closes and today's price so far, oldest first
+    pub async fn closes(&self, crypto: &str, fiat: &str, days: u32, verbose: bool) -> Result<(Vec<DailyClose>, String), CryptoError> {
+        let direct = self
+            .market_data(
+                "price history",
+                |p| p.supports(crypto) && p.plan_closes(&self.client, crypto, fiat, days).is_some(),
+                async |p| p.closes(&self.client, crypto, fiat, days, verbose).await,
+            )
+            .await;
+        match direct {
+            // Priced in a coin no provider pairs `crypto` with: both in USD, divided day by day
+            Err(error) if !coins::is_currency(fiat) => match Box::pin(self.ratio_closes(crypto, fiat, days, verbose)).await {
+                Ok(closes) => Ok(closes),
+                Err(e) => {
+                    if verbose {
+                        eprintln!("Warning: converting via USD failed ({})", e);
+                    }
+                    Err(error)
+                }
+            },
+            direct => direct,
+        }
+    }
+
+    async fn ratio_closes(&self, crypto: &str, quote: &str, days: u32, verbose: bool) -> Result<(Vec<DailyClose>, String), CryptoError> {
+        let quote = coins::resolve_symbol(quote);
+        let ((closes, source), (quotes, quote_source)) = try_join(self.closes(crypto, "usd", days, verbose), self.closes(&quote, "usd", days, verbose)).await?;
+        let quotes: HashMap<_, f64> = quotes.into_iter().filter(|close| close.price > 0.0).map(|close| (close.date, close.price)).collect();
+        let closes: Vec<DailyClose> =
+            closes.into_iter().filter_map(|close| Some(DailyClose { price: close.price / quotes.get(&close.date)?, date: close.date })).collect();
+        if closes.is_empty() {
+            return Err(CryptoError::NotFound { provider: source, what: format!("days with both {} and {} usd prices", crypto, quote) });
+        }
+        let source = if source == quote_source { source } else { format!("{} + {}", source, quote_source) };
+        Ok((closes, source))
+    }
+
+    // The last `days` days' volumes and the 24 hours up to now, oldest first
+    pub async fn volumes(&self, crypto: &str, fiat: &str, days: u32, verbose: bool) -> Result<(Vec<DailyVolume>, String), CryptoError> {
+        self.market_data(
+            "volume history",
+            |p| p.supports(crypto) && p.plan_volumes(&self.client, crypto, fiat, days).is_some(),
+            async |p| p.volumes(&self.client, crypto, fiat, days, verbose).await,
+        )
+        .await
+    }
+
+    pub async fn indicators(&self, crypto: &str, fiat: &str, set: &[String], verbose: bool) -> Result<String, CryptoError> {
+        let set = parse_indicators(set)?;
+        let (closes, source) = self.closes(crypto, fiat, indicator_days(&set), verbose).await?;
+        let latest = &closes[closes.len() - 1];
+        let prices: Vec<f64> = closes.iter().map(|close| close.price).collect();
+        let mut lines = vec![format!(
+            "{} in {}: {} on {} ({} daily closes from {})",
+            crypto,
+            fiat.to_uppercase(),
+            latest.price,
+            latest.date,
+            closes.len(),
+            source
+        )];
+        lines.extend(set.iter().map(|indicator| format!("  {}: {}", indicator, indicators::describe(*indicator, &prices))));
+        let signals = indicators::signals(&set, &prices);
+        match signals.is_empty() {
+            true => lines.push("Signals: none today".to_string()),
+            false => {
+                lines.push("Signals:".to_string());
+                lines.extend(signals.iter().map(|signal| format!("  {}", signal)));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    // Both coins' closes over the last `days`, on the dates they share
+    pub async fn compare(&self, a: &str, b: &str, fiat: &str, days: u32, chart: bool, verbose: bool) -> Result<String, CryptoError> {
+        let (a, b, fiat) = (coins::resolve_symbol(a), coins::resolve_symbol(b), fiat.to_lowercase());
+        if a == b {
+            return Err(CryptoError::InvalidInput(format!("Give two different coins to compare, not {} twice", a)));
+        }
+        if days < 1 {
+            return Err(CryptoError::InvalidInput("--days must be 1 or more to compare over".to_string()));
+        }
+        let ((closes_a, source_a), (closes_b, source_b)) =
+            try_join(self.closes(&a, &fiat, days, verbose), self.closes(&b, &fiat, days, verbose)).await?;
+        let source = if source_a == source_b { source_a } else { format!("{}, {}", source_a, source_b) };
+        let comparison = compare::Comparison::new(&closes_a, &closes_b).ok_or_else(|| CryptoError::NotFound {
+            provider: source.clone(),
+            what: format!("two days of {} prices both {} and {} have", fiat, a, b),
+        })?;
+        Ok(comparison.render(&a, &b, &fiat, &source, chart))
+    }
+
+    pub async fn backtest_dca(&self, crypto: &str, fiat: &str, amount: f64, interval: &str, from: &str, verbose: bool) -> Result<String, CryptoError> {
+        let (interval, from) = dca_schedule(amount, interval, from)?;
+        let (closes, source) = self.closes(crypto, fiat, backtest_days(from), verbose).await?;
+        let backtest = dca::Backtest::run(&closes, amount, interval, from).ok_or_else(|| CryptoError::NotFound {
+            provider: source.clone(),
+            what: format!("{} prices for {} since {}", fiat, crypto, from),
+        })?;
+        Ok(backtest.render(crypto, fiat, &source))
+    }
+
+    // Daily closes of `asset` in `fiat` by date; other fiat currencies go through bitcoin, like
+    // asset_price
+    pub async fn close_history(&self, asset: &str, fiat: &str, days: u32, verbose: bool) -> Result<(HashMap<chrono::NaiveDate, f64>, String), CryptoError> {
+        if !coins::FIAT_CODES.contains(&asset) {
+            let (closes, source) = self.closes(asset, fiat, days, verbose).await?;
+            return Ok((closes.into_iter().map(|close| (close.date, close.price)).collect(), source));
+        }
+        let ((in_fiat, source), (in_asset, _)) = try_join(self.closes("bitcoin", fiat, days, verbose), self.closes("bitcoin", asset, days, verbose)).await?;
+        let in_asset: HashMap<_, _> = in_asset.into_iter().map(|close| (close.date, close.price)).collect();
+        let ratios = in_fiat.into_iter().filter_map(|close| Some((close.date, close.price / in_asset.get(&close.date)?))).collect();
+        Ok((ratios, source))
+    }
+
+    // Closes of every asset but `fiat` itself, and the providers they came from
+    pub async fn asset_histories(&self, assets: Vec<String>, fiat: &str, days: u32, verbose: bool) -> Result<(returns::Prices, String), CryptoError> {
+        let priced: Vec<String> = assets.into_iter().filter(|asset| asset != fiat).collect();
+        let histories = futures::future::try_join_all(priced.iter().map(async |asset| self.close_history(asset, fiat, days, verbose).await)).await?;
+        let mut sources: Vec<String> = Vec::new();
+        let mut prices = returns::Prices::new();
+        for (asset, (closes, source)) in priced.into_iter().zip(histories) {
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+            prices.insert(asset, closes);
+        }
+        let sources = if sources.is_empty() { "no prices needed".to_string() } else { sources.join(", ") };
+        Ok((prices, sources))
+    }
+}
+
+pub fn parse_indicators(set: &[String]) -> Result<Vec<indicators::Indicator>, CryptoError> {
+    let set = set.iter().map(|name| name.parse()).collect::<Result<Vec<indicators::Indicator>, _>>()?;
+    match set.is_empty() {
+        true => Err(CryptoError::InvalidInput("--set needs at least one indicator".to_string())),
+        false => Ok(set),
+    }
+}
+
+// `backtest-dca`'s interval and first buy date, which must be in the past
+pub fn dca_schedule(amount: f64, interval: &str, from: &str) -> Result<(dca::Interval, chrono::NaiveDate), CryptoError> {
+    if amount.is_nan() || amount <= 0.0 {
+        return Err(CryptoError::InvalidInput(format!("Can't buy {} each time; give a positive --amount", amount)));
+    }
+    let interval = dca::Interval::parse(interval).ok_or_else(|| CryptoError::InvalidInput(format!("Unknown interval '{}'", interval)))?;
+    let from = chrono::NaiveDate::parse_from_str(from, "%Y-%m-%d")
+        .map_err(|_| CryptoError::InvalidInput(format!("--from must be a date like 2021-01-01, got '{}'", from)))?;
+    if from >= chrono::Utc::now().date_naive() {
+        return Err(CryptoError::InvalidInput(format!("--from {} isn't in the past", from)));
+    }
+    Ok((interval, from))
+}
+
+// Days from `from` to today
+pub fn backtest_days(from: chrono::NaiveDate) -> u32 {
+    (chrono::Utc::now().date_naive() - from).num_days().max(1) as u32
+}
+
+// Days of history the longest indicator in the set needs
+pub fn indicator_days(set: &[indicators::Indicator]) -> u32 {
+    set.iter().map(|indicator| indicator.closes_needed()).max().unwrap_or(1) as u32
+}
@@ -0,0 +1,150 @@
+use crate::providers::DailyClose;
+use crate::{AddressBookCommand, BasketCommand, CryptoError, CryptoTool, Lookup, WatchlistCommand, addressbook, basket, coins, watchlist};
+
+pub struct WatchlistQuotes {
+    fiat: String,
+    quotes: Vec<(String, Result<Lookup, CryptoError>)>,
+}
+
+impl CryptoTool {
+    pub fn watchlist(&self) -> Result<watchlist::Watchlist, CryptoError> {
+        let path = self.watchlist_path.as_ref().ok_or_else(|| {
+            CryptoError::ConfigError("No data directory for the watchlist; set [watchlist] path".to_string())
+        })?;
+        watchlist::Watchlist::open(path)
+    }
+
+    pub fn address_book(&self) -> Result<addressbook::AddressBook, CryptoError> {
+        let path = self.addressbook_path.as_ref().ok_or_else(|| {
+            CryptoError::ConfigError("No data directory for the address book; set [addressbook] path".to_string())
+        })?;
+        addressbook::AddressBook::open(path)
+    }
+
+    pub fn run_addressbook_command(&self, action: AddressBookCommand) -> Result<String, CryptoError> {
+        let mut book = self.address_book()?;
+        let message = match action {
+            AddressBookCommand::Show if book.entries().is_empty() => {
+                return Ok("The address book is empty; add addresses with `addressbook add <name> <chain> <address>`".to_string());
+            }
+            AddressBookCommand::Show => {
+                let lines: Vec<String> =
+                    book.entries().iter().map(|(name, entry)| format!("@{}: {} {}", name, entry.chain, entry.address)).collect();
+                return Ok(lines.join("\n"));
+            }
+            AddressBookCommand::Add { name, chain, address } => {
+                let name = name.strip_prefix('@').unwrap_or(&name).to_string();
+                if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ',' || c == '@') {
+                    return Err(CryptoError::InvalidInput(format!("'{}' can't be a name; use letters, digits and dashes", name)));
+                }
+                let chain = coins::resolve_symbol(&chain);
+                let address = address.trim().to_string();
+                self.check_address(&chain, &address)?;
+                let message = format!("@{}: {} {}", name, chain, address);
+                match book.add(&name, addressbook::Entry { chain, address }) {
+                    true => format!("Updated {}", message),
+                    false => format!("Added {}", message),
+                }
+            }
+            AddressBookCommand::Remove { name } => {
+                book.get(&name)?;
+                book.remove(&name);
+                format!("Removed @{}", name.strip_prefix('@').unwrap_or(&name))
+            }
+        };
+        book.save()?;
+        Ok(message)
+    }
+
+    pub fn run_watchlist_command(&self, action: WatchlistCommand) -> Result<String, CryptoError> {
+        let mut list = self.watchlist()?;
+        let resolve = |coins: Vec<String>| coins.iter().map(|c| coins::resolve_symbol(c)).collect::<Vec<_>>();
+        let (verb, changed) = match action {
+            WatchlistCommand::Show if list.coins().is_empty() => {
+                return Ok("The watchlist is empty; add coins with `watchlist add <coin>`".to_string());
+            }
+            WatchlistCommand::Show => return Ok(list.coins().join("\n")),
+            WatchlistCommand::Add { coins } => ("Added", list.add(&resolve(coins))),
+            WatchlistCommand::Remove { coins } => ("Removed", list.remove(&resolve(coins))),
+        };
+        list.save()?;
+        Ok(format!("{} {} coin{} ({} on the watchlist)", verb, changed, if changed == 1 { "" } else { "s" }, list.coins().len()))
+    }
+
+    pub fn basket(&self, name: &str) -> Result<basket::Basket, CryptoError> {
+        if let Some(coins) = self.baskets.get(name) {
+            return basket::Basket::from_config(name, coins);
+        }
+        let mut names: Vec<&str> = self.baskets.keys().map(String::as_str).collect();
+        names.sort();
+        Err(match names.is_empty() {
+            true => CryptoError::InvalidInput(format!("No basket named '{}'; set baskets under [baskets] in the config", name)),
+            false => CryptoError::InvalidInput(format!("No basket named '{}'; the config has {}", name, names.join(", "))),
+        })
+    }
+
+    pub async fn run_basket_command(&self, action: BasketCommand) -> Result<String, CryptoError> {
+        match action {
+            BasketCommand::List => {
+                let mut names: Vec<&String> = self.baskets.keys().collect();
+                names.sort();
+                let lines = names.into_iter().map(|name| Ok(self.basket(name)?.describe())).collect::<Result<Vec<_>, CryptoError>>()?;
+                match lines.is_empty() {
+                    true => Ok("No baskets; set them under [baskets] in the config".to_string()),
+                    false => Ok(lines.join("\n")),
+                }
+            }
+            BasketCommand::Quote { name, fiat, verbose } => {
+                let (basket, fiat) = (self.basket(&name)?, fiat.to_lowercase());
+                let quotes = self.batched_quotes(basket.ids(), &fiat, verbose).await?;
+                let prices = quotes.into_iter().map(|(_, quote)| quote).collect::<Result<Vec<_>, CryptoError>>()?;
+                Ok(basket.render_quote(&fiat, &prices))
+            }
+            BasketCommand::History { .. } if self.offline => {
+                Err(CryptoError::InvalidInput("basket history needs the network; drop --offline".to_string()))
+            }
+            BasketCommand::History { name, days, fiat, chart, verbose } => {
+                let (basket, fiat) = (self.basket(&name)?, fiat.to_lowercase());
+                let histories = futures::future::try_join_all(basket.ids().into_iter().map(async |coin| self.closes(&coin, &fiat, days, verbose).await)).await?;
+                let mut sources: Vec<String> = Vec::new();
+                for (_, source) in &histories {
+                    if !sources.contains(source) {
+                        sources.push(source.clone());
+                    }
+                }
+                let histories: Vec<Vec<DailyClose>> = histories.into_iter().map(|(closes, _)| closes).collect();
+                let closes = basket.closes(&histories);
+                if closes.is_empty() {
+                    return Err(CryptoError::NotFound { provider: sources.join(", "), what: format!("a day of {} prices all of {}'s coins have", fiat, name) });
+                }
+                Ok(basket.render_history(&fiat, &closes, &sources.join(", "), chart))
+            }
+        }
+    }
+
+    // Every watchlist coin in the [watchlist] fiat, uncached ones in as few requests as the
+    // providers allow. A coin that can't be priced is shown as unavailable.
+    pub async fn watchlist_prices(&self, verbose: bool) -> Result<WatchlistQuotes, CryptoError> {
+        let coins = self.watchlist()?.coins().to_vec();
+        if coins.is_empty() {
+            return Err(CryptoError::InvalidInput("The watchlist is empty; add coins with `watchlist add <coin>`".to_string()));
+        }
+        let fiat = self.watchlist_fiat.clone();
+        let quotes = self.batched_quotes(coins, &fiat, verbose).await?;
+        Ok(WatchlistQuotes { fiat, quotes })
+    }
+}
+
+impl WatchlistQuotes {
+    pub fn render(&self) -> String {
+        let fiat = self.fiat.to_uppercase();
+        self.quotes
+            .iter()
+            .map(|(coin, quote)| match quote {
+                Ok(quote) => format!("{}: {} {}", coin, quote.value, fiat),
+                Err(e) => format!("{}: unavailable ({})", coin, e),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
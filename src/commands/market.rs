@@ -0,0 +1,344 @@
+use crate::cache::{Cache, CacheEntry, Validators};
+use crate::providers::{Funding, GlobalMarket, OrderBook, PriceProvider, TickerProvider, TrendingCoin};
+use crate::{CryptoError, CryptoTool, Lookup, coins, format_timestamp, tidy, timezone};
+
+pub struct Pegs {
+    threshold: f64,
+    checks: Vec<(String, &'static str, Result<Lookup, CryptoError>)>,
+}
+
+pub struct FundingRates {
+    symbol: String,
+    venues: Vec<(String, Result<Funding, CryptoError>)>,
+}
+
+// An order book with running totals, best levels first
+#[derive(serde::Serialize)]
+pub struct Depth {
+    exchange: String,
+    market: String,
+    mid_price: f64,
+    spread: f64,
+    bids: Vec<DepthLevel>,
+    asks: Vec<DepthLevel>,
+}
+
+#[derive(serde::Serialize)]
+struct DepthLevel {
+    price: f64,
+    size: f64,
+    // Size at this price and every better one
+    cumulative: f64,
+}
+
+impl CryptoTool {
+    // The Fear & Greed index for the last `days` days, today's first, cached like prices
+    pub async fn sentiment(&self, days: usize, verbose: bool) -> Result<Vec<Lookup>, CryptoError> {
+        if days == 0 {
+            return Err(CryptoError::InvalidInput("--days must be at least 1".to_string()));
+        }
+        let keys: Vec<String> = (0..days).map(Cache::sentiment_key).collect();
+        if self.offline {
+            return keys.iter().map(|key| self.read_offline_cache(key, "Fear & Greed value")).collect();
+        }
+        let cached: Vec<CacheEntry> = keys.iter().filter_map(|key| self.cache.peek_fresh(key)).collect();
+        if cached.len() == days && self.cache.get_fresh(&keys[0])?.is_some() {
+            if verbose {
+                eprintln!("Using cached Fear & Greed values");
+            }
+            return Ok(cached.into_iter().map(Lookup::cached).collect());
+        }
+        let providers: Vec<_> = self.providers.sentiment.iter().map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput("No sentiment provider configured".to_string());
+        let (values, provider) = self.first_answer(&providers, |p| p.name(), false, none, |p| p.history(&self.client, days, verbose)).await?;
+        Ok(keys
+            .into_iter()
+            .zip(values)
+            .map(|(key, value)| Lookup::fetched(self.cache.insert(key, value, provider.name(), Validators::default())))
+            .collect())
+    }
+
+    // Each stablecoin's price in the fiat it's pegged to, failing only when all lookups do
+    pub async fn peg(&self, coins: &[String], threshold: Option<f64>, verbose: bool) -> Result<Pegs, CryptoError> {
+        let threshold = threshold.unwrap_or(self.peg_threshold);
+        if threshold.is_nan() || threshold < 0.0 {
+            return Err(CryptoError::InvalidInput(format!("Peg threshold must be a percent of 0 or more, got {}", threshold)));
+        }
+        let pegged = pegged_coins(coins)?;
+        let checks = futures::future::join_all(pegged.into_iter().map(async |(coin, fiat)| {
+            let price = self.lookup_price(&coin, fiat, verbose).await;
+            (coin, fiat, price)
+        }))
+        .await;
+        if checks.iter().all(|(_, _, price)| price.is_err()) {
+            let error = checks.into_iter().find_map(|(_, _, price)| price.err());
+            return Err(error.unwrap_or_else(|| CryptoError::InvalidInput("No stablecoins to check".to_string())));
+        }
+        Ok(Pegs { threshold, checks })
+    }
+
+    // The first answer from the price providers `has` picks, for data beyond prices
+    pub async fn market_data<T>(
+        &self,
+        what: &str,
+        has: impl Fn(&dyn PriceProvider) -> bool,
+        fetch: impl AsyncFn(&dyn PriceProvider) -> Result<T, CryptoError>,
+    ) -> Result<(T, String), CryptoError> {
+        let providers: Vec<&dyn PriceProvider> = self.providers.price.iter().map(|p| p.as_ref()).filter(|p| has(*p)).collect();
+        let none = || CryptoError::InvalidInput(format!("No price provider has {}", what));
+        let (data, provider) = self.first_answer(&providers, |p| p.name(), false, none, |p| fetch(p)).await?;
+        Ok((data, provider.name().to_string()))
+    }
+
+    pub async fn global_market(&self, fiat: &str, verbose: bool) -> Result<(GlobalMarket, String), CryptoError> {
+        self.market_data(
+            "global market data",
+            |p| p.plan_global(&self.client, fiat).is_some(),
+            async |p| p.global(&self.client, fiat, verbose).await,
+        )
+        .await
+    }
+
+    pub async fn trending(&self, verbose: bool) -> Result<(Vec<TrendingCoin>, String), CryptoError> {
+        self.market_data("trending coins", |p| p.plan_trending(&self.client).is_some(), async |p| p.trending(&self.client, verbose).await)
+            .await
+    }
+
+    // Both exchanges at once, failing only when both do
+    pub async fn funding(&self, symbol: &str, verbose: bool) -> Result<FundingRates, CryptoError> {
+        let symbol = perpetual_symbol(symbol);
+        let venues = futures::future::join_all(self.providers.funding.iter().map(async |provider| {
+            if !self.breakers.allow(provider.name()) {
+                return (provider.name().to_string(), Err(CryptoError::skipped(provider.name())));
+            }
+            let result = provider.funding(&self.client, &symbol, verbose).await;
+            self.breakers.record(provider.name(), result.as_ref().err());
+            (provider.name().to_string(), result)
+        }))
+        .await;
+        if venues.iter().all(|(_, result)| result.is_err()) {
+            let error = venues.into_iter().find_map(|(_, result)| result.err());
+            return Err(error.unwrap_or_else(|| CryptoError::InvalidInput("No exchange to read funding from".to_string())));
+        }
+        Ok(FundingRates { symbol, venues })
+    }
+
+    pub fn ticker_provider(&self, name: &str) -> Result<&dyn TickerProvider, CryptoError> {
+        self.providers
+            .tickers
+            .iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name))
+            .map(|p| p.as_ref())
+            .ok_or_else(|| CryptoError::InvalidInput(format!("No market data for {}", name)))
+    }
+
+    pub async fn order_book(&self, exchange: &str, pair: &str, depth: usize, verbose: bool) -> Result<Depth, CryptoError> {
+        if depth == 0 {
+            return Err(CryptoError::InvalidInput("--depth must be at least 1".to_string()));
+        }
+        let (base, quote) = parse_pair(pair)?;
+        let provider = self.ticker_provider(exchange)?;
+        let result = provider.book(&self.client, &base, &quote, depth, verbose).await;
+        self.breakers.record(provider.name(), result.as_ref().err());
+        Depth::new(provider.name(), result?)
+    }
+}
+
+// BASE/QUOTE or BASE-QUOTE, as uppercase tickers
+pub fn parse_pair(pair: &str) -> Result<(String, String), CryptoError> {
+    match pair.split_once(['/', '-']) {
+        Some((base, quote)) if !base.is_empty() && !quote.is_empty() => Ok((coins::ticker(base), quote.to_uppercase())),
+        _ => Err(CryptoError::InvalidInput(format!("'{}' isn't a market; give it as BASE/QUOTE, e.g. BTC/USD", pair))),
+    }
+}
+
+// alternative.me's bands
+fn sentiment_label(value: f64) -> &'static str {
+    match value {
+        v if v < 25.0 => "Extreme Fear",
+        v if v < 47.0 => "Fear",
+        v if v < 55.0 => "Neutral",
+        v if v < 76.0 => "Greed",
+        _ => "Extreme Greed",
+    }
+}
+
+// Today's value and its trend against the oldest day shown, then each day
+pub fn render_sentiment(values: &[Lookup]) -> String {
+    let today = chrono::Utc::now().date_naive();
+    let current = values[0].value;
+    let mut lines = vec![format!("Fear & Greed: {} ({})", current, sentiment_label(current))];
+    if let Some(oldest) = values.last().filter(|_| values.len() > 1) {
+        let change = current - oldest.value;
+        let trend = match change {
+            c if c > 0.0 => "rising",
+            c if c < 0.0 => "falling",
+            _ => "steady",
+        };
+        lines.push(format!("Trend: {} ({:+} over {} days)", trend, change, values.len() - 1));
+    }
+    for (days_ago, lookup) in values.iter().enumerate() {
+        let day = today - chrono::Duration::days(days_ago as i64);
+        lines.push(format!("  {}: {} {}", day, lookup.value, sentiment_label(lookup.value)));
+    }
+    lines.join("\n")
+}
+
+// Coins get their USDT perpetual; anything already naming a USDT or USDC contract is kept
+pub fn perpetual_symbol(symbol: &str) -> String {
+    let symbol = coins::ticker(symbol);
+    match symbol.ends_with("USDT") || symbol.ends_with("USDC") {
+        true => symbol,
+        false => format!("{}USDT", symbol),
+    }
+}
+
+// Ids come last so they can be copied into other commands
+pub fn render_trending(coins: &[TrendingCoin], source: &str) -> String {
+    let mut lines = vec![format!("Trending on {}:", source)];
+    for (i, coin) in coins.iter().enumerate() {
+        let mut line = format!("  {}. {} ({})", i + 1, coin.name, coin.symbol.to_uppercase());
+        if let Some(price) = coin.price {
+            line.push_str(&format!(": {} USD", price));
+        }
+        if let Some(change) = coin.change_24h {
+            line.push_str(&format!(", {:+.2}% 24h", change));
+        }
+        if let Some(rank) = coin.market_cap_rank {
+            line.push_str(&format!(", rank #{}", rank));
+        }
+        line.push_str(&format!(" [{}]", coin.id));
+        lines.push(line);
+    }
+    if coins.is_empty() {
+        lines.push("  nothing right now".to_string());
+    }
+    lines.join("\n")
+}
+
+// The coins `peg` checks, each with the fiat it's pegged to
+pub fn pegged_coins(coins: &[String]) -> Result<Vec<(String, &'static str)>, CryptoError> {
+    let coins: Vec<String> = match coins.is_empty() {
+        true => coins::DEFAULT_PEGGED.iter().map(|c| c.to_string()).collect(),
+        false => coins.iter().map(|c| coins::resolve_symbol(c)).collect(),
+    };
+    coins
+        .into_iter()
+        .map(|coin| match coins::peg_fiat(&coin) {
+            Some(fiat) => Ok((coin, fiat)),
+            None => Err(CryptoError::InvalidInput(format!("{} isn't a stablecoin coinwagon knows the peg of", coin))),
+        })
+        .collect()
+}
+
+impl Pegs {
+    // Percent above or below 1.00
+    fn deviation(price: &Lookup) -> f64 {
+        tidy((price.value - 1.0) * 100.0)
+    }
+
+    fn off_peg(&self, price: &Lookup) -> bool {
+        Self::deviation(price).abs() > self.threshold
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!("Pegs (off peg beyond {}%):", self.threshold)];
+        for (coin, fiat, price) in &self.checks {
+            let name = coins::ticker(coin);
+            lines.push(match price {
+                Ok(price) => format!(
+                    "  {}: {} {} ({:+.2}%) {}",
+                    name,
+                    price.value,
+                    fiat.to_uppercase(),
+                    Self::deviation(price),
+                    if self.off_peg(price) { "OFF PEG" } else { "ok" }
+                ),
+                Err(e) => format!("  {}: unavailable ({})", name, e),
+            });
+        }
+        lines.join("\n")
+    }
+
+    // For monitoring scripts: `off_peg` on each coin, or `error` where its price failed
+    pub fn json(&self) -> Result<String, CryptoError> {
+        let coins: Vec<serde_json::Value> = self
+            .checks
+            .iter()
+            .map(|(coin, fiat, price)| match price {
+                Ok(price) => serde_json::json!({
+                    "coin": coin,
+                    "fiat": fiat,
+                    "price": price.value,
+                    "deviation_percent": Self::deviation(price),
+                    "off_peg": self.off_peg(price),
+                    "source": price.source,
+                    "timestamp": format_timestamp(price.fetched_at),
+                }),
+                Err(e) => serde_json::json!({ "coin": coin, "fiat": fiat, "error": e.to_string() }),
+            })
+            .collect();
+        let json = serde_json::json!({ "threshold_percent": self.threshold, "coins": coins });
+        serde_json::to_string_pretty(&json).map_err(|e| CryptoError::RuntimeError(e.to_string()))
+    }
+}
+
+impl FundingRates {
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!("{} funding:", self.symbol)];
+        for (venue, result) in &self.venues {
+            lines.push(match result {
+                Ok(funding) => {
+                    // Settlements come every (next - last), 8 hours on most contracts
+                    let interval = (funding.next_time - funding.last_time).num_seconds() as f64;
+                    let yearly = match interval > 0.0 {
+                        true => format!(", {:.2}% a year", funding.predicted_rate * 365.0 * 86400.0 / interval * 100.0),
+                        false => String::new(),
+                    };
+                    format!(
+                        "  {}: last {:.4}% ({}), next {:.4}% at {}{}",
+                        venue,
+                        funding.last_rate * 100.0,
+                        timezone::iso(funding.last_time),
+                        funding.predicted_rate * 100.0,
+                        timezone::iso(funding.next_time),
+                        yearly
+                    )
+                }
+                Err(e) => format!("  {}: unavailable ({})", venue, e),
+            });
+        }
+        lines.join("\n")
+    }
+}
+
+impl Depth {
+    fn new(exchange: &str, book: OrderBook) -> Result<Self, CryptoError> {
+        let (Some(bid), Some(ask)) = (book.bids.first(), book.asks.first()) else {
+            return Err(CryptoError::NotFound { provider: exchange.to_string(), what: format!("orders on both sides of {}", book.market) });
+        };
+        let (mid_price, spread) = (tidy((bid.0 + ask.0) / 2.0), tidy(ask.0 - bid.0));
+        let running = |side: Vec<(f64, f64)>| {
+            let mut cumulative = 0.0;
+            side.into_iter()
+                .map(|(price, size)| {
+                    cumulative = tidy(cumulative + size);
+                    DepthLevel { price, size, cumulative }
+                })
+                .collect()
+        };
+        Ok(Depth { exchange: exchange.to_string(), market: book.market, mid_price, spread, bids: running(book.bids), asks: running(book.asks) })
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!("{} on {}", self.market, self.exchange),
+            format!("Mid price: {}, spread {}", self.mid_price, self.spread),
+        ];
+        for (name, side) in [("Bids", &self.bids), ("Asks", &self.asks)] {
+            lines.push(format!("{} (price, size, cumulative):", name));
+            lines.extend(side.iter().map(|level| format!("  {} {} {}", level.price, level.size, level.cumulative)));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,302 @@
+use std::time::{Duration, SystemTime};
+
+use crate::cache::Cache;
+use crate::{CacheCommand, Commands, CryptoError, CryptoTool, Output, alerts, coins, completions, daemon, descriptor, format_timestamp, global_json, plan, repl, secrets, stats, status, units, using_tool, watch};
+#[cfg(windows)]
+use crate::service;
+
+mod balances;
+mod chain;
+mod coin_list;
+mod data;
+mod exchanges;
+mod history;
+mod lists;
+mod market;
+mod portfolio;
+mod prices;
+mod swap;
+mod wallet;
+
+use chain::{fees_json, render_fees};
+use market::{render_sentiment, render_trending};
+
+// What the dry-run planner and the alerts daemon share with the commands
+pub(crate) use balances::{GAP_LIMIT, parse_as_of};
+pub(crate) use chain::{BLOCK_TIME_SAMPLE, parse_block_id};
+pub(crate) use history::{backtest_days, dca_schedule, indicator_days, parse_indicators};
+pub(crate) use market::{parse_pair, pegged_coins, perpetual_symbol};
+pub(crate) use portfolio::returns_range;
+pub(crate) use wallet::{WalletTokens, batch_groups};
+
+// Runs `command` with `tool`, or with --dry-run only plans its requests. The second value is
+// the oldest cache timestamp served offline.
+pub async fn run_on(tool: &CryptoTool, command: Commands) -> Result<(Output, Option<SystemTime>), CryptoError> {
+    if tool.dry_run {
+        return Ok((Output::Text(plan::run(tool, &command)?), None));
+    }
+    using_tool(tool, async |tool| match command {
+        Commands::CurrentPrice { watchlist: true, verbose, .. } => Ok(Output::Text(tool.watchlist_prices(verbose).await?.render())),
+        // Metals are quoted like any other currency, per troy ounce
+        Commands::CurrentPrice { crypto: Some(crypto), fiat: Some(fiat), verbose, .. }
+        | Commands::CurrentPrice { crypto: Some(crypto), vs: Some(fiat), verbose, .. } => {
+            let quote = tool.lookup_price(&tool.coin_id(&crypto, verbose).await?, &fiat, verbose).await?;
+            Ok(Output::Price { crypto, fiat, quote })
+        }
+        Commands::CurrentPrice { .. } => Err(CryptoError::InvalidInput("current-price needs a coin and a fiat, or --watchlist".to_string())),
+        Commands::CheckPrice { crypto, fiat, above, below, verbose } => {
+            let quote = tool.lookup_price(&tool.coin_id(&crypto, verbose).await?, &fiat, verbose).await?;
+            let (held, side, threshold) = match (above, below) {
+                (Some(above), _) => (quote.value > above, "above", above),
+                (_, Some(below)) => (quote.value < below, "below", below),
+                (None, None) => return Err(CryptoError::InvalidInput("check-price needs --above or --below".to_string())),
+            };
+            let text = match verbose {
+                true => {
+                    let not = if held { "" } else { "not " };
+                    let fiat = fiat.to_uppercase();
+                    format!("{} is {} {}, {}{} {} {} ({})", crypto, quote.value, fiat, not, side, threshold, fiat, quote.source)
+                }
+                false => String::new(),
+            };
+            Ok(Output::Check { held, text })
+        }
+        Commands::Watchlist { action } => tool.run_watchlist_command(action).map(Output::Text),
+        Commands::Addressbook { action } => tool.run_addressbook_command(action).map(Output::Text),
+        Commands::Basket { action } => tool.run_basket_command(action).await.map(Output::Text),
+        Commands::AddressBalance { at, height, .. } if tool.offline && (at.is_some() || height.is_some()) => {
+            Err(CryptoError::InvalidInput("address-balance --at and --height need the network; drop --offline".to_string()))
+        }
+        Commands::AddressBalance { crypto, address, at, height, verbose } => {
+            let (crypto, address) = tool.balance_target(&crypto, address.as_deref())?;
+            let quote = match (parse_as_of(at.as_deref(), height)?, descriptor::is_descriptor(&address)) {
+                (Some(_), true) => return Err(CryptoError::InvalidInput("--at and --height take one address, not a descriptor's".to_string())),
+                (Some(at), false) => tool.past_balance(&crypto, &address, &at, verbose).await?,
+                (None, true) => tool.descriptor_balance(&crypto, &address, verbose).await?,
+                (None, false) => tool.lookup_balance(&crypto, &address, verbose).await?,
+            };
+            Ok(Output::Balance { crypto, address, quote })
+        }
+        Commands::WalletBalance { wallet, fiat, tokens, include_unverified, verbose } => {
+            let tokens = match (tokens || tool.wallet_tokens, include_unverified) {
+                (false, _) => WalletTokens::None,
+                (true, false) => WalletTokens::Verified,
+                (true, true) => WalletTokens::All,
+            };
+            let lines = tool.wallet_lines(&wallet, &fiat, tokens, verbose).await?;
+            Ok(Output::Wallet { fiat, lines })
+        }
+        Commands::Tokens { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("tokens needs the network; drop --offline".to_string()))
+        }
+        Commands::Tokens { address, chain, fiat, include_unverified, verbose } => {
+            let lines = tool.discover_tokens(&chain, &address, &fiat, include_unverified, verbose).await?;
+            Ok(Output::Wallet { fiat, lines })
+        }
+        Commands::TokenBalance { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("token-balance needs the network; drop --offline".to_string()))
+        }
+        Commands::TokenBalance { crypto, address, token, verbose } => {
+            let (balance, token) = tool.get_token_balance(&crypto, &address, &token, verbose).await?;
+            Ok(Output::Text(format!("{} {}", balance, token.symbol)))
+        }
+        Commands::ExchangeBalance { exchange, fiat, verbose } => {
+            let lines = tool.exchange_lines(&exchange, &fiat, verbose).await?;
+            Ok(Output::Wallet { fiat, lines })
+        }
+        Commands::ImportHistory { exchange, .. } if tool.offline => {
+            Err(CryptoError::InvalidInput(format!("importing {} history needs the network; drop --offline", exchange)))
+        }
+        Commands::ImportHistory { exchange, verbose } => tool.import_history(&exchange, verbose).await.map(Output::Text),
+        Commands::Transactions { exchange } => tool.list_transactions(exchange.as_deref()).map(Output::Text),
+        Commands::Gains { .. } if tool.offline => Err(CryptoError::InvalidInput("gains needs the network; drop --offline".to_string())),
+        Commands::Gains { method, fiat, year, verbose } => {
+            tool.gains(method.as_deref(), &fiat.to_lowercase(), year, verbose).await.map(Output::Text)
+        }
+        Commands::Export8949 { .. } if tool.offline => Err(CryptoError::InvalidInput("export-8949 needs the network; drop --offline".to_string())),
+        Commands::Export8949 { year, method, verbose } => tool.export_8949(method.as_deref(), year, verbose).await.map(Output::Text),
+        Commands::Query { sql, format } => tool.query(&sql, &format).map(Output::Text),
+        Commands::Export { dataset, format, output } => tool.export(dataset, &format, output.as_deref()).await.map(Output::Text),
+        Commands::Returns { .. } if tool.offline => Err(CryptoError::InvalidInput("returns needs the network; drop --offline".to_string())),
+        Commands::Returns { from, to, fiat, benchmark, exchange, verbose } => tool
+            .returns(&from, to.as_deref(), &fiat.to_lowercase(), benchmark.as_deref(), exchange.as_deref(), verbose)
+            .await
+            .map(Output::Text),
+        Commands::Spread { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("spread needs the network; drop --offline".to_string()))
+        }
+        Commands::Spread { crypto, fiat, verbose } => Ok(Output::Text(tool.spread(&crypto, &fiat, verbose).await?.render())),
+        Commands::Sentiment { days, verbose } => tool.sentiment(days, verbose).await.map(|values| Output::Text(render_sentiment(&values))),
+        Commands::WatchAddress { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("watch-address needs the network; drop --offline".to_string()))
+        }
+        Commands::WatchAddress { crypto, address, interval, confirmations, polls, verbose } => {
+            if interval == 0 {
+                return Err(CryptoError::InvalidInput("--interval must be at least 1 second".to_string()));
+            }
+            let (crypto, address) = tool.balance_target(&crypto, address.as_deref())?;
+            watch::run(tool, &crypto, &address, Duration::from_secs(interval), confirmations, polls, verbose).await.map(Output::Text)
+        }
+        Commands::Alerts { .. } if tool.offline => Err(CryptoError::InvalidInput("alerts needs the network; drop --offline".to_string())),
+        Commands::Alerts { interval, polls, pid_file, listen, verbose } => {
+            if interval == 0 {
+                return Err(CryptoError::InvalidInput("--interval must be at least 1 second".to_string()));
+            }
+            let _pid_file = pid_file.as_deref().map(daemon::PidFile::create).transpose()?;
+            alerts::run(tool, &tool.alerts, Duration::from_secs(interval), polls, listen.as_deref(), verbose).await.map(Output::Text)
+        }
+        #[cfg(windows)]
+        Commands::Service { action } => service::run(action, tool.config_path.as_deref()).map(Output::Text),
+        Commands::Tip { .. } if tool.offline => Err(CryptoError::InvalidInput("tip needs the network; drop --offline".to_string())),
+        Commands::Tip { crypto, verbose } => tool.chain_tip(&coins::resolve_symbol(&crypto), verbose).await.map(Output::Text),
+        Commands::Block { .. } if tool.offline => Err(CryptoError::InvalidInput("block needs the network; drop --offline".to_string())),
+        Commands::Block { crypto, block, verbose } => {
+            tool.chain_block(&coins::resolve_symbol(&crypto), &block, verbose).await.map(Output::Text)
+        }
+        Commands::Fees { .. } if tool.offline => Err(CryptoError::InvalidInput("fees needs the network; drop --offline".to_string())),
+        Commands::Fees { crypto, json, verbose } => {
+            let crypto = coins::resolve_symbol(&crypto);
+            let (fees, source) = tool.fees(&crypto, verbose).await?;
+            match json {
+                true => fees_json(&crypto, &fees, &source).map(Output::Text),
+                false => Ok(Output::Text(render_fees(&crypto, &fees, &source))),
+            }
+        }
+        Commands::Network { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("network needs the network; drop --offline".to_string()))
+        }
+        Commands::Network { crypto, verbose } => tool.network_stats(&coins::resolve_symbol(&crypto), verbose).await.map(Output::Text),
+        Commands::Halving { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("halving needs the network; drop --offline".to_string()))
+        }
+        Commands::Halving { crypto, verbose } => Ok(Output::Text(tool.halving(&coins::resolve_symbol(&crypto), verbose).await?.render())),
+        Commands::Whales { .. } if tool.offline => Err(CryptoError::InvalidInput("whales needs the network; drop --offline".to_string())),
+        Commands::Whales { crypto, min, interval, polls, verbose } => {
+            if !(min.is_finite() && min > 0.0) {
+                return Err(CryptoError::InvalidInput(format!("--min must be above 0, not {}", min)));
+            }
+            if interval == 0 {
+                return Err(CryptoError::InvalidInput("--interval must be at least 1 second".to_string()));
+            }
+            let crypto = coins::resolve_symbol(&crypto);
+            watch::whales(tool, &crypto, min, Duration::from_secs(interval), polls, verbose).await.map(Output::Text)
+        }
+        Commands::UtxoReport { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("utxo-report needs the network; drop --offline".to_string()))
+        }
+        Commands::UtxoReport { address, fee_rate, verbose } => tool.utxo_report(&address, fee_rate, verbose).await.map(Output::Text),
+        Commands::Funding { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("funding needs the network; drop --offline".to_string()))
+        }
+        Commands::Funding { symbol, verbose } => Ok(Output::Text(tool.funding(&symbol, verbose).await?.render())),
+        Commands::Orderbook { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("orderbook needs the network; drop --offline".to_string()))
+        }
+        Commands::Orderbook { exchange, pair, depth, json, verbose } => {
+            let book = tool.order_book(&exchange, &pair, depth, verbose).await?;
+            match json {
+                true => serde_json::to_string_pretty(&book).map(Output::Text).map_err(|e| CryptoError::RuntimeError(e.to_string())),
+                false => Ok(Output::Text(book.render())),
+            }
+        }
+        Commands::Peg { coins, threshold, json, verbose } => {
+            let pegs = tool.peg(&coins, threshold, verbose).await?;
+            match json {
+                true => pegs.json().map(Output::Text),
+                false => Ok(Output::Text(pegs.render())),
+            }
+        }
+        Commands::Indicators { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("indicators needs the network; drop --offline".to_string()))
+        }
+        Commands::Indicators { crypto, fiat, set, verbose } => tool.indicators(&crypto, &fiat, &set, verbose).await.map(Output::Text),
+        Commands::BacktestDca { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("backtest-dca needs the network; drop --offline".to_string()))
+        }
+        Commands::BacktestDca { crypto, fiat, amount, interval, from, verbose } => {
+            tool.backtest_dca(&crypto, &fiat, amount, &interval, &from, verbose).await.map(Output::Text)
+        }
+        Commands::DcaPlan { allocation, budget, fiat, verbose } => tool.dca_plan(&allocation, budget, &fiat, verbose).await.map(Output::Text),
+        Commands::Risk { .. } if tool.offline => Err(CryptoError::InvalidInput("risk needs the network; drop --offline".to_string())),
+        Commands::Risk { wallet, fiat, days, risk_free_rate, verbose } => {
+            tool.risk(&wallet, &fiat, days, risk_free_rate, verbose).await.map(Output::Text)
+        }
+        Commands::Rebalance { wallet, fiat, target, band, verbose } => tool.rebalance(&wallet, &fiat, &target, band, verbose).await.map(Output::Text),
+        Commands::Compare { .. } if tool.offline => Err(CryptoError::InvalidInput("compare needs the network; drop --offline".to_string())),
+        Commands::Compare { a, b, days, fiat, chart, verbose } => tool.compare(&a, &b, &fiat, days, chart, verbose).await.map(Output::Text),
+        Commands::CoinList { query, refresh, verbose } => tool.coin_list_command(query.as_deref(), refresh, verbose).await.map(Output::Text),
+        Commands::Trending { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("trending needs the network; drop --offline".to_string()))
+        }
+        Commands::Trending { verbose } => {
+            let (coins, source) = tool.trending(verbose).await?;
+            Ok(Output::Text(render_trending(&coins, &source)))
+        }
+        Commands::Global { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("global needs the network; drop --offline".to_string()))
+        }
+        Commands::Global { fiat, json, verbose } => {
+            let (market, source) = tool.global_market(&fiat, verbose).await?;
+            match json {
+                true => global_json(&market, &fiat, &source).map(Output::Text),
+                false => Ok(Output::Global { fiat, market, source }),
+            }
+        }
+        Commands::QuoteSwap { .. } if tool.offline => {
+            Err(CryptoError::InvalidInput("quote-swap needs the network; drop --offline".to_string()))
+        }
+        Commands::QuoteSwap { amount, from, to, chain, verbose } => {
+            let swap = tool.quote_swap(&chain, amount, &from, &to, verbose).await?;
+            Ok(Output::Text(swap.render(&chain)))
+        }
+        Commands::Units { amount, from, to } => units::convert(&amount, &from, &to).map(Output::Text),
+        Commands::Cache { action } => Ok(Output::Text(run_cache_command(&tool.cache, action))),
+        Commands::Status if tool.offline => Err(CryptoError::InvalidInput("status needs the network; drop --offline".to_string())),
+        Commands::Status => Ok(Output::Text(status::run(&tool.client, &tool.providers).await)),
+        Commands::Stats => Ok(Output::Text(stats::run(&tool.cache))),
+        Commands::Secrets { action } => secrets::run(action).map(Output::Text),
+        Commands::Repl { wallet, fiat } => repl::run(tool, wallet, fiat).await.map(Output::Text),
+        Commands::Completions { shell } => completions::generate(shell).map(Output::Text),
+        Commands::CompleteCoins => {
+            let listed = tool.coin_list.saved().unwrap_or_default().into_iter().map(|listing| listing.id).collect();
+            Ok(Output::Text(completions::coins(&tool.cache, listed)))
+        }
+    })
+    .await
+}
+
+// `cache`
+fn run_cache_command(cache: &Cache, action: CacheCommand) -> String {
+    match action {
+        CacheCommand::List => {
+            let entries = cache.list();
+            if entries.is_empty() {
+                return "Cache is empty".to_string();
+            }
+            entries
+                .iter()
+                .map(|(key, entry)| format!("{} = {} (fetched {})", key, entry.value, format_timestamp(entry.fetched_at)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        CacheCommand::Stats => {
+            let stats = cache.stats();
+            let hit_ratio = match stats.hit_ratio() {
+                Some(ratio) => format!("{:.1}%", ratio * 100.0),
+                None => "n/a".to_string(),
+            };
+            let [fresh, hour, day, older] = stats.age_buckets;
+            format!(
+                "Entries: {}\nHits: {}\nMisses: {}\nHit ratio: {}\nAge: <5m: {}, 5m-1h: {}, 1h-24h: {}, >24h: {}",
+                stats.entries, stats.hits, stats.misses, hit_ratio, fresh, hour, day, older
+            )
+        }
+        CacheCommand::Clear { coin } => {
+            let removed = cache.clear(coin.as_deref());
+            match coin {
+                Some(coin) => format!("Removed {} cached entries for {}", removed, coin),
+                None => format!("Removed {} cached entries", removed),
+            }
+        }
+    }
+}
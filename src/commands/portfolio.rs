@@ -0,0 +1,186 @@
+use crate::{CryptoError, CryptoTool, coins, dca, lots, rebalance, returns, risk, transactions};
+use super::history::backtest_days;
+use super::wallet::WalletTokens;
+
+impl CryptoTool {
+    // Today's wallet weights applied to each coin's daily returns over the last `days`
+    pub async fn risk(&self, wallet: &str, fiat: &str, days: u32, risk_free_rate: Option<f64>, verbose: bool) -> Result<String, CryptoError> {
+        let risk_free_rate = risk_free_rate.unwrap_or(self.risk_free_rate);
+        if !risk_free_rate.is_finite() {
+            return Err(CryptoError::InvalidInput(format!("{} isn't a risk-free rate", risk_free_rate)));
+        }
+        if days < 2 {
+            return Err(CryptoError::InvalidInput("--days must be 2 or more to measure returns over".to_string()));
+        }
+        // A coin on several lines is one holding
+        let mut values: Vec<(String, f64)> = Vec::new();
+        for line in self.wallet_lines(wallet, fiat, WalletTokens::None, verbose).await? {
+            match values.iter_mut().find(|(coin, _)| *coin == line.crypto) {
+                Some((_, value)) => *value += line.fiat_value(),
+                None => values.push((line.crypto.clone(), line.fiat_value())),
+            }
+        }
+        values.retain(|(_, value)| *value > 0.0);
+        if values.is_empty() {
+            return Err(CryptoError::InvalidInput(format!("Nothing in {} is worth anything to measure", wallet)));
+        }
+        let histories = futures::future::try_join_all(values.into_iter().map(async |(coin, value)| {
+            let (closes, source) = self.closes(&coin, fiat, days, verbose).await?;
+            Ok::<_, CryptoError>((risk::Holding { coin, value, closes }, source))
+        }))
+        .await?;
+        let mut sources: Vec<String> = Vec::new();
+        for (_, source) in &histories {
+            if !sources.contains(source) {
+                sources.push(source.clone());
+            }
+        }
+        let holdings: Vec<risk::Holding> = histories.into_iter().map(|(holding, _)| holding).collect();
+        let risk = risk::Risk::estimate(&holdings, risk_free_rate / 100.0).ok_or_else(|| CryptoError::NotFound {
+            provider: sources.join(", "),
+            what: format!("three days of {} prices all of {}'s coins have", fiat, wallet),
+        })?;
+        Ok(risk.render(wallet, fiat, &sources.join(", ")))
+    }
+
+    // The wallet's coins at today's prices against `target`, coins in neither priced too
+    pub async fn rebalance(&self, wallet: &str, fiat: &str, target: &str, band: f64, verbose: bool) -> Result<String, CryptoError> {
+        if !band.is_finite() || band < 0.0 {
+            return Err(CryptoError::InvalidInput(format!("Can't rebalance within a band of {}; give 0 or more points", band)));
+        }
+        let (target, fiat) = (dca::parse_allocation(target)?, fiat.to_lowercase());
+        let mut positions: Vec<rebalance::Position> = Vec::new();
+        for line in self.wallet_lines(wallet, &fiat, WalletTokens::None, verbose).await? {
+            match positions.iter_mut().find(|p| p.coin == line.crypto) {
+                Some(position) => position.value += line.fiat_value(),
+                None => positions.push(rebalance::Position { coin: line.crypto.clone(), value: line.fiat_value(), price: line.price.value, target: 0.0 }),
+            }
+        }
+        let missing: Vec<String> = target.iter().map(|(coin, _)| coin.clone()).filter(|coin| positions.iter().all(|p| p.coin != *coin)).collect();
+        for (coin, quote) in self.batched_quotes(missing, &fiat, verbose).await? {
+            positions.push(rebalance::Position { coin, value: 0.0, price: quote?.value, target: 0.0 });
+        }
+        for (coin, share) in target {
+            if let Some(position) = positions.iter_mut().find(|p| p.coin == coin) {
+                position.target = share;
+            }
+        }
+        if positions.iter().map(|p| p.value).sum::<f64>() <= 0.0 {
+            return Err(CryptoError::InvalidInput(format!("Nothing in {} is worth anything to rebalance", wallet)));
+        }
+        Ok(rebalance::render(wallet, &fiat, band, &positions))
+    }
+
+    // Buys for one period of `dca-plan`, splitting the budget by the allocation's weights
+    pub async fn dca_plan(&self, allocation: &str, budget: f64, fiat: &str, verbose: bool) -> Result<String, CryptoError> {
+        if budget.is_nan() || budget <= 0.0 {
+            return Err(CryptoError::InvalidInput(format!("Can't split a budget of {}; give a positive --budget", budget)));
+        }
+        let allocation = dca::parse_allocation(allocation)?;
+        let coins = allocation.iter().map(|(coin, _)| coin.clone()).collect();
+        let quotes = self.batched_quotes(coins, fiat, verbose).await?;
+        let buys = allocation
+            .into_iter()
+            .zip(quotes)
+            .map(|((coin, share), (_, quote))| Ok(dca::Buy { coin, share, price: quote? }))
+            .collect::<Result<Vec<_>, CryptoError>>()?;
+        Ok(dca::render_plan(budget, fiat, &buys))
+    }
+
+    // The imported transactions `returns` counts, and the coins they hold by the end of `to`
+    pub fn returns_transactions(&self, exchange: Option<&str>, to: chrono::NaiveDate) -> Result<(Vec<transactions::Transaction>, Vec<String>), CryptoError> {
+        let store = self.transaction_store()?;
+        let transactions: Vec<_> = store.all().iter().filter(|t| exchange.is_none_or(|name| t.source == name)).cloned().collect();
+        if transactions.is_empty() {
+            return Err(CryptoError::InvalidInput("No transactions imported; run import-history first".to_string()));
+        }
+        let assets = returns::assets(&transactions, to);
+        Ok((transactions, assets))
+    }
+
+    pub async fn gains(&self, method: Option<&str>, fiat: &str, year: Option<i32>, verbose: bool) -> Result<String, CryptoError> {
+        let (disposals, method, sources) = self.disposals(method, fiat, year, verbose).await?;
+        Ok(lots::render(&disposals, method, fiat, &sources))
+    }
+
+    // The year's sales as rows for IRS Form 8949, always in USD
+    pub async fn export_8949(&self, method: Option<&str>, year: i32, verbose: bool) -> Result<String, CryptoError> {
+        let (disposals, _, _) = self.disposals(method, "usd", Some(year), verbose).await?;
+        Ok(lots::form_8949(&disposals))
+    }
+
+    // Sales matched to lots by `method` (else lot_method), with the providers of the closes used
+    async fn disposals(
+        &self,
+        method: Option<&str>,
+        fiat: &str,
+        year: Option<i32>,
+        verbose: bool,
+    ) -> Result<(Vec<lots::Disposal>, lots::Method, String), CryptoError> {
+        let name = method.unwrap_or(&self.lot_method);
+        let method = lots::Method::parse(name)
+            .ok_or_else(|| CryptoError::ConfigError(format!("Unknown lot method '{}'; use fifo, lifo or hifo", name)))?;
+        let today = chrono::Utc::now().date_naive();
+        let (transactions, assets) = self.returns_transactions(None, today)?;
+        let days = backtest_days(transactions[0].time.date_naive());
+        let (prices, sources) = self.asset_histories(assets, fiat, days, verbose).await?;
+        let value = |asset: &str, date: chrono::NaiveDate| match asset == fiat {
+            true => Ok(1.0),
+            false => prices
+                .get(asset)
+                .and_then(|closes| closes.get(&date))
+                .copied()
+                .ok_or_else(|| CryptoError::InvalidInput(format!("No {} close for {} on {}", fiat, asset, date))),
+        };
+        let mut disposals = lots::realize(&transactions, method, value)?;
+        disposals.retain(|disposal| year.is_none_or(|year| chrono::Datelike::year(&disposal.disposed) == year));
+        Ok((disposals, method, sources))
+    }
+
+    pub async fn returns(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        fiat: &str,
+        benchmark: Option<&str>,
+        exchange: Option<&str>,
+        verbose: bool,
+    ) -> Result<String, CryptoError> {
+        let (from, to) = returns_range(from, to)?;
+        let (transactions, assets) = self.returns_transactions(exchange, to)?;
+        let days = backtest_days(from - chrono::Days::new(1));
+        let (prices, sources) = self.asset_histories(assets, fiat, days, verbose).await?;
+        let returns = returns::Returns::compute(&transactions, &prices, fiat, from, to)?;
+        let benchmark = match benchmark.map(coins::resolve_symbol) {
+            Some(coin) => {
+                let (closes, _) = self.close_history(&coin, fiat, days, verbose).await?;
+                let start = from - chrono::Days::new(1);
+                let change = match (closes.get(&start), closes.get(&to)) {
+                    (Some(start), Some(end)) => end / start - 1.0,
+                    _ => return Err(CryptoError::InvalidInput(format!("No {} closes for {} on {} and {}", fiat, coin, start, to))),
+                };
+                Some((coin, change))
+            }
+            None => None,
+        };
+        Ok(returns.render(fiat, &sources, benchmark.as_ref().map(|(coin, change)| (coin.as_str(), *change))))
+    }
+}
+
+// `returns --from/--to`, the last day defaulting to today
+pub fn returns_range(from: &str, to: Option<&str>) -> Result<(chrono::NaiveDate, chrono::NaiveDate), CryptoError> {
+    let parse = |flag: &str, date: &str| {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| CryptoError::InvalidInput(format!("--{} must be a date like 2024-01-01, got '{}'", flag, date)))
+    };
+    let today = chrono::Utc::now().date_naive();
+    let from = parse("from", from)?;
+    let to = to.map(|to| parse("to", to)).transpose()?.unwrap_or(today);
+    if to > today {
+        return Err(CryptoError::InvalidInput(format!("--to {} is in the future", to)));
+    }
+    if from > to {
+        return Err(CryptoError::InvalidInput(format!("--from {} is after --to {}", from, to)));
+    }
+    Ok((from, to))
+}
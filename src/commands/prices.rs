@@ -0,0 +1,291 @@
+use futures::future::try_join;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use crate::cache::{Cache, CacheEntry, Validators};
+use crate::providers::{PriceProvider, PriceQuote, Ticker};
+use crate::{CryptoError, CryptoTool, Lookup, coins, suggest, tidy};
+
+pub struct Spread {
+    market: String,
+    venues: Vec<(String, Result<Ticker, CryptoError>)>,
+}
+
+impl CryptoTool {
+    pub async fn get_current_price(&self, crypto: &str, fiat: &str, verbose: bool) -> Result<f64, CryptoError> {
+        Ok(self.lookup_price(crypto, fiat, verbose).await?.value)
+    }
+
+    pub async fn lookup_price(&self, crypto: &str, fiat: &str, verbose: bool) -> Result<Lookup, CryptoError> {
+        let key = Cache::price_key(crypto, fiat);
+        if self.offline {
+            return self.read_offline_cache(&key, &format!("price for {}/{}", crypto, fiat));
+        }
+        if self.cache.get_fresh(&key)?.is_some()
+            && let Some(entry) = self.cache.get(&key)
+        {
+            if verbose {
+                eprintln!("Using cached price for {}/{}", crypto, fiat);
+            }
+            return Ok(Lookup::cached(entry));
+        }
+
+        self.in_flight.run(&key, self.fetch_price(&key, crypto, fiat, verbose)).await
+    }
+
+    // A price asked for now even while the cache holds a fresh one, for comparing prices over
+    // less time than the cache TTL; it still refreshes the cache
+    pub async fn live_price(&self, crypto: &str, fiat: &str, verbose: bool) -> Result<Lookup, CryptoError> {
+        let key = Cache::price_key(crypto, fiat);
+        self.in_flight.run(&key, self.fetch_price(&key, crypto, fiat, verbose)).await
+    }
+
+    async fn fetch_price(&self, key: &str, crypto: &str, fiat: &str, verbose: bool) -> Result<Lookup, CryptoError> {
+        let stale = self.cache.get(key).filter(|entry| !entry.validators.is_empty());
+        let providers: Vec<_> = self.providers.price.iter().filter(|p| p.supports(crypto)).map(|p| p.as_ref()).collect();
+        let validators = stale.as_ref().map(|entry| &entry.validators);
+        // A 304 only answers a conditional request; to any other it's a provider gone wrong
+        let fetch = async |p: &dyn PriceProvider| match p.price(&self.client, crypto, fiat, validators, verbose).await? {
+            PriceQuote::NotModified if stale.is_none() => Err(CryptoError::api(p.name(), "answered 304 to an unconditional request")),
+            quote => Ok(quote),
+        };
+        let none = || CryptoError::InvalidInput(format!("No price provider supports {}", crypto));
+        let error = match (self.first_answer(&providers, |p| p.name(), verbose, none, fetch).await, &stale) {
+            (Ok((PriceQuote::Fresh { price, validators }, provider)), _) => {
+                let entry = self.cache.insert(key.to_string(), price, provider.name(), validators);
+                if verbose {
+                    eprintln!("Fetched price from {}: {}", provider.name(), price);
+                }
+                return self.log_price(crypto, fiat, Lookup::fetched(entry));
+            }
+            (Ok((PriceQuote::NotModified, provider)), Some(entry)) => {
+                self.cache.refresh(key);
+                if verbose {
+                    eprintln!("{} price unchanged (304), refreshed cache for {}/{}", provider.name(), crypto, fiat);
+                }
+                return self.log_price(crypto, fiat, Lookup::fetched(CacheEntry { fetched_at: SystemTime::now(), ..entry.clone() }));
+            }
+            // `fetch` already turned these into errors, to try the next provider
+            (Ok((PriceQuote::NotModified, provider)), None) => CryptoError::api(provider.name(), "answered 304 to an unconditional request"),
+            (Err(error), _) => error,
+        };
+        // A fiat the providers don't quote is worked out from the USD price instead
+        if matches!(error, CryptoError::NotFound { .. }) && fiat != "usd" {
+            match self.derive_price(key, crypto, fiat, verbose).await {
+                Ok(lookup) => return Ok(lookup),
+                Err(e) if verbose => eprintln!("Warning: converting via USD failed ({})", e),
+                Err(_) => {}
+            }
+        }
+        Err(self.unknown_price(error, crypto, fiat))
+    }
+
+    // A price no provider has is usually a misspelt fiat or coin, so `error` gets the nearest
+    // names suggested: fiats from the currency codes, coins from the built-in tickers, the coin
+    // list and coins looked up before
+    fn unknown_price(&self, error: CryptoError, crypto: &str, fiat: &str) -> CryptoError {
+        let CryptoError::NotFound { provider, what } = error else {
+            return error;
+        };
+        let listed = self.coin_listings.get().cloned().flatten().or_else(|| self.coin_list.saved()).unwrap_or_default();
+        let known = coins::known_coins(&self.cache);
+        // A quote that's a coin is priced in that coin
+        let quote_is_coin = coins::resolve_symbol(fiat) != fiat
+            || known.iter().any(|coin| coin == fiat)
+            || listed.iter().any(|listing| listing.id == fiat || listing.symbol == fiat);
+        let currencies = coins::FIAT_CODES.iter().copied().chain(["xau", "xag"]);
+        if !coins::is_currency(fiat) && !quote_is_coin {
+            let suggestions = suggest::did_you_mean(&suggest::nearest(fiat, currencies));
+            return CryptoError::InvalidInput(format!("Unknown fiat '{}': no provider quotes it{}", fiat, suggestions));
+        }
+        let names = coins::symbol_names()
+            .chain(known.iter().map(String::as_str))
+            .chain(listed.iter().flat_map(|listing| [listing.id.as_str(), listing.symbol.as_str()]));
+        let what = format!("{}{}", what, suggest::did_you_mean(&suggest::nearest(crypto, names)));
+        CryptoError::NotFound { provider, what }
+    }
+
+    async fn derive_price(&self, key: &str, crypto: &str, fiat: &str, verbose: bool) -> Result<Lookup, CryptoError> {
+        let usd = Box::pin(self.lookup_price(crypto, "usd", verbose)).await?;
+        let (price, fx_source) = match coins::is_currency(fiat) {
+            true => {
+                let (rate, fx_source) = self.fx_rate("usd", fiat, verbose).await?;
+                let price = usd.value * rate;
+                if verbose {
+                    let fiat = fiat.to_uppercase();
+                    eprintln!("Converted via USD: {} USD × {} {}/USD ({}) = {} {}", tidy(usd.value), rate, fiat, fx_source, tidy(price), fiat);
+                }
+                (price, fx_source)
+            }
+            // Priced in another coin: divided by that coin's USD price
+            false => {
+                let quote = Box::pin(self.lookup_price(&coins::resolve_symbol(fiat), "usd", verbose)).await?;
+                if quote.value <= 0.0 {
+                    return Err(CryptoError::NotFound { provider: quote.source, what: format!("a usd price for {} to divide by", fiat) });
+                }
+                let price = usd.value / quote.value;
+                if verbose {
+                    let fiat = fiat.to_uppercase();
+                    eprintln!("Converted via USD: {} USD ÷ {} USD/{} ({}) = {} {}", tidy(usd.value), tidy(quote.value), fiat, quote.source, tidy(price), fiat);
+                }
+                (price, quote.source)
+            }
+        };
+        let source = match usd.source == fx_source {
+            true => usd.source,
+            false => format!("{} + {}", usd.source, fx_source),
+        };
+        self.log_price(crypto, fiat, Lookup::fetched(self.cache.insert(key.to_string(), price, &source, Validators::default())))
+    }
+
+    // Passes `price`, just fetched, through --log-prices on the way out
+    fn log_price(&self, crypto: &str, fiat: &str, price: Lookup) -> Result<Lookup, CryptoError> {
+        if let Some(log) = &self.price_log {
+            log.append(crypto, fiat, &price)?;
+        }
+        Ok(price)
+    }
+
+    // How many of `to` one `from` buys, cached like a price
+    async fn fx_rate(&self, from: &str, to: &str, verbose: bool) -> Result<(f64, String), CryptoError> {
+        let key = Cache::fx_key(from, to);
+        if self.cache.get_fresh(&key)?.is_some()
+            && let Some(entry) = self.cache.get(&key)
+        {
+            return Ok((entry.value, entry.source.unwrap_or_else(|| "unknown".to_string())));
+        }
+        let providers: Vec<_> = self.providers.fx.iter().map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No exchange rate from {} to {}", from, to));
+        let (rate, provider) = self.first_answer(&providers, |p| p.name(), false, none, |p| p.rate(&self.client, from, to, verbose)).await?;
+        self.cache.insert(key, rate, provider.name(), Validators::default());
+        Ok((rate, provider.name().to_string()))
+    }
+
+    // Every exchange's bid and ask at once. Exchanges that fail are listed with the error; it's
+    // only an error when all of them do.
+    pub async fn spread(&self, crypto: &str, fiat: &str, verbose: bool) -> Result<Spread, CryptoError> {
+        let (base, quote) = (coins::ticker(crypto), fiat.to_uppercase());
+        let venues = futures::future::join_all(self.providers.tickers.iter().map(async |provider| {
+            if !self.breakers.allow(provider.name()) {
+                return (provider.name().to_string(), Err(CryptoError::skipped(provider.name())));
+            }
+            let result = provider.ticker(&self.client, &base, &quote, verbose).await;
+            self.breakers.record(provider.name(), result.as_ref().err());
+            (provider.name().to_string(), result)
+        }))
+        .await;
+        if venues.iter().all(|(_, result)| result.is_err()) {
+            let error = venues.into_iter().find_map(|(_, result)| result.err());
+            return Err(error.unwrap_or_else(|| CryptoError::InvalidInput("No exchange to quote from".to_string())));
+        }
+        Ok(Spread { market: format!("{}/{}", base, quote), venues })
+    }
+
+    // Prices of each coin, in order, batched where a provider can and one by one otherwise
+    pub async fn batched_quotes(&self, coins: Vec<String>, fiat: &str, verbose: bool) -> Result<Vec<(String, Result<Lookup, CryptoError>)>, CryptoError> {
+        let batched = self.batch_prices(&coins, fiat, verbose).await?;
+        Ok(futures::future::join_all(coins.into_iter().map(async |coin| {
+            let quote = match batched.get(&coin) {
+                Some(lookup) => Ok(lookup.clone()),
+                None => self.lookup_price(&coin, fiat, verbose).await,
+            };
+            (coin, quote)
+        }))
+        .await)
+    }
+
+    pub fn batch_price_provider(&self, coins: &[String]) -> Option<&dyn PriceProvider> {
+        if self.offline {
+            return None;
+        }
+        self.providers
+            .price
+            .iter()
+            .find(|p| coins.iter().all(|c| p.supports(c)) && p.batch_limit() > 1 && self.breakers.allow(p.name()))
+            .map(|p| p.as_ref())
+    }
+
+    // Prices of the coins without a fresh cache entry, a few requests at a time from the first
+    // provider with a batch endpoint. Coins left out are looked up one by one as usual.
+    async fn batch_prices(&self, coins: &[String], fiat: &str, verbose: bool) -> Result<HashMap<String, Lookup>, CryptoError> {
+        // Peeked rather than counted: lookup_price counts the coins the batch leaves to it
+        let stale: Vec<String> =
+            coins.iter().filter(|coin| self.cache.peek_fresh(&Cache::price_key(coin, fiat)).is_none()).cloned().collect();
+        let mut batched = HashMap::new();
+        let Some(provider) = self.batch_price_provider(&stale).filter(|_| stale.len() > 1) else {
+            return Ok(batched);
+        };
+        for chunk in stale.chunks(provider.batch_limit()) {
+            let result = provider.prices(&self.client, chunk, fiat, verbose).await;
+            self.breakers.record(provider.name(), result.as_ref().err());
+            match result {
+                Ok(prices) => {
+                    for (coin, price) in prices.into_iter().filter(|(coin, _)| chunk.contains(coin)) {
+                        self.cache.count_miss();
+                        let entry = self.cache.insert(Cache::price_key(&coin, fiat), price, provider.name(), Validators::default());
+                        let lookup = self.log_price(&coin, fiat, Lookup::fetched(entry))?;
+                        batched.insert(coin, lookup);
+                    }
+                }
+                Err(e) if verbose => {
+                    eprintln!("Warning: {} batch lookup failed ({}), looking up prices one by one", provider.name(), e);
+                }
+                Err(_) => {}
+            }
+        }
+        Ok(batched)
+    }
+
+    // Exchanges hold fiat too, which CoinGecko has no price for; other fiat currencies are
+    // converted through their bitcoin prices
+    pub async fn asset_price(&self, crypto: &str, fiat: &str, verbose: bool) -> Result<Lookup, CryptoError> {
+        if crypto == fiat {
+            return Ok(Lookup { value: 1.0, source: "par".to_string(), fetched_at: SystemTime::now(), cached: false });
+        }
+        if !coins::FIAT_CODES.contains(&crypto) {
+            return self.lookup_price(crypto, fiat, verbose).await;
+        }
+        let (in_fiat, in_asset) = try_join(self.lookup_price("bitcoin", fiat, verbose), self.lookup_price("bitcoin", crypto, verbose)).await?;
+        Ok(Lookup { value: in_fiat.value / in_asset.value, ..in_fiat })
+    }
+}
+
+impl Spread {
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!("{} bid/ask by exchange:", self.market)];
+        for (venue, result) in &self.venues {
+            lines.push(match result {
+                Ok(ticker) if ticker.market != self.market => {
+                    format!("  {} ({}): bid {}, ask {}", venue, ticker.market, ticker.bid, ticker.ask)
+                }
+                Ok(ticker) => format!("  {}: bid {}, ask {}", venue, ticker.bid, ticker.ask),
+                Err(e) => format!("  {}: unavailable ({})", venue, e),
+            });
+        }
+        // Buying on one exchange at its ask and selling on another at its bid
+        let quoted: Vec<(&str, &Ticker)> =
+            self.venues.iter().filter_map(|(venue, result)| Some((venue.as_str(), result.as_ref().ok()?))).collect();
+        let best = quoted
+            .iter()
+            .flat_map(|buy| quoted.iter().filter(move |sell| sell.0 != buy.0).map(move |sell| (buy, sell)))
+            .max_by(|(a_buy, a_sell), (b_buy, b_sell)| {
+                (a_sell.1.bid - a_buy.1.ask).total_cmp(&(b_sell.1.bid - b_buy.1.ask))
+            });
+        lines.push(match best {
+            Some(((buy, bought), (sell, sold))) => {
+                let spread = tidy(sold.bid - bought.ask);
+                format!(
+                    "Best spread: buy on {} at {}, sell on {} at {}: {} ({:.2}%)",
+                    buy,
+                    bought.ask,
+                    sell,
+                    sold.bid,
+                    spread,
+                    spread / bought.ask * 100.0
+                )
+            }
+            None => "Best spread: needs quotes from two exchanges".to_string(),
+        });
+        lines.join("\n")
+    }
+}
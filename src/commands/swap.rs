@@ -0,0 +1,83 @@
+use futures::future::join;
+
+use crate::providers::{PriceProvider, SwapProvider, SwapQuote, Token};
+use crate::{CryptoError, CryptoTool, coins};
+
+// A swap quote with the tokens it's for
+pub struct Swap {
+    provider: String,
+    amount: f64,
+    sell: Token,
+    buy: Token,
+    quote: SwapQuote,
+    // Share of the sold value lost against market prices; negative when the quote beats them
+    price_impact: Option<f64>,
+}
+
+impl CryptoTool {
+    // A swap side: the chain's gas coin by its id or symbol (ethereum, ETH), else a token from
+    // the token lists
+    async fn swap_token(&self, crypto: &str, chain_id: u64, token: &str, verbose: bool) -> Result<Token, CryptoError> {
+        if coins::is_gas_coin(crypto, token) {
+            return Ok(Token::native(token));
+        }
+        self.tokens.resolve(&self.client, crypto, chain_id, token, verbose).await
+    }
+
+    pub async fn quote_swap(&self, crypto: &str, amount: f64, from: &str, to: &str, verbose: bool) -> Result<Swap, CryptoError> {
+        if amount.is_nan() || amount <= 0.0 {
+            return Err(CryptoError::InvalidInput(format!("Can't quote selling {}; give a positive amount", amount)));
+        }
+        let chain_id = self.chain_id(crypto)?;
+        let sell = self.swap_token(crypto, chain_id, from, verbose).await?;
+        let buy = self.swap_token(crypto, chain_id, to, verbose).await?;
+        let providers: Vec<&dyn SwapProvider> =
+            self.providers.swap.iter().filter(|p| p.supports(chain_id)).map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No swap provider quotes on {}; add a 0x API key", crypto));
+        let (quote, provider) =
+            self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.quote(&self.client, chain_id, &sell, &buy, amount, verbose)).await?;
+        let (sell_price, buy_price) = join(self.market_price(crypto, &sell, verbose), self.market_price(crypto, &buy, verbose)).await;
+        let price_impact =
+            sell_price.zip(buy_price).map(|(sell_price, buy_price)| 1.0 - quote.buy_amount * buy_price / (amount * sell_price));
+        Ok(Swap { provider: provider.name().to_string(), amount, sell, buy, quote, price_impact })
+    }
+
+    // USD price of a swap side, to measure the quote against. None when no provider has one.
+    async fn market_price(&self, crypto: &str, token: &Token, verbose: bool) -> Option<f64> {
+        if token.is_native() {
+            return self.get_current_price(crypto, "usd", verbose).await.ok();
+        }
+        let contract = token.address.to_lowercase();
+        let providers: Vec<_> = self.providers.price.iter().map(|p| p.as_ref()).collect();
+        // A provider without this token's price is passed over like one that failed
+        let fetch = async |p: &dyn PriceProvider| {
+            let prices = p.token_prices(&self.client, crypto, std::slice::from_ref(&contract), "usd").await?;
+            prices.get(&contract).map(|quote| quote.price).ok_or_else(|| CryptoError::NotFound {
+                provider: p.name().to_string(),
+                what: format!("a price for {}", token.symbol),
+            })
+        };
+        let none = || CryptoError::InvalidInput("No price provider configured".to_string());
+        self.first_answer(&providers, |p| p.name(), false, none, fetch).await.ok().map(|(price, _)| price)
+    }
+}
+
+impl Swap {
+    pub fn render(&self, crypto: &str) -> String {
+        let (sell, buy) = (&self.sell.symbol, &self.buy.symbol);
+        let mut lines = vec![
+            format!("{} {} -> {} {} (quoted by {})", self.amount, sell, self.quote.buy_amount, buy, self.provider),
+            format!("Rate: {} {} per {}", self.quote.buy_amount / self.amount, buy, sell),
+        ];
+        lines.push(match self.price_impact {
+            Some(impact) => format!("Price impact: {:.2}% against market prices", impact * 100.0),
+            None => "Price impact: unknown, no market price for both tokens".to_string(),
+        });
+        lines.push(match (self.quote.gas, self.quote.network_fee) {
+            (Some(gas), Some(fee)) => format!("Gas: {} units, about {} {}", gas, fee, crypto.to_uppercase()),
+            (Some(gas), None) => format!("Gas: {} units", gas),
+            _ => "Gas: unknown".to_string(),
+        });
+        lines.join("\n")
+    }
+}
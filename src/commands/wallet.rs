@@ -0,0 +1,268 @@
+use futures::future::try_join;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+
+use crate::cache::{Cache, Validators};
+use crate::providers::BalanceProvider;
+use crate::{CryptoError, CryptoTool, Lookup, WalletLine, addressbook, descriptor};
+use super::balances::descriptor_address;
+
+// Which tokens held by a wallet's EVM addresses count towards its total
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WalletTokens {
+    None,
+    // Those that pass the spam filter
+    Verified,
+    All,
+}
+
+// Wallet lines looked up at the same time
+pub(crate) const WALLET_CONCURRENCY: usize = 8;
+
+impl CryptoTool {
+    // A wallet file's lines with its descriptors scanned, one line per used address
+    async fn wallet_addresses(&self, wallet: &str, verbose: bool) -> Result<Vec<(String, String)>, CryptoError> {
+        let mut lines = Vec::new();
+        for (crypto, address) in self.read_wallet(wallet)? {
+            match descriptor::is_descriptor(&address) {
+                true => lines.extend(self.descriptor_addresses(&crypto, &address, verbose).await?.into_iter().map(|address| (crypto.clone(), address))),
+                false => lines.push((crypto, address)),
+            }
+        }
+        Ok(lines)
+    }
+
+    // A wallet file's (coin, address) lines. A line with just an @name is that address book
+    // entry, and an address may be a descriptor, left as it is when it has more than one.
+    pub fn read_wallet(&self, wallet: &str) -> Result<Vec<(String, String)>, CryptoError> {
+        let contents = std::fs::read_to_string(wallet).map_err(|e| CryptoError::InvalidInput(format!("Failed to read wallet file: {}", e)))?;
+        let mut book = None;
+        let mut lines = Vec::new();
+        for line in contents.lines().filter(|line| !line.starts_with('#') && !line.trim().is_empty()) {
+            // Descriptors have commas of their own
+            match line.split_once(',') {
+                None if line.trim().starts_with('@') => {
+                    let book = match &mut book {
+                        Some(book) => book,
+                        None => book.insert(self.address_book()?),
+                    };
+                    let entry = book.get(line.trim())?;
+                    lines.push((entry.chain.clone(), entry.address.clone()));
+                }
+                Some((crypto, address)) if address.contains('(') || !address.contains(',') => {
+                    lines.push((crypto.to_string(), descriptor_address(address)?))
+                }
+                _ => return Err(CryptoError::InvalidInput(format!("Invalid wallet line: {}", line))),
+            }
+        }
+        Ok(lines)
+    }
+
+    // Wallet lines whose address has a name in the address book get it
+    fn name_wallet_lines(&self, lines: &mut [WalletLine]) -> Result<(), CryptoError> {
+        let Some(path) = &self.addressbook_path else {
+            return Ok(());
+        };
+        let book = addressbook::AddressBook::open(path)?;
+        for line in lines {
+            line.name = book.name_of(&line.crypto, &line.address).map(str::to_string);
+        }
+        Ok(())
+    }
+
+    pub fn discovery_provider(&self, crypto: &str) -> Option<&dyn BalanceProvider> {
+        self.providers
+            .balance
+            .iter()
+            .find(|p| p.discovers_tokens(crypto) && !self.breakers.is_open(p.name()))
+            .map(|p| p.as_ref())
+    }
+
+    // Every token `address` holds on `crypto`'s chain, valued in `fiat`, as wallet lines.
+    // Tokens the filter turns down are left out, or with `include_unverified` kept and, when
+    // they have no price, valued at 0.
+    pub async fn discover_tokens(
+        &self,
+        crypto: &str,
+        address: &str,
+        fiat: &str,
+        include_unverified: bool,
+        verbose: bool,
+    ) -> Result<Vec<WalletLine>, CryptoError> {
+        self.check_address(crypto, address)?;
+        let client = self.client_for_address(address)?;
+        let providers: Vec<&dyn BalanceProvider> =
+            self.providers.balance.iter().filter(|p| p.discovers_tokens(crypto)).map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput(format!("No provider lists the tokens on {}; add a Covalent key", crypto));
+        let (holdings, provider) =
+            self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.holdings(&client, crypto, address, verbose)).await?;
+        if holdings.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let contracts: Vec<String> = holdings.iter().map(|h| h.token.address.to_lowercase()).collect();
+        let providers: Vec<_> = self.providers.price.iter().map(|p| p.as_ref()).collect();
+        let none = || CryptoError::InvalidInput("No price provider configured".to_string());
+        let found = self.first_answer(&providers, |p| p.name(), verbose, none, |p| p.token_prices(&self.client, crypto, &contracts, fiat)).await;
+        let (price_source, prices) = match found {
+            Ok((prices, provider)) => (provider.name().to_string(), prices),
+            Err(e) => {
+                if verbose {
+                    eprintln!("Warning: no token prices ({})", e);
+                }
+                ("unpriced".to_string(), HashMap::new())
+            }
+        };
+        let now = SystemTime::now();
+        let mut left_out = 0;
+        let lines = holdings
+            .into_iter()
+            .filter_map(|holding| {
+                let quote = prices.get(&holding.token.address.to_lowercase());
+                if let Some(reason) = self.token_filter.reject(&holding.token, quote) {
+                    if verbose {
+                        eprintln!("{} {} ({}): {}", if include_unverified { "Unverified" } else { "Left out" }, holding.token.symbol, holding.token.address, reason);
+                    }
+                    if !include_unverified {
+                        left_out += 1;
+                        return None;
+                    }
+                }
+                let price = quote.map(|quote| quote.price);
+                Some(WalletLine {
+                    crypto: holding.token.symbol,
+                    address: address.to_string(),
+                    name: None,
+                    balance: Lookup { value: holding.balance, source: provider.name().to_string(), fetched_at: now, cached: false },
+                    price: Lookup {
+                        value: price.unwrap_or(0.0),
+                        source: if price.is_some() { price_source.clone() } else { "unpriced".to_string() },
+                        fetched_at: now,
+                        cached: false,
+                    },
+                })
+            })
+            .collect();
+        if left_out > 0 {
+            eprintln!(
+                "Left out {} token{} of {} that look like spam; --include-unverified counts them",
+                left_out,
+                if left_out == 1 { "" } else { "s" },
+                address
+            );
+        }
+        Ok(lines)
+    }
+
+    pub async fn get_wallet_balance(&self, wallet: &str, fiat: &str, verbose: bool) -> Result<Vec<WalletLine>, CryptoError> {
+        let tokens = if self.wallet_tokens { WalletTokens::Verified } else { WalletTokens::None };
+        self.wallet_lines(wallet, fiat, tokens, verbose).await
+    }
+
+    pub async fn wallet_lines(&self, wallet: &str, fiat: &str, tokens: WalletTokens, verbose: bool) -> Result<Vec<WalletLine>, CryptoError> {
+        let wallets = self.wallet_addresses(wallet, verbose).await?;
+        for (crypto, address) in &wallets {
+            self.check_address(crypto, address)?;
+        }
+        let batched = self.batch_balances(&wallets, verbose).await;
+        // Each EVM address once, however many lines it's on
+        let mut token_addresses: Vec<(String, String)> = Vec::new();
+        if tokens != WalletTokens::None {
+            for line in &wallets {
+                if self.discovery_provider(&line.0).is_some() && !token_addresses.contains(line) {
+                    token_addresses.push(line.clone());
+                }
+            }
+        }
+
+        // Lines are looked up a batch at a time, so a dead provider's circuit opens after the
+        // first few timeouts instead of every line waiting on it; lines sharing a coin share one
+        // price request
+        let mut lines: Vec<WalletLine> = stream::iter(wallets)
+            .map(async |(crypto, address)| {
+                let balance = async {
+                    match batched.get(&(crypto.clone(), address.clone())) {
+                        Some(lookup) => Ok(lookup.clone()),
+                        None => self.lookup_balance(&crypto, &address, verbose).await,
+                    }
+                };
+                let (balance, price) = try_join(balance, self.lookup_price(&crypto, fiat, verbose)).await?;
+                Ok::<_, CryptoError>(WalletLine { crypto, address, name: None, balance, price })
+            })
+            .buffered(WALLET_CONCURRENCY)
+            .try_collect()
+            .await?;
+        // A failed token listing leaves that address's tokens out rather than failing the wallet
+        for (crypto, address) in token_addresses {
+            match self.discover_tokens(&crypto, &address, fiat, tokens == WalletTokens::All, verbose).await {
+                Ok(tokens) => lines.extend(tokens),
+                Err(e) => eprintln!("Warning: tokens of {} left out of the total: {}", address, e),
+            }
+        }
+        // Likewise an exchange that can't be read
+        for name in &self.wallet_exchanges {
+            match self.exchange_lines(name, fiat, verbose).await {
+                Ok(holdings) => lines.extend(holdings),
+                Err(e) => eprintln!("Warning: {} holdings left out of the total: {}", name, e),
+            }
+        }
+        self.name_wallet_lines(&mut lines)?;
+        Ok(lines)
+    }
+
+    pub fn batch_provider(&self, crypto: &str) -> Option<&dyn BalanceProvider> {
+        // One request naming several addresses links them together, which per-address Tor
+        // circuits are meant to prevent
+        if self.offline || self.tor_isolation_proxy.is_some() {
+            return None;
+        }
+        self.providers
+            .balance
+            .iter()
+            .find(|p| p.supports(crypto) && p.batch_limit() > 1 && self.breakers.allow(p.name()))
+            .map(|p| p.as_ref())
+    }
+
+    // Wallet lines whose coin has several addresses, fetched a few requests at a time from the
+    // first provider with a batch endpoint for it. Lines left out (failed requests, addresses the
+    // provider doesn't know) are looked up one by one as usual.
+    async fn batch_balances(&self, wallets: &[(String, String)], verbose: bool) -> HashMap<(String, String), Lookup> {
+        let mut batched = HashMap::new();
+        for (crypto, addresses) in batch_groups(wallets) {
+            let Some(provider) = self.batch_provider(crypto) else {
+                continue;
+            };
+            for chunk in addresses.chunks(provider.batch_limit()) {
+                let result = provider.balances(&self.client, crypto, chunk, verbose).await;
+                self.breakers.record(provider.name(), result.as_ref().err());
+                match result {
+                    Ok(balances) => {
+                        for (address, balance) in balances.into_iter().filter(|(address, _)| chunk.contains(address)) {
+                            let entry = self.cache.insert(Cache::balance_key(crypto, &address), balance, provider.name(), Validators::default());
+                            batched.insert((crypto.to_string(), address), Lookup::fetched(entry));
+                        }
+                    }
+                    Err(e) if verbose => {
+                        eprintln!("Warning: {} batch lookup failed ({}), looking up addresses one by one", provider.name(), e);
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+        batched
+    }
+}
+
+// Distinct addresses per coin, for coins with more than one
+pub fn batch_groups(wallets: &[(String, String)]) -> BTreeMap<&str, Vec<String>> {
+    let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (crypto, address) in wallets {
+        let addresses = groups.entry(crypto).or_default();
+        if !addresses.contains(address) {
+            addresses.push(address.clone());
+        }
+    }
+    groups.retain(|_, addresses| addresses.len() > 1);
+    groups
+}
@@ -0,0 +1,69 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::providers::DailyClose;
+use crate::{chart, tidy};
+
+// Two coins' closes over the dates both have one, each rebased to 100 on the first
+pub struct Comparison {
+    pub dates: Vec<NaiveDate>,
+    pub a: Vec<f64>,
+    pub b: Vec<f64>,
+    // a's price in b's, on the first and last date
+    pub start_ratio: f64,
+    pub end_ratio: f64,
+}
+
+impl Comparison {
+    // None without two shared dates, or with a first price of zero to rebase from
+    pub fn new(a: &[DailyClose], b: &[DailyClose]) -> Option<Self> {
+        let others: HashMap<NaiveDate, f64> = b.iter().map(|close| (close.date, close.price)).collect();
+        let shared: BTreeMap<NaiveDate, (f64, f64)> =
+            a.iter().filter_map(|close| Some((close.date, (close.price, *others.get(&close.date)?)))).collect();
+        if shared.len() < 2 {
+            return None;
+        }
+        let &(first_a, first_b) = shared.values().next()?;
+        let &(last_a, last_b) = shared.values().last()?;
+        if first_a <= 0.0 || first_b <= 0.0 || last_b <= 0.0 {
+            return None;
+        }
+        Some(Comparison {
+            dates: shared.keys().copied().collect(),
+            a: shared.values().map(|(price, _)| price / first_a * 100.0).collect(),
+            b: shared.values().map(|(_, price)| price / first_b * 100.0).collect(),
+            start_ratio: first_a / first_b,
+            end_ratio: last_a / last_b,
+        })
+    }
+
+    pub fn render(&self, a: &str, b: &str, fiat: &str, source: &str, chart: bool) -> String {
+        let (first, last) = (self.dates[0], self.dates[self.dates.len() - 1]);
+        let (end_a, end_b) = (self.a[self.a.len() - 1], self.b[self.b.len() - 1]);
+        let mut lines = vec![
+            format!("{} vs {} in {} over {} daily closes, {} to {} ({}):", a, b, fiat.to_uppercase(), self.dates.len(), first, last, source),
+            format!("  {}: 100 -> {:.2} ({:+.2}%)", a, end_a, end_a - 100.0),
+            format!("  {}: 100 -> {:.2} ({:+.2}%)", b, end_b, end_b - 100.0),
+            format!(
+                "  {}/{}: {} -> {} ({:+.2}%)",
+                a,
+                b,
+                tidy(self.start_ratio),
+                tidy(self.end_ratio),
+                (self.end_ratio / self.start_ratio - 1.0) * 100.0
+            ),
+        ];
+        // Both started at 100, so the gap in points is the gap in percent returned
+        let gap = end_a - end_b;
+        lines.push(match gap {
+            gap if gap > 0.0 => format!("{} outperformed {} by {:.2} points", a, b, gap),
+            gap if gap < 0.0 => format!("{} outperformed {} by {:.2} points", b, a, -gap),
+            _ => format!("{} and {} performed the same", a, b),
+        });
+        if chart {
+            lines.push(String::new());
+            lines.extend(chart::draw(&self.dates, &[(a, '*', &self.a), (b, 'o', &self.b)]));
+        }
+        lines.join("\n")
+    }
+}
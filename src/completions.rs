@@ -0,0 +1,73 @@
+use clap::{Command, CommandFactory};
+use clap::builder::PossibleValuesParser;
+use clap_complete::Shell;
+
+use crate::cache::Cache;
+use crate::coins::{FIAT_CODES, known_coins};
+use crate::{Cli, CryptoError};
+
+// Stands in for the coin list in the generated script until it's swapped for a call back
+// into the binary
+const COINS_PLACEHOLDER: &str = "__COINWAGON_COINS__";
+
+// Coin and fiat arguments are free-form when parsing; for completion only they get values
+// attached so the generated script can offer them. Fiat codes are fixed and written into the
+// script; coins are asked of `coinwagon complete-coins` each time a coin is completed, so the
+// script keeps up with the cache and coin list without being regenerated. Only bash, zsh and
+// fish complete coins; PowerShell and Elvish scripts complete subcommands and options only.
+pub fn generate(shell: Shell) -> Result<String, CryptoError> {
+    let cli = Cli::command();
+    let mut coin_commands = Vec::new();
+    // Hidden subcommands (`complete-coins`) would otherwise be offered too
+    let subcommands = cli.get_subcommands().filter(|sub| !sub.is_hide_set()).map(|sub| {
+        if sub.get_arguments().any(|a| a.get_id() == "crypto") {
+            coin_commands.push(sub.get_name().to_string());
+        }
+        // In place, so positionals keep their order
+        sub.clone().mut_args(|arg| match arg.get_id().as_str() {
+            "crypto" => arg.value_parser(PossibleValuesParser::new([COINS_PLACEHOLDER])),
+            "fiat" => arg.value_parser(PossibleValuesParser::new(FIAT_CODES)),
+            _ => arg,
+        })
+    });
+    let mut command = Command::new("coinwagon")
+        .args(cli.get_arguments().cloned())
+        .subcommands(subcommands.collect::<Vec<_>>());
+
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, "coinwagon", &mut script);
+    let script =
+        String::from_utf8(script).map_err(|e| CryptoError::RuntimeError(format!("Invalid completion script: {}", e)))?;
+
+    Ok(match shell {
+        Shell::Bash => script.replace(COINS_PLACEHOLDER, "$(coinwagon complete-coins 2>/dev/null)"),
+        Shell::Zsh => script
+            .replace(&format!("({})", COINS_PLACEHOLDER), "_coinwagon_coins")
+            .replacen(
+                "autoload -U is-at-least\n",
+                "autoload -U is-at-least\n\n_coinwagon_coins() {\n    local -a coins\n    coins=(${(f)\"$(coinwagon complete-coins 2>/dev/null)\"})\n    compadd -a coins\n}\n",
+                1,
+            ),
+        Shell::Fish => {
+            let mut script = script;
+            for name in coin_commands {
+                script.push_str(&format!(
+                    "complete -c coinwagon -n \"__fish_coinwagon_using_subcommand {}\" -f -a \"(coinwagon complete-coins)\"\n",
+                    name
+                ));
+            }
+            script
+        }
+        _ => script,
+    })
+}
+
+// What `complete-coins` prints, one per line: coins looked up before (via the cache) and
+// `listed`, the ids in the saved coin list, if any
+pub fn coins(cache: &Cache, listed: Vec<String>) -> String {
+    let mut coins = known_coins(cache);
+    coins.extend(listed);
+    coins.sort();
+    coins.dedup();
+    coins.join("\n")
+}
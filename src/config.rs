@@ -0,0 +1,537 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::CryptoError;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub network: NetworkConfig,
+    pub tor: TorConfig,
+    pub tls: TlsConfig,
+    pub cache: CacheConfig,
+    pub breaker: BreakerConfig,
+    // Provider name -> base URL used instead of the public API, e.g. a local mock server
+    pub endpoints: HashMap<String, String>,
+    // Provider name -> extra headers sent with each of its requests, e.g. auth for a
+    // self-hosted explorer
+    pub headers: HashMap<String, HashMap<String, String>>,
+    // Provider name -> API keys to spread its requests over
+    pub api_keys: HashMap<String, ApiKeysConfig>,
+    // Provider name -> how many requests its plan allows a day or a month
+    pub quotas: HashMap<String, QuotaConfig>,
+    // EVM coin id -> JSON-RPC endpoint for its balances, e.g. an Alchemy or Infura URL
+    pub rpc: HashMap<String, String>,
+    // Cassette file to record provider responses to, or to replay them from (--record/--replay)
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    // HAR file logging every provider request and response (--debug-http)
+    pub debug_http: Option<String>,
+    // JSON Lines file every provider call is appended to (--audit-log)
+    pub audit_log: Option<String>,
+    // JSON Lines file every price fetched from a provider is appended to (--log-prices)
+    pub log_prices: Option<String>,
+    // Language of labels and error messages, like "de" (--lang); defaults to the locale
+    pub lang: Option<String>,
+    // Timezone timestamps are shown in, "local" or an IANA name (--timezone); defaults to local
+    pub timezone: Option<String>,
+    // Serve everything from the persistent cache (same as --offline)
+    pub offline: bool,
+    // Print the requests a command would make instead of making them (same as --dry-run)
+    pub dry_run: bool,
+    pub plugins: Vec<PluginConfig>,
+    pub chains: Vec<ChainConfig>,
+    pub tokens: TokensConfig,
+    // binance, kraken or coinbase -> read-only API credentials for `exchange-balance`
+    pub exchanges: HashMap<String, ExchangeConfig>,
+    pub transactions: TransactionsConfig,
+    pub peg: PegConfig,
+    pub watchlist: WatchlistConfig,
+    pub risk: RiskConfig,
+    // Basket name -> coin -> amount of it in one unit of the basket, for `basket`
+    pub baskets: HashMap<String, HashMap<String, f64>>,
+    pub alerts: AlertsConfig,
+    pub addressbook: AddressBookConfig,
+    pub coin_list: CoinListConfig,
+    // The file this was read from, for reloading on SIGHUP; None for the defaults
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+// Where the provider's list of every coin is kept, and how often it's downloaded again
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CoinListConfig {
+    // Defaults to coin-list.json next to the persistent cache file
+    pub path: Option<String>,
+    pub refresh_hours: u64,
+}
+
+impl Default for CoinListConfig {
+    fn default() -> Self {
+        CoinListConfig { path: None, refresh_hours: 24 }
+    }
+}
+
+// What the `alerts` daemon watches and the rules it fires on
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    // Wallet file each poll snapshots, for the rules on the portfolio
+    pub wallet: Option<String>,
+    pub fiat: String,
+    // JSON Lines file each poll's snapshot is appended to; unset keeps only the latest
+    pub snapshots: Option<String>,
+    pub rules: Vec<AlertRule>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig { wallet: None, fiat: "usd".to_string(), snapshots: None, rules: Vec::new() }
+    }
+}
+
+// An [[alerts.rules]] entry, picked by its `kind`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertRule {
+    // A coin's share of the wallet more than `points` percentage points off `target`, given as
+    // coin=weight pairs like `rebalance --target`
+    Drift { target: String, points: f64 },
+    // The wallet worth less than `below`, in the [alerts] fiat
+    Value { below: f64 },
+    // The wallet `percent` or more under its highest snapshot of the last `days`
+    Drawdown {
+        percent: f64,
+        #[serde(default = "default_drawdown_days")]
+        days: u32,
+    },
+    // `coin`'s price, in the [alerts] fiat, `percent` or more up or down within `minutes`
+    Move {
+        coin: String,
+        percent: f64,
+        #[serde(default = "default_move_minutes")]
+        minutes: u32,
+    },
+    // `coin`'s last 24 hours of volume at least `multiple` times its daily average over `days`
+    Volume {
+        coin: String,
+        multiple: f64,
+        #[serde(default = "default_volume_days")]
+        days: u32,
+    },
+    // `coin`'s latest daily close crossing `indicator` (like `indicators --set`) in `direction`:
+    // the price over a moving average, MACD over its signal line, or RSI past 70 or 30
+    Cross { coin: String, indicator: String, direction: CrossDirection },
+    // A transaction moving `min` or more of `coin` reaching its mempool, as `whales` prints
+    Whale { coin: String, min: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossDirection {
+    Above,
+    Below,
+}
+
+fn default_move_minutes() -> u32 {
+    60
+}
+
+fn default_volume_days() -> u32 {
+    30
+}
+
+fn default_drawdown_days() -> u32 {
+    30
+}
+
+// Where `addressbook` keeps named addresses
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct AddressBookConfig {
+    // Defaults to ~/.local/share/coinwagon/addressbook.json
+    pub path: Option<String>,
+}
+
+// The annual risk-free rate, in percent, `risk` measures Sharpe ratios against
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub risk_free_rate: f64,
+}
+
+// Where `watchlist` keeps its coins, and what `current-price --watchlist` quotes them in
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WatchlistConfig {
+    // Defaults to ~/.local/share/coinwagon/watchlist.json
+    pub path: Option<String>,
+    pub fiat: String,
+}
+
+impl Default for WatchlistConfig {
+    fn default() -> Self {
+        WatchlistConfig { path: None, fiat: "usd".to_string() }
+    }
+}
+
+// How far `peg` lets a stablecoin drift from 1.00 before flagging it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PegConfig {
+    // Percent either way
+    pub threshold: f64,
+}
+
+impl Default for PegConfig {
+    fn default() -> Self {
+        PegConfig { threshold: 0.5 }
+    }
+}
+
+// Where `import-history` keeps exchange transactions
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct TransactionsConfig {
+    // Defaults to ~/.local/share/coinwagon/transactions.json
+    pub path: Option<String>,
+    // How `gains` matches sales to lots: fifo (the default), lifo or hifo, as the tax rules
+    // where you file allow
+    pub lot_method: Option<String>,
+}
+
+// An exchange account's API key. Give it read permissions only; coinwagon never trades.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExchangeConfig {
+    pub key: String,
+    // Unset with `keychain = true`
+    pub secret: Option<String>,
+    // Read the secret stored with `coinwagon secrets set <exchange>`
+    #[serde(default)]
+    pub keychain: bool,
+    // Count the account's holdings in wallet-balance totals
+    #[serde(default = "default_true")]
+    pub in_wallets: bool,
+    // Binance only lists trades by market, so `import-history` asks for these ("ETH/BTC");
+    // unset means each held asset against USDT
+    #[serde(default)]
+    pub trade_pairs: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Token lists `token-balance --token SYMBOL` looks symbols up in
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TokensConfig {
+    // URLs or local paths of Uniswap-format token lists, searched together
+    pub lists: Vec<String>,
+    // Downloaded lists are kept next to the cache file and fetched again after this long
+    pub refresh_hours: u64,
+    // Count every token held by a wallet's EVM addresses in wallet totals (same as
+    // wallet-balance --tokens)
+    pub in_wallets: bool,
+    // Discovered tokens to leave out, by contract address or symbol, and files listing more
+    // (one per line); --include-unverified keeps them
+    pub deny: Vec<String>,
+    pub deny_lists: Vec<String>,
+    // Leave out tokens below these, in the fiat they're valued in; 0 turns a check off
+    pub min_market_cap: f64,
+    pub min_volume_24h: f64,
+}
+
+impl Default for TokensConfig {
+    fn default() -> Self {
+        TokensConfig {
+            lists: vec!["https://tokens.uniswap.org".to_string()],
+            refresh_hours: 24,
+            in_wallets: false,
+            deny: Vec::new(),
+            deny_lists: Vec::new(),
+            min_market_cap: 0.0,
+            min_volume_24h: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginCapability {
+    Price,
+    Balance,
+}
+
+// A [[plugins]] entry: an external provider executable (see providers/plugin.rs)
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    // Coins this plugin answers for; it is tried before the built-in providers
+    pub coins: Vec<String>,
+    #[serde(default = "default_capabilities")]
+    pub provides: Vec<PluginCapability>,
+}
+
+fn default_capabilities() -> Vec<PluginCapability> {
+    vec![PluginCapability::Price, PluginCapability::Balance]
+}
+
+// A [[chains]] entry: a chain coinwagon has no provider for, looked up through a JSON-RPC
+// node (`rpc`) or an explorer that returns the balance as JSON (`explorer`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainConfig {
+    // Name used in commands and wallet files; its CoinGecko id, so it can be priced
+    pub name: String,
+    pub rpc: Option<String>,
+    // URL with `{address}` in place of the address
+    pub explorer: Option<String>,
+    // Dotted path to the balance in the explorer's response
+    #[serde(default = "default_balance_field")]
+    pub balance_field: String,
+    // The balance's smallest unit is 10^-decimals of a coin
+    #[serde(default = "default_decimals")]
+    pub decimals: i32,
+    #[serde(default)]
+    pub address_format: AddressFormat,
+    // EIP-155 chain id, for finding the chain's tokens in token lists
+    pub chain_id: Option<u64>,
+}
+
+fn default_balance_field() -> String {
+    "balance".to_string()
+}
+
+fn default_decimals() -> i32 {
+    18
+}
+
+// What a chain's addresses look like, checked before anything is sent
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct AddressFormat {
+    pub prefix: Option<String>,
+    pub length: Option<usize>,
+}
+
+impl AddressFormat {
+    pub fn check(&self, chain: &str, address: &str) -> Result<(), CryptoError> {
+        let prefixed = self.prefix.as_ref().is_none_or(|prefix| address.starts_with(prefix.as_str()));
+        let sized = self.length.is_none_or(|length| address.chars().count() == length);
+        if prefixed && sized {
+            return Ok(());
+        }
+        let mut expected = Vec::new();
+        if let Some(prefix) = &self.prefix {
+            expected.push(format!("starting with {}", prefix));
+        }
+        if let Some(length) = self.length {
+            expected.push(format!("{} characters long", length));
+        }
+        Err(CryptoError::InvalidInput(format!(
+            "{} doesn't look like a {} address (expected one {})",
+            address,
+            chain,
+            expected.join(" and ")
+        )))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rotation {
+    // A different key for each request
+    #[default]
+    RoundRobin,
+    // The same key until the provider rate limits it
+    OnRateLimit,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ApiKeysConfig {
+    pub keys: Vec<String>,
+    pub rotation: Rotation,
+    // Send the key in this header instead of where the provider usually takes it
+    pub header: Option<String>,
+    // Also use the keys stored with `coinwagon secrets set <provider>`
+    pub keychain: bool,
+    // What the plan behind each key allows. Requests are spaced out to stay under it instead
+    // of running into 429s; unset means no spacing, except the provider's anonymous limit
+    // while it has no keys.
+    pub requests_per_second: Option<f64>,
+}
+
+// Counted per UTC day and calendar month, in quota.json next to the persistent cache file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    pub daily: Option<u64>,
+    pub monthly: Option<u64>,
+    // Percent of a quota used that's warned about, once per run
+    pub warn_at: f64,
+    // Percent used at which the provider is skipped for the next one, as when it's down
+    pub switch_at: f64,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        QuotaConfig { daily: None, monthly: None, warn_at: 80.0, switch_at: 100.0 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    // http://, https:// or socks5:// URL; when unset reqwest falls back to HTTPS_PROXY/HTTP_PROXY
+    pub proxy: Option<String>,
+    pub connect_timeout_secs: u64,
+    // Whole request, including reading the body
+    pub timeout_secs: u64,
+    // Times a request that got HTTP 429 is retried after waiting as the provider asked
+    pub rate_limit_retries: u32,
+    // Longest wait for a provider's quota to come back; anything longer fails right away
+    pub max_retry_wait_secs: u64,
+    pub user_agent: String,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            proxy: None,
+            connect_timeout_secs: 10,
+            timeout_secs: 30,
+            rate_limit_retries: 2,
+            max_retry_wait_secs: 30,
+            user_agent: concat!("coinwagon/", env!("CARGO_PKG_VERSION")).to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub ttl_secs: u64,
+    // Keep prices and balances on disk between runs (required for --offline)
+    pub persistent: bool,
+    // Defaults to ~/.cache/coinwagon/cache.json
+    pub path: Option<String>,
+    // Shared cache across processes, e.g. redis://127.0.0.1/ (needs the `redis` feature)
+    pub redis_url: Option<String>,
+    pub redis_prefix: String,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            ttl_secs: 300,
+            persistent: true,
+            path: None,
+            redis_url: None,
+            redis_prefix: "coinwagon".to_string(),
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn resolved_path(&self) -> Option<PathBuf> {
+        if !self.persistent {
+            return None;
+        }
+        match &self.path {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::cache_dir().map(|dir| dir.join("coinwagon").join("cache.json")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BreakerConfig {
+    // Consecutive timeouts/5xx/429s before a provider is skipped; 0 never skips
+    pub failures: u32,
+    pub cooldown_secs: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            failures: 3,
+            cooldown_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    // PEM file with extra trusted roots (corporate MITM proxies, self-hosted explorers)
+    pub ca_bundle: Option<String>,
+    // Host -> accepted "sha256/<base64>" (or hex) SPKI pins of the server's leaf certificate
+    pub pins: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TorConfig {
+    pub enabled: bool,
+    // socks5h so hostnames (including .onion) are resolved by Tor, not locally
+    pub proxy: String,
+    // Use a separate circuit per looked-up address (via SOCKS auth isolation)
+    pub isolate: bool,
+    // Provider name -> .onion base URL used instead of the clearnet endpoint
+    pub onion: HashMap<String, String>,
+}
+
+impl Default for TorConfig {
+    fn default() -> Self {
+        TorConfig {
+            enabled: false,
+            proxy: "socks5h://127.0.0.1:9050".to_string(),
+            isolate: false,
+            onion: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    // Explicit path > $COINWAGON_CONFIG > ~/.config/coinwagon/config.toml (if present)
+    pub fn load(path: Option<&str>) -> Result<Self, CryptoError> {
+        let path = match path {
+            Some(p) => Some(PathBuf::from(p)),
+            None => match std::env::var("COINWAGON_CONFIG") {
+                Ok(p) => Some(PathBuf::from(p)),
+                Err(_) => default_path().filter(|p| p.exists()),
+            },
+        };
+
+        match path {
+            Some(path) => {
+                let contents = std::fs::read_to_string(&path).map_err(|e| {
+                    CryptoError::ConfigError(format!("Failed to read {}: {}", path.display(), e))
+                })?;
+                let config: Config = toml::from_str(&contents).map_err(|e| {
+                    CryptoError::ConfigError(format!("Failed to parse {}: {}", path.display(), e))
+                })?;
+                // reqwest takes a zero timeout literally and fails every request at once
+                if config.network.timeout_secs == 0 || config.network.connect_timeout_secs == 0 {
+                    return Err(CryptoError::ConfigError(format!(
+                        "{}: [network] timeout_secs and connect_timeout_secs must be at least 1",
+                        path.display()
+                    )));
+                }
+                Ok(Config { path: Some(path), ..config })
+            }
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("coinwagon").join("config.toml"))
+}
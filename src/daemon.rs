@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use crate::CryptoError;
+
+// What a long-running command was asked to do by a signal
+pub enum Signal {
+    // SIGTERM or Ctrl-C: finish the current poll and exit
+    Stop,
+    // SIGHUP: read the config file again
+    Reload,
+}
+
+// Stop and reload from the Service Control Manager, for a daemon running as a Windows service
+#[cfg(windows)]
+pub static SERVICE_STOP: tokio::sync::Notify = tokio::sync::Notify::const_new();
+#[cfg(windows)]
+pub static SERVICE_RELOAD: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+// Signals a daemon handles itself, caught from when this is made so none are missed mid-poll
+pub struct Signals {
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    pub fn catch() -> Result<Self, CryptoError> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            let catch = |kind| signal(kind).map_err(|e| CryptoError::RuntimeError(format!("Can't catch signals: {}", e)));
+            Ok(Signals { terminate: catch(SignalKind::terminate())?, interrupt: catch(SignalKind::interrupt())?, hangup: catch(SignalKind::hangup())? })
+        }
+        #[cfg(not(unix))]
+        Ok(Signals {})
+    }
+
+    pub async fn next(&mut self) -> Signal {
+        #[cfg(unix)]
+        {
+            tokio::select! {
+                _ = self.terminate.recv() => Signal::Stop,
+                _ = self.interrupt.recv() => Signal::Stop,
+                _ = self.hangup.recv() => Signal::Reload,
+            }
+        }
+        // A service has no console, so Ctrl-C can't be caught and its branch is left out
+        #[cfg(windows)]
+        {
+            tokio::select! {
+                Ok(()) = tokio::signal::ctrl_c() => Signal::Stop,
+                _ = SERVICE_STOP.notified() => Signal::Stop,
+                _ = SERVICE_RELOAD.notified() => Signal::Reload,
+            }
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+            Signal::Stop
+        }
+    }
+}
+
+// sd_notify: tells systemd about the service's state, e.g. "READY=1", when it started us with
+// Type=notify and so set NOTIFY_SOCKET. A Windows service reports the same states to the
+// Service Control Manager. Anywhere else it does nothing, and a failed send is only worth a
+// warning.
+pub fn notify(state: &str) {
+    #[cfg(unix)]
+    if let Some(socket) = std::env::var_os("NOTIFY_SOCKET")
+        && let Err(e) = send(&socket, state)
+    {
+        eprintln!("Warning: can't notify systemd: {}", e);
+    }
+    #[cfg(windows)]
+    crate::service::report(state);
+    #[cfg(not(any(unix, windows)))]
+    let _ = state;
+}
+
+#[cfg(unix)]
+fn send(socket: &std::ffi::OsStr, state: &str) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+    // "@name" is a socket in Linux's abstract namespace rather than a path
+    let address = match socket.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name)?
+        }
+        _ => SocketAddr::from_pathname(socket)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+// --pid-file: the process id, written at startup and removed when the daemon exits
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create(path: &str) -> Result<Self, CryptoError> {
+        std::fs::write(path, format!("{}\n", std::process::id()))
+            .map_err(|e| CryptoError::ConfigError(format!("Can't write PID file {}: {}", path, e)))?;
+        Ok(PidFile { path: PathBuf::from(path) })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,176 @@
+use chrono::{Days, Months, NaiveDate};
+
+use crate::providers::DailyClose;
+use crate::{CryptoError, Lookup, coins, tidy};
+
+// How often `backtest-dca` buys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    Daily,
+    Weekly,
+    Biweekly,
+    Monthly,
+}
+
+impl Interval {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "daily" => Some(Interval::Daily),
+            "weekly" => Some(Interval::Weekly),
+            "biweekly" => Some(Interval::Biweekly),
+            "monthly" => Some(Interval::Monthly),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Interval::Daily => "daily",
+            Interval::Weekly => "weekly",
+            Interval::Biweekly => "biweekly",
+            Interval::Monthly => "monthly",
+        }
+    }
+
+    // The `n`th buy date after `start`. Months are counted from the start so the 31st stays
+    // the last day of shorter months.
+    fn step(self, start: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match self {
+            Interval::Daily => start.checked_add_days(Days::new(n as u64)),
+            Interval::Weekly => start.checked_add_days(Days::new(7 * n as u64)),
+            Interval::Biweekly => start.checked_add_days(Days::new(14 * n as u64)),
+            Interval::Monthly => start.checked_add_months(Months::new(n)),
+        }
+    }
+}
+
+// A fixed amount bought every interval, against the same total bought at once on the first day
+pub struct Backtest {
+    pub interval: Interval,
+    pub amount: f64,
+    pub first: NaiveDate,
+    pub buys: usize,
+    pub units: f64,
+    pub first_price: f64,
+    pub latest: NaiveDate,
+    pub latest_price: f64,
+}
+
+impl Backtest {
+    // Buys at the close of each buy date, or the next day with one, until the history runs
+    // out. None when there's no close from `from` on.
+    pub fn run(closes: &[DailyClose], amount: f64, interval: Interval, from: NaiveDate) -> Option<Self> {
+        let start = closes.iter().position(|close| close.date >= from)?;
+        let (first, latest) = (&closes[start], &closes[closes.len() - 1]);
+        let mut index = start;
+        let (mut buys, mut units) = (0, 0.0);
+        for n in 0.. {
+            let Some(date) = interval.step(from, n) else { break };
+            let Some(offset) = closes[index..].iter().position(|close| close.date >= date) else { break };
+            index += offset;
+            units += amount / closes[index].price;
+            buys += 1;
+        }
+        Some(Backtest {
+            interval,
+            amount,
+            first: first.date,
+            buys,
+            units,
+            first_price: first.price,
+            latest: latest.date,
+            latest_price: latest.price,
+        })
+    }
+
+    pub fn invested(&self) -> f64 {
+        self.amount * self.buys as f64
+    }
+
+    pub fn render(&self, crypto: &str, fiat: &str, source: &str) -> String {
+        let fiat = fiat.to_uppercase();
+        let invested = self.invested();
+        let value = self.units * self.latest_price;
+        let lump_units = invested / self.first_price;
+        let lump_value = lump_units * self.latest_price;
+        let roi = |value: f64| (value - invested) / invested * 100.0;
+        [
+            format!(
+                "DCA of {} {} {} into {} from {} to {} ({}):",
+                self.amount,
+                fiat,
+                self.interval.name(),
+                crypto,
+                self.first,
+                self.latest,
+                source
+            ),
+            format!("  Buys: {}", self.buys),
+            format!("  Invested: {} {}", tidy(invested), fiat),
+            format!("  Units: {}", tidy(self.units)),
+            format!("  Average cost: {} {}", tidy(invested / self.units), fiat),
+            format!("  Value now: {} {} (ROI {:+.2}%)", tidy(value), fiat, roi(value)),
+            format!(
+                "Lump sum on {}: {} units, value now {} {} (ROI {:+.2}%)",
+                self.first,
+                tidy(lump_units),
+                tidy(lump_value),
+                fiat,
+                roi(lump_value)
+            ),
+            format!(
+                "DCA {} the lump sum by {:.2} points",
+                if value >= lump_value { "beat" } else { "trailed" },
+                (roi(value) - roi(lump_value)).abs()
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+// `dca-plan`'s target allocation as coin=weight pairs, with the weights scaled to add up to 1
+pub fn parse_allocation(text: &str) -> Result<Vec<(String, f64)>, CryptoError> {
+    let mut allocation: Vec<(String, f64)> = Vec::new();
+    for pair in text.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let invalid = || CryptoError::InvalidInput(format!("Allocation entries look like bitcoin=60, got '{}'", pair));
+        let (coin, weight) = pair.split_once('=').ok_or_else(invalid)?;
+        let weight = weight.trim().trim_end_matches('%').parse::<f64>().ok().filter(|w| w.is_finite() && *w > 0.0).ok_or_else(invalid)?;
+        let coin = coins::resolve_symbol(coin.trim());
+        if allocation.iter().any(|(seen, _)| *seen == coin) {
+            return Err(CryptoError::InvalidInput(format!("{} is in the allocation twice", coin)));
+        }
+        allocation.push((coin, weight));
+    }
+    if allocation.is_empty() {
+        return Err(CryptoError::InvalidInput("The allocation is empty; give coin=weight pairs like bitcoin=60,ethereum=40".to_string()));
+    }
+    let total: f64 = allocation.iter().map(|(_, weight)| weight).sum();
+    Ok(allocation.into_iter().map(|(coin, weight)| (coin, weight / total)).collect())
+}
+
+// One coin's share of the budget, at its current price
+pub struct Buy {
+    pub coin: String,
+    pub share: f64,
+    pub price: Lookup,
+}
+
+pub fn render_plan(budget: f64, fiat: &str, buys: &[Buy]) -> String {
+    let fiat = fiat.to_uppercase();
+    let mut lines = vec![format!("Buys for {} {}:", budget, fiat)];
+    for buy in buys {
+        let spend = budget * buy.share;
+        lines.push(format!(
+            "  {}: {} {} ({:.2}%) = {} at {} {} ({})",
+            buy.coin,
+            tidy(spend),
+            fiat,
+            buy.share * 100.0,
+            tidy(spend / buy.price.value),
+            buy.price.value,
+            fiat,
+            buy.price.source
+        ));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,359 @@
+use bitcoin::bip32::{ChildNumber, Xpub};
+use bitcoin::opcodes::all::OP_CHECKMULTISIG;
+use bitcoin::script::Builder;
+use bitcoin::secp256k1::{Secp256k1, VerifyOnly};
+use bitcoin::{Address, CompressedPublicKey, Network, ScriptBuf};
+use std::sync::LazyLock;
+
+use crate::CryptoError;
+
+static SECP: LazyLock<Secp256k1<VerifyOnly>> = LazyLock::new(Secp256k1::verification_only);
+
+// Output descriptors (BIP 380 on) for bitcoin's mainnet, derived here so a wallet can be given
+// by its descriptor instead of address by address. Covered: addr(), pkh(), wpkh(), sh(wpkh()),
+// and multi() or sortedmulti() under sh(), wsh() or sh(wsh()). Keys are compressed hex public
+// keys or xpubs followed by unhardened steps, optionally ending in /* and with one <a;b> step
+// for receive and change. tr(), miniscript and private keys aren't. A bare xpub, ypub or zpub
+// stands for its account: pkh(), sh(wpkh()) or wpkh() of its /<0;1>/* addresses.
+pub struct Descriptor {
+    script: Script,
+    // Alternatives of the <a;b> step, 1 without one
+    branches: usize,
+}
+
+enum Script {
+    Address(String),
+    Pkh(Key),
+    Wpkh(Key),
+    ShWpkh(Key),
+    Sh(Multi),
+    Wsh(Multi),
+    ShWsh(Multi),
+}
+
+struct Multi {
+    threshold: usize,
+    keys: Vec<Key>,
+    sorted: bool,
+}
+
+// A key expression, derived at parsing as far as each branch goes
+enum Key {
+    Hex(CompressedPublicKey),
+    Extended { bases: Vec<Xpub>, wildcard: bool },
+}
+
+// Whether `text` is a descriptor rather than a plain address
+pub fn is_descriptor(text: &str) -> bool {
+    text.contains('(') || VERSIONS.iter().any(|(prefix, _, _)| text.trim().starts_with(prefix))
+}
+
+// The descriptor a bare account key stands for, by the script its version is for
+fn account(text: &str) -> Result<String, CryptoError> {
+    match text.get(..4) {
+        Some("ypub") => Ok(format!("sh(wpkh({}/<0;1>/*))", text)),
+        Some("zpub") => Ok(format!("wpkh({}/<0;1>/*)", text)),
+        Some(prefix @ ("Ypub" | "Zpub")) => Err(CryptoError::InvalidInput(format!(
+            "A {} is one key of a multisig; give the wallet's descriptor, such as wsh(sortedmulti(2,{}...,{}...,{}...))",
+            prefix, prefix, prefix, prefix
+        ))),
+        // tpub, upub and vpub get as far as the testnet error
+        _ => Ok(format!("pkh({}/<0;1>/*)", text)),
+    }
+}
+
+impl Descriptor {
+    // The checksum after '#' isn't checked
+    pub fn parse(text: &str) -> Result<Self, CryptoError> {
+        let text = text.trim();
+        let text = text.split_once('#').map_or(text, |(descriptor, _)| descriptor);
+        if !text.contains('(') {
+            return Descriptor::parse(&account(text)?);
+        }
+        let script = match call(text)? {
+            ("addr", inner) if !inner.is_empty() && !inner.contains(['(', ')']) => Script::Address(inner.to_string()),
+            ("pkh", inner) => Script::Pkh(Key::parse(inner)?),
+            ("wpkh", inner) => Script::Wpkh(Key::parse(inner)?),
+            ("sh", inner) => match call(inner)? {
+                ("wpkh", inner) => Script::ShWpkh(Key::parse(inner)?),
+                ("wsh", inner) => Script::ShWsh(Multi::parse(call(inner)?, 20)?),
+                multi => Script::Sh(Multi::parse(multi, 15)?),
+            },
+            ("wsh", inner) => Script::Wsh(Multi::parse(call(inner)?, 20)?),
+            (name, _) => return Err(unsupported(text, &format!("{}() isn't", name))),
+        };
+        let keys: Vec<&Key> = match &script {
+            Script::Address(_) => Vec::new(),
+            Script::Pkh(key) | Script::Wpkh(key) | Script::ShWpkh(key) => vec![key],
+            Script::Sh(multi) | Script::Wsh(multi) | Script::ShWsh(multi) => multi.keys.iter().collect(),
+        };
+        let counts: Vec<usize> = keys.iter().map(|key| key.branches()).filter(|count| *count > 1).collect();
+        if counts.windows(2).any(|pair| pair[0] != pair[1]) {
+            return Err(unsupported(text, "keys with different numbers of <a;b> alternatives aren't"));
+        }
+        Ok(Descriptor { branches: counts.first().copied().unwrap_or(1), script })
+    }
+
+    // Whether it ends in /*, for a run of addresses rather than one per branch
+    pub fn is_ranged(&self) -> bool {
+        let ranged = |key: &Key| matches!(key, Key::Extended { wildcard: true, .. });
+        match &self.script {
+            Script::Address(_) => false,
+            Script::Pkh(key) | Script::Wpkh(key) | Script::ShWpkh(key) => ranged(key),
+            Script::Sh(multi) | Script::Wsh(multi) | Script::ShWsh(multi) => multi.keys.iter().any(ranged),
+        }
+    }
+
+    pub fn branches(&self) -> usize {
+        self.branches
+    }
+
+    // The address at `index` of the `branch`th <a;b> alternative; `index` is ignored unless ranged
+    pub fn address(&self, branch: usize, index: u32) -> Result<String, CryptoError> {
+        let address = match &self.script {
+            Script::Address(address) => return Ok(address.clone()),
+            Script::Pkh(key) => Address::p2pkh(key.at(branch, index)?.pubkey_hash(), Network::Bitcoin),
+            Script::Wpkh(key) => Address::p2wpkh(&key.at(branch, index)?, Network::Bitcoin),
+            Script::ShWpkh(key) => Address::p2shwpkh(&key.at(branch, index)?, Network::Bitcoin),
+            Script::Sh(multi) => Address::p2sh(&multi.script(branch, index)?, Network::Bitcoin)
+                .map_err(|e| CryptoError::InvalidInput(format!("Can't pay to that multisig script: {}", e)))?,
+            Script::Wsh(multi) => Address::p2wsh(&multi.script(branch, index)?, Network::Bitcoin),
+            Script::ShWsh(multi) => Address::p2shwsh(&multi.script(branch, index)?, Network::Bitcoin),
+        };
+        Ok(address.to_string())
+    }
+}
+
+// `name(inner)`
+fn call(text: &str) -> Result<(&str, &str), CryptoError> {
+    text.split_once('(')
+        .and_then(|(name, rest)| Some((name.trim(), rest.strip_suffix(')')?)))
+        .ok_or_else(|| CryptoError::InvalidInput(format!("Can't read the descriptor {}", text)))
+}
+
+fn unsupported(descriptor: &str, what: &str) -> CryptoError {
+    CryptoError::InvalidInput(format!(
+        "Can't derive addresses from the descriptor {}: {} supported, only addr, pkh, wpkh, sh(wpkh) and multi or sortedmulti under sh, wsh or sh(wsh)",
+        descriptor, what
+    ))
+}
+
+impl Multi {
+    fn parse((name, inner): (&str, &str), most: usize) -> Result<Self, CryptoError> {
+        let sorted = match name {
+            "multi" => false,
+            "sortedmulti" => true,
+            _ => return Err(unsupported(inner, &format!("{}() there isn't", name))),
+        };
+        let (threshold, keys) = inner.split_once(',').ok_or_else(|| CryptoError::InvalidInput(format!("{}() needs a threshold and keys", name)))?;
+        let threshold = threshold
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| CryptoError::InvalidInput(format!("{}() starts with its threshold, not '{}'", name, threshold.trim())))?;
+        let keys = keys.split(',').map(Key::parse).collect::<Result<Vec<_>, _>>()?;
+        if threshold == 0 || threshold > keys.len() || keys.len() > most {
+            return Err(CryptoError::InvalidInput(format!(
+                "{}() needs a threshold from 1 to its number of keys, and at most {} keys here",
+                name, most
+            )));
+        }
+        Ok(Multi { threshold, keys, sorted })
+    }
+
+    // OP_k <keys> OP_n OP_CHECKMULTISIG
+    fn script(&self, branch: usize, index: u32) -> Result<ScriptBuf, CryptoError> {
+        let mut keys = self.keys.iter().map(|key| key.at(branch, index)).collect::<Result<Vec<_>, _>>()?;
+        if self.sorted {
+            keys.sort_by_key(CompressedPublicKey::to_bytes);
+        }
+        let script = keys.into_iter().fold(Builder::new().push_int(self.threshold as i64), |script, key| script.push_key(&key.into()));
+        Ok(script.push_int(self.keys.len() as i64).push_opcode(OP_CHECKMULTISIG).into_script())
+    }
+}
+
+impl Key {
+    // [origin]KEY/steps, where the origin is only for signers and is skipped
+    fn parse(text: &str) -> Result<Self, CryptoError> {
+        let text = text.trim();
+        let text = match text.strip_prefix('[') {
+            Some(rest) => rest.split_once(']').map(|(_, key)| key).ok_or_else(|| CryptoError::InvalidInput(format!("Unclosed key origin in {}", text)))?,
+            None => text,
+        };
+        let mut steps = text.split('/');
+        let root = steps.next().unwrap_or_default();
+        if root.len() == 66 && root.chars().all(|c| c.is_ascii_hexdigit()) {
+            if text.contains('/') {
+                return Err(CryptoError::InvalidInput(format!("{} is a plain key; only xpubs take derivation steps", root)));
+            }
+            let bytes: Vec<u8> = (0..33).map(|i| u8::from_str_radix(&root[i * 2..i * 2 + 2], 16).unwrap_or_default()).collect();
+            let key = CompressedPublicKey::from_slice(&bytes)
+                .map_err(|_| CryptoError::InvalidInput(format!("{} isn't a compressed public key", root)))?;
+            return Ok(Key::Hex(key));
+        }
+        let mut bases = vec![parse_xpub(root)?];
+        let mut wildcard = false;
+        let mut branched = false;
+        for step in steps {
+            if wildcard {
+                return Err(CryptoError::InvalidInput(format!("/* must come last in {}", text)));
+            }
+            match step {
+                "*" => wildcard = true,
+                _ if step.ends_with(['\'', 'h', 'H']) => {
+                    return Err(CryptoError::InvalidInput(format!(
+                        "Can't follow the hardened step {} in {} without the private key; give the xpub at that path",
+                        step, text
+                    )));
+                }
+                _ => match step.strip_prefix('<').and_then(|step| step.strip_suffix('>')) {
+                    Some(_) if branched => return Err(CryptoError::InvalidInput(format!("Only one <a;b> step is supported, in {}", text))),
+                    Some(alternatives) => {
+                        let alternatives = alternatives.split(';').map(|step| index(step, text)).collect::<Result<Vec<u32>, _>>()?;
+                        let base = bases.remove(0);
+                        bases = alternatives.into_iter().map(|step| derive(&base, step)).collect::<Result<_, _>>()?;
+                        branched = true;
+                    }
+                    None => {
+                        let step = index(step, text)?;
+                        bases = bases.iter().map(|base| derive(base, step)).collect::<Result<_, _>>()?;
+                    }
+                },
+            }
+        }
+        Ok(Key::Extended { bases, wildcard })
+    }
+
+    fn branches(&self) -> usize {
+        match self {
+            Key::Hex(_) => 1,
+            Key::Extended { bases, .. } => bases.len(),
+        }
+    }
+
+    fn at(&self, branch: usize, index: u32) -> Result<CompressedPublicKey, CryptoError> {
+        match self {
+            Key::Hex(key) => Ok(*key),
+            // Keys without alternatives stand in every branch
+            Key::Extended { bases, wildcard } => {
+                let base = &bases[branch.min(bases.len() - 1)];
+                match wildcard {
+                    true => Ok(derive(base, index)?.to_pub()),
+                    false => Ok(base.to_pub()),
+                }
+            }
+        }
+    }
+}
+
+// An unhardened derivation step
+fn index(step: &str, key: &str) -> Result<u32, CryptoError> {
+    step.parse::<u32>()
+        .ok()
+        .filter(|step| *step < HARDENED)
+        .ok_or_else(|| CryptoError::InvalidInput(format!("'{}' isn't a derivation step, in {}", step, key)))
+}
+
+const HARDENED: u32 = 1 << 31;
+
+// Extended public keys by version bytes, and the mainnet ones by the script their wallets use them for
+const VERSIONS: &[(&str, [u8; 4], bool)] = &[
+    ("xpub", [0x04, 0x88, 0xb2, 0x1e], true),
+    ("ypub", [0x04, 0x9d, 0x7c, 0xb2], true),
+    ("zpub", [0x04, 0xb2, 0x47, 0x46], true),
+    ("Ypub", [0x02, 0x95, 0xb4, 0x3f], true),
+    ("Zpub", [0x02, 0xaa, 0x7e, 0xd3], true),
+    ("tpub", [0x04, 0x35, 0x87, 0xcf], false),
+    ("upub", [0x04, 0x4a, 0x52, 0x62], false),
+    ("vpub", [0x04, 0x5f, 0x1c, 0xf6], false),
+];
+
+// An extended public key of any of VERSIONS, read as the xpub it is underneath: the version
+// only says which script a wallet uses it for
+fn parse_xpub(text: &str) -> Result<Xpub, CryptoError> {
+    let invalid = || CryptoError::InvalidInput(format!("'{}' isn't an extended public key or a hex public key", text));
+    let mut bytes = bitcoin::base58::decode_check(text).map_err(|_| invalid())?;
+    if bytes.len() != 78 {
+        return Err(invalid());
+    }
+    let Some((prefix, _, mainnet)) = VERSIONS.iter().find(|(_, version, _)| bytes[..4] == *version) else {
+        return Err(match text.get(1..4) {
+            Some("prv") => CryptoError::InvalidInput(format!("{}... is a private key; give its public key instead", &text[..4])),
+            _ => invalid(),
+        });
+    };
+    if !mainnet {
+        return Err(CryptoError::InvalidInput(format!("{} keys are for testnet; only mainnet bitcoin addresses are derived", prefix)));
+    }
+    bytes[..4].copy_from_slice(&VERSIONS[0].1);
+    Xpub::decode(&bytes).map_err(|_| invalid())
+}
+
+// The unhardened child (BIP 32)
+fn derive(key: &Xpub, index: u32) -> Result<Xpub, CryptoError> {
+    let invalid = || CryptoError::InvalidInput(format!("Child {} of the key is invalid (BIP 32); that index has no address", index));
+    let step = ChildNumber::from_normal_idx(index).map_err(|_| invalid())?;
+    key.ckd_pub(&SECP, step).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Descriptor, derive, parse_xpub};
+
+    // Public derivation along the BIP 32 test vectors, from the first xpub each step can start at
+    #[test]
+    fn derives_the_bip32_test_vectors() {
+        let steps = [
+            // Vector 1: m/0H -> m/0H/1, then m/0H/1/2H -> m/0H/1/2H/2 -> m/0H/1/2H/2/1000000000
+            (
+                "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+                1,
+                "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+            ),
+            (
+                "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+                2,
+                "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+            ),
+            (
+                "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+                1_000_000_000,
+                "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+            ),
+            // Vector 2: m -> m/0
+            (
+                "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+                0,
+                "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+            ),
+        ];
+        for (parent, index, child) in steps {
+            assert_eq!(derive(&parse_xpub(parent).unwrap(), index).unwrap().to_string(), child);
+        }
+    }
+
+    #[test]
+    fn reads_the_bip32_vector_3_master_key() {
+        // Its only child is hardened, so there's nothing to derive publicly
+        let master = "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13";
+        assert_eq!(parse_xpub(master).unwrap().to_string(), master);
+    }
+
+    #[test]
+    fn rejects_a_bad_checksum() {
+        let key = "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduC";
+        assert!(parse_xpub(key).is_err());
+    }
+
+    // The examples of BIP 381 and BIP 382
+    #[test]
+    fn derives_the_bip_examples() {
+        let examples = [
+            ("pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)", "1cMh228HTCiwS8ZsaakH8A8wze1JR5ZsP"),
+            ("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)", "bc1q0ht9tyks4vh7p5p904t340cr9nvahy7u3re7zg"),
+            ("sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))", "3LKyvRN6SmYXGBNn8fcQvYxW9MGKtwcinN"),
+        ];
+        for (descriptor, address) in examples {
+            assert_eq!(Descriptor::parse(descriptor).unwrap().address(0, 0).unwrap(), address, "{}", descriptor);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::alerts::Snapshot;
+use crate::pricelog::Logged;
+use crate::providers::DailyClose;
+use crate::transactions::Transaction;
+use crate::{CryptoError, parquet, timezone};
+
+// A dataset `export` writes: named columns of the same length, any value of which may be missing
+pub struct Table {
+    pub columns: Vec<(String, Values)>,
+}
+
+pub enum Values {
+    Text(Vec<Option<String>>),
+    Number(Vec<Option<f64>>),
+    Time(Vec<Option<DateTime<Utc>>>),
+    Date(Vec<Option<NaiveDate>>),
+}
+
+impl Values {
+    // Times in --timezone, dates as YYYY-MM-DD
+    pub fn text(&self, row: usize) -> Option<String> {
+        match self {
+            Values::Text(column) => column[row].clone(),
+            Values::Number(column) => column[row].map(|number| number.to_string()),
+            Values::Time(column) => column[row].map(timezone::iso),
+            Values::Date(column) => column[row].map(|date| date.to_string()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Values::Text(column) => column.len(),
+            Values::Number(column) => column.len(),
+            Values::Time(column) => column.len(),
+            Values::Date(column) => column.len(),
+        }
+    }
+}
+
+impl Table {
+    // One row per transaction, counter and fee split into asset and amount columns
+    pub fn transactions(transactions: &[Transaction]) -> Self {
+        let text = |f: fn(&Transaction) -> Option<String>| Values::Text(transactions.iter().map(f).collect());
+        let number = |f: fn(&Transaction) -> Option<f64>| Values::Number(transactions.iter().map(f).collect());
+        Table {
+            columns: vec![
+                ("time".to_string(), Values::Time(transactions.iter().map(|t| Some(t.time)).collect())),
+                ("source".to_string(), text(|t| Some(t.source.clone()))),
+                ("id".to_string(), text(|t| Some(t.id.clone()))),
+                ("kind".to_string(), text(|t| Some(t.kind.label().to_string()))),
+                ("asset".to_string(), text(|t| Some(t.asset.clone()))),
+                ("amount".to_string(), number(|t| Some(t.amount))),
+                ("counter_asset".to_string(), text(|t| Some(t.counter.as_ref()?.asset.clone()))),
+                ("counter_amount".to_string(), number(|t| Some(t.counter.as_ref()?.amount))),
+                ("fee_asset".to_string(), text(|t| Some(t.fee.as_ref()?.asset.clone()))),
+                ("fee_amount".to_string(), number(|t| Some(t.fee.as_ref()?.amount))),
+            ],
+        }
+    }
+
+    // One row per daily close of `coin` in `fiat`, oldest first
+    pub fn history(coin: &str, fiat: &str, closes: &[DailyClose], source: &str) -> Self {
+        let repeat = |value: &str| Values::Text(vec![Some(value.to_string()); closes.len()]);
+        Table {
+            columns: vec![
+                ("date".to_string(), Values::Date(closes.iter().map(|close| Some(close.date)).collect())),
+                ("coin".to_string(), repeat(coin)),
+                ("fiat".to_string(), repeat(fiat)),
+                ("price".to_string(), Values::Number(closes.iter().map(|close| Some(close.price)).collect())),
+                ("source".to_string(), repeat(source)),
+            ],
+        }
+    }
+
+    // One row per line of the price log
+    pub fn prices(prices: &[Logged]) -> Self {
+        let text = |f: fn(&Logged) -> &str| Values::Text(prices.iter().map(|price| Some(f(price).to_string())).collect());
+        Table {
+            columns: vec![
+                ("time".to_string(), Values::Time(prices.iter().map(|price| Some(price.timestamp)).collect())),
+                ("coin".to_string(), text(|price| &price.coin)),
+                ("fiat".to_string(), text(|price| &price.fiat)),
+                ("price".to_string(), Values::Number(prices.iter().map(|price| Some(price.price)).collect())),
+                ("source".to_string(), text(|price| &price.source)),
+            ],
+        }
+    }
+
+    // One row per coin of each alerts snapshot, oldest first, with the snapshot's total repeated
+    pub fn snapshots(snapshots: &[Snapshot]) -> Self {
+        let rows: Vec<(&Snapshot, &String, f64)> =
+            snapshots.iter().flat_map(|snapshot| snapshot.holdings.iter().map(move |(coin, value)| (snapshot, coin, *value))).collect();
+        Table {
+            columns: vec![
+                ("time".to_string(), Values::Time(rows.iter().map(|(snapshot, _, _)| Some(snapshot.time)).collect())),
+                ("coin".to_string(), Values::Text(rows.iter().map(|(_, coin, _)| Some(coin.to_string())).collect())),
+                ("value".to_string(), Values::Number(rows.iter().map(|(_, _, value)| Some(*value)).collect())),
+                ("fiat".to_string(), Values::Text(rows.iter().map(|(snapshot, _, _)| Some(snapshot.fiat.clone())).collect())),
+                ("total".to_string(), Values::Number(rows.iter().map(|(snapshot, _, _)| Some(snapshot.total)).collect())),
+            ],
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.columns.first().map_or(0, |(_, values)| values.len())
+    }
+
+    // Missing values are empty cells
+    pub fn csv(&self) -> String {
+        let mut lines = vec![self.columns.iter().map(|(name, _)| quote(name)).collect::<Vec<_>>().join(",")];
+        for row in 0..self.rows() {
+            let cells: Vec<String> = self.columns.iter().map(|(_, values)| values.text(row).as_deref().map_or(String::new(), quote)).collect();
+            lines.push(cells.join(","));
+        }
+        lines.join("\n")
+    }
+
+    // An array with an object per row, keys in column order; missing values are null
+    pub fn json(&self) -> Result<String, CryptoError> {
+        let rows: Vec<Row> = (0..self.rows()).map(|row| Row { table: self, row }).collect();
+        serde_json::to_string_pretty(&rows).map_err(|e| CryptoError::RuntimeError(e.to_string()))
+    }
+
+    // Columns padded to line up, numbers on the right, "-" where a value is missing
+    pub fn render(&self) -> String {
+        let cells: Vec<Vec<String>> = self
+            .columns
+            .iter()
+            .map(|(_, values)| (0..self.rows()).map(|row| values.text(row).unwrap_or_else(|| "-".to_string())).collect())
+            .collect();
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .zip(&cells)
+            .map(|((name, _), column)| column.iter().map(|cell| cell.chars().count()).chain([name.chars().count()]).max().unwrap_or(0))
+            .collect();
+        let line = |texts: Vec<&str>| -> String {
+            let padded: Vec<String> = texts
+                .iter()
+                .zip(&widths)
+                .zip(&self.columns)
+                .map(|((text, &width), (_, values))| match values {
+                    Values::Number(_) => format!("{:>width$}", text),
+                    _ => format!("{:width$}", text),
+                })
+                .collect();
+            padded.join("  ").trim_end().to_string()
+        };
+        let mut lines = vec![line(self.columns.iter().map(|(name, _)| name.as_str()).collect())];
+        for row in 0..self.rows() {
+            lines.push(line(cells.iter().map(|column| column[row].as_str()).collect()));
+        }
+        lines.join("\n")
+    }
+
+    // `format` is csv or parquet; without `path` a CSV is returned to print instead
+    pub fn write(&self, format: &str, path: Option<&str>) -> Result<String, CryptoError> {
+        let Some(path) = path else {
+            return match format {
+                "parquet" => Err(CryptoError::InvalidInput("--format parquet needs --output FILE".to_string())),
+                _ => Ok(self.csv()),
+            };
+        };
+        let bytes = match format {
+            "parquet" => parquet::write(self)?,
+            _ => format!("{}\n", self.csv()).into_bytes(),
+        };
+        std::fs::write(path, bytes).map_err(|e| CryptoError::RuntimeError(format!("Failed to write {}: {}", path, e)))?;
+        Ok(format!("Wrote {} rows to {}", self.rows(), path))
+    }
+}
+
+struct Row<'a> {
+    table: &'a Table,
+    row: usize,
+}
+
+impl Serialize for Row<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.table.columns.len()))?;
+        for (name, values) in &self.table.columns {
+            match values {
+                Values::Number(column) => map.serialize_entry(name, &column[self.row])?,
+                _ => map.serialize_entry(name, &values.text(self.row))?,
+            }
+        }
+        map.end()
+    }
+}
+
+// A CSV field, quoted when it holds a comma, quote or line break
+fn quote(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
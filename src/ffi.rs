@@ -0,0 +1,148 @@
+use clap::Parser;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::ptr;
+
+use crate::config::Config;
+use crate::{Cli, CryptoError, execute, with_tool};
+
+// C API for embedders. Every call loads the config the same way the CLI does
+// ($COINWAGON_CONFIG, then ~/.config/coinwagon/config.toml) and persists the cache.
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(e: CryptoError) {
+    let message = CString::new(e.to_string().replace('\0', "")).ok();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, CryptoError> {
+    if ptr.is_null() {
+        return Err(CryptoError::InvalidInput(format!("{} is NULL", name)));
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|_| CryptoError::InvalidInput(format!("{} is not valid UTF-8", name)))
+}
+
+// 0 and `*out` set on success, -1 with the message in coinwagon_last_error() on failure
+unsafe fn finish(result: Result<f64, CryptoError>, out: *mut f64) -> c_int {
+    match result {
+        Ok(_) if out.is_null() => {
+            set_last_error(CryptoError::InvalidInput("out is NULL".to_string()));
+            -1
+        }
+        Ok(value) => {
+            unsafe { *out = value };
+            0
+        }
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// Current price of `crypto` in `fiat`, written to `out`.
+/// Returns 0 on success, -1 on failure (see `coinwagon_last_error`).
+///
+/// # Safety
+/// `crypto` and `fiat` must be NUL-terminated strings and `out` must point to a writable double.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn coinwagon_get_price(crypto: *const c_char, fiat: *const c_char, out: *mut f64) -> c_int {
+    let result = (|| {
+        let (crypto, fiat) = unsafe { (str_arg(crypto, "crypto")?, str_arg(fiat, "fiat")?) };
+        let config = Config::load(None)?;
+        let (quote, _) = with_tool(&config, async |tool| tool.lookup_price(crypto, fiat, false).await)?;
+        Ok(quote.value)
+    })();
+    unsafe { finish(result, out) }
+}
+
+/// Balance of `address` on the `crypto` chain, written to `out`.
+/// Returns 0 on success, -1 on failure (see `coinwagon_last_error`).
+///
+/// # Safety
+/// `crypto` and `address` must be NUL-terminated strings and `out` must point to a writable double.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn coinwagon_get_balance(crypto: *const c_char, address: *const c_char, out: *mut f64) -> c_int {
+    let result = (|| {
+        let (crypto, address) = unsafe { (str_arg(crypto, "crypto")?, str_arg(address, "address")?) };
+        let config = Config::load(None)?;
+        let (quote, _) = with_tool(&config, async |tool| tool.lookup_balance(crypto, address, false).await)?;
+        Ok(quote.value)
+    })();
+    unsafe { finish(result, out) }
+}
+
+/// Total value in `fiat` of the wallet file at `path`, written to `out`.
+/// Returns 0 on success, -1 on failure (see `coinwagon_last_error`).
+///
+/// # Safety
+/// `path` and `fiat` must be NUL-terminated strings and `out` must point to a writable double.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn coinwagon_get_wallet_value(path: *const c_char, fiat: *const c_char, out: *mut f64) -> c_int {
+    let result = (|| {
+        let (path, fiat) = unsafe { (str_arg(path, "path")?, str_arg(fiat, "fiat")?) };
+        let config = Config::load(None)?;
+        let (lines, _) = with_tool(&config, async |tool| tool.get_wallet_balance(path, fiat, false).await)?;
+        Ok(lines.iter().map(|line| line.fiat_value()).sum())
+    })();
+    unsafe { finish(result, out) }
+}
+
+/// Runs a CLI command, e.g. `command = "current-price"`, `args = {"bitcoin", "usd"}`, and
+/// returns its output. Free the result with `coinwagon_string_free`. Returns NULL on failure
+/// (see `coinwagon_last_error`).
+///
+/// # Safety
+/// `command` must be a NUL-terminated string and `args` must point to `nargs` of them
+/// (`args` may be NULL when `nargs` is 0).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn coinwagon_run_command(
+    command: *const c_char,
+    args: *const *const c_char,
+    nargs: usize,
+) -> *mut c_char {
+    let result = (|| {
+        let command = unsafe { str_arg(command, "command")? };
+        let mut argv = vec!["coinwagon".to_string(), command.to_string()];
+        if nargs > 0 {
+            if args.is_null() {
+                return Err(CryptoError::InvalidInput("args is NULL".to_string()));
+            }
+            for &arg in unsafe { std::slice::from_raw_parts(args, nargs) } {
+                argv.push(unsafe { str_arg(arg, "args")? }.to_string());
+            }
+        }
+        let cli = Cli::try_parse_from(argv).map_err(|e| CryptoError::InvalidInput(e.to_string()))?;
+        let output = execute(cli)?;
+        Ok(CString::new(output.replace('\0', "")).unwrap_or_default())
+    })();
+    match result {
+        Ok(output) => output.into_raw(),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a string returned by `coinwagon_run_command`. NULL is ignored.
+///
+/// # Safety
+/// `s` must come from `coinwagon_run_command` and not have been freed already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn coinwagon_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Message for the most recent failure on this thread, or NULL. The pointer stays valid
+/// until the next failing coinwagon call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn coinwagon_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
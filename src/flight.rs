@@ -0,0 +1,85 @@
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use std::future::Future;
+use tokio::sync::watch;
+
+// Outcome of the leading call: None while it runs, then Some(None) if it failed
+type Outcome<T> = Option<Option<T>>;
+
+// Single-flight: while a fetch for a key is running, later calls for the same key wait for it
+// and share its result instead of sending their own request. Only successes are shared; when
+// the leading call fails, the others fall back to fetching for themselves.
+pub struct Flights<T> {
+    in_flight: DashMap<String, watch::Receiver<Outcome<T>>>,
+}
+
+// Clears the key even if the leading call is cancelled, so later calls don't wait on it
+struct Leader<'a, T> {
+    flights: &'a Flights<T>,
+    key: &'a str,
+}
+
+impl<T> Drop for Leader<'_, T> {
+    fn drop(&mut self) {
+        self.flights.in_flight.remove(self.key);
+    }
+}
+
+impl<T: Clone> Flights<T> {
+    pub fn new() -> Self {
+        Flights { in_flight: DashMap::new() }
+    }
+
+    pub async fn run<E>(&self, key: &str, fetch: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let (sender, receiver) = watch::channel(None);
+        let running = match self.in_flight.entry(key.to_string()) {
+            Entry::Occupied(entry) => Some(entry.get().clone()),
+            Entry::Vacant(entry) => {
+                entry.insert(receiver);
+                None
+            }
+        };
+
+        if let Some(mut running) = running {
+            // Errs when the leader was dropped without finishing
+            if let Ok(outcome) = running.wait_for(Option::is_some).await
+                && let Some(Some(value)) = &*outcome
+            {
+                return Ok(value.clone());
+            }
+            return fetch.await;
+        }
+
+        let _leader = Leader { flights: self, key };
+        let result = fetch.await;
+        sender.send_replace(Some(result.as_ref().ok().cloned()));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Flights;
+    use futures::future::join_all;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn waiters_fetch_for_themselves_when_the_leader_fails() {
+        let flights = Flights::new();
+        let calls = AtomicUsize::new(0);
+        let fetch = || async {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            if call == 0 { Err("provider down") } else { Ok(42.0) }
+        };
+
+        let results = join_all((0..4).map(|_| flights.run("price:bitcoin:usd", fetch()))).await;
+        assert_eq!(results, [Err("provider down"), Ok(42.0), Ok(42.0), Ok(42.0)]);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // The failed flight is cleared, so the next call leads a new one
+        assert_eq!(flights.run("price:bitcoin:usd", fetch()).await, Ok(42.0));
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+}
@@ -0,0 +1,258 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+use crate::CryptoError;
+use crate::cassette::rebuild_response;
+use crate::keys::redact;
+
+// HAR 1.2, trimmed to what a saved request and response need; browser devtools and most HTTP
+// tools open it
+#[derive(Serialize)]
+struct Har<'a> {
+    log: Log<'a>,
+}
+
+#[derive(Serialize)]
+struct Log<'a> {
+    version: &'static str,
+    creator: Creator,
+    entries: &'a [Entry],
+}
+
+#[derive(Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: String,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: Empty,
+    timings: Timings,
+    // Set when no response came back at all, e.g. a connect error or timeout
+    #[serde(rename = "_error", skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: String,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    cookies: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    headers: Vec<NameValue>,
+    cookies: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: usize,
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct Empty {}
+
+#[derive(Serialize)]
+struct Timings {
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+// --debug-http: every provider exchange, bodies included, written out as a HAR file so a
+// parsing failure can be diagnosed from the exact response the provider sent. API keys,
+// and the values of headers that look like credentials, are replaced with REDACTED.
+pub struct HarLog {
+    path: PathBuf,
+    secrets: Vec<String>,
+    // Sent by the client on every request, so missing from the built request
+    user_agent: String,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl HarLog {
+    pub fn new(path: &str, secrets: Vec<String>, user_agent: &str) -> Self {
+        HarLog {
+            path: PathBuf::from(path),
+            secrets,
+            user_agent: user_agent.to_string(),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Sends `request` with `send` and logs it along with whatever came back
+    pub async fn capture<F: Future<Output = Result<Response, CryptoError>>>(
+        &self,
+        request: RequestBuilder,
+        send: impl FnOnce(RequestBuilder) -> F,
+    ) -> Result<Response, CryptoError> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let mut headers = self.headers(request.headers());
+        if !request.headers().contains_key("user-agent") {
+            headers.push(NameValue { name: "user-agent".to_string(), value: self.user_agent.clone() });
+        }
+        let body = request.body().and_then(|body| body.as_bytes()).map(|body| redact(&self.secrets, &String::from_utf8_lossy(body)));
+        let har_request = HarRequest {
+            method: request.method().to_string(),
+            url: redact(&self.secrets, request.url().as_str()),
+            http_version: "HTTP/1.1".to_string(),
+            headers,
+            query_string: request
+                .url()
+                .query_pairs()
+                .map(|(name, value)| NameValue { name: name.to_string(), value: redact(&self.secrets, &value) })
+                .collect(),
+            cookies: Vec::new(),
+            headers_size: -1,
+            body_size: body.as_ref().map_or(0, |body| body.len() as i64),
+            post_data: body.map(|text| PostData { mime_type: header(request.headers(), "content-type"), text }),
+        };
+
+        let started: DateTime<Utc> = SystemTime::now().into();
+        let timer = Instant::now();
+        let result = send(RequestBuilder::from_parts(client, request)).await;
+        let (result, response, error) = match result {
+            Ok(resp) => {
+                let url = resp.url().to_string();
+                let status = resp.status();
+                let http_version = format!("{:?}", resp.version());
+                let headers: Vec<(String, String)> = resp
+                    .headers()
+                    .iter()
+                    .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                    .collect();
+                let har_headers = self.headers(resp.headers());
+                let mime_type = header(resp.headers(), "content-type");
+                let redirect_url = header(resp.headers(), "location");
+                let body = resp.text().await?;
+                let response = HarResponse {
+                    status: status.as_u16(),
+                    status_text: status.canonical_reason().unwrap_or_default().to_string(),
+                    http_version,
+                    headers: har_headers,
+                    cookies: Vec::new(),
+                    content: Content { size: body.len(), mime_type, text: redact(&self.secrets, &body) },
+                    redirect_url,
+                    headers_size: -1,
+                    body_size: body.len() as i64,
+                };
+                (rebuild_response(&url, status.as_u16(), &headers, body), response, None)
+            }
+            Err(e) => {
+                let message = e.to_string();
+                (Err(e), failed_response(), Some(message))
+            }
+        };
+        let elapsed = timer.elapsed().as_secs_f64() * 1000.0;
+        self.entries.lock().unwrap().push(Entry {
+            started_date_time: started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            time: elapsed,
+            request: har_request,
+            response,
+            cache: Empty {},
+            timings: Timings { send: 0.0, wait: elapsed, receive: 0.0 },
+            error,
+        });
+        result
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Vec<NameValue> {
+        headers
+            .iter()
+            .map(|(name, value)| NameValue {
+                name: name.to_string(),
+                value: if is_credential(name.as_str()) {
+                    "REDACTED".to_string()
+                } else {
+                    redact(&self.secrets, &String::from_utf8_lossy(value.as_bytes()))
+                },
+            })
+            .collect()
+    }
+
+    pub fn save(&self) -> Result<(), CryptoError> {
+        let entries = self.entries.lock().unwrap();
+        let har = Har {
+            log: Log {
+                version: "1.2",
+                creator: Creator { name: "coinwagon", version: env!("CARGO_PKG_VERSION") },
+                entries: &entries,
+            },
+        };
+        let json = serde_json::to_string_pretty(&har)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to serialize HAR: {}", e)))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> String {
+    headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string()
+}
+
+fn is_credential(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["auth", "key", "token", "secret", "cookie"].iter().any(|word| name.contains(word))
+}
+
+// HAR wants a response even when there wasn't one; status 0 is the convention
+fn failed_response() -> HarResponse {
+    HarResponse {
+        status: 0,
+        status_text: String::new(),
+        http_version: String::new(),
+        headers: Vec::new(),
+        cookies: Vec::new(),
+        content: Content { size: 0, mime_type: String::new(), text: String::new() },
+        redirect_url: String::new(),
+        headers_size: -1,
+        body_size: -1,
+    }
+}
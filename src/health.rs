@@ -0,0 +1,116 @@
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::{CryptoError, CryptoTool};
+
+// A daemon's polls so far, for its health endpoints
+#[derive(Default)]
+pub struct Health {
+    polls: Cell<u32>,
+    // Why the latest poll failed, None once one succeeds
+    last_error: RefCell<Option<String>>,
+}
+
+impl Health {
+    pub fn polled(&self, result: &Result<(), CryptoError>) {
+        self.polls.set(self.polls.get() + 1);
+        *self.last_error.borrow_mut() = result.as_ref().err().map(ToString::to_string);
+    }
+}
+
+pub async fn bind(address: &str) -> Result<TcpListener, CryptoError> {
+    TcpListener::bind(address).await.map_err(|e| CryptoError::InvalidInput(format!("Can't listen on {}: {}", address, e)))
+}
+
+// --listen: answers health checks until the daemon stops, several connections at once.
+//   /healthz  200 while the daemon runs
+//   /readyz   200 once a poll has succeeded, the latest one included, and some price provider
+//             isn't skipped by its circuit breaker; 503 otherwise, with what's missing
+pub async fn serve(listener: TcpListener, tool: &CryptoTool, health: &Health) {
+    // Answered side by side, so a slow client doesn't hold up the probes; none is kept past 5s
+    let mut answering = FuturesUnordered::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => answering.push(tokio::time::timeout(Duration::from_secs(5), answer(stream, tool, health))),
+                // Out of file descriptors, say: accepting again straight away would only spin
+                Err(e) => {
+                    eprintln!("Warning: health checks can't accept a connection ({}), retrying in 1s", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            },
+            Some(_) = answering.next(), if !answering.is_empty() => {}
+        }
+    }
+}
+
+async fn answer(stream: TcpStream, tool: &CryptoTool, health: &Health) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    reader.read_line(&mut request).await?;
+    // The headers aren't needed, but are read so closing doesn't reset the connection
+    let mut header = String::new();
+    while reader.read_line(&mut header).await? > 2 {
+        header.clear();
+    }
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let (status, body) = match (method, path.split('?').next().unwrap_or_default()) {
+        ("GET" | "HEAD", "/healthz") => (200, serde_json::json!({"status": "ok", "polls": health.polls.get()})),
+        ("GET" | "HEAD", "/readyz") => readiness(tool, health),
+        ("GET" | "HEAD", _) => (404, serde_json::json!({"error": format!("no endpoint {}", path)})),
+        _ => (405, serde_json::json!({"error": "only GET and HEAD are answered"})),
+    };
+    let body = body.to_string();
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    };
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        body.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn readiness(tool: &CryptoTool, health: &Health) -> (u16, serde_json::Value) {
+    let providers: serde_json::Map<String, serde_json::Value> = tool
+        .providers
+        .price
+        .iter()
+        .map(|p| (p.name().to_string(), (if tool.breakers.is_open(p.name()) { "skipped" } else { "up" }).into()))
+        .collect();
+    let last_error = health.last_error.borrow().clone();
+    let mut waiting = Vec::new();
+    if health.polls.get() == 0 {
+        waiting.push("the first poll hasn't finished".to_string());
+    }
+    if let Some(error) = &last_error {
+        waiting.push(format!("the last poll failed: {}", error));
+    }
+    if !providers.values().any(|state| state == "up") {
+        waiting.push("every price provider is being skipped after repeated failures".to_string());
+    }
+    let ready = waiting.is_empty();
+    let body = serde_json::json!({
+        "ready": ready,
+        "polls": health.polls.get(),
+        "last_poll": if health.polls.get() == 0 { serde_json::Value::Null } else { last_error.as_deref().unwrap_or("ok").into() },
+        "providers": providers,
+        "cache_entries": tool.cache.stats().entries,
+        "waiting_on": waiting,
+    });
+    (if ready { 200 } else { 503 }, body)
+}
@@ -0,0 +1,107 @@
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use fluent_syntax::parser::ParserError;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{LazyLock, RwLock};
+use unic_langid::LanguageIdentifier;
+
+use crate::CryptoError;
+
+// Bundled translations by language code, English first as the fallback for every message
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+];
+
+// A locale's messages
+type Bundle = FluentBundle<FluentResource>;
+
+// The locale files that parse, and what's wrong with those that don't, which `select` refuses
+// to start with
+static BUNDLES: LazyLock<(HashMap<&'static str, Bundle>, Vec<String>)> = LazyLock::new(|| {
+    let (mut bundles, mut errors) = (HashMap::new(), Vec::new());
+    for (code, source) in LOCALES {
+        match bundle(code, source) {
+            Ok(bundle) => {
+                bundles.insert(*code, bundle);
+            }
+            Err(e) => errors.push(format!("locales/{}.ftl {}", code, e)),
+        }
+    }
+    (bundles, errors)
+});
+
+// Isolation marks around placeables are left out: output goes to terminals and scripts, which
+// would show or keep them
+fn bundle(code: &str, source: &str) -> Result<Bundle, String> {
+    let resource = FluentResource::try_new(source.to_string()).map_err(|(_, errors)| {
+        // The line the broken entry starts on
+        let line = |e: &ParserError| source[..e.slice.as_ref().map_or(e.pos.start, |slice| slice.start)].matches('\n').count() + 1;
+        errors.iter().map(|e| format!("line {}: {}", line(e), e)).collect::<Vec<_>>().join("; ")
+    })?;
+    let language: LanguageIdentifier = code.parse().map_err(|e| format!("isn't a language: {}", e))?;
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).map_err(|errors| errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))?;
+    Ok(bundle)
+}
+
+// None until `select` runs, meaning the environment's locale
+static LANGUAGE: RwLock<Option<&'static str>> = RwLock::new(None);
+
+// The language of every later message: `lang` (--lang or the config's lang) when given, else
+// the locale in LC_ALL, LC_MESSAGES or LANG. A locale without translations means English, but
+// asking for one by name is an error.
+pub fn select(lang: Option<&str>) -> Result<(), CryptoError> {
+    if let Some(e) = BUNDLES.1.first() {
+        return Err(CryptoError::ConfigError(e.clone()));
+    }
+    let code = match lang {
+        Some(lang) => known(lang).ok_or_else(|| {
+            let available: Vec<&str> = LOCALES.iter().map(|(code, _)| *code).collect();
+            CryptoError::ConfigError(format!("No translations for '{}'; pick one of {}", lang, available.join(", ")))
+        })?,
+        None => detect(),
+    };
+    *LANGUAGE.write().unwrap() = Some(code);
+    Ok(())
+}
+
+// Message `id` in the selected language with its `{ $name }` variables filled from `args`,
+// each as its Display text so numbers read the same in every language
+pub fn tr(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    let language = LANGUAGE.read().unwrap().unwrap_or_else(detect);
+    let found = [language, "en"].iter().find_map(|code| {
+        let bundle = BUNDLES.0.get(code)?;
+        Some((bundle, bundle.get_message(id)?.value()?))
+    });
+    match found {
+        Some((bundle, pattern)) => {
+            let mut fluent_args = FluentArgs::new();
+            for (name, value) in args {
+                fluent_args.set(*name, value.to_string());
+            }
+            bundle.format_pattern(pattern, Some(&fluent_args), &mut Vec::new()).into_owned()
+        }
+        // Without even English, as when en.ftl doesn't parse: the id and what would've filled it
+        None if args.is_empty() => id.to_string(),
+        None => format!("{}: {}", id, args.iter().map(|(_, value)| value.to_string()).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+// "de_DE.UTF-8", "de-AT" or "DE" -> "de", when there are German translations
+fn known(locale: &str) -> Option<&'static str> {
+    let language = locale.split(['_', '-', '.', '@']).next()?.to_lowercase();
+    LOCALES.iter().map(|(code, _)| *code).find(|code| *code == language)
+}
+
+fn detect() -> &'static str {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|locale| known(&locale))
+        .unwrap_or("en")
+}
@@ -0,0 +1,205 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{CryptoError, tidy};
+
+// An indicator `indicators --set` takes, e.g. sma50, ema20, rsi14 or macd
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indicator {
+    Sma(usize),
+    Ema(usize),
+    Rsi(usize),
+    // 12/26-day EMAs and a 9-day signal line
+    Macd,
+}
+
+impl FromStr for Indicator {
+    type Err = CryptoError;
+
+    fn from_str(name: &str) -> Result<Self, CryptoError> {
+        let name = name.trim().to_lowercase();
+        if name == "macd" {
+            return Ok(Indicator::Macd);
+        }
+        let split = name.find(|c: char| c.is_ascii_digit()).unwrap_or(name.len());
+        let period = name[split..].parse::<usize>().ok().filter(|period| *period > 0);
+        match (&name[..split], period) {
+            ("sma", Some(period)) => Ok(Indicator::Sma(period)),
+            ("ema", Some(period)) => Ok(Indicator::Ema(period)),
+            ("rsi", Some(period)) => Ok(Indicator::Rsi(period)),
+            _ => Err(CryptoError::InvalidInput(format!(
+                "Unknown indicator '{}'; use smaN, emaN, rsiN or macd (e.g., sma50)",
+                name
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Indicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Indicator::Sma(period) => write!(f, "SMA{}", period),
+            Indicator::Ema(period) => write!(f, "EMA{}", period),
+            Indicator::Rsi(period) => write!(f, "RSI{}", period),
+            Indicator::Macd => f.write_str("MACD"),
+        }
+    }
+}
+
+impl Indicator {
+    // Closes needed for a settled value the day before as well as today, so crossings show.
+    // EMAs and RSI start from a plain average, so they get a few periods to wear it off.
+    pub fn closes_needed(self) -> usize {
+        match self {
+            Indicator::Sma(period) => period + 1,
+            Indicator::Ema(period) | Indicator::Rsi(period) => period * 4 + 1,
+            Indicator::Macd => 26 * 4 + 9,
+        }
+    }
+}
+
+// Simple moving averages, one per close from the `period`th on
+pub fn sma(closes: &[f64], period: usize) -> Vec<f64> {
+    closes.windows(period).map(|window| window.iter().sum::<f64>() / period as f64).collect()
+}
+
+// Exponential moving averages seeded with the SMA of the first `period` closes, one per close
+// from the `period`th on
+pub fn ema(closes: &[f64], period: usize) -> Vec<f64> {
+    if period == 0 || closes.len() < period {
+        return Vec::new();
+    }
+    let k = 2.0 / (period as f64 + 1.0);
+    let mut values = vec![closes[..period].iter().sum::<f64>() / period as f64];
+    for close in &closes[period..] {
+        let last = values[values.len() - 1];
+        values.push(close * k + last * (1.0 - k));
+    }
+    values
+}
+
+// Wilder's relative strength index, one per close from the `period + 1`th on
+pub fn rsi(closes: &[f64], period: usize) -> Vec<f64> {
+    if period == 0 || closes.len() <= period {
+        return Vec::new();
+    }
+    let changes: Vec<f64> = closes.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let mut gain = changes[..period].iter().map(|c| c.max(0.0)).sum::<f64>() / period as f64;
+    let mut loss = changes[..period].iter().map(|c| (-c).max(0.0)).sum::<f64>() / period as f64;
+    let index = |gain: f64, loss: f64| if loss == 0.0 { 100.0 } else { 100.0 - 100.0 / (1.0 + gain / loss) };
+    let mut values = vec![index(gain, loss)];
+    for change in &changes[period..] {
+        gain = (gain * (period as f64 - 1.0) + change.max(0.0)) / period as f64;
+        loss = (loss * (period as f64 - 1.0) + (-change).max(0.0)) / period as f64;
+        values.push(index(gain, loss));
+    }
+    values
+}
+
+pub struct Macd {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+// MACD line, signal line and histogram, one per close once all three have a value
+pub fn macd(closes: &[f64]) -> Vec<Macd> {
+    let (fast, slow) = (ema(closes, 12), ema(closes, 26));
+    // The fast EMA starts 14 closes earlier
+    let line: Vec<f64> = slow.iter().zip(fast.iter().skip(14)).map(|(slow, fast)| fast - slow).collect();
+    let signal = ema(&line, 9);
+    line[line.len().saturating_sub(signal.len())..]
+        .iter()
+        .zip(signal)
+        .map(|(macd, signal)| Macd { macd: *macd, signal, histogram: macd - signal })
+        .collect()
+}
+
+// Which way `fast` crossed `slow` between their last two values, which must be for the same
+// closes: true for upwards
+pub fn crossed(fast: &[f64], slow: &[f64]) -> Option<bool> {
+    let ([.., fast_before, fast_now], [.., slow_before, slow_now]) = (fast, slow) else {
+        return None;
+    };
+    match (fast_before <= slow_before, fast_now > slow_now) {
+        (true, true) => Some(true),
+        (false, false) => Some(false),
+        _ => None,
+    }
+}
+
+// The indicator's latest value and where that puts the price
+pub fn describe(indicator: Indicator, closes: &[f64]) -> String {
+    let latest = closes.last().copied().unwrap_or_default();
+    let side = |average: f64| if latest > average { "price above" } else { "price below" };
+    let value = match indicator {
+        Indicator::Sma(period) => sma(closes, period).last().map(|v| format!("{}, {}", tidy(*v), side(*v))),
+        Indicator::Ema(period) => ema(closes, period).last().map(|v| format!("{}, {}", tidy(*v), side(*v))),
+        Indicator::Rsi(period) => rsi(closes, period).last().map(|v| {
+            let zone = match *v {
+                v if v >= 70.0 => "overbought",
+                v if v <= 30.0 => "oversold",
+                _ => "neutral",
+            };
+            format!("{:.2}, {}", v, zone)
+        }),
+        Indicator::Macd => macd(closes).last().map(|m| {
+            format!("{}, signal {}, histogram {}", tidy(m.macd), tidy(m.signal), tidy(m.histogram))
+        }),
+    };
+    value.unwrap_or_else(|| format!("not enough history ({} daily closes)", closes.len()))
+}
+
+// Which way the latest close crossed the indicator, true for upwards: the price over a moving
+// average, MACD over its signal line, or RSI up past 70 and down past 30
+pub fn crossing(indicator: Indicator, closes: &[f64]) -> Option<bool> {
+    match indicator {
+        Indicator::Sma(period) => crossed(closes, &sma(closes, period)),
+        Indicator::Ema(period) => crossed(closes, &ema(closes, period)),
+        Indicator::Macd => {
+            let values = macd(closes);
+            let line: Vec<f64> = values.iter().map(|m| m.macd).collect();
+            let signal: Vec<f64> = values.iter().map(|m| m.signal).collect();
+            crossed(&line, &signal)
+        }
+        Indicator::Rsi(period) => {
+            let values = rsi(closes, period);
+            match (crossed(&values, &[70.0, 70.0]), crossed(&values, &[30.0, 30.0])) {
+                (Some(true), _) => Some(true),
+                (_, Some(false)) => Some(false),
+                _ => None,
+            }
+        }
+    }
+}
+
+// Crossings on the latest close: the set's shortest SMA over its longest (golden and death
+// crosses), the price over each moving average, MACD over its signal line and RSI into its
+// extremes
+pub fn signals(set: &[Indicator], closes: &[f64]) -> Vec<String> {
+    let mut signals = Vec::new();
+    let periods: Vec<usize> = set.iter().filter_map(|i| if let Indicator::Sma(p) = i { Some(*p) } else { None }).collect();
+    if let (Some(short), Some(long)) = (periods.iter().min(), periods.iter().max())
+        && short != long
+    {
+        let (fast, slow) = (sma(closes, *short), sma(closes, *long));
+        match crossed(&fast, &slow) {
+            Some(true) => signals.push(format!("Golden cross: SMA{} crossed above SMA{}", short, long)),
+            Some(false) => signals.push(format!("Death cross: SMA{} crossed below SMA{}", short, long)),
+            None => {}
+        }
+    }
+    for indicator in set {
+        let signal = match (indicator, crossing(*indicator, closes)) {
+            (_, None) => continue,
+            (Indicator::Sma(_) | Indicator::Ema(_), Some(true)) => format!("Price crossed above {}", indicator),
+            (Indicator::Sma(_) | Indicator::Ema(_), Some(false)) => format!("Price crossed below {}", indicator),
+            (Indicator::Macd, Some(true)) => "MACD crossed above its signal line".to_string(),
+            (Indicator::Macd, Some(false)) => "MACD crossed below its signal line".to_string(),
+            (Indicator::Rsi(period), Some(true)) => format!("RSI{} turned overbought", period),
+            (Indicator::Rsi(period), Some(false)) => format!("RSI{} turned oversold", period),
+        };
+        signals.push(signal);
+    }
+    signals
+}
@@ -0,0 +1,117 @@
+use reqwest::RequestBuilder;
+use reqwest::header::HeaderName;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::config::Rotation;
+
+// Where a provider expects its API key
+pub enum KeyPlacement {
+    Header(HeaderName),
+    Query(&'static str),
+}
+
+struct Key {
+    value: String,
+    used: AtomicU64,
+    limited: AtomicU64,
+}
+
+pub struct KeyUsage {
+    pub label: String,
+    pub used: u64,
+    pub limited: u64,
+}
+
+// A provider's API keys and which one the next request gets. Round-robin hands them out in
+// turn; on-rate-limit sticks with one key until it gets a 429. Either way keys the provider
+// has said are out of quota are passed over while another one is still good.
+pub struct KeyRing {
+    keys: Vec<Key>,
+    rotation: Rotation,
+    placement: KeyPlacement,
+    // The next key for round-robin, the current one for on-rate-limit
+    next: AtomicUsize,
+}
+
+impl KeyRing {
+    pub fn new(keys: &[String], rotation: Rotation, placement: KeyPlacement) -> Self {
+        KeyRing {
+            keys: keys
+                .iter()
+                .map(|value| Key { value: value.clone(), used: AtomicU64::new(0), limited: AtomicU64::new(0) })
+                .collect(),
+            rotation,
+            placement,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    // Index of the key for the next request; `blocked` says which keys are out of quota
+    pub fn pick(&self, blocked: impl Fn(usize) -> bool) -> usize {
+        let n = self.keys.len();
+        let start = match self.rotation {
+            Rotation::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % n,
+            Rotation::OnRateLimit => self.next.load(Ordering::Relaxed) % n,
+        };
+        let index = (0..n).map(|i| (start + i) % n).find(|&i| !blocked(i)).unwrap_or(start);
+        if self.rotation == Rotation::OnRateLimit {
+            self.next.store(index, Ordering::Relaxed);
+        }
+        self.keys[index].used.fetch_add(1, Ordering::Relaxed);
+        index
+    }
+
+    pub fn rate_limited(&self, index: usize) {
+        self.keys[index].limited.fetch_add(1, Ordering::Relaxed);
+        if self.rotation == Rotation::OnRateLimit {
+            let n = self.keys.len();
+            let _ = self.next.compare_exchange(index, (index + 1) % n, Ordering::Relaxed, Ordering::Relaxed);
+        }
+    }
+
+    pub fn apply(&self, index: usize, request: RequestBuilder) -> RequestBuilder {
+        let key = &self.keys[index].value;
+        match &self.placement {
+            KeyPlacement::Header(name) => request.header(name, key),
+            KeyPlacement::Query(name) => request.query(&[(name, key)]),
+        }
+    }
+
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        self.keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| KeyUsage {
+                label: label(i, &key.value),
+                used: key.used.load(Ordering::Relaxed),
+                limited: key.limited.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    pub fn secrets(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(|key| key.value.as_str())
+    }
+}
+
+// "key 2 (…f3a9)": enough to tell keys apart without printing them
+fn label(index: usize, key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() < 12 {
+        return format!("key {}", index + 1);
+    }
+    format!("key {} (…{})", index + 1, chars[chars.len() - 4..].iter().collect::<String>())
+}
+
+// Blanks out API keys in anything that gets printed or written to disk, like URLs carrying
+// a key as a query parameter
+pub fn redact(secrets: &[String], text: &str) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| text.replace(secret.as_str(), "REDACTED"))
+}
@@ -1,237 +1,1388 @@
-use pyo3::prelude::*;
+// Only the providers and coin tables are used on wasm32 (see src/wasm.rs); the CLI and
+// Python engine below are native-only
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+
 use clap::{Parser, Subcommand};
 use reqwest::Client;
-use serde_json::Value;
-use dashmap::DashMap;
 use thiserror::Error;
 use tokio::runtime::{Builder, Runtime};
-use std::sync::Arc;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
+#[cfg(not(target_arch = "wasm32"))]
+mod addressbook;
+#[cfg(not(target_arch = "wasm32"))]
+mod alerts;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(not(target_arch = "wasm32"))]
+mod audit;
+#[cfg(not(target_arch = "wasm32"))]
+mod basket;
+mod breaker;
+mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+mod cassette;
+#[cfg(not(target_arch = "wasm32"))]
+mod chart;
+#[cfg(not(target_arch = "wasm32"))]
+mod coinlist;
+mod coins;
+#[cfg(not(target_arch = "wasm32"))]
+mod commands;
+#[cfg(not(target_arch = "wasm32"))]
+mod compare;
+mod completions;
+mod config;
+#[cfg(not(target_arch = "wasm32"))]
+mod daemon;
+#[cfg(not(target_arch = "wasm32"))]
+mod dca;
+#[cfg(not(target_arch = "wasm32"))]
+mod descriptor;
+#[cfg(not(target_arch = "wasm32"))]
+mod export;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod flight;
+#[cfg(not(target_arch = "wasm32"))]
+mod har;
+#[cfg(not(target_arch = "wasm32"))]
+mod health;
+mod i18n;
+#[cfg(not(target_arch = "wasm32"))]
+mod indicators;
+mod keys;
+#[cfg(not(target_arch = "wasm32"))]
+mod lots;
+#[cfg(not(target_arch = "wasm32"))]
+mod parquet;
+#[cfg(not(target_arch = "wasm32"))]
+mod plan;
+#[cfg(not(target_arch = "wasm32"))]
+mod pricelog;
+mod providers;
+#[cfg(not(target_arch = "wasm32"))]
+mod python;
+#[cfg(not(target_arch = "wasm32"))]
+mod query;
+#[cfg(not(target_arch = "wasm32"))]
+mod quota;
+#[cfg(not(target_arch = "wasm32"))]
+mod ratelimit;
+#[cfg(not(target_arch = "wasm32"))]
+mod rebalance;
+#[cfg(feature = "redis")]
+mod redis_cache;
+#[cfg(not(target_arch = "wasm32"))]
+mod repl;
+#[cfg(not(target_arch = "wasm32"))]
+mod returns;
+#[cfg(not(target_arch = "wasm32"))]
+mod risk;
+#[cfg(not(target_arch = "wasm32"))]
+mod secrets;
+#[cfg(windows)]
+mod service;
+#[cfg(not(target_arch = "wasm32"))]
+mod signing;
+#[cfg(not(target_arch = "wasm32"))]
+mod stats;
+#[cfg(not(target_arch = "wasm32"))]
+mod status;
+#[cfg(not(target_arch = "wasm32"))]
+mod suggest;
+#[cfg(not(target_arch = "wasm32"))]
+mod template;
+mod timezone;
+#[cfg(not(target_arch = "wasm32"))]
+mod tls;
+#[cfg(not(target_arch = "wasm32"))]
+mod tokens;
+#[cfg(not(target_arch = "wasm32"))]
+mod transactions;
+#[cfg(not(target_arch = "wasm32"))]
+mod units;
+#[cfg(not(target_arch = "wasm32"))]
+mod utxo;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+#[cfg(not(target_arch = "wasm32"))]
+mod watch;
+#[cfg(not(target_arch = "wasm32"))]
+mod watchlist;
+
+use breaker::Breakers;
+use cache::{Cache, CacheEntry};
+use flight::Flights;
+use i18n::tr;
+use config::{AddressFormat, CacheConfig, Config, TlsConfig};
+use providers::{GlobalMarket, Providers};
+
+// Displayed in the selected language (see src/i18n.rs); the details inside stay in English
 #[derive(Error, Debug)]
 pub enum CryptoError {
-    #[error("HTTP request failed: {0}")]
+    #[error("{}", tr("error-http", &[("message", .0)]))]
     HttpError(#[from] reqwest::Error),
-    #[error("Invalid input: {0}")]
+    #[error("{}", tr("error-invalid-input", &[("message", .0)]))]
     InvalidInput(String),
-    #[error("API error: {0}")]
-    ApiError(String),
-    #[error("System time error: {0}")]
+    #[error("{}", tr("error-api", &[("provider", provider), ("message", message)]))]
+    ApiError { provider: String, message: String },
+    #[error("{}", match retry_after {
+        Some(wait) => tr("error-rate-limited-retry", &[("provider", provider), ("seconds", &wait.as_secs())]),
+        None => tr("error-rate-limited", &[("provider", provider)]),
+    })]
+    RateLimited { provider: String, retry_after: Option<Duration> },
+    #[error("{}", tr("error-not-found", &[("provider", provider), ("what", what)]))]
+    NotFound { provider: String, what: String },
+    #[error("{}", tr("error-unavailable", &[("provider", provider), ("reason", reason)]))]
+    ProviderUnavailable { provider: String, reason: String },
+    #[error("{}", tr("error-system-time", &[("message", .0)]))]
     SystemTimeError(#[from] std::time::SystemTimeError),
+    #[error("{}", tr("error-config", &[("message", .0)]))]
+    ConfigError(String),
+    #[error("{}", tr("error-runtime", &[("message", .0)]))]
+    RuntimeError(String),
+}
+
+impl CryptoError {
+    pub(crate) fn api(provider: &str, message: impl Into<String>) -> Self {
+        CryptoError::ApiError { provider: provider.to_string(), message: message.into() }
+    }
+
+    // A provider the breakers are skipping
+    pub(crate) fn skipped(provider: &str) -> Self {
+        CryptoError::ProviderUnavailable { provider: provider.to_string(), reason: "skipped after repeated failures".to_string() }
+    }
+
+    // Whether the same request may succeed later, as opposed to a bad input or response
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            CryptoError::RateLimited { .. } | CryptoError::ProviderUnavailable { .. } => true,
+            // Failed to send at all: DNS, connect or TLS errors
+            CryptoError::HttpError(e) => e.is_timeout() || e.is_request(),
+            _ => false,
+        }
+    }
+
+    // How long the provider asked us to wait, if it said
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            CryptoError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    // Provider the error came from, for errors raised while talking to one
+    pub fn provider(&self) -> Option<&str> {
+        match self {
+            CryptoError::ApiError { provider, .. }
+            | CryptoError::RateLimited { provider, .. }
+            | CryptoError::NotFound { provider, .. }
+            | CryptoError::ProviderUnavailable { provider, .. } => Some(provider),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Parser)]
 #[command(name = "coinwagon", about = "A cryptocurrency CLI tool")]
-struct Cli {
+pub struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[arg(long, global = true, help = "Path to config file (TOML)")]
+    config: Option<String>,
+    #[arg(long, global = true, help = "Proxy URL for all requests (http://, https:// or socks5://)")]
+    proxy: Option<String>,
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..), help = "Total request timeout in seconds (default: 30)")]
+    timeout: Option<u64>,
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..), help = "Connect timeout in seconds (default: 10)")]
+    connect_timeout: Option<u64>,
+    #[arg(long, global = true, help = "Answer from the local cache only, never touching the network")]
+    offline: bool,
+    #[arg(long, global = true, help = "Route all requests through the Tor SOCKS5 proxy")]
+    tor: bool,
+    #[arg(long, global = true, help = "Use a separate Tor circuit for each address lookup")]
+    tor_isolate: bool,
+    #[arg(long, global = true, value_name = "PROVIDER=URL", help = "Send a provider's requests to another base URL (repeatable)")]
+    endpoint_override: Vec<String>,
+    #[arg(long, global = true, value_name = "CASSETTE", conflicts_with = "replay", help = "Save every provider response to a cassette file")]
+    record: Option<String>,
+    #[arg(long, global = true, value_name = "CASSETTE", help = "Answer provider requests from a recorded cassette, offline")]
+    replay: Option<String>,
+    #[arg(long, global = true, value_name = "HAR", help = "Log every provider request and response to a HAR file, API keys redacted")]
+    debug_http: Option<String>,
+    #[arg(long, global = true, value_name = "FILE", help = "Append a JSON line for every provider call to an audit log, secrets redacted")]
+    audit_log: Option<String>,
+    #[arg(long, global = true, value_name = "FILE", help = "Append a JSON line for every price fetched from a provider to a file")]
+    log_prices: Option<String>,
+    #[arg(long, global = true, value_name = "LANG", help = "Language of labels and error messages (en, de or es); defaults to the locale")]
+    lang: Option<String>,
+    #[arg(long, global = true, value_name = "TZ", help = "Timezone of timestamps: local (the default) or an IANA name like Europe/Berlin")]
+    timezone: Option<String>,
+    #[arg(long, global = true, help = "Print the requests the command would make, without sending them")]
+    dry_run: bool,
+    #[arg(long, global = true, value_name = "FILE", help = "Render the result through a Handlebars template file instead")]
+    template: Option<String>,
 }
 
+impl Cli {
+    // check-price exits 2 on errors, so a failed fetch isn't taken for a check that didn't hold
+    pub fn error_status(&self) -> i32 {
+        match self.command {
+            Commands::CheckPrice { .. } => 2,
+            _ => 1,
+        }
+    }
+}
+
+// `query --help`: the tables `query` loads, and how their times and dates compare
+const QUERY_TABLES: &str = "The query is SQLite's SQL, run over an in-memory copy of the local data:
+  transactions  the imported transactions, with export transactions' columns
+  prices        the price log: time, coin, fiat, price, source (needs log_prices or --log-prices)
+  snapshots     the alerts daemon's snapshots: time, coin, value, fiat, total (needs [alerts] snapshots)
+
+Times are ISO 8601 UTC text ('2024-06-01T12:00:00Z') and dates YYYY-MM-DD, so they compare as
+text: time >= '2024-01-01'. Statements that would change the data are rejected.";
+
 #[derive(Subcommand)]
 enum Commands {
+    #[command(about = "Get the current price of a cryptocurrency")]
     CurrentPrice {
+        #[arg(required_unless_present = "watchlist", help = "Cryptocurrency symbol (e.g., bitcoin)")]
+        crypto: Option<String>,
+        #[arg(required_unless_present_any = ["watchlist", "vs"], help = "Fiat currency symbol, or a coin to price in (e.g., usd, btc)")]
+        fiat: Option<String>,
+        #[arg(long, conflicts_with_all = ["crypto", "fiat"], help = "Quote every coin on the watchlist instead")]
+        watchlist: bool,
+        #[arg(long, value_parser = ["xau", "xag"], conflicts_with_all = ["fiat", "watchlist"], help = "Price in troy ounces of gold (xau) or silver (xag) instead of a fiat")]
+        vs: Option<String>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Check a price against a threshold: exits 0 when it holds, 1 when not, 2 on errors")]
+    CheckPrice {
         #[arg(help = "Cryptocurrency symbol (e.g., bitcoin)")]
         crypto: String,
-        #[arg(help = "Fiat currency symbol (e.g., usd)")]
+        #[arg(help = "Fiat currency symbol, or a coin to price in (e.g., usd, btc)")]
         fiat: String,
-        #[arg(long, help = "Enable verbose output")]
+        #[arg(long, required_unless_present = "below", conflicts_with = "below", help = "Holds when the price is above this")]
+        above: Option<f64>,
+        #[arg(long, help = "Holds when the price is below this")]
+        below: Option<f64>,
+        #[arg(long, help = "Print the price and whether it holds, plus API responses")]
         verbose: bool,
     },
+    #[command(about = "Get the balance of a single address")]
     AddressBalance {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin), or an @name from the address book")]
+        crypto: String,
+        #[arg(help = "Wallet address or @name; left out after an @name")]
+        address: Option<String>,
+        #[arg(long, conflicts_with = "height", help = "Balance at the end of this day (YYYY-MM-DD) in --timezone instead of now")]
+        at: Option<String>,
+        #[arg(long, help = "Balance after this block instead of now")]
+        height: Option<u64>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Watch an address and print its new transactions and their confirmations as they come")]
+    WatchAddress {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin), or an @name from the address book")]
+        crypto: String,
+        #[arg(help = "Wallet address or @name; left out after an @name")]
+        address: Option<String>,
+        #[arg(long, default_value_t = 60, help = "Seconds between polls")]
+        interval: u64,
+        #[arg(long, default_value_t = 1, help = "Report each new confirmation until a transaction has this many")]
+        confirmations: u64,
+        #[arg(long, help = "Stop after this many polls (default: run until interrupted)")]
+        polls: Option<u32>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Run the alert daemon, printing each [[alerts.rules]] rule in the config as it fires")]
+    Alerts {
+        #[arg(long, default_value_t = 60, help = "Seconds between polls")]
+        interval: u64,
+        #[arg(long, help = "Stop after this many polls (default: run until interrupted)")]
+        polls: Option<u32>,
+        #[arg(long, value_name = "FILE", help = "Write the process id to this file while running")]
+        pid_file: Option<String>,
+        #[arg(long, value_name = "ADDR", help = "Answer /healthz and /readyz over HTTP here, e.g. 127.0.0.1:9100")]
+        listen: Option<String>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[cfg(windows)]
+    #[command(about = "Install, remove or run the alerts daemon as a Windows service")]
+    Service {
+        #[command(subcommand)]
+        action: ServiceCommand,
+    },
+    #[command(about = "Show the height, hash and time of a chain's newest block")]
+    Tip {
         #[arg(help = "Cryptocurrency symbol (e.g., bitcoin)")]
         crypto: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Show a block's hash, time, size, transaction count and fees")]
+    Block {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin)")]
+        crypto: String,
+        #[arg(help = "Block height or hash")]
+        block: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Recommend fee rates for a chain: sat/vB for bitcoin, gwei for EVM chains")]
+    Fees {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin, ethereum)")]
+        crypto: String,
+        #[arg(long, help = "Print JSON instead of text")]
+        json: bool,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Show a chain's difficulty, hashrate and next difficulty adjustment")]
+    Network {
+        #[arg(default_value = "bitcoin", help = "Cryptocurrency symbol (e.g., bitcoin)")]
+        crypto: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Count down the blocks and time to a chain's next halving")]
+    Halving {
+        #[arg(default_value = "bitcoin", help = "Cryptocurrency symbol (bitcoin, bitcoin-cash or litecoin)")]
+        crypto: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Print large transactions as they reach the mempool")]
+    Whales {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin)")]
+        crypto: String,
+        #[arg(long, help = "Smallest transaction to report, in the coin (e.g., 100)")]
+        min: f64,
+        #[arg(long, default_value_t = 10, help = "Seconds between polls")]
+        interval: u64,
+        #[arg(long, help = "Stop after this many polls (default: run until interrupted)")]
+        polls: Option<u32>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Count a bitcoin address's UTXOs, its dust and their ages, and what consolidating them costs")]
+    UtxoReport {
+        #[arg(help = "Bitcoin address, xpub or output descriptor, or an @name from the address book")]
+        address: String,
+        #[arg(long, help = "Fee rate in sat/vB (default: the provider's suggestion)")]
+        fee_rate: Option<f64>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Get an address's balance of an ERC-20 token")]
+    TokenBalance {
+        #[arg(help = "Chain, by its gas coin (e.g., ethereum)")]
+        crypto: String,
         #[arg(help = "Wallet address")]
         address: String,
+        #[arg(long, help = "Token symbol from the token lists (e.g., LINK) or contract address")]
+        token: String,
         #[arg(long, help = "Enable verbose output")]
         verbose: bool,
     },
+    #[command(about = "Value every address in a wallet file")]
     WalletBalance {
         #[arg(help = "Path to wallet file (crypto,address per line)")]
         wallet: String,
         #[arg(help = "Fiat currency symbol (e.g., usd)")]
         fiat: String,
+        #[arg(long, help = "Also count the tokens each EVM address holds")]
+        tokens: bool,
+        #[arg(long, help = "With --tokens, also count tokens that look like spam")]
+        include_unverified: bool,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "List and value every token an EVM address holds")]
+    Tokens {
+        #[arg(help = "Wallet address")]
+        address: String,
+        #[arg(long, default_value = "ethereum", help = "Chain, by its gas coin")]
+        chain: String,
+        #[arg(long, default_value = "usd", help = "Fiat currency to value them in")]
+        fiat: String,
+        #[arg(long, help = "Also list tokens that look like spam")]
+        include_unverified: bool,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "List and value the holdings of an exchange account")]
+    ExchangeBalance {
+        #[arg(value_parser = ["binance", "kraken", "coinbase"], help = "Exchange with credentials in [exchanges]")]
+        exchange: String,
+        #[arg(long, default_value = "usd", help = "Fiat currency to value them in")]
+        fiat: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Import an exchange account's trades, deposits and withdrawals")]
+    ImportHistory {
+        #[arg(value_parser = ["binance", "kraken", "coinbase"], help = "Exchange with credentials in [exchanges]")]
+        exchange: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "List imported transactions, oldest first")]
+    Transactions {
+        #[arg(long, value_parser = ["binance", "kraken", "coinbase"], help = "Only those from this exchange")]
+        exchange: Option<String>,
+    },
+    #[command(about = "Report realized gains on the imported transactions, matching each sale to the lots it came from")]
+    Gains {
+        #[arg(long, value_parser = ["fifo", "lifo", "hifo"], help = "Lot matching method; defaults to [transactions] lot_method, else fifo")]
+        method: Option<String>,
+        #[arg(long, default_value = "usd", help = "Fiat currency to value the trades in")]
+        fiat: String,
+        #[arg(long, help = "Only sales in this calendar year (UTC)")]
+        year: Option<i32>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(name = "export-8949", about = "Write a year's realized gains as Form 8949-style CSV rows for US tax filing")]
+    Export8949 {
+        #[arg(long, help = "Tax year of the sales (UTC)")]
+        year: i32,
+        #[arg(long, value_parser = ["fifo", "lifo", "hifo"], help = "Lot matching method; defaults to [transactions] lot_method, else fifo")]
+        method: Option<String>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Write imported transactions or a coin's daily price history as CSV or Parquet")]
+    Export {
+        #[command(subcommand)]
+        dataset: ExportDataset,
+        #[arg(long, global = true, value_parser = ["csv", "parquet"], default_value = "csv", help = "File format")]
+        format: String,
+        #[arg(long, global = true, value_name = "FILE", help = "File to write; CSV is printed without one")]
+        output: Option<String>,
+    },
+    #[command(about = "Run a read-only SQL SELECT over the imported transactions, the price log or the alerts snapshots", after_help = QUERY_TABLES)]
+    Query {
+        #[arg(help = "Query, e.g. \"SELECT asset, sum(amount) FROM transactions GROUP BY asset\"")]
+        sql: String,
+        #[arg(long, value_parser = ["table", "csv", "json"], default_value = "table", help = "Output format")]
+        format: String,
+    },
+    #[command(about = "Compute the time- and money-weighted returns of the imported transactions over a date range")]
+    Returns {
+        #[arg(long, help = "First day, as YYYY-MM-DD")]
+        from: String,
+        #[arg(long, help = "Last day, as YYYY-MM-DD; defaults to today")]
+        to: Option<String>,
+        #[arg(long, default_value = "usd", help = "Fiat currency to value the holdings in")]
+        fiat: String,
+        #[arg(long, help = "Coin to compare against, as if it had been held throughout (e.g., bitcoin)")]
+        benchmark: Option<String>,
+        #[arg(long, value_parser = ["binance", "kraken", "coinbase"], help = "Only the transactions from this exchange")]
+        exchange: Option<String>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Compare bid and ask across exchanges and find the best spread")]
+    Spread {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin)")]
+        crypto: String,
+        #[arg(help = "Fiat currency symbol (e.g., usd)")]
+        fiat: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Show an exchange's order book depth and mid price")]
+    Orderbook {
+        #[arg(value_parser = ["binance", "kraken", "coinbase"], help = "Exchange to read the book from")]
+        exchange: String,
+        #[arg(help = "Market as BASE/QUOTE (e.g., BTC/USD)")]
+        pair: String,
+        #[arg(long, default_value_t = 20, help = "Levels per side")]
+        depth: usize,
+        #[arg(long, help = "Print JSON instead of text")]
+        json: bool,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Show perpetual futures funding rates on Binance and Bybit")]
+    Funding {
+        #[arg(help = "Coin (e.g., bitcoin or BTC) for its USDT perpetual, or a contract like ETHUSDC")]
+        symbol: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Show the crypto Fear & Greed index and where it's heading")]
+    Sentiment {
+        #[arg(long, default_value_t = 7, help = "Days of history, today included")]
+        days: usize,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Check stablecoins against their 1.00 peg")]
+    Peg {
+        #[arg(help = "Stablecoins to check (default: usdt usdc dai)")]
+        coins: Vec<String>,
+        #[arg(long, help = "Percent off 1.00 that counts as off peg (default: [peg] threshold, else 0.5)")]
+        threshold: Option<f64>,
+        #[arg(long, help = "Print JSON instead of text")]
+        json: bool,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Compute moving averages, RSI and MACD from daily closes")]
+    Indicators {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin)")]
+        crypto: String,
+        #[arg(help = "Fiat currency symbol (e.g., usd)")]
+        fiat: String,
+        #[arg(long, value_delimiter = ',', default_value = "sma50,sma200,rsi14,macd", help = "Indicators: smaN, emaN, rsiN, macd")]
+        set: Vec<String>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Backtest buying a fixed amount every interval against a lump sum")]
+    BacktestDca {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin)")]
+        crypto: String,
+        #[arg(help = "Fiat currency symbol (e.g., usd)")]
+        fiat: String,
+        #[arg(long, default_value_t = 100.0, help = "Fiat spent on each buy")]
+        amount: f64,
+        #[arg(long, default_value = "weekly", value_parser = ["daily", "weekly", "biweekly", "monthly"], help = "How often to buy")]
+        interval: String,
+        #[arg(long, help = "First buy date, as YYYY-MM-DD")]
+        from: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Split a periodic budget across a target allocation at current prices")]
+    DcaPlan {
+        #[arg(help = "Target allocation as coin=weight pairs (e.g., bitcoin=60,ethereum=40)")]
+        allocation: String,
+        #[arg(long, help = "Fiat to spend this period")]
+        budget: f64,
+        #[arg(long, default_value = "usd", help = "Fiat currency of the budget")]
+        fiat: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Estimate a wallet's volatility, Sharpe ratio and each coin's share of its risk")]
+    Risk {
+        #[arg(help = "Path to wallet file (crypto,address per line)")]
+        wallet: String,
+        #[arg(help = "Fiat currency symbol (e.g., usd)")]
+        fiat: String,
+        #[arg(long, default_value_t = 90, help = "Days of daily returns to measure over")]
+        days: u32,
+        #[arg(long, help = "Annual risk-free rate in percent (default from [risk] in the config, else 0)")]
+        risk_free_rate: Option<f64>,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Suggest the trades that bring a wallet back to its target weights")]
+    Rebalance {
+        #[arg(help = "Path to wallet file (crypto,address per line)")]
+        wallet: String,
+        #[arg(help = "Fiat currency symbol (e.g., usd)")]
+        fiat: String,
+        #[arg(long, help = "Target weights as coin=weight pairs (e.g., btc=60,eth=30,sol=10)")]
+        target: String,
+        #[arg(long, default_value_t = 5.0, help = "Percentage points a weight may drift from its target before rebalancing")]
+        band: f64,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Compare two coins' performance, both rebased to 100, with an optional chart")]
+    Compare {
+        #[arg(help = "First cryptocurrency (e.g., bitcoin)")]
+        a: String,
+        #[arg(help = "Second cryptocurrency (e.g., ethereum)")]
+        b: String,
+        #[arg(long, default_value_t = 90, help = "Days of daily closes to compare over")]
+        days: u32,
+        #[arg(long, default_value = "usd", help = "Fiat currency to price both in")]
+        fiat: String,
+        #[arg(long, help = "Draw both rebased series as an ASCII chart")]
+        chart: bool,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Search the saved list of every coin id, or download it again")]
+    CoinList {
+        #[arg(help = "Part of a coin's id, symbol or name; without one, how many coins are saved and when")]
+        query: Option<String>,
+        #[arg(long, help = "Download the list now, whatever the saved copy's age")]
+        refresh: bool,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Show the coins trending on CoinGecko")]
+    Trending {
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Show total market cap, 24h volume and BTC/ETH dominance")]
+    Global {
+        #[arg(long, default_value = "usd", help = "Fiat currency for the totals")]
+        fiat: String,
+        #[arg(long, help = "Print JSON instead of text")]
+        json: bool,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Quote selling one token for another on a DEX aggregator")]
+    QuoteSwap {
+        #[arg(help = "How much to sell")]
+        amount: f64,
+        #[arg(help = "Token to sell: a symbol from the token lists, a contract address, or the gas coin (e.g., ETH)")]
+        from: String,
+        #[arg(help = "Token to buy, likewise")]
+        to: String,
+        #[arg(long, default_value = "ethereum", help = "Chain, by its gas coin")]
+        chain: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Convert an amount between denominations of a coin, e.g. sat to BTC or gwei to ETH")]
+    Units {
+        #[arg(help = "Amount to convert (e.g., 150000)")]
+        amount: String,
+        #[arg(help = "Unit it's in (e.g., sat, gwei, lamport, BTC)")]
+        from: String,
+        #[arg(help = "Unit to convert to, of the same coin")]
+        to: String,
+    },
+    #[command(about = "Name addresses, to use as @name in place of them")]
+    Addressbook {
+        #[command(subcommand)]
+        action: AddressBookCommand,
+    },
+    #[command(about = "Manage the coins `current-price --watchlist` quotes")]
+    Watchlist {
+        #[command(subcommand)]
+        action: WatchlistCommand,
+    },
+    #[command(about = "Quote and chart the baskets of coins set under [baskets] in the config")]
+    Basket {
+        #[command(subcommand)]
+        action: BasketCommand,
+    },
+    #[command(about = "Inspect or purge the local cache")]
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    #[command(about = "Check that each provider is reachable, and how fast")]
+    Status,
+    #[command(about = "Cache hit ratio and each provider's requests, errors and latency, counted across runs")]
+    Stats,
+    #[command(about = "Store provider API keys in the OS keychain")]
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsCommand,
+    },
+    #[command(about = "Interactive prompt reusing one warm client and cache")]
+    Repl {
+        #[arg(long, help = "Wallet file used by `portfolio`")]
+        wallet: Option<String>,
+        #[arg(long, default_value = "usd", help = "Default fiat currency")]
+        fiat: String,
+    },
+    #[command(about = "Print a shell completion script")]
+    Completions {
+        #[arg(help = "Target shell")]
+        shell: clap_complete::Shell,
+    },
+    // Called by the completion scripts for the current coin ids
+    #[command(hide = true)]
+    CompleteCoins,
+}
+
+#[derive(Subcommand)]
+enum ExportDataset {
+    #[command(about = "Every imported transaction, oldest first")]
+    Transactions {
+        #[arg(long, help = "Only this exchange's transactions")]
+        exchange: Option<String>,
+    },
+    #[command(about = "The alerts daemon's portfolio snapshots, a row per coin, oldest first")]
+    Snapshots {
+        #[arg(long, value_name = "FILE", help = "Snapshots file (default: [alerts] snapshots in the config)")]
+        file: Option<String>,
+    },
+    #[command(about = "Daily closes of a coin, oldest first")]
+    History {
+        #[arg(help = "Cryptocurrency symbol (e.g., bitcoin)")]
+        crypto: String,
+        #[arg(long, default_value = "usd", help = "Fiat currency symbol, or a coin to price in (e.g., usd, btc)")]
+        fiat: String,
+        #[arg(long, default_value_t = 365, help = "Days of closes")]
+        days: u32,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+}
+
+#[cfg(windows)]
+#[derive(Subcommand)]
+enum ServiceCommand {
+    #[command(about = "Register the alerts daemon as a service that starts with Windows (run as Administrator)")]
+    Install {
+        #[arg(long, default_value = "coinwagon", help = "Service name")]
+        name: String,
+        #[arg(last = true, help = "What the service runs: `alerts` and its options, after -- (default: alerts)")]
+        args: Vec<String>,
+    },
+    #[command(about = "Stop the service and remove it")]
+    Uninstall {
+        #[arg(long, default_value = "coinwagon", help = "Service name")]
+        name: String,
+    },
+    #[command(about = "What the Service Control Manager starts; not for running by hand")]
+    Run {
+        #[arg(long, default_value = "coinwagon", help = "Service name")]
+        name: String,
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    #[command(about = "List cached entries")]
+    List,
+    #[command(about = "Show entry count, hit ratio and age distribution")]
+    Stats,
+    #[command(about = "Remove cached entries")]
+    Clear {
+        #[arg(long, help = "Only clear entries for this coin (e.g., bitcoin)")]
+        coin: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WatchlistCommand {
+    #[command(about = "Add coins to the watchlist")]
+    Add {
+        #[arg(required = true, help = "Coins (e.g., bitcoin or btc)")]
+        coins: Vec<String>,
+    },
+    #[command(about = "Remove coins from the watchlist")]
+    Remove {
+        #[arg(required = true, help = "Coins (e.g., bitcoin or btc)")]
+        coins: Vec<String>,
+    },
+    #[command(about = "List the coins on the watchlist")]
+    Show,
+}
+
+#[derive(Subcommand)]
+enum BasketCommand {
+    #[command(about = "List the baskets and what one unit of each holds")]
+    List,
+    #[command(about = "Price one unit of a basket now")]
+    Quote {
+        #[arg(help = "Basket name from the config (e.g., l1_index)")]
+        name: String,
+        #[arg(long, default_value = "usd", help = "Fiat currency to price it in")]
+        fiat: String,
+        #[arg(long, help = "Enable verbose output")]
+        verbose: bool,
+    },
+    #[command(about = "Show a basket's daily closes, as if it were a single asset")]
+    History {
+        #[arg(help = "Basket name from the config (e.g., l1_index)")]
+        name: String,
+        #[arg(long, default_value_t = 90, help = "Days of daily closes")]
+        days: u32,
+        #[arg(long, default_value = "usd", help = "Fiat currency to price it in")]
+        fiat: String,
+        #[arg(long, help = "Draw the closes as an ASCII chart")]
+        chart: bool,
         #[arg(long, help = "Enable verbose output")]
         verbose: bool,
     },
 }
 
+#[derive(Subcommand)]
+enum AddressBookCommand {
+    #[command(about = "Name an address, replacing what the name was for")]
+    Add {
+        #[arg(help = "Name, used as @name (e.g., cold-storage)")]
+        name: String,
+        #[arg(help = "Cryptocurrency the address is on (e.g., bitcoin)")]
+        chain: String,
+        address: String,
+    },
+    #[command(about = "Forget a name")]
+    Remove { name: String },
+    #[command(about = "List the named addresses")]
+    Show,
+}
+
+#[derive(Subcommand)]
+enum SecretsCommand {
+    #[command(about = "Store keys for a provider, prompting for them (or reading stdin)")]
+    Set {
+        #[arg(help = "Provider name (e.g., coingecko)")]
+        name: String,
+    },
+    #[command(about = "Remove a provider's stored keys")]
+    Delete {
+        #[arg(help = "Provider name (e.g., coingecko)")]
+        name: String,
+    },
+}
+
+// Everything the HTTP client is built from; configs with equal settings can share a client
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, PartialEq)]
+struct ClientSettings {
+    proxy: Option<String>,
+    tls: TlsConfig,
+    connect_timeout: Duration,
+    timeout: Duration,
+    user_agent: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ClientSettings {
+    fn from_config(config: &Config) -> Self {
+        ClientSettings {
+            proxy: if config.tor.enabled {
+                Some(config.tor.proxy.clone())
+            } else {
+                config.network.proxy.clone()
+            },
+            tls: config.tls.clone(),
+            connect_timeout: Duration::from_secs(config.network.connect_timeout_secs),
+            timeout: Duration::from_secs(config.network.timeout_secs),
+            user_agent: config.network.user_agent.clone(),
+        }
+    }
+
+    fn build(&self) -> Result<Client, CryptoError> {
+        let mut builder = tls::apply(Client::builder(), &self.tls)?
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .user_agent(&self.user_agent);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 struct CryptoTool {
     client: Client,
-    cache: Arc<DashMap<String, (f64, SystemTime)>>,
-    cache_ttl: Duration,
+    cache: Arc<Cache>,
+    providers: Providers,
+    // Set when each address lookup should get its own Tor circuit
+    tor_isolation_proxy: Option<String>,
+    client_settings: ClientSettings,
+    offline: bool,
+    dry_run: bool,
+    // Oldest cache timestamp served in offline mode, reported alongside the result
+    stale_as_of: Mutex<Option<SystemTime>>,
+    // Network lookups currently running, keyed by cache key
+    in_flight: Flights<Lookup>,
+    breakers: Breakers,
+    // Address formats of the [[chains]] the user declared
+    address_formats: HashMap<String, AddressFormat>,
+    // Their chain ids, when given
+    chain_ids: HashMap<String, u64>,
+    tokens: tokens::TokenLists,
+    // Wallet totals include discovered tokens
+    wallet_tokens: bool,
+    token_filter: tokens::TokenFilter,
+    // Exchanges whose holdings count towards wallet totals
+    wallet_exchanges: Vec<String>,
+    // None when there's no data directory and no [transactions] path
+    transactions_path: Option<std::path::PathBuf>,
+    // Default `gains --method`
+    lot_method: String,
+    // Default `peg --threshold`, in percent
+    peg_threshold: f64,
+    // None when there's no data directory and no [watchlist] path
+    watchlist_path: Option<std::path::PathBuf>,
+    watchlist_fiat: String,
+    // Default `risk --risk-free-rate`, in percent
+    risk_free_rate: f64,
+    baskets: HashMap<String, HashMap<String, f64>>,
+    alerts: config::AlertsConfig,
+    // The config file, re-read when a daemon gets SIGHUP
+    config_path: Option<std::path::PathBuf>,
+    // None when there's no data directory and no [addressbook] path
+    addressbook_path: Option<std::path::PathBuf>,
+    coin_list: coinlist::CoinList,
+    // Loaded on first use; None when there's no copy to check coins against
+    coin_listings: tokio::sync::OnceCell<Option<Vec<providers::CoinListing>>>,
+    price_log: Option<pricelog::PriceLog>,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl CryptoTool {
-    fn new() -> Self {
-        CryptoTool {
-            client: Client::new(),
-            cache: Arc::new(DashMap::new()),
-            cache_ttl: Duration::from_secs(300), // 5-minute TTL
-        }
+    fn new(config: &Config) -> Result<Self, CryptoError> {
+        let client = ClientSettings::from_config(config).build()?;
+        Self::with_client(config, client)
     }
 
-    async fn get_current_price(&self, crypto: &str, fiat: &str, verbose: bool) -> Result<f64, CryptoError> {
-        let key = format!("{}_{}", crypto, fiat);
-        if let Some(entry) = self.cache.get(&key) {
-            let (price, timestamp) = *entry;
-            if SystemTime::now().duration_since(timestamp)? < self.cache_ttl {
-                if verbose {
-                    println!("Using cached price for {}/{}", crypto, fiat);
-                }
-                return Ok(price);
+    // `client` must have been built from the same config's ClientSettings. Messages are in
+    // the config's language and times in its timezone from here on, for the whole process.
+    fn with_client(config: &Config, client: Client) -> Result<Self, CryptoError> {
+        i18n::select(config.lang.as_deref())?;
+        timezone::select(config.timezone.as_deref())?;
+        // A cassette should see every lookup, so it gets a throwaway in-memory cache
+        let cache_config = if config.record.is_some() || config.replay.is_some() {
+            CacheConfig {
+                persistent: false,
+                redis_url: None,
+                ..config.cache.clone()
             }
+        } else {
+            config.cache.clone()
+        };
+        Ok(CryptoTool {
+            client,
+            cache: Arc::new(Cache::from_config(&cache_config)),
+            providers: Providers::from_config(config)?,
+            tor_isolation_proxy: (config.tor.enabled && config.tor.isolate).then(|| config.tor.proxy.clone()),
+            client_settings: ClientSettings::from_config(config),
+            offline: config.offline,
+            dry_run: config.dry_run,
+            stale_as_of: Mutex::new(None),
+            in_flight: Flights::new(),
+            breakers: Breakers::new(&config.breaker),
+            address_formats: config.chains.iter().map(|chain| (chain.name.clone(), chain.address_format.clone())).collect(),
+            chain_ids: config.chains.iter().filter_map(|chain| Some((chain.name.clone(), chain.chain_id?))).collect(),
+            tokens: tokens::TokenLists::new(&config.tokens, cache_config.resolved_path()),
+            wallet_tokens: config.tokens.in_wallets,
+            token_filter: tokens::TokenFilter::new(&config.tokens)?,
+            wallet_exchanges: config
+                .exchanges
+                .iter()
+                .filter(|(_, exchange)| exchange.in_wallets)
+                .map(|(name, _)| name.to_lowercase())
+                .collect(),
+            transactions_path: transactions::TransactionStore::path(&config.transactions),
+            lot_method: config.transactions.lot_method.clone().unwrap_or_else(|| "fifo".to_string()),
+            peg_threshold: config.peg.threshold,
+            watchlist_path: watchlist::Watchlist::path(&config.watchlist),
+            watchlist_fiat: config.watchlist.fiat.to_lowercase(),
+            risk_free_rate: config.risk.risk_free_rate,
+            baskets: config.baskets.clone(),
+            alerts: config.alerts.clone(),
+            config_path: config.path.clone(),
+            addressbook_path: addressbook::AddressBook::path(&config.addressbook),
+            coin_list: coinlist::CoinList::new(&config.coin_list, cache_config.resolved_path()),
+            coin_listings: tokio::sync::OnceCell::new(),
+            price_log: config.log_prices.as_deref().map(pricelog::PriceLog::open).transpose()?,
+        })
+    }
+
+    fn chain_id(&self, crypto: &str) -> Result<u64, CryptoError> {
+        self.chain_ids
+            .get(crypto)
+            .copied()
+            .or_else(|| coins::chain_id(crypto))
+            .ok_or_else(|| CryptoError::InvalidInput(format!("{} isn't an EVM chain coinwagon knows the chain id of", crypto)))
+    }
+
+    fn check_address(&self, crypto: &str, address: &str) -> Result<(), CryptoError> {
+        match self.address_formats.get(crypto) {
+            Some(format) => format.check(crypto, address),
+            None if crypto == "bitcoin" && coins::is_taproot(address) && !coins::is_valid_taproot(address) => {
+                Err(CryptoError::InvalidInput(format!(
+                    "{} isn't a valid taproot address; P2TR addresses have a 32-byte program and a bech32m checksum",
+                    address
+                )))
+            }
+            None => Ok(()),
         }
+    }
 
-        let url = format!(
-            "https://api.coingecko.com/api/v3/simple/price?ids={}&vs_currencies={}",
-            crypto, fiat
-        );
-        let resp = self.client.get(&url).send().await?.json::<Value>().await?;
-        let price = resp[crypto][fiat]
-            .as_f64()
-            .ok_or_else(|| CryptoError::ApiError("Invalid price response".to_string()))?;
-
-        self.cache.insert(key, (price, SystemTime::now()));
-        if verbose {
-            println!("Fetched price from CoinGecko: {}", price);
+    // Tor isolates streams by SOCKS credentials, so a per-address username yields a fresh circuit
+    fn client_for_address(&self, address: &str) -> Result<Client, CryptoError> {
+        let Some(proxy) = &self.tor_isolation_proxy else {
+            return Ok(self.client.clone());
+        };
+        let mut hasher = DefaultHasher::new();
+        address.hash(&mut hasher);
+        let mut url = reqwest::Url::parse(proxy)
+            .map_err(|e| CryptoError::ConfigError(format!("Invalid Tor proxy URL: {}", e)))?;
+        url.set_username(&format!("{:016x}", hasher.finish()))
+            .and_then(|_| url.set_password(Some("coinwagon")))
+            .map_err(|_| CryptoError::ConfigError("Tor proxy URL cannot carry credentials".to_string()))?;
+        let settings = ClientSettings {
+            proxy: Some(url.to_string()),
+            ..self.client_settings.clone()
+        };
+        settings.build()
+    }
+
+    fn read_offline_cache(&self, key: &str, what: &str) -> Result<Lookup, CryptoError> {
+        let entry = self
+            .cache
+            .get(key)
+            .ok_or_else(|| CryptoError::InvalidInput(format!("No cached {} available in offline mode", what)))?;
+        let mut stale_as_of = self.stale_as_of.lock().unwrap();
+        if stale_as_of.is_none_or(|oldest| entry.fetched_at < oldest) {
+            *stale_as_of = Some(entry.fetched_at);
         }
-        Ok(price)
-    }
-
-    async fn get_address_balance(&self, crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError> {
-        // Try BlockCypher API first (more reliable)
-        if crypto == "bitcoin" {
-            let url = format!("https://api.blockcypher.com/v1/btc/main/addrs/{}/balance", address);
-            if let Ok(resp) = self.client.get(&url).send().await {
-                if let Ok(json) = resp.json::<Value>().await {
-                    if let Some(balance) = json["balance"].as_u64() {
-                        let btc_balance = balance as f64 / 100_000_000.0; // Convert satoshis to BTC
-                        if verbose {
-                            println!("Fetched balance from BlockCypher: {} BTC", btc_balance);
-                        }
-                        return Ok(btc_balance);
+        Ok(Lookup::cached(entry))
+    }
+
+    fn stale_as_of(&self) -> Option<SystemTime> {
+        *self.stale_as_of.lock().unwrap()
+    }
+
+    // The first answer from `providers`, in order, as every lookup with fallbacks goes: those
+    // behind an open circuit are skipped, each answer is recorded with the breakers, and a failure
+    // moves on to the next. The error is the first skip or the last failure, else `none`'s.
+    async fn first_answer<'p, P: ?Sized, T, F: Future<Output = Result<T, CryptoError>>>(
+        &self,
+        providers: &[&'p P],
+        name: impl Fn(&P) -> &str,
+        verbose: bool,
+        none: impl FnOnce() -> CryptoError,
+        fetch: impl Fn(&'p P) -> F,
+    ) -> Result<(T, &'p P), CryptoError> {
+        let mut last_error = None;
+        for (i, provider) in providers.iter().enumerate() {
+            if !self.breakers.allow(name(provider)) {
+                if verbose {
+                    eprintln!("Skipping {}: circuit open after repeated failures", name(provider));
+                }
+                last_error.get_or_insert_with(|| CryptoError::skipped(name(provider)));
+                continue;
+            }
+            let result = fetch(*provider).await;
+            self.breakers.record(name(provider), result.as_ref().err());
+            match result {
+                Ok(answer) => return Ok((answer, *provider)),
+                Err(e) => {
+                    if verbose && i + 1 < providers.len() {
+                        eprintln!("Warning: {} failed ({}), falling back to {}", name(provider), e, name(providers[i + 1]));
                     }
+                    last_error = Some(e);
                 }
             }
         }
+        Err(last_error.unwrap_or_else(none))
+    }
+}
+
+// Drops the binary noise sums and differences of decimal prices pick up
+#[cfg(not(target_arch = "wasm32"))]
+fn tidy(value: f64) -> f64 {
+    (value * 1e8).round() / 1e8
+}
 
-        // Fallback to Blockchair API with improved error handling
-        let url = format!("https://api.blockchair.com/{}/dashboards/address/{}", crypto, address);
-        let resp = self.client.get(&url).send().await?;
-        let json: Value = resp.json().await?;
-        
-        if verbose {
-            println!("API Response: {}", serde_json::to_string_pretty(&json).unwrap_or_else(|_| "Invalid JSON".to_string()));
+// A looked-up value plus where and when it came from
+#[derive(Debug, Clone)]
+pub struct Lookup {
+    pub value: f64,
+    pub source: String,
+    pub fetched_at: SystemTime,
+    // Served from the cache rather than fetched by this call
+    pub cached: bool,
+}
+
+impl Lookup {
+    fn fetched(entry: CacheEntry) -> Self {
+        Lookup {
+            value: entry.value,
+            source: entry.source.unwrap_or_else(|| "unknown".to_string()),
+            fetched_at: entry.fetched_at,
+            cached: false,
         }
-        
-        // Try different possible response structures
-        let balance = if let Some(data) = json.get("data") {
-            if let Some(addr_data) = data.get(address) {
-                if let Some(address_info) = addr_data.get("address") {
-                    address_info.get("balance").and_then(|v| v.as_f64())
-                } else {
-                    addr_data.get("balance").and_then(|v| v.as_f64())
-                }
-            } else {
-                None
-            }
-        } else {
-            json.get("balance").and_then(|v| v.as_f64())
-        };
+    }
 
-        match balance {
-            Some(bal) => {
-                let btc_balance = bal / 100_000_000.0; // Convert satoshis to BTC
-                if verbose {
-                    println!("Fetched balance from Blockchair: {} {}", btc_balance, crypto.to_uppercase());
-                }
-                Ok(btc_balance)
-            },
-            None => Err(CryptoError::ApiError(format!("Could not parse balance from response: {}", 
-                serde_json::to_string(&json).unwrap_or_else(|_| "Invalid JSON".to_string()))))
+    fn cached(entry: CacheEntry) -> Self {
+        Lookup {
+            cached: true,
+            ..Self::fetched(entry)
         }
     }
+}
 
-    async fn get_wallet_balance(&self, wallet: &str, fiat: &str, verbose: bool) -> Result<Vec<(String, f64, f64, f64)>, CryptoError> {
-        let wallets = std::fs::read_to_string(wallet)
-            .map_err(|e| CryptoError::InvalidInput(format!("Failed to read wallet file: {}", e)))?
-            .lines()
-            .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
-            .map(|line| {
-                let parts: Vec<&str> = line.split(',').collect();
-                if parts.len() != 2 {
-                    Err(CryptoError::InvalidInput(format!("Invalid wallet line: {}", line)))
-                } else {
-                    Ok((parts[0].to_string(), parts[1].trim().to_string()))
-                }
-            })
-            .collect::<Result<Vec<_>, CryptoError>>()?;
-
-        let mut results = Vec::new();
-        for (crypto, address) in wallets {
-            let balance = self.get_address_balance(&crypto, &address, verbose).await?;
-            let price = self.get_current_price(&crypto, fiat, verbose).await?;
-            let fiat_value = balance * price;
-            results.push((crypto.to_uppercase(), balance, fiat_value, price));
+#[derive(Debug, Clone)]
+pub struct WalletLine {
+    pub crypto: String,
+    pub address: String,
+    // The address's name in the address book
+    pub name: Option<String>,
+    pub balance: Lookup,
+    pub price: Lookup,
+}
+
+impl WalletLine {
+    pub fn fiat_value(&self) -> f64 {
+        self.balance.value * self.price.value
+    }
+}
+
+// Result of a command, rendered as text for run_command/the binary or converted to
+// Python objects by the structured API
+pub enum Output {
+    Price { crypto: String, fiat: String, quote: Lookup },
+    Balance { crypto: String, address: String, quote: Lookup },
+    Wallet { fiat: String, lines: Vec<WalletLine> },
+    Global { fiat: String, market: GlobalMarket, source: String },
+    // check-price's answer; `text` is empty unless verbose
+    Check { held: bool, text: String },
+    Text(String),
+}
+
+impl Output {
+    pub fn render(&self) -> String {
+        match self {
+            Output::Price { fiat, quote, .. } => format!("{} {}", quote.value, fiat.to_uppercase()),
+            Output::Balance { crypto, quote, .. } => format!("{} {}", quote.value, crypto.to_uppercase()),
+            Output::Wallet { fiat, lines } => format_wallet(lines, fiat),
+            Output::Global { fiat, market, .. } => format_global(market, fiat),
+            Output::Check { text, .. } | Output::Text(text) => text.clone(),
         }
-        Ok(results)
-    }
-}
-
-#[pyfunction]
-unsafe fn run_command(_command: String, args: Vec<String>) -> PyResult<String> {
-    unsafe {
-        let rt = Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Runtime error: {}", e)))?;
-        let tool = CryptoTool::new();
-        
-        let cli = Cli::try_parse_from(std::iter::once("coinwagon".to_string())
-            .chain(std::iter::once(_command))
-            .chain(args.into_iter()))
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid arguments: {}", e)))?;
-
-        let result = rt.block_on(async {
-            match cli.command {
-                Commands::CurrentPrice { crypto, fiat, verbose } => {
-                    match tool.get_current_price(&crypto, &fiat, verbose).await {
-                        Ok(price) => Ok(format!("{} {}", price, fiat.to_uppercase())),
-                        Err(e) => Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Error: {}", e))),
-                    }
-                }
-                Commands::AddressBalance { crypto, address, verbose } => {
-                    match tool.get_address_balance(&crypto, &address, verbose).await {
-                        Ok(balance) => Ok(format!("{} {}", balance, crypto.to_uppercase())),
-                        Err(e) => Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Error: {}", e))),
-                    }
-                }
-                Commands::WalletBalance { wallet, fiat, verbose } => {
-                    match tool.get_wallet_balance(&wallet, &fiat, verbose).await {
-                        Ok(results) => {
-                            let mut output = String::new();
-                            let mut total_fiat = 0.0;
-                            for (crypto, balance, fiat_value, price) in results {
-                                output.push_str(&format!(
-                                    "{}: {} {} = {} {}\n",
-                                    crypto, balance, crypto, fiat_value, fiat.to_uppercase()
-                                ));
-                                total_fiat += fiat_value;
-                            }
-                            output.push_str(&format!("Total: {} {}", total_fiat, fiat.to_uppercase()));
-                            Ok(output)
-                        }
-                        Err(e) => Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Error: {}", e))),
-                    }
-                }
-            }
-        })?;
-        Ok(result)
     }
 }
 
-#[pymodule]
-fn coinwagon(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(run_command, m)?)?;
-    Ok(())
-}
\ No newline at end of file
+fn format_wallet(results: &[WalletLine], fiat: &str) -> String {
+    let mut output = String::new();
+    let mut total_fiat = 0.0;
+    for line in results {
+        let crypto = line.crypto.to_uppercase();
+        let name = line.name.as_ref().map(|name| format!(" @{}", name)).unwrap_or_default();
+        output.push_str(&format!(
+            "{}{}: {} {} = {} {}\n",
+            crypto, name, line.balance.value, crypto, line.fiat_value(), fiat.to_uppercase()
+        ));
+        total_fiat += line.fiat_value();
+    }
+    output.push_str(&tr("wallet-total", &[("amount", &total_fiat), ("fiat", &fiat.to_uppercase())]));
+    output
+}
+
+fn format_global(market: &GlobalMarket, fiat: &str) -> String {
+    let fiat = fiat.to_uppercase();
+    [
+        tr("global-market-cap", &[("amount", &market.total_market_cap), ("fiat", &fiat)]),
+        tr("global-volume", &[("amount", &market.total_volume_24h), ("fiat", &fiat)]),
+        tr("global-btc-dominance", &[("percent", &format!("{:.2}", market.btc_dominance))]),
+        tr("global-eth-dominance", &[("percent", &format!("{:.2}", market.eth_dominance))]),
+        tr("global-active-coins", &[("count", &market.active_coins)]),
+    ]
+    .join("\n")
+}
+
+// `global --json`, with when the provider last totalled the market
+#[cfg(not(target_arch = "wasm32"))]
+fn global_json(market: &GlobalMarket, fiat: &str, source: &str) -> Result<String, CryptoError> {
+    serde_json::to_string_pretty(&global_fields(market, fiat, source)).map_err(|e| CryptoError::RuntimeError(e.to_string()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn global_fields(market: &GlobalMarket, fiat: &str, source: &str) -> serde_json::Value {
+    serde_json::json!({
+        "fiat": fiat,
+        "total_market_cap": market.total_market_cap,
+        "total_volume_24h": market.total_volume_24h,
+        "btc_dominance": market.btc_dominance,
+        "eth_dominance": market.eth_dominance,
+        "active_coins": market.active_coins,
+        "updated_at": format_timestamp(market.updated_at),
+        "source": source,
+    })
+}
+
+// What a `--template` sees of a result: its fields, with the text output as `output`
+#[cfg(not(target_arch = "wasm32"))]
+fn template_context((output, stale_as_of): &(Output, Option<SystemTime>)) -> serde_json::Value {
+    let lookup = |quote: &Lookup| {
+        serde_json::json!({"source": quote.source, "fetched_at": format_timestamp(quote.fetched_at), "cached": quote.cached})
+    };
+    let mut context = match output {
+        Output::Price { crypto, fiat, quote } => serde_json::json!({"crypto": crypto, "fiat": fiat, "price": quote.value, "quote": lookup(quote)}),
+        Output::Balance { crypto, address, quote } => {
+            serde_json::json!({"crypto": crypto, "address": address, "balance": quote.value, "quote": lookup(quote)})
+        }
+        Output::Wallet { fiat, lines } => {
+            let json: Vec<serde_json::Value> = lines
+                .iter()
+                .map(|line| {
+                    serde_json::json!({
+                        "crypto": line.crypto,
+                        "address": line.address,
+                        "name": line.name,
+                        "balance": line.balance.value,
+                        "price": line.price.value,
+                        "value": line.fiat_value(),
+                        "balance_quote": lookup(&line.balance),
+                        "price_quote": lookup(&line.price),
+                    })
+                })
+                .collect();
+            serde_json::json!({"fiat": fiat, "lines": json, "total": lines.iter().map(WalletLine::fiat_value).sum::<f64>()})
+        }
+        Output::Global { fiat, market, source } => global_fields(market, fiat, source),
+        Output::Check { held, .. } => serde_json::json!({"held": held}),
+        Output::Text(text) => serde_json::json!({"lines": text.lines().collect::<Vec<_>>()}),
+    };
+    context["output"] = output.render().into();
+    context["stale_as_of"] = stale_as_of.map(format_timestamp).into();
+    context
+}
+
+fn format_timestamp(time: SystemTime) -> String {
+    timezone::iso(time)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn runtime() -> Result<Runtime, CryptoError> {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| CryptoError::RuntimeError(e.to_string()))
+}
+
+// Runs `f` and persists the cache afterwards.
+// The second value is the oldest cache timestamp served offline.
+#[cfg(not(target_arch = "wasm32"))]
+async fn using_tool<T>(
+    tool: &CryptoTool,
+    f: impl AsyncFnOnce(&CryptoTool) -> Result<T, CryptoError>,
+) -> Result<(T, Option<SystemTime>), CryptoError> {
+    let result = f(tool).await;
+    // Written even when the command failed, since that's usually what the cassette is for
+    if let Err(e) = tool.providers.transport.save() {
+        eprintln!("Warning: {}", e);
+    }
+    // Also saved when the command failed, or `stats` would leave out the requests that erred
+    tool.cache.add_requests(tool.providers.transport.take_requests());
+    if let Err(e) = tool.cache.save() {
+        eprintln!("Warning: {}", e);
+    }
+    Ok((result?, tool.stale_as_of()))
+}
+
+// Blocking version of `using_tool` with a fresh tool and runtime for `config`
+#[cfg(feature = "ffi")]
+fn with_tool<T>(
+    config: &Config,
+    f: impl AsyncFnOnce(&CryptoTool) -> Result<T, CryptoError>,
+) -> Result<(T, Option<SystemTime>), CryptoError> {
+    let tool = CryptoTool::new(config)?;
+    runtime()?.block_on(using_tool(&tool, f))
+}
+
+// Config file plus the global command-line overrides
+#[cfg(not(target_arch = "wasm32"))]
+fn load_config(cli: &Cli) -> Result<Config, CryptoError> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    if let Some(proxy) = &cli.proxy {
+        config.network.proxy = Some(proxy.clone());
+    }
+    if let Some(timeout) = cli.timeout {
+        config.network.timeout_secs = timeout;
+    }
+    if let Some(connect_timeout) = cli.connect_timeout {
+        config.network.connect_timeout_secs = connect_timeout;
+    }
+    if cli.offline {
+        config.offline = true;
+    }
+    if cli.dry_run {
+        config.dry_run = true;
+    }
+    if cli.tor || cli.tor_isolate {
+        config.tor.enabled = true;
+    }
+    if cli.tor_isolate {
+        config.tor.isolate = true;
+    }
+    if let Some(path) = &cli.record {
+        config.record = Some(path.clone());
+    }
+    if let Some(path) = &cli.replay {
+        config.replay = Some(path.clone());
+    }
+    if let Some(path) = &cli.debug_http {
+        config.debug_http = Some(path.clone());
+    }
+    if let Some(path) = &cli.audit_log {
+        config.audit_log = Some(path.clone());
+    }
+    if let Some(path) = &cli.log_prices {
+        config.log_prices = Some(path.clone());
+    }
+    if let Some(lang) = &cli.lang {
+        config.lang = Some(lang.clone());
+    }
+    if let Some(timezone) = &cli.timezone {
+        config.timezone = Some(timezone.clone());
+    }
+    for endpoint in &cli.endpoint_override {
+        let (provider, url) = endpoint
+            .split_once('=')
+            .ok_or_else(|| CryptoError::ConfigError(format!("Expected PROVIDER=URL, got '{}'", endpoint)))?;
+        config.endpoints.insert(provider.to_lowercase(), url.to_string());
+    }
+    Ok(config)
+}
+
+// Runs a parsed command; the second value is the oldest cache timestamp served offline
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run(cli: Cli) -> Result<(Output, Option<SystemTime>), CryptoError> {
+    let tool = CryptoTool::new(&load_config(&cli)?)?;
+    runtime()?.block_on(commands::run_on(&tool, cli.command))
+}
+
+// Shared entry point for the Python string API and the `coinwagon` binary
+#[cfg(not(target_arch = "wasm32"))]
+pub fn execute(cli: Cli) -> Result<String, CryptoError> {
+    execute_checked(cli).map(|(output, _)| output)
+}
+
+// `execute`, plus whether the command's check held: false only when check-price's didn't, for
+// the binary's exit status
+#[cfg(not(target_arch = "wasm32"))]
+pub fn execute_checked(cli: Cli) -> Result<(String, bool), CryptoError> {
+    // Read first, so a broken template fails before any request is made
+    let template = cli.template.as_deref().map(template::Template::load).transpose()?;
+    let result = run(cli)?;
+    let held = !matches!(result.0, Output::Check { held: false, .. });
+    let output = match template {
+        Some(template) => template.render(&template_context(&result))?,
+        None => render(result),
+    };
+    Ok((output, held))
+}
+
+// How the CLI reports a failed command, in the selected language
+pub fn error_message(error: &CryptoError) -> String {
+    format!("{}: {}", tr("error", &[]), error)
+}
+
+// Text output, flagged when any of it came from the offline cache
+fn render((output, stale_as_of): (Output, Option<SystemTime>)) -> String {
+    let result = output.render();
+    match stale_as_of {
+        Some(fetched_at) if !result.is_empty() => tr("stale-as-of", &[("result", &result), ("time", &format_timestamp(fetched_at))]),
+        _ => result,
+    }
+}
+
+// Collects the Python signatures for `src/bin/stub_gen.rs`
+#[cfg(not(target_arch = "wasm32"))]
+pyo3_stub_gen::define_stub_info_gatherer!(stub_info);
@@ -0,0 +1,231 @@
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use std::collections::HashMap;
+
+use crate::transactions::{Kind, Transaction};
+use crate::{CryptoError, coins};
+
+// Amounts smaller than this are rounding left over from splitting lots
+const DUST: f64 = 1e-12;
+
+// Which lots a disposal is matched against first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Fifo,
+    Lifo,
+    // Highest cost first, which realizes the smallest gains
+    Hifo,
+}
+
+impl Method {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "fifo" => Some(Method::Fifo),
+            "lifo" => Some(Method::Lifo),
+            "hifo" => Some(Method::Hifo),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Fifo => "FIFO",
+            Method::Lifo => "LIFO",
+            Method::Hifo => "HIFO",
+        }
+    }
+}
+
+struct Lot {
+    acquired: DateTime<Utc>,
+    amount: f64,
+    // Fiat paid per unit, fees included
+    unit_cost: f64,
+}
+
+// Part of a sale matched to one lot. `acquired` is None for coins sold beyond every lot on
+// record, which count at no cost.
+pub struct Disposal {
+    pub asset: String,
+    pub amount: f64,
+    pub disposed: DateTime<Utc>,
+    pub acquired: Option<DateTime<Utc>>,
+    pub proceeds: f64,
+    pub cost: f64,
+}
+
+impl Disposal {
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+}
+
+fn id(asset: &str) -> String {
+    coins::resolve_symbol(asset)
+}
+
+fn is_fiat(asset: &str) -> bool {
+    coins::FIAT_CODES.contains(&asset)
+}
+
+// Open lots per coin, oldest first
+#[derive(Default)]
+struct Ledger {
+    lots: HashMap<String, Vec<Lot>>,
+}
+
+impl Ledger {
+    fn acquire(&mut self, asset: String, acquired: DateTime<Utc>, amount: f64, cost: f64) {
+        if amount > DUST {
+            self.lots.entry(asset).or_default().push(Lot { acquired, amount, unit_cost: cost / amount });
+        }
+    }
+
+    // Takes `amount` out of the lots in `method`'s order, returning (acquired, amount, cost) for
+    // each lot drawn on and, last, what no lot covered
+    fn take(&mut self, asset: &str, amount: f64, method: Method) -> (Vec<(DateTime<Utc>, f64, f64)>, f64) {
+        let lots = self.lots.entry(asset.to_string()).or_default();
+        let mut drawn = Vec::new();
+        let mut remaining = amount;
+        while remaining > DUST && !lots.is_empty() {
+            let index = match method {
+                Method::Fifo => 0,
+                Method::Lifo => lots.len() - 1,
+                Method::Hifo => (0..lots.len()).max_by(|a, b| lots[*a].unit_cost.total_cmp(&lots[*b].unit_cost)).unwrap_or(0),
+            };
+            let lot = &mut lots[index];
+            let used = lot.amount.min(remaining);
+            drawn.push((lot.acquired, used, used * lot.unit_cost));
+            lot.amount -= used;
+            remaining -= used;
+            if lot.amount <= DUST {
+                lots.remove(index);
+            }
+        }
+        (drawn, remaining.max(0.0))
+    }
+
+    fn dispose(&mut self, asset: &str, amount: f64, disposed: DateTime<Utc>, proceeds: f64, method: Method, out: &mut Vec<Disposal>) {
+        let (drawn, uncovered) = self.take(asset, amount, method);
+        let share = |part: f64| if amount > 0.0 { proceeds * part / amount } else { 0.0 };
+        for (acquired, part, cost) in drawn {
+            out.push(Disposal { asset: asset.to_string(), amount: part, disposed, acquired: Some(acquired), proceeds: share(part), cost });
+        }
+        if uncovered > DUST {
+            out.push(Disposal { asset: asset.to_string(), amount: uncovered, disposed, acquired: None, proceeds: share(uncovered), cost: 0.0 });
+        }
+    }
+}
+
+// Every disposal in `transactions` (oldest first), matched against the lots bought or deposited
+// before it. `value` is a coin's fiat price at a day's close. A trade is worth its fiat side
+// when it has one, otherwise what it received; its fee goes onto the cost of what it bought
+// with fiat, or comes off the proceeds of what it sold. Deposited coins start a lot at their
+// value that day, and withdrawn ones leave the lots without a sale.
+pub fn realize(
+    transactions: &[Transaction],
+    method: Method,
+    value: impl Fn(&str, NaiveDate) -> Result<f64, CryptoError>,
+) -> Result<Vec<Disposal>, CryptoError> {
+    let mut ledger = Ledger::default();
+    let mut disposals = Vec::new();
+    for transaction in transactions {
+        let asset = id(&transaction.asset);
+        let date = transaction.time.date_naive();
+        let fee = match &transaction.fee {
+            Some(fee) => {
+                let fee_asset = id(&fee.asset);
+                if !is_fiat(&fee_asset) {
+                    ledger.take(&fee_asset, fee.amount, method);
+                }
+                fee.amount * value(&fee_asset, date)?
+            }
+            None => 0.0,
+        };
+        match (transaction.kind, &transaction.counter) {
+            (Kind::Deposit, _) if !is_fiat(&asset) => {
+                let cost = transaction.amount * value(&asset, date)?;
+                ledger.acquire(asset, transaction.time, transaction.amount, cost);
+            }
+            (Kind::Withdrawal, _) if !is_fiat(&asset) => {
+                ledger.take(&asset, transaction.amount, method);
+            }
+            (Kind::Trade, Some(counter)) => {
+                let paid = id(&counter.asset);
+                let worth = match is_fiat(&paid) {
+                    true => counter.amount * value(&paid, date)?,
+                    false => transaction.amount * value(&asset, date)?,
+                };
+                if !is_fiat(&paid) {
+                    ledger.dispose(&paid, counter.amount, transaction.time, worth - fee, method, &mut disposals);
+                }
+                if !is_fiat(&asset) {
+                    let cost = if is_fiat(&paid) { worth + fee } else { worth };
+                    ledger.acquire(asset, transaction.time, transaction.amount, cost);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(disposals)
+}
+
+pub fn render(disposals: &[Disposal], method: Method, fiat: &str, sources: &str) -> String {
+    let fiat = fiat.to_uppercase();
+    let mut lines = vec![format!("Realized gains in {}, {} ({}):", fiat, method.name(), sources)];
+    if disposals.is_empty() {
+        lines.push("  No disposals".to_string());
+        return lines.join("\n");
+    }
+    for disposal in disposals {
+        let acquired = match disposal.acquired {
+            Some(acquired) => {
+                let days = (disposal.disposed - acquired).num_days();
+                format!("acquired {}, held {} day{}", acquired.date_naive(), days, if days == 1 { "" } else { "s" })
+            }
+            None => "no acquisition on record, counted at no cost".to_string(),
+        };
+        lines.push(format!(
+            "  {} sold {} {} ({}): proceeds {:.2}, cost {:.2}, gain {:.2}",
+            disposal.disposed.date_naive(),
+            crate::tidy(disposal.amount),
+            disposal.asset,
+            acquired,
+            disposal.proceeds,
+            disposal.cost,
+            disposal.gain()
+        ));
+    }
+    let total = |field: fn(&Disposal) -> f64| disposals.iter().map(field).sum::<f64>();
+    lines.push(format!(
+        "Total: proceeds {:.2}, cost {:.2}, gain {:.2} {}",
+        total(|d| d.proceeds),
+        total(|d| d.cost),
+        total(Disposal::gain),
+        fiat
+    ));
+    lines.join("\n")
+}
+
+// One CSV row per disposal with Form 8949's columns (a), (b), (c), (d), (e) and (h), dates as
+// MM/DD/YYYY, plus whether it goes in Part I or II. Coins held more than a year are long term;
+// those with no acquisition on record have no date acquired and count as short term.
+pub fn form_8949(disposals: &[Disposal]) -> String {
+    let mut lines = vec!["Description,Date Acquired,Date Sold,Proceeds,Cost Basis,Gain or Loss,Term".to_string()];
+    for disposal in disposals {
+        let sold = disposal.disposed.date_naive();
+        let acquired = disposal.acquired.map(|acquired| acquired.date_naive());
+        let long = acquired.and_then(|acquired| acquired.checked_add_months(Months::new(12))).is_some_and(|year_on| sold > year_on);
+        lines.push(format!(
+            "{} {},{},{},{:.2},{:.2},{:.2},{}",
+            crate::tidy(disposal.amount),
+            coins::ticker(&disposal.asset),
+            acquired.map_or(String::new(), |acquired| acquired.format("%m/%d/%Y").to_string()),
+            sold.format("%m/%d/%Y"),
+            disposal.proceeds,
+            disposal.cost,
+            disposal.gain(),
+            if long { "long" } else { "short" }
+        ));
+    }
+    lines.join("\n")
+}
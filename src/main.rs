@@ -0,0 +1,22 @@
+use clap::Parser;
+use std::io::Write;
+
+fn main() {
+    let cli = coinwagon::Cli::parse();
+    let error_status = cli.error_status();
+    match coinwagon::execute_checked(cli) {
+        Ok((output, held)) => {
+            if !output.is_empty() {
+                // A closed pipe (e.g. `| head`) isn't an error worth reporting
+                let _ = writeln!(std::io::stdout().lock(), "{}", output);
+            }
+            if !held {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", coinwagon::error_message(&e));
+            std::process::exit(error_status);
+        }
+    }
+}
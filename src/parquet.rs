@@ -0,0 +1,74 @@
+use ::parquet::basic::{Compression, LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use ::parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use ::parquet::errors::ParquetError;
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::SerializedFileWriter;
+use ::parquet::schema::types::Type;
+use chrono::DateTime;
+use std::sync::Arc;
+
+use crate::CryptoError;
+use crate::export::{Table, Values};
+
+// The table as a Parquet file of one row group, a Snappy-compressed optional column per table
+// column: text as STRING, numbers as DOUBLE, times as UTC TIMESTAMP(MICROS) and dates as DATE
+pub fn write(table: &Table) -> Result<Vec<u8>, CryptoError> {
+    encode(table).map_err(|e| CryptoError::RuntimeError(format!("Failed to write Parquet: {}", e)))
+}
+
+fn encode(table: &Table) -> Result<Vec<u8>, ParquetError> {
+    let fields = table.columns.iter().map(|(name, values)| column_type(name, values).map(Arc::new)).collect::<Result<_, _>>()?;
+    let schema = Type::group_type_builder("schema").with_fields(fields).build()?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_created_by(concat!("coinwagon ", env!("CARGO_PKG_VERSION")).to_string())
+        .build();
+
+    let mut file = Vec::new();
+    let mut writer = SerializedFileWriter::new(&mut file, Arc::new(schema), Arc::new(properties))?;
+    let mut group = writer.next_row_group()?;
+    for (_, values) in &table.columns {
+        let Some(mut column) = group.next_column()? else {
+            return Err(ParquetError::General("fewer column writers than columns".to_string()));
+        };
+        // 1 for a value, 0 for a null; only the values that aren't null are written
+        let levels = |present: Vec<bool>| present.into_iter().map(i16::from).collect::<Vec<_>>();
+        match values {
+            Values::Text(rows) => {
+                let texts: Vec<ByteArray> = rows.iter().flatten().map(|text| ByteArray::from(text.as_str())).collect();
+                let levels = levels(rows.iter().map(Option::is_some).collect());
+                column.typed::<ByteArrayType>().write_batch(&texts, Some(&levels), None)?;
+            }
+            Values::Number(rows) => {
+                let numbers: Vec<f64> = rows.iter().flatten().copied().collect();
+                let levels = levels(rows.iter().map(Option::is_some).collect());
+                column.typed::<DoubleType>().write_batch(&numbers, Some(&levels), None)?;
+            }
+            Values::Time(rows) => {
+                let micros: Vec<i64> = rows.iter().flatten().map(|time| time.timestamp_micros()).collect();
+                let levels = levels(rows.iter().map(Option::is_some).collect());
+                column.typed::<Int64Type>().write_batch(&micros, Some(&levels), None)?;
+            }
+            Values::Date(rows) => {
+                let epoch = DateTime::UNIX_EPOCH.date_naive();
+                let days: Vec<i32> = rows.iter().flatten().map(|date| date.signed_duration_since(epoch).num_days() as i32).collect();
+                let levels = levels(rows.iter().map(Option::is_some).collect());
+                column.typed::<Int32Type>().write_batch(&days, Some(&levels), None)?;
+            }
+        }
+        column.close()?;
+    }
+    group.close()?;
+    writer.close()?;
+    Ok(file)
+}
+
+fn column_type(name: &str, values: &Values) -> Result<Type, ParquetError> {
+    let (physical, logical) = match values {
+        Values::Text(_) => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        Values::Number(_) => (PhysicalType::DOUBLE, None),
+        Values::Time(_) => (PhysicalType::INT64, Some(LogicalType::timestamp(true, TimeUnit::MICROS))),
+        Values::Date(_) => (PhysicalType::INT32, Some(LogicalType::Date)),
+    };
+    Type::primitive_type_builder(name, physical).with_repetition(Repetition::OPTIONAL).with_logical_type(logical).build()
+}
@@ -0,0 +1,541 @@
+use std::collections::BTreeSet;
+
+use crate::cache::Cache;
+use crate::coins;
+use crate::commands::{BLOCK_TIME_SAMPLE, GAP_LIMIT, backtest_days, batch_groups, dca_schedule, indicator_days, parse_as_of, parse_block_id, parse_indicators, parse_pair, pegged_coins, perpetual_symbol, returns_range};
+use crate::dca;
+use crate::descriptor;
+use crate::providers::{AsOf, Planned, Token};
+use crate::{BasketCommand, Commands, CryptoError, CryptoTool, ExportDataset, format_timestamp};
+
+// --dry-run: the requests `command` would make, provider by provider, without sending any.
+// Providers after the first for a lookup are only tried when the ones before them fail.
+pub fn run(tool: &CryptoTool, command: &Commands) -> Result<String, CryptoError> {
+    let mut lookups = Vec::new();
+    match command {
+        Commands::CurrentPrice { crypto: Some(crypto), fiat: Some(fiat), .. } | Commands::CurrentPrice { crypto: Some(crypto), vs: Some(fiat), .. } => {
+            if tool.coin_list.is_stale() && coins::resolve_symbol(crypto) == crypto.to_lowercase() {
+                lookups.push(coin_list(tool));
+            }
+            lookups.push(price(tool, crypto, fiat))
+        }
+        Commands::CurrentPrice { watchlist: true, .. } => lookups.push(watchlist(tool)?),
+        Commands::CheckPrice { crypto, fiat, .. } => {
+            if tool.coin_list.is_stale() && coins::resolve_symbol(crypto) == crypto.to_lowercase() {
+                lookups.push(coin_list(tool));
+            }
+            lookups.push(price(tool, crypto, fiat))
+        }
+        Commands::AddressBalance { crypto, address, at, height, .. } => {
+            let (crypto, address) = tool.balance_target(crypto, address.as_deref())?;
+            match parse_as_of(at.as_deref(), *height)? {
+                _ if descriptor::is_descriptor(&address) => lookups.push(descriptor_scan(tool, &crypto, &address, "balance")),
+                Some(at) => lookups.push(past_balance(tool, &crypto, &address, &at)?),
+                None => lookups.push(balance(tool, &crypto, &address)?),
+            }
+        }
+        Commands::WalletBalance { wallet: path, fiat, tokens, .. } => wallet(tool, path, fiat, *tokens || tool.wallet_tokens, &mut lookups)?,
+        Commands::Tokens { address, chain, .. } => lookups.push(token_discovery(tool, chain, address)?),
+        Commands::TokenBalance { crypto, address, token, .. } => {
+            let stale = tool.tokens.stale_urls();
+            if !stale.is_empty() {
+                let urls: Vec<String> = stale.iter().map(|url| format!("\n  GET {}", url)).collect();
+                lookups.push(format!("Token lists, not downloaded yet or out of date:{}", urls.concat()));
+            }
+            lookups.push(token_balance(tool, crypto, address, token)?);
+        }
+        Commands::ExchangeBalance { exchange: name, .. } => lookups.push(exchange(tool, name)?),
+        Commands::ImportHistory { exchange: name, .. } => lookups.push(history(tool, name)?),
+        Commands::Spread { crypto, fiat, .. } => lookups.push(spread(tool, crypto, fiat)),
+        Commands::Funding { symbol, .. } => lookups.push(funding(tool, symbol)),
+        Commands::Tip { crypto, .. } => {
+            let crypto = coins::resolve_symbol(crypto);
+            let steps = tool.providers.chain.iter().filter(|p| p.supports(&crypto)).map(|p| (p.name(), p.plan_tip(&tool.client, &crypto)));
+            lookups.push(list(tool, format!("Tip of {}:", crypto), steps, &format!("no provider reads {} blocks", crypto)));
+        }
+        Commands::Block { crypto, block, .. } => {
+            let (crypto, block) = (coins::resolve_symbol(crypto), parse_block_id(block)?);
+            let steps = tool.providers.chain.iter().filter(|p| p.supports(&crypto)).map(|p| (p.name(), p.plan_block(&tool.client, &crypto, &block)));
+            lookups.push(list(tool, format!("Block {} of {}:", block, crypto), steps, &format!("no provider reads {} blocks", crypto)));
+        }
+        Commands::Fees { crypto, .. } => {
+            let crypto = coins::resolve_symbol(crypto);
+            let steps = tool.providers.fees.iter().filter(|p| p.supports(&crypto)).map(|p| (p.name(), p.plan_fees(&tool.client, &crypto)));
+            lookups.push(list(tool, format!("Fees on {}:", crypto), steps, &format!("no fee provider supports {}", crypto)));
+        }
+        Commands::Network { crypto, .. } => {
+            let crypto = coins::resolve_symbol(crypto);
+            let steps = tool.providers.chain.iter().filter(|p| p.supports(&crypto)).map(|p| (p.name(), p.plan_network(&tool.client, &crypto)));
+            lookups.push(list(tool, format!("Mining stats of {}:", crypto), steps, &format!("no provider reads {} blocks", crypto)));
+        }
+        Commands::Halving { crypto, .. } => {
+            let crypto = coins::resolve_symbol(crypto);
+            let steps = tool.providers.chain.iter().filter(|p| p.supports(&crypto)).map(|p| (p.name(), p.plan_tip(&tool.client, &crypto)));
+            let mut text = list(tool, format!("Next {} halving:", crypto), steps, &format!("no provider reads {} blocks", crypto));
+            text.push_str(&format!("\n  then the block {} below the tip, for the average block time", BLOCK_TIME_SAMPLE));
+            lookups.push(text);
+        }
+        Commands::Whales { crypto, interval, .. } => {
+            let crypto = coins::resolve_symbol(crypto);
+            let steps = tool.providers.history.iter().filter(|p| p.supports(&crypto)).take(1).map(|p| (p.name(), p.plan_recent(&tool.client, &crypto)));
+            lookups.push(list(tool, format!("The {} mempool, every {}s:", crypto, interval), steps, &format!("no provider lists {} mempool transactions", crypto)));
+        }
+        Commands::UtxoReport { address: target, .. } => {
+            let (crypto, address) = tool.utxo_target(target)?;
+            match descriptor::is_descriptor(&address) {
+                true => lookups.push(descriptor_scan(tool, &crypto, &address, "UTXOs")),
+                false => {
+                    let client = tool.client_for_address(&address)?;
+                    let provider = tool.providers.utxo.iter().find(|p| p.supports(&crypto));
+                    let requests = provider.map(|p| (p.name(), p.plan(&client, &crypto, &address)));
+                    lookups.push(together(tool, format!("UTXOs of {} address {}:", crypto, address), requests, &format!("no provider lists {} UTXOs", crypto)));
+                }
+            }
+        }
+        Commands::WatchAddress { crypto, address, interval, .. } => {
+            let (crypto, address) = tool.balance_target(crypto, address.as_deref())?;
+            let client = tool.client_for_address(&address)?;
+            let provider = tool.providers.history.iter().find(|p| p.supports(&crypto));
+            let requests = provider.map(|p| (p.name(), p.plan(&client, &crypto, &address)));
+            let heading = format!("Transactions of {} address {}, every {}s:", crypto, address, interval);
+            lookups.push(together(tool, heading, requests, &format!("no provider lists {} transactions", crypto)));
+        }
+        Commands::Sentiment { days, .. } => lookups.push(sentiment(tool, *days)),
+        Commands::Orderbook { exchange, pair, depth, .. } => lookups.push(order_book(tool, exchange, pair, *depth)?),
+        Commands::Peg { coins, .. } => {
+            for (coin, fiat) in pegged_coins(coins)? {
+                lookups.push(price(tool, &coin, fiat));
+            }
+        }
+        Commands::Global { fiat, .. } => lookups.push(global(tool, fiat)),
+        Commands::Indicators { crypto, fiat, set, .. } => {
+            lookups.push(closes(tool, crypto, fiat, indicator_days(&parse_indicators(set)?)));
+        }
+        Commands::DcaPlan { allocation, fiat, .. } => {
+            let coins: Vec<String> = dca::parse_allocation(allocation)?.into_iter().map(|(coin, _)| coin).collect();
+            lookups.push(batched(tool, format!("Prices in {}:", fiat), &coins, fiat));
+        }
+        Commands::Risk { wallet: path, fiat, days, .. } => {
+            wallet(tool, path, fiat, false, &mut lookups)?;
+            let coins: BTreeSet<String> = tool.read_wallet(path)?.into_iter().map(|(crypto, _)| crypto).collect();
+            lookups.extend(coins.iter().map(|crypto| closes(tool, crypto, fiat, *days)));
+        }
+        Commands::Rebalance { wallet: path, fiat, target, .. } => {
+            let fiat = fiat.to_lowercase();
+            wallet(tool, path, &fiat, false, &mut lookups)?;
+            let held: BTreeSet<String> = tool.read_wallet(path)?.into_iter().map(|(crypto, _)| crypto).collect();
+            let missing: Vec<String> = dca::parse_allocation(target)?.into_iter().map(|(coin, _)| coin).filter(|coin| !held.contains(coin)).collect();
+            if !missing.is_empty() {
+                lookups.push(batched(tool, format!("Prices in {}:", fiat), &missing, &fiat));
+            }
+        }
+        Commands::Compare { a, b, days, fiat, .. } => {
+            let fiat = fiat.to_lowercase();
+            lookups.push(closes(tool, &coins::resolve_symbol(a), &fiat, *days));
+            lookups.push(closes(tool, &coins::resolve_symbol(b), &fiat, *days));
+        }
+        Commands::Basket { action: BasketCommand::Quote { name, fiat, .. } } => {
+            let fiat = fiat.to_lowercase();
+            lookups.push(batched(tool, format!("Prices in {}:", fiat), &tool.basket(name)?.ids(), &fiat));
+        }
+        Commands::Basket { action: BasketCommand::History { name, days, fiat, .. } } => {
+            let fiat = fiat.to_lowercase();
+            lookups.extend(tool.basket(name)?.ids().iter().map(|coin| closes(tool, coin, &fiat, *days)));
+        }
+        Commands::BacktestDca { crypto, fiat, amount, interval, from, .. } => {
+            let (_, from) = dca_schedule(*amount, interval, from)?;
+            lookups.push(closes(tool, crypto, fiat, backtest_days(from)));
+        }
+        Commands::Returns { from, to, fiat, benchmark, exchange, .. } => {
+            let (from, to) = returns_range(from, to.as_deref())?;
+            let (_, assets) = tool.returns_transactions(exchange.as_deref(), to)?;
+            let assets = assets.into_iter().chain(benchmark.as_deref().map(coins::resolve_symbol));
+            lookups.extend(asset_closes(tool, assets, &fiat.to_lowercase(), backtest_days(from - chrono::Days::new(1))));
+        }
+        Commands::Gains { fiat, .. } => lookups.extend(gains(tool, &fiat.to_lowercase())?),
+        Commands::Export8949 { .. } => lookups.extend(gains(tool, "usd")?),
+        Commands::Export { dataset: ExportDataset::History { crypto, fiat, days, .. }, .. } => {
+            lookups.push(closes(tool, &coins::resolve_symbol(crypto), &fiat.to_lowercase(), *days));
+        }
+        Commands::CoinList { refresh, .. } if *refresh || tool.coin_list.is_stale() => lookups.push(coin_list(tool)),
+        Commands::Trending { .. } => {
+            let steps = tool.providers.price.iter().filter_map(|p| Some((p.name(), p.plan_trending(&tool.client)?)));
+            lookups.push(list(tool, "Trending coins:".to_string(), steps, "no price provider has trending coins"));
+        }
+        Commands::QuoteSwap { amount, from, to, chain, .. } => lookups.push(swap(tool, chain, *amount, from, to)?),
+        Commands::Status => {
+            let urls = tool
+                .providers
+                .price
+                .iter()
+                .map(|p| (p.name(), p.status_url()))
+                .chain(tool.providers.balance.iter().map(|p| (p.name(), p.status_url())));
+            for (name, url) in urls {
+                if let Some(url) = url {
+                    let request = tool.providers.transport.describe(name, tool.client.get(url));
+                    lookups.push(format!("Status of {}:\n  {}", name, request.replace('\n', "\n  ")));
+                }
+            }
+        }
+        _ => return Ok("Nothing to send: this command doesn't call any provider".to_string()),
+    }
+
+    let mut output = vec![format!(
+        "Dry run, nothing sent. Requests go {} with User-Agent: {}",
+        match (&tool.client_settings.proxy, &tool.tor_isolation_proxy) {
+            (_, Some(proxy)) => format!("through {} (a separate Tor circuit per address)", proxy),
+            (Some(proxy), None) => format!("through {}", without_password(proxy)),
+            (None, None) => match env_proxy() {
+                Some((name, proxy)) => format!("through {} (from ${})", without_password(&proxy), name),
+                None => "direct".to_string(),
+            },
+        },
+        tool.client_settings.user_agent
+    )];
+    output.extend(lookups);
+    Ok(output.join("\n\n"))
+}
+
+// reqwest falls back to these when no proxy is configured
+fn env_proxy() -> Option<(&'static str, String)> {
+    ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+        .into_iter()
+        .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()).map(|v| (name, v)))
+}
+
+fn without_password(proxy: &str) -> String {
+    match reqwest::Url::parse(proxy) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("REDACTED"));
+            url.to_string()
+        }
+        _ => proxy.to_string(),
+    }
+}
+
+// A wallet file's balances and prices. Lines sharing a coin or an address share the lookup.
+fn wallet(tool: &CryptoTool, wallet: &str, fiat: &str, tokens: bool, lookups: &mut Vec<String>) -> Result<(), CryptoError> {
+    let (descriptors, lines): (Vec<_>, Vec<_>) = tool.read_wallet(wallet)?.into_iter().partition(|(_, address)| descriptor::is_descriptor(address));
+    for (crypto, address) in &lines {
+        tool.check_address(crypto, address)?;
+    }
+    lookups.extend(descriptors.iter().map(|(crypto, text)| descriptor_scan(tool, crypto, text, "balance")));
+    let coins: BTreeSet<&str> = lines.iter().map(|(crypto, _)| crypto.as_str()).collect();
+    let mut seen = BTreeSet::new();
+    for (crypto, addresses) in batch_groups(&lines) {
+        let Some(provider) = tool.batch_provider(crypto) else {
+            continue;
+        };
+        for chunk in addresses.chunks(provider.batch_limit()) {
+            let Some(Planned::Http(request)) = provider.plan_batch(&tool.client, crypto, chunk) else {
+                continue;
+            };
+            let request = tool.providers.transport.describe(provider.name(), *request);
+            lookups.push(format!(
+                "Balances of {} {} addresses in one request (any it leaves out are looked up one by one):\n  {}: {}",
+                chunk.len(),
+                crypto,
+                provider.name(),
+                request.replace('\n', "\n  ")
+            ));
+            seen.extend(chunk.iter().map(|address| (crypto.to_string(), address.clone())));
+        }
+    }
+    for (crypto, address) in &lines {
+        if seen.insert((crypto.clone(), address.clone())) {
+            lookups.push(balance(tool, crypto, address)?);
+        }
+    }
+    lookups.extend(coins.into_iter().map(|crypto| price(tool, crypto, fiat)));
+    for name in &tool.wallet_exchanges {
+        lookups.push(exchange(tool, name)?);
+    }
+    if tokens {
+        let mut listed = BTreeSet::new();
+        for (crypto, address) in &lines {
+            if tool.discovery_provider(crypto).is_some() && listed.insert((crypto, address)) {
+                lookups.push(token_discovery(tool, crypto, address)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Which of a descriptor's addresses get looked up depends on which have been used
+fn descriptor_scan(tool: &CryptoTool, crypto: &str, text: &str, then: &str) -> String {
+    let provider = tool.providers.history.iter().find(|p| p.supports(crypto)).map_or("no provider", |p| p.name());
+    format!(
+        "Addresses of {}:\n  derived from its keys and checked one by one on {} until {} in a row have no transactions, then each used one's {}",
+        text, provider, GAP_LIMIT, then
+    )
+}
+
+fn price(tool: &CryptoTool, crypto: &str, fiat: &str) -> String {
+    let heading = format!("Price of {} in {}:", crypto, fiat);
+    let key = Cache::price_key(crypto, fiat);
+    if tool.offline {
+        return format!("{}\n  from the offline cache", heading);
+    }
+    if let Some(entry) = tool.cache.peek_fresh(&key) {
+        return format!("{}\n  cached (fetched {}), no request", heading, format_timestamp(entry.fetched_at));
+    }
+    let stale = tool.cache.get(&key).filter(|entry| !entry.validators.is_empty());
+    let validators = stale.as_ref().map(|entry| &entry.validators);
+    let steps = tool
+        .providers
+        .price
+        .iter()
+        .filter(|p| p.supports(crypto))
+        .map(|p| (p.name(), p.plan(&tool.client, crypto, fiat, validators)));
+    // Then the USD price above, converted at the reference rate
+    let fx = tool.providers.fx.iter().filter(|_| fiat != "usd").map(|p| (p.name(), p.plan(&tool.client, "usd", fiat)));
+    list(tool, heading, steps.chain(fx), &format!("no price provider supports {}", crypto))
+}
+
+// Balances are never answered from the cache online
+fn balance(tool: &CryptoTool, crypto: &str, address: &str) -> Result<String, CryptoError> {
+    tool.check_address(crypto, address)?;
+    let heading = format!("Balance of {} address {}:", crypto, address);
+    if tool.offline {
+        return Ok(format!("{}\n  from the offline cache", heading));
+    }
+    let client = tool.client_for_address(address)?;
+    let steps = tool
+        .providers
+        .balance
+        .iter()
+        .filter(|p| p.supports_address(crypto, address))
+        .map(|p| (p.name(), p.plan(&client, crypto, address)));
+    Ok(list(tool, heading, steps, &format!("no balance provider supports {}", crypto)))
+}
+
+fn past_balance(tool: &CryptoTool, crypto: &str, address: &str, at: &AsOf) -> Result<String, CryptoError> {
+    tool.check_address(crypto, address)?;
+    let heading = format!("Balance of {} address {} as of {}:", crypto, address, at);
+    let client = tool.client_for_address(address)?;
+    let provider = tool.providers.past.iter().find(|p| p.supports(crypto));
+    let requests = provider.map(|p| (p.name(), p.plan_balance_at(&client, crypto, address, at)));
+    let mut text = together(tool, heading, requests, &format!("no provider reads past balances of {} addresses", crypto));
+    if provider.is_some() {
+        text.push_str("\n  then the follow-up requests those answers call for");
+    }
+    Ok(text)
+}
+
+fn token_discovery(tool: &CryptoTool, crypto: &str, address: &str) -> Result<String, CryptoError> {
+    tool.check_address(crypto, address)?;
+    let heading = format!("Tokens held by {} address {}:", crypto, address);
+    let client = tool.client_for_address(address)?;
+    let provider = tool.discovery_provider(crypto);
+    let steps = provider.map(|p| (p.name(), p.plan(&client, crypto, address)));
+    let mut text = list(tool, heading, steps.into_iter(), &format!("no provider lists the tokens on {}", crypto));
+    if provider.is_some() {
+        text.push_str("\n  then a price request for the tokens found");
+    }
+    Ok(text)
+}
+
+fn exchange(tool: &CryptoTool, name: &str) -> Result<String, CryptoError> {
+    let exchange = tool.exchange(name)?;
+    let steps = [(exchange.name(), exchange.plan(&tool.client))];
+    let mut text = list(tool, format!("Holdings on {}:", exchange.name()), steps.into_iter(), "");
+    text.push_str("\n  then a price request per asset held");
+    Ok(text)
+}
+
+fn history(tool: &CryptoTool, name: &str) -> Result<String, CryptoError> {
+    let exchange = tool.exchange(name)?;
+    let steps = [(exchange.name(), exchange.plan_history(&tool.client))];
+    let mut text = list(tool, format!("History on {}:", exchange.name()), steps.into_iter(), "");
+    text.push_str("\n  then the rest of the history, page by page");
+    Ok(text)
+}
+
+// Sent to every exchange at once
+fn spread(tool: &CryptoTool, crypto: &str, fiat: &str) -> String {
+    let (base, quote) = (coins::ticker(crypto), fiat.to_uppercase());
+    let mut text = format!("Bid and ask for {}/{}, from every exchange at once:", base, quote);
+    for provider in &tool.providers.tickers {
+        let step = match provider.plan(&tool.client, &base, &quote) {
+            Planned::Http(request) => tool.providers.transport.describe(provider.name(), *request),
+            Planned::Command { command, stdin } => format!("run `{}` with stdin {}", command, stdin),
+        };
+        text.push_str(&format!("\n  {}: {}", provider.name(), step.replace('\n', "\n  ")));
+    }
+    text
+}
+
+fn sentiment(tool: &CryptoTool, days: usize) -> String {
+    let heading = format!("Fear & Greed index, {} days:", days);
+    if tool.offline {
+        return format!("{}\n  from the offline cache", heading);
+    }
+    let cached: Vec<_> = (0..days).filter_map(|days_ago| tool.cache.peek_fresh(&Cache::sentiment_key(days_ago))).collect();
+    if let Some(entry) = cached.first().filter(|_| cached.len() == days) {
+        return format!("{}\n  cached (fetched {}), no request", heading, format_timestamp(entry.fetched_at));
+    }
+    let steps = tool.providers.sentiment.iter().map(|p| (p.name(), p.plan(&tool.client, days)));
+    list(tool, heading, steps, "no sentiment provider")
+}
+
+fn funding(tool: &CryptoTool, symbol: &str) -> String {
+    let symbol = perpetual_symbol(symbol);
+    let mut text = format!("Funding for {}, from both exchanges at once:", symbol);
+    for provider in &tool.providers.funding {
+        let step = match provider.plan(&tool.client, &symbol) {
+            Planned::Http(request) => tool.providers.transport.describe(provider.name(), *request),
+            Planned::Command { command, stdin } => format!("run `{}` with stdin {}", command, stdin),
+        };
+        text.push_str(&format!("\n  {}: {}", provider.name(), step.replace('\n', "\n  ")));
+    }
+    text.push_str("\n  then each exchange's last settled rate");
+    text
+}
+
+fn watchlist(tool: &CryptoTool) -> Result<String, CryptoError> {
+    let coins = tool.watchlist()?.coins().to_vec();
+    let fiat = &tool.watchlist_fiat;
+    Ok(batched(tool, format!("Watchlist prices in {}:", fiat), &coins, fiat))
+}
+
+// The requests `batched_quotes` makes for the coins. Coins with a fresh cache entry are left
+// out of the batch.
+fn batched(tool: &CryptoTool, heading: String, coins: &[String], fiat: &str) -> String {
+    if tool.offline {
+        return format!("{}\n  from the offline cache", heading);
+    }
+    let stale: Vec<String> = coins.iter().filter(|coin| tool.cache.peek_fresh(&Cache::price_key(coin, fiat)).is_none()).cloned().collect();
+    let Some(provider) = tool.batch_price_provider(&stale).filter(|_| stale.len() > 1) else {
+        let lines: Vec<String> = coins.iter().map(|coin| price(tool, coin, fiat)).collect();
+        return lines.join("\n\n");
+    };
+    let mut text = heading;
+    for chunk in stale.chunks(provider.batch_limit()) {
+        let step = match provider.plan_batch(&tool.client, chunk, fiat) {
+            Some(Planned::Http(request)) => tool.providers.transport.describe(provider.name(), *request),
+            Some(Planned::Command { command, stdin }) => format!("run `{}` with stdin {}", command, stdin),
+            None => continue,
+        };
+        text.push_str(&format!("\n  {}: {}", provider.name(), step.replace('\n', "\n  ")));
+    }
+    if stale.len() < coins.len() {
+        text.push_str(&format!("\n  {} cached, no request", coins.len() - stale.len()));
+    }
+    text.push_str("\n  then one by one for coins the batch leaves out");
+    text
+}
+
+fn closes(tool: &CryptoTool, crypto: &str, fiat: &str, days: u32) -> String {
+    let steps = tool
+        .providers
+        .price
+        .iter()
+        .filter(|p| p.supports(crypto))
+        .filter_map(|p| Some((p.name(), p.plan_closes(&tool.client, crypto, fiat, days)?)));
+    list(tool, format!("{} days of {} closes in {}:", days, crypto, fiat), steps, "no price provider has price history")
+}
+
+// Downloading the coin list, saved copy missing or out of date
+fn coin_list(tool: &CryptoTool) -> String {
+    let steps = tool.providers.price.iter().filter_map(|p| Some((p.name(), p.plan_coin_list(&tool.client)?)));
+    list(tool, "Coin list, to check coins against:".to_string(), steps, "no price provider has a coin list")
+}
+
+// The closes of every coin the imported transactions held since the first of them
+fn gains(tool: &CryptoTool, fiat: &str) -> Result<Vec<String>, CryptoError> {
+    let (transactions, assets) = tool.returns_transactions(None, chrono::Utc::now().date_naive())?;
+    let days = backtest_days(transactions[0].time.date_naive());
+    Ok(asset_closes(tool, assets.into_iter(), fiat, days))
+}
+
+// Closes for each asset but `fiat` itself; other fiat currencies are priced through bitcoin in both
+fn asset_closes(tool: &CryptoTool, assets: impl Iterator<Item = String>, fiat: &str, days: u32) -> Vec<String> {
+    let mut pairs: BTreeSet<(String, String)> = BTreeSet::new();
+    for asset in assets.filter(|asset| asset != fiat) {
+        match coins::FIAT_CODES.contains(&asset.as_str()) {
+            true => pairs.extend([("bitcoin".to_string(), fiat.to_string()), ("bitcoin".to_string(), asset)]),
+            false => pairs.extend([(asset, fiat.to_string())]),
+        }
+    }
+    pairs.iter().map(|(crypto, fiat)| closes(tool, crypto, fiat, days)).collect()
+}
+
+fn global(tool: &CryptoTool, fiat: &str) -> String {
+    let heading = format!("Global market totals in {}:", fiat);
+    let steps = tool.providers.price.iter().filter_map(|p| Some((p.name(), p.plan_global(&tool.client, fiat)?)));
+    list(tool, heading, steps, "no price provider has global market data")
+}
+
+fn order_book(tool: &CryptoTool, exchange: &str, pair: &str, depth: usize) -> Result<String, CryptoError> {
+    let (base, quote) = parse_pair(pair)?;
+    let provider = tool.ticker_provider(exchange)?;
+    let steps = [(provider.name(), provider.plan_book(&tool.client, &base, &quote, depth))];
+    Ok(list(tool, format!("Order book for {}/{} on {}:", base, quote, provider.name()), steps.into_iter(), ""))
+}
+
+fn swap(tool: &CryptoTool, crypto: &str, amount: f64, from: &str, to: &str) -> Result<String, CryptoError> {
+    let chain_id = tool.chain_id(crypto)?;
+    let [sell, buy] = [from, to].map(|token| match coins::is_gas_coin(crypto, token) {
+        true => Token::native(token),
+        false => planned_token(tool, crypto, chain_id, token),
+    });
+    let heading = format!("Quote for {} {} -> {} on {}:", amount, sell.symbol, buy.symbol, crypto);
+    let steps = tool
+        .providers
+        .swap
+        .iter()
+        .filter(|p| p.supports(chain_id))
+        .map(|p| (p.name(), p.plan(&tool.client, chain_id, &sell, &buy, amount)));
+    let mut text = list(tool, heading, steps, &format!("no swap provider quotes on {}", crypto));
+    if tool.providers.swap.iter().any(|p| p.supports(chain_id)) {
+        text.push_str("\n  then price requests for both tokens, for the price impact");
+    }
+    Ok(text)
+}
+
+// Without a saved list the contract isn't known until the list is downloaded
+fn planned_token(tool: &CryptoTool, crypto: &str, chain_id: u64, token: &str) -> Token {
+    tool.tokens.resolve_offline(crypto, chain_id, token).unwrap_or_else(|| Token {
+        symbol: token.to_string(),
+        address: format!("<{} contract>", token),
+        decimals: 18,
+    })
+}
+
+fn token_balance(tool: &CryptoTool, crypto: &str, address: &str, token: &str) -> Result<String, CryptoError> {
+    tool.check_address(crypto, address)?;
+    let token = planned_token(tool, crypto, tool.chain_id(crypto)?, token);
+    let heading = format!("{} balance of {} address {}:", token.symbol, crypto, address);
+    let client = tool.client_for_address(address)?;
+    let steps = tool
+        .providers
+        .balance
+        .iter()
+        .filter(|p| p.supports_tokens(crypto))
+        .filter_map(|p| Some((p.name(), p.plan_token(&client, crypto, address, &token)?)));
+    Ok(list(tool, heading, steps, &format!("no provider reads token balances on {}", crypto)))
+}
+
+// One provider's requests, sent at the same time rather than as fallbacks
+fn together(tool: &CryptoTool, heading: String, requests: Option<(&str, Vec<Planned>)>, none: &str) -> String {
+    let steps = requests.into_iter().flat_map(|(name, plans)| plans.into_iter().map(move |planned| (name, planned)));
+    list(tool, heading, steps, none).replace(", if the above fail:", ", alongside:")
+}
+
+fn list<'a>(tool: &CryptoTool, heading: String, steps: impl Iterator<Item = (&'a str, Planned)>, none: &str) -> String {
+    let mut text = heading;
+    let mut empty = true;
+    for (i, (name, planned)) in steps.enumerate() {
+        let fallback = if i == 0 { "" } else { ", if the above fail" };
+        let step = match planned {
+            Planned::Http(request) => tool.providers.transport.describe(name, *request),
+            Planned::Command { command, stdin } => format!("run `{}` with stdin {}", command, stdin),
+        };
+        text.push_str(&format!("\n  {}{}: {}", name, fallback, step.replace('\n', "\n  ")));
+        empty = false;
+    }
+    if empty {
+        text.push_str(&format!("\n  {}", none));
+    }
+    text
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+
+use crate::{CryptoError, Lookup};
+
+// One fetched price, as a line of the price log
+#[derive(Serialize)]
+struct Entry<'a> {
+    coin: &'a str,
+    fiat: &'a str,
+    price: f64,
+    source: &'a str,
+    timestamp: String,
+}
+
+// A line of the price log read back
+#[derive(Deserialize)]
+pub struct Logged {
+    pub coin: String,
+    pub fiat: String,
+    pub price: f64,
+    pub source: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+// log_prices / --log-prices: every price fetched from a provider appended to a JSON Lines
+// file, building up a price history from normal use. Prices served from the cache aren't
+// repeated; timestamps are UTC whatever --timezone says, so the file sorts as text.
+pub struct PriceLog {
+    path: String,
+    file: Mutex<File>,
+}
+
+impl PriceLog {
+    pub fn open(path: &str) -> Result<Self, CryptoError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| CryptoError::ConfigError(format!("Can't open price log {}: {}", path, e)))?;
+        Ok(PriceLog { path: path.to_string(), file: Mutex::new(file) })
+    }
+
+    pub fn append(&self, coin: &str, fiat: &str, price: &Lookup) -> Result<(), CryptoError> {
+        let fetched_at: DateTime<Utc> = price.fetched_at.into();
+        let entry = Entry {
+            coin,
+            fiat,
+            price: price.value,
+            source: &price.source,
+            timestamp: fetched_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        };
+        let line = serde_json::to_string(&entry)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to serialize price log entry: {}", e)))?;
+        writeln!(self.file.lock().unwrap(), "{}", line)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to write to the price log: {}", e)))
+    }
+
+    // Every price logged so far, oldest first
+    pub fn read(&self) -> Result<Vec<Logged>, CryptoError> {
+        let contents = std::fs::read_to_string(&self.path)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to read {}: {}", self.path, e)))?;
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| CryptoError::InvalidInput(format!("Line {} of {} isn't a logged price: {}", i + 1, self.path, e)))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{BalanceProvider, Planned, Transport, decode};
+use crate::CryptoError;
+
+// /{chain}/dashboards/address/{address}, keyed by the queried address
+#[derive(Deserialize)]
+struct Dashboard {
+    data: HashMap<String, AddressDashboard>,
+}
+
+#[derive(Deserialize)]
+struct AddressDashboard {
+    address: AddressInfo,
+}
+
+// Balance in the chain's smallest unit
+#[derive(Deserialize)]
+struct AddressInfo {
+    balance: f64,
+}
+
+// /{chain}/dashboards/addresses/{a},{b},...: the per-address part is keyed by address
+#[derive(Deserialize)]
+struct MultiDashboard {
+    data: MultiData,
+}
+
+#[derive(Deserialize)]
+struct MultiData {
+    addresses: HashMap<String, AddressInfo>,
+}
+
+// The most addresses the multi-address dashboard takes
+const BATCH_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub(super) struct ErrorBody {
+    context: ErrorContext,
+}
+
+#[derive(Deserialize)]
+struct ErrorContext {
+    error: String,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.context.error)
+    }
+}
+
+pub struct Blockchair {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl Blockchair {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        Blockchair { base_url, transport }
+    }
+
+    fn url(&self, crypto: &str, address: &str) -> String {
+        format!("{}/{}/dashboards/address/{}", self.base_url, crypto, address)
+    }
+
+    fn batch_url(&self, crypto: &str, addresses: &[String]) -> String {
+        format!("{}/{}/dashboards/addresses/{}", self.base_url, crypto, addresses.join(","))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl BalanceProvider for Blockchair {
+    fn name(&self) -> &str {
+        "Blockchair"
+    }
+
+    fn status_url(&self) -> Option<String> {
+        Some(format!("{}/bitcoin/stats", self.base_url))
+    }
+
+    fn supports(&self, _crypto: &str) -> bool {
+        true
+    }
+
+    fn plan(&self, client: &Client, crypto: &str, address: &str) -> Planned {
+        Planned::Http(Box::new(client.get(self.url(crypto, address))))
+    }
+
+    async fn balance(&self, client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError> {
+        let url = self.url(crypto, address);
+        let resp = self.transport.send(self.name(), client.get(&url)).await?;
+        let body = resp.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+
+        let mut dashboard: Dashboard = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        let info = dashboard
+            .data
+            .remove(address)
+            .ok_or_else(|| CryptoError::NotFound {
+                provider: self.name().to_string(),
+                what: format!("address {}", address),
+            })?;
+
+        let btc_balance = info.address.balance / 100_000_000.0; // Convert satoshis to BTC
+        if verbose {
+            eprintln!("Fetched balance from Blockchair: {} {}", btc_balance, crypto.to_uppercase());
+        }
+        Ok(btc_balance)
+    }
+
+    fn batch_limit(&self) -> usize {
+        BATCH_LIMIT
+    }
+
+    fn plan_batch(&self, client: &Client, crypto: &str, addresses: &[String]) -> Option<Planned> {
+        Some(Planned::Http(Box::new(client.get(self.batch_url(crypto, addresses)))))
+    }
+
+    async fn balances(
+        &self,
+        client: &Client,
+        crypto: &str,
+        addresses: &[String],
+        verbose: bool,
+    ) -> Result<HashMap<String, f64>, CryptoError> {
+        let url = self.batch_url(crypto, addresses);
+        let resp = self.transport.send(self.name(), client.get(&url)).await?;
+        let MultiDashboard { data } = decode::<_, ErrorBody>(self.name(), &url, &resp.text().await?)?;
+        if verbose {
+            eprintln!("Fetched {} of {} balances from Blockchair in one request", data.addresses.len(), addresses.len());
+        }
+        Ok(data
+            .addresses
+            .into_iter()
+            .map(|(address, info)| (address, info.balance / 100_000_000.0))
+            .collect())
+    }
+}
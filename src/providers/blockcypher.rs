@@ -0,0 +1,78 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{BalanceProvider, Planned, Transport, decode};
+use crate::CryptoError;
+use crate::coins::is_taproot;
+
+// /addrs/{address}/balance, in satoshis
+#[derive(Deserialize)]
+struct AddressBalance {
+    balance: u64,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.error)
+    }
+}
+
+pub struct BlockCypher {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl BlockCypher {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        BlockCypher { base_url, transport }
+    }
+
+    fn url(&self, address: &str) -> String {
+        format!("{}/btc/main/addrs/{}/balance", self.base_url, address)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl BalanceProvider for BlockCypher {
+    fn name(&self) -> &str {
+        "BlockCypher"
+    }
+
+    fn status_url(&self) -> Option<String> {
+        Some(format!("{}/btc/main", self.base_url))
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        crypto == "bitcoin"
+    }
+
+    // BlockCypher doesn't index P2TR outputs, so taproot addresses have no balance there
+    fn supports_address(&self, crypto: &str, address: &str) -> bool {
+        self.supports(crypto) && !is_taproot(address)
+    }
+
+    fn plan(&self, client: &Client, _crypto: &str, address: &str) -> Planned {
+        Planned::Http(Box::new(client.get(self.url(address))))
+    }
+
+    async fn balance(&self, client: &Client, _crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError> {
+        let url = self.url(address);
+        let resp = self.transport.send(self.name(), client.get(&url)).await?;
+        let AddressBalance { balance } = decode::<_, ErrorBody>(self.name(), &url, &resp.text().await?)?;
+
+        let btc_balance = balance as f64 / 100_000_000.0; // Convert satoshis to BTC
+        if verbose {
+            eprintln!("Fetched balance from BlockCypher: {} BTC", btc_balance);
+        }
+        Ok(btc_balance)
+    }
+}
@@ -0,0 +1,200 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::blockchair::ErrorBody;
+use super::{Block, BlockId, ChainProvider, ChainTip, FeeProvider, FeeTier, Fees, NetworkStats, Planned, Transport, decode};
+use crate::CryptoError;
+
+const NAME: &str = "Blockchair";
+
+// The UTXO chains whose block dashboards count fees in satoshis
+const CHAINS: &[&str] = &["bitcoin", "bitcoin-cash", "litecoin", "dogecoin", "dash", "zcash"];
+
+// /{chain}/stats. The hashrate is a string of hashes per second; the retarget estimates are
+// only there for chains that retarget in periods, like bitcoin.
+#[derive(Deserialize)]
+struct Stats {
+    data: StatsData,
+}
+
+#[derive(Deserialize)]
+struct StatsData {
+    best_block_height: u64,
+    best_block_hash: String,
+    best_block_time: String,
+    difficulty: f64,
+    hashrate_24h: String,
+    next_difficulty_estimate: Option<f64>,
+    next_retarget_time_estimate: Option<String>,
+}
+
+// /{chain}/stats again: UTXO chains suggest one rate per byte, ethereum gwei for a range of speeds
+#[derive(Deserialize)]
+struct FeeStats {
+    data: FeeData,
+}
+
+#[derive(Deserialize)]
+struct FeeData {
+    suggested_transaction_fee_per_byte_sat: Option<f64>,
+    suggested_transaction_fee_gwei_options: Option<GweiOptions>,
+}
+
+#[derive(Deserialize)]
+struct GweiOptions {
+    slow: f64,
+    normal: f64,
+    fast: f64,
+}
+
+// /{chain}/dashboards/block/{height or hash}, keyed by the query; null for no such block
+#[derive(Deserialize)]
+struct Dashboard {
+    data: HashMap<String, Option<BlockDashboard>>,
+}
+
+#[derive(Deserialize)]
+struct BlockDashboard {
+    block: BlockInfo,
+}
+
+#[derive(Deserialize)]
+struct BlockInfo {
+    id: u64,
+    hash: String,
+    time: String,
+    size: u64,
+    transaction_count: u64,
+    fee_total: u64,
+}
+
+// Blockchair's tips and blocks, with times in UTC like "2024-06-22 10:15:00"
+pub struct BlockchairChain {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl BlockchairChain {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        BlockchairChain { base_url, transport }
+    }
+
+    fn tip_url(&self, crypto: &str) -> String {
+        format!("{}/{}/stats", self.base_url, crypto)
+    }
+
+    fn block_url(&self, crypto: &str, block: &BlockId) -> String {
+        format!("{}/{}/dashboards/block/{}", self.base_url, crypto, block)
+    }
+
+    fn time(&self, time: &str) -> Result<DateTime<Utc>, CryptoError> {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+            .map(|time| time.and_utc())
+            .map_err(|_| CryptoError::api(NAME, format!("unexpected block time '{}'", time)))
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, client: &Client, url: &str, verbose: bool) -> Result<T, CryptoError> {
+        let body = self.transport.send(NAME, client.get(url)).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        decode::<_, ErrorBody>(NAME, url, &body)
+    }
+}
+
+#[async_trait]
+impl ChainProvider for BlockchairChain {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        CHAINS.contains(&crypto)
+    }
+
+    fn plan_tip(&self, client: &Client, crypto: &str) -> Planned {
+        Planned::Http(Box::new(client.get(self.tip_url(crypto))))
+    }
+
+    async fn tip(&self, client: &Client, crypto: &str, verbose: bool) -> Result<ChainTip, CryptoError> {
+        let stats: Stats = self.get(client, &self.tip_url(crypto), verbose).await?;
+        Ok(ChainTip {
+            height: stats.data.best_block_height,
+            hash: stats.data.best_block_hash,
+            time: self.time(&stats.data.best_block_time)?,
+        })
+    }
+
+    fn plan_network(&self, client: &Client, crypto: &str) -> Planned {
+        self.plan_tip(client, crypto)
+    }
+
+    async fn network(&self, client: &Client, crypto: &str, verbose: bool) -> Result<NetworkStats, CryptoError> {
+        let Stats { data } = self.get(client, &self.tip_url(crypto), verbose).await?;
+        let hashrate = data
+            .hashrate_24h
+            .parse::<f64>()
+            .map_err(|_| CryptoError::api(NAME, format!("unexpected hashrate '{}'", data.hashrate_24h)))?;
+        Ok(NetworkStats {
+            height: data.best_block_height,
+            difficulty: data.difficulty,
+            hashrate,
+            next_difficulty: data.next_difficulty_estimate,
+            next_retarget: data.next_retarget_time_estimate.as_deref().map(|time| self.time(time)).transpose()?,
+        })
+    }
+
+    fn plan_block(&self, client: &Client, crypto: &str, block: &BlockId) -> Planned {
+        Planned::Http(Box::new(client.get(self.block_url(crypto, block))))
+    }
+
+    async fn block(&self, client: &Client, crypto: &str, block: &BlockId, verbose: bool) -> Result<Block, CryptoError> {
+        let mut dashboard: Dashboard = self.get(client, &self.block_url(crypto, block), verbose).await?;
+        let info = dashboard.data.remove(&block.to_string()).flatten().ok_or_else(|| CryptoError::NotFound {
+            provider: NAME.to_string(),
+            what: format!("{} block {}", crypto, block),
+        })?;
+        let info = info.block;
+        Ok(Block {
+            height: info.id,
+            time: self.time(&info.time)?,
+            hash: info.hash,
+            size: info.size,
+            transactions: info.transaction_count,
+            fees: info.fee_total as f64 / 100_000_000.0,
+        })
+    }
+}
+
+#[async_trait]
+impl FeeProvider for BlockchairChain {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        CHAINS.contains(&crypto) || crypto == "ethereum"
+    }
+
+    fn plan_fees(&self, client: &Client, crypto: &str) -> Planned {
+        Planned::Http(Box::new(client.get(self.tip_url(crypto))))
+    }
+
+    async fn fees(&self, client: &Client, crypto: &str, verbose: bool) -> Result<Fees, CryptoError> {
+        let FeeStats { data } = self.get(client, &self.tip_url(crypto), verbose).await?;
+        let missing = || CryptoError::NotFound { provider: NAME.to_string(), what: format!("a {} fee suggestion", crypto) };
+        if crypto == "ethereum" {
+            let options = data.suggested_transaction_fee_gwei_options.ok_or_else(missing)?;
+            let tiers = [("fast", options.fast), ("normal", options.normal), ("slow", options.slow)];
+            return Ok(Fees { unit: "gwei", tiers: tiers.map(|(name, rate)| FeeTier { name, rate, blocks: None }).into() });
+        }
+        let rate = data.suggested_transaction_fee_per_byte_sat.ok_or_else(missing)?;
+        // Segwit chains count weight in vbytes
+        let unit = if crypto == "bitcoin" || crypto == "litecoin" { "sat/vB" } else { "sat/B" };
+        Ok(Fees { unit, tiers: vec![FeeTier { name: "normal", rate, blocks: None }] })
+    }
+}
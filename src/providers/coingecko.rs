@@ -0,0 +1,372 @@
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{CoinListing, DailyClose, DailyVolume, GlobalMarket, Planned, PriceProvider, PriceQuote, TokenQuote, TrendingCoin, Transport, decode};
+use crate::CryptoError;
+use crate::cache::Validators;
+
+// /simple/price: coin id -> fiat -> price. Unknown ids are left out rather than reported.
+type SimplePrice = HashMap<String, HashMap<String, f64>>;
+
+// /simple/token_price/{platform}: lowercase contract address -> "usd", "usd_market_cap",
+// "usd_24h_vol" -> value, null where CoinGecko doesn't know it
+type TokenPrice = HashMap<String, HashMap<String, Option<f64>>>;
+
+// /coins/{id}/market_chart: [milliseconds, value] pairs, with market caps and volumes alongside
+#[derive(Deserialize)]
+struct MarketChart {
+    prices: Vec<(f64, f64)>,
+    #[serde(default)]
+    total_volumes: Vec<(f64, f64)>,
+}
+
+impl MarketChart {
+    // A midnight point closes the day before, so each counts for the day its last millisecond
+    // fell in, and the last of a day wins. Today ends up with the latest.
+    fn daily(points: Vec<(f64, f64)>) -> Vec<(chrono::NaiveDate, f64)> {
+        let mut days: Vec<(chrono::NaiveDate, f64)> = Vec::new();
+        for (ms, value) in points {
+            let Some(time) = chrono::DateTime::from_timestamp_millis(ms as i64 - 1) else {
+                continue;
+            };
+            let date = time.date_naive();
+            match days.last_mut() {
+                Some(last) if last.0 == date => last.1 = value,
+                _ => days.push((date, value)),
+            }
+        }
+        days
+    }
+}
+
+// /global: totals keyed by currency, with market_cap_percentage keyed by ticker. updated_at is
+// in seconds.
+#[derive(Deserialize)]
+struct Global {
+    data: GlobalData,
+}
+
+#[derive(Deserialize)]
+struct GlobalData {
+    active_cryptocurrencies: u64,
+    total_market_cap: HashMap<String, f64>,
+    total_volume: HashMap<String, f64>,
+    market_cap_percentage: HashMap<String, f64>,
+    updated_at: u64,
+}
+
+// /search/trending: the top searched coins of the last 24 hours, with nfts and categories
+// alongside. data.price is in USD.
+#[derive(Deserialize)]
+struct Trending {
+    coins: Vec<TrendingItem>,
+}
+
+#[derive(Deserialize)]
+struct TrendingItem {
+    item: TrendingEntry,
+}
+
+#[derive(Deserialize)]
+struct TrendingEntry {
+    id: String,
+    symbol: String,
+    name: String,
+    market_cap_rank: Option<u64>,
+    data: Option<TrendingData>,
+}
+
+#[derive(Deserialize)]
+struct TrendingData {
+    price: Option<f64>,
+    #[serde(default)]
+    price_change_percentage_24h: HashMap<String, f64>,
+}
+
+// CoinGecko asset platforms, by the coin id of the chain's gas coin
+const PLATFORMS: &[(&str, &str)] = &[
+    ("ethereum", "ethereum"),
+    ("binancecoin", "binance-smart-chain"),
+    ("matic-network", "polygon-pos"),
+    ("polygon-ecosystem-token", "polygon-pos"),
+    ("avalanche-2", "avalanche"),
+    ("fantom", "fantom"),
+    ("xdai", "xdai"),
+    ("celo", "celo"),
+    ("moonbeam", "moonbeam"),
+    ("crypto-com-chain", "cronos"),
+];
+
+// Contracts per token_price request, to keep the URL a sane length
+const CONTRACTS_PER_REQUEST: usize = 50;
+
+// Likewise coin ids per simple/price request
+const IDS_PER_REQUEST: usize = 50;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorBody {
+    Plain { error: String },
+    // What the public API sends when throttling or rejecting a key
+    Status { status: ErrorStatus },
+}
+
+#[derive(Deserialize)]
+struct ErrorStatus {
+    error_message: String,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorBody::Plain { error } => f.write_str(error),
+            ErrorBody::Status { status } => f.write_str(&status.error_message),
+        }
+    }
+}
+
+pub struct CoinGecko {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl CoinGecko {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        CoinGecko { base_url, transport }
+    }
+
+    fn url(&self, crypto: &str, fiat: &str) -> String {
+        format!("{}/simple/price?ids={}&vs_currencies={}", self.base_url, crypto, fiat)
+    }
+
+    fn request(&self, client: &Client, crypto: &str, fiat: &str, validators: Option<&Validators>) -> RequestBuilder {
+        let request = client.get(self.url(crypto, fiat));
+        match validators {
+            Some(validators) => validators.apply(request),
+            None => request,
+        }
+    }
+
+    fn global_url(&self) -> String {
+        format!("{}/global", self.base_url)
+    }
+
+    // Daily points are at midnight UTC, plus one for now
+    fn closes_url(&self, crypto: &str, fiat: &str, days: u32) -> String {
+        format!("{}/coins/{}/market_chart?vs_currency={}&days={}&interval=daily", self.base_url, crypto, fiat, days)
+    }
+
+    fn trending_url(&self) -> String {
+        format!("{}/search/trending", self.base_url)
+    }
+
+    fn coin_list_url(&self) -> String {
+        format!("{}/coins/list", self.base_url)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl PriceProvider for CoinGecko {
+    fn name(&self) -> &str {
+        "CoinGecko"
+    }
+
+    fn status_url(&self) -> Option<String> {
+        Some(format!("{}/ping", self.base_url))
+    }
+
+    fn plan(&self, client: &Client, crypto: &str, fiat: &str, validators: Option<&Validators>) -> Planned {
+        Planned::Http(Box::new(self.request(client, crypto, fiat, validators)))
+    }
+
+    async fn price(
+        &self,
+        client: &Client,
+        crypto: &str,
+        fiat: &str,
+        validators: Option<&Validators>,
+        _verbose: bool,
+    ) -> Result<PriceQuote, CryptoError> {
+        let url = self.url(crypto, fiat);
+        let resp = self.transport.send(self.name(), self.request(client, crypto, fiat, validators)).await?;
+        if validators.is_some() && resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(PriceQuote::NotModified);
+        }
+
+        let validators = Validators::from_headers(resp.headers());
+        let prices: SimplePrice = decode::<_, ErrorBody>(self.name(), &url, &resp.text().await?)?;
+        let price = prices
+            .get(crypto)
+            .and_then(|fiats| fiats.get(fiat))
+            .copied()
+            .ok_or_else(|| CryptoError::NotFound {
+                provider: self.name().to_string(),
+                what: format!("{} price for {}", fiat, crypto),
+            })?;
+        Ok(PriceQuote::Fresh { price, validators })
+    }
+
+    fn batch_limit(&self) -> usize {
+        IDS_PER_REQUEST
+    }
+
+    fn plan_batch(&self, client: &Client, cryptos: &[String], fiat: &str) -> Option<Planned> {
+        Some(Planned::Http(Box::new(client.get(self.url(&cryptos.join(","), fiat)))))
+    }
+
+    async fn prices(&self, client: &Client, cryptos: &[String], fiat: &str, verbose: bool) -> Result<HashMap<String, f64>, CryptoError> {
+        let url = self.url(&cryptos.join(","), fiat);
+        let body = self.transport.send(self.name(), client.get(&url)).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let prices: SimplePrice = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        Ok(prices.into_iter().filter_map(|(crypto, fiats)| Some((crypto, *fiats.get(fiat)?))).collect())
+    }
+
+    async fn token_prices(
+        &self,
+        client: &Client,
+        crypto: &str,
+        contracts: &[String],
+        fiat: &str,
+    ) -> Result<HashMap<String, TokenQuote>, CryptoError> {
+        let platform = PLATFORMS
+            .iter()
+            .find(|(id, _)| *id == crypto)
+            .map(|(_, platform)| *platform)
+            .ok_or_else(|| CryptoError::api(self.name(), format!("no token prices on {}", crypto)))?;
+        let mut found = HashMap::new();
+        for chunk in contracts.chunks(CONTRACTS_PER_REQUEST) {
+            let url = format!(
+                "{}/simple/token_price/{}?contract_addresses={}&vs_currencies={}&include_market_cap=true&include_24hr_vol=true",
+                self.base_url,
+                platform,
+                chunk.join(","),
+                fiat
+            );
+            let resp = self.transport.send(self.name(), client.get(&url)).await?;
+            let prices: TokenPrice = decode::<_, ErrorBody>(self.name(), &url, &resp.text().await?)?;
+            found.extend(prices.into_iter().filter_map(|(contract, values)| {
+                let value = |key: String| values.get(&key).copied().flatten();
+                let quote = TokenQuote {
+                    price: value(fiat.to_string())?,
+                    market_cap: value(format!("{}_market_cap", fiat)),
+                    volume_24h: value(format!("{}_24h_vol", fiat)),
+                };
+                Some((contract.to_lowercase(), quote))
+            }));
+        }
+        Ok(found)
+    }
+
+    fn plan_global(&self, client: &Client, _fiat: &str) -> Option<Planned> {
+        Some(Planned::Http(Box::new(client.get(self.global_url()))))
+    }
+
+    async fn global(&self, client: &Client, fiat: &str, verbose: bool) -> Result<GlobalMarket, CryptoError> {
+        let url = self.global_url();
+        let body = self.transport.send(self.name(), client.get(&url)).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let global: Global = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        let data = global.data;
+        let total = |totals: &HashMap<String, f64>| {
+            totals.get(fiat).copied().ok_or_else(|| CryptoError::NotFound {
+                provider: self.name().to_string(),
+                what: format!("{} market totals", fiat),
+            })
+        };
+        let dominance = |ticker: &str| data.market_cap_percentage.get(ticker).copied().unwrap_or(0.0);
+        Ok(GlobalMarket {
+            total_market_cap: total(&data.total_market_cap)?,
+            total_volume_24h: total(&data.total_volume)?,
+            btc_dominance: dominance("btc"),
+            eth_dominance: dominance("eth"),
+            active_coins: data.active_cryptocurrencies,
+            updated_at: std::time::UNIX_EPOCH + std::time::Duration::from_secs(data.updated_at),
+        })
+    }
+
+    fn plan_trending(&self, client: &Client) -> Option<Planned> {
+        Some(Planned::Http(Box::new(client.get(self.trending_url()))))
+    }
+
+    async fn trending(&self, client: &Client, verbose: bool) -> Result<Vec<TrendingCoin>, CryptoError> {
+        let url = self.trending_url();
+        let body = self.transport.send(self.name(), client.get(&url)).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let trending: Trending = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        Ok(trending
+            .coins
+            .into_iter()
+            .map(|TrendingItem { item }| TrendingCoin {
+                price: item.data.as_ref().and_then(|data| data.price),
+                change_24h: item.data.as_ref().and_then(|data| data.price_change_percentage_24h.get("usd").copied()),
+                id: item.id,
+                symbol: item.symbol,
+                name: item.name,
+                market_cap_rank: item.market_cap_rank,
+            })
+            .collect())
+    }
+
+    fn plan_coin_list(&self, client: &Client) -> Option<Planned> {
+        Some(Planned::Http(Box::new(client.get(self.coin_list_url()))))
+    }
+
+    // /coins/list: every coin id with its symbol and name, some 15,000 of them
+    async fn coin_list(&self, client: &Client, verbose: bool) -> Result<Vec<CoinListing>, CryptoError> {
+        let url = self.coin_list_url();
+        let body = self.transport.send(self.name(), client.get(&url)).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {} bytes", body.len());
+        }
+        decode::<_, ErrorBody>(self.name(), &url, &body)
+    }
+
+    fn plan_closes(&self, client: &Client, crypto: &str, fiat: &str, days: u32) -> Option<Planned> {
+        Some(Planned::Http(Box::new(client.get(self.closes_url(crypto, fiat, days)))))
+    }
+
+    async fn closes(&self, client: &Client, crypto: &str, fiat: &str, days: u32, verbose: bool) -> Result<Vec<DailyClose>, CryptoError> {
+        let url = self.closes_url(crypto, fiat, days);
+        let body = self.transport.send(self.name(), client.get(&url)).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let chart: MarketChart = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        let closes: Vec<DailyClose> = MarketChart::daily(chart.prices).into_iter().map(|(date, price)| DailyClose { date, price }).collect();
+        if closes.is_empty() {
+            return Err(CryptoError::NotFound { provider: self.name().to_string(), what: format!("{} price history for {}", fiat, crypto) });
+        }
+        Ok(closes)
+    }
+
+    fn plan_volumes(&self, client: &Client, crypto: &str, fiat: &str, days: u32) -> Option<Planned> {
+        self.plan_closes(client, crypto, fiat, days)
+    }
+
+    // The same chart as the closes; each volume point is the 24 hours up to it
+    async fn volumes(&self, client: &Client, crypto: &str, fiat: &str, days: u32, verbose: bool) -> Result<Vec<DailyVolume>, CryptoError> {
+        let url = self.closes_url(crypto, fiat, days);
+        let body = self.transport.send(self.name(), client.get(&url)).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let chart: MarketChart = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        let volumes: Vec<DailyVolume> = MarketChart::daily(chart.total_volumes).into_iter().map(|(date, volume)| DailyVolume { date, volume }).collect();
+        if volumes.is_empty() {
+            return Err(CryptoError::NotFound { provider: self.name().to_string(), what: format!("{} volume history for {}", fiat, crypto) });
+        }
+        Ok(volumes)
+    }
+}
@@ -0,0 +1,178 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{BalanceProvider, Holding, Planned, Token, Transport, decode};
+use crate::CryptoError;
+
+// /{chain}/address/{address}/balances_v2/: the native coin and every token the address holds
+#[derive(Deserialize)]
+struct Balances {
+    data: BalancesData,
+}
+
+#[derive(Deserialize)]
+struct BalancesData {
+    items: Vec<Item>,
+}
+
+// `balance` is in the token's smallest unit, as a string since it overflows u64
+#[derive(Deserialize)]
+struct Item {
+    contract_ticker_symbol: Option<String>,
+    contract_decimals: Option<i32>,
+    contract_address: Option<String>,
+    balance: Option<String>,
+    #[serde(default)]
+    native_token: bool,
+}
+
+impl Item {
+    fn amount(&self) -> f64 {
+        let raw: f64 = self.balance.as_deref().and_then(|b| b.parse().ok()).unwrap_or(0.0);
+        raw / 10f64.powi(self.contract_decimals.unwrap_or(0))
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error_message: String,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.error_message)
+    }
+}
+
+// CoinGecko ids of EVM gas coins -> Covalent chain names
+const CHAINS: &[(&str, &str)] = &[
+    ("ethereum", "eth-mainnet"),
+    ("matic-network", "matic-mainnet"),
+    ("polygon-ecosystem-token", "matic-mainnet"),
+    ("binancecoin", "bsc-mainnet"),
+    ("avalanche-2", "avalanche-mainnet"),
+    ("fantom", "fantom-mainnet"),
+    ("xdai", "gnosis-mainnet"),
+    ("celo", "celo-mainnet"),
+    ("moonbeam", "moonbeam-mainnet"),
+    ("crypto-com-chain", "cronos-mainnet"),
+];
+
+fn chain(crypto: &str) -> Option<&'static str> {
+    CHAINS.iter().find(|(id, _)| *id == crypto).map(|(_, chain)| *chain)
+}
+
+// Covalent's unified balances API: one request per address answers for any EVM chain it
+// indexes. Needs an API key, so it's only set up when api_keys.covalent has one.
+pub struct Covalent {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl Covalent {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        Covalent { base_url, transport }
+    }
+
+    fn url(&self, chain: &str, address: &str) -> String {
+        format!("{}/{}/address/{}/balances_v2/", self.base_url, chain, address)
+    }
+
+    fn request(&self, client: &Client, crypto: &str, address: &str) -> (String, reqwest::RequestBuilder) {
+        let url = self.url(chain(crypto).unwrap_or(crypto), address);
+        let request = client.get(&url).query(&[("no-spam", "true")]);
+        (url, request)
+    }
+
+    async fn items(&self, client: &Client, crypto: &str, address: &str) -> Result<Vec<Item>, CryptoError> {
+        let (url, request) = self.request(client, crypto, address);
+        let resp = self.transport.send(self.name(), request).await?;
+        let body = resp.text().await?;
+        let Balances { data } = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        Ok(data.items)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl BalanceProvider for Covalent {
+    fn name(&self) -> &str {
+        "Covalent"
+    }
+
+    fn status_url(&self) -> Option<String> {
+        Some(format!("{}/chains/status/", self.base_url))
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        chain(crypto).is_some()
+    }
+
+    fn plan(&self, client: &Client, crypto: &str, address: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, crypto, address).1))
+    }
+
+    async fn balance(&self, client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError> {
+        let items = self.items(client, crypto, address).await?;
+
+        // An address that never received the native coin has no row for it
+        let balance = items.iter().find(|item| item.native_token).map_or(0.0, Item::amount);
+        if verbose {
+            eprintln!("Fetched balance from Covalent: {} {}", balance, crypto.to_uppercase());
+            for item in items.iter().filter(|item| !item.native_token) {
+                eprintln!("  token: {} {}", item.amount(), item.contract_ticker_symbol.as_deref().unwrap_or("?"));
+            }
+        }
+        Ok(balance)
+    }
+
+    fn supports_tokens(&self, crypto: &str) -> bool {
+        self.supports(crypto)
+    }
+
+    fn plan_token(&self, client: &Client, crypto: &str, address: &str, _token: &Token) -> Option<Planned> {
+        Some(self.plan(client, crypto, address))
+    }
+
+    async fn token_balance(&self, client: &Client, crypto: &str, address: &str, token: &Token, verbose: bool) -> Result<f64, CryptoError> {
+        let items = self.items(client, crypto, address).await?;
+        // Only tokens the address holds are listed
+        let balance = items
+            .iter()
+            .find(|item| item.contract_address.as_deref().is_some_and(|a| a.eq_ignore_ascii_case(&token.address)))
+            .map_or(0.0, Item::amount);
+        if verbose {
+            eprintln!("Fetched {} balance from Covalent: {}", token.symbol, balance);
+        }
+        Ok(balance)
+    }
+
+    fn discovers_tokens(&self, crypto: &str) -> bool {
+        self.supports(crypto)
+    }
+
+    async fn holdings(&self, client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<Vec<Holding>, CryptoError> {
+        let items = self.items(client, crypto, address).await?;
+        let holdings: Vec<Holding> = items
+            .iter()
+            .filter(|item| !item.native_token && item.amount() > 0.0)
+            .filter_map(|item| {
+                Some(Holding {
+                    token: Token {
+                        symbol: item.contract_ticker_symbol.clone().unwrap_or_else(|| "?".to_string()),
+                        address: item.contract_address.clone()?,
+                        decimals: item.contract_decimals.unwrap_or(0),
+                    },
+                    balance: item.amount(),
+                })
+            })
+            .collect();
+        if verbose {
+            eprintln!("Found {} tokens at {} on Covalent", holdings.len(), address);
+        }
+        Ok(holdings)
+    }
+}
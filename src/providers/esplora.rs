@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+use futures::future::try_join;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{AddressHistory, AddressTx, AsOf, FeeProvider, FeeTier, Fees, HistoryProvider, MempoolTx, PastBalanceProvider, Planned, Transport};
+use crate::CryptoError;
+
+const NAME: &str = "Esplora";
+
+// Confirmation targets in blocks behind the fast, normal and slow rates
+const FEE_TARGETS: [(&str, u32); 3] = [("fast", 1), ("normal", 6), ("slow", 144)];
+
+// GET /address/{address}/txs: mempool transactions, then the newest confirmed ones
+#[derive(Deserialize)]
+struct Tx {
+    txid: String,
+    vin: Vec<Input>,
+    vout: Vec<Output>,
+    status: Status,
+}
+
+// prevout is null for a coinbase input
+#[derive(Deserialize)]
+struct Input {
+    prevout: Option<Output>,
+}
+
+// scriptpubkey_address is missing for scripts without one, like OP_RETURN
+#[derive(Deserialize)]
+struct Output {
+    scriptpubkey_address: Option<String>,
+    value: u64,
+}
+
+// Unix seconds for block_time
+#[derive(Deserialize)]
+struct Status {
+    block_height: Option<u64>,
+    block_time: Option<i64>,
+}
+
+// GET /address/{address}: confirmed totals in satoshis, mempool ones left out
+#[derive(Deserialize)]
+struct Address {
+    chain_stats: ChainStats,
+}
+
+#[derive(Deserialize)]
+struct ChainStats {
+    funded_txo_sum: u64,
+    spent_txo_sum: u64,
+}
+
+// Confirmed transactions per page of /address/{address}/txs/chain
+const CHAIN_PAGE: usize = 25;
+
+// GET /mempool/recent: the last ten transactions to enter the mempool, value in satoshis
+#[derive(Deserialize)]
+struct Recent {
+    txid: String,
+    value: u64,
+}
+
+// An Esplora server (Blockstream's, mempool.space or your own), which answers errors in plain text
+pub struct Esplora {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+// Satoshis `tx` moved into `address`, less what it spent from it
+fn net(tx: &Tx, address: &str) -> i64 {
+    let to_address = |output: &Output| output.scriptpubkey_address.as_deref() == Some(address);
+    let received: u64 = tx.vout.iter().filter(|output| to_address(output)).map(|output| output.value).sum();
+    let spent: u64 = tx.vin.iter().filter_map(|input| input.prevout.as_ref()).filter(|output| to_address(output)).map(|output| output.value).sum();
+    received as i64 - spent as i64
+}
+
+impl Esplora {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        Esplora { base_url, transport }
+    }
+
+    fn requests(&self, client: &Client, address: &str) -> [(String, RequestBuilder); 2] {
+        let txs = format!("{}/address/{}/txs", self.base_url, address);
+        let tip = format!("{}/blocks/tip/height", self.base_url);
+        [(txs.clone(), client.get(&txs)), (tip.clone(), client.get(&tip))]
+    }
+
+    fn recent_request(&self, client: &Client) -> (String, RequestBuilder) {
+        let url = format!("{}/mempool/recent", self.base_url);
+        (url.clone(), client.get(&url))
+    }
+
+    fn fees_request(&self, client: &Client) -> (String, RequestBuilder) {
+        let url = format!("{}/fee-estimates", self.base_url);
+        (url.clone(), client.get(&url))
+    }
+
+    fn address_request(&self, client: &Client, address: &str) -> (String, RequestBuilder) {
+        let url = format!("{}/address/{}", self.base_url, address);
+        (url.clone(), client.get(&url))
+    }
+
+    // Newest first, continuing after `last_seen` when given
+    fn chain_request(&self, client: &Client, address: &str, last_seen: Option<&str>) -> (String, RequestBuilder) {
+        let url = format!("{}/address/{}/txs/chain", self.base_url, address);
+        let url = last_seen.map_or(url.clone(), |txid| format!("{}/{}", url, txid));
+        (url.clone(), client.get(&url))
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str, request: RequestBuilder, verbose: bool) -> Result<T, CryptoError> {
+        let body = self.get(url, request, verbose).await?;
+        serde_json::from_str(&body).map_err(|e| CryptoError::api(NAME, format!("unexpected response from {}: {}", url, e)))
+    }
+
+    async fn get(&self, url: &str, request: RequestBuilder, verbose: bool) -> Result<String, CryptoError> {
+        let resp = self.transport.send(NAME, request).await?;
+        let status = resp.status();
+        let body = resp.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        if !status.is_success() {
+            return Err(CryptoError::api(NAME, format!("{} (from {})", body.trim(), url)));
+        }
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl HistoryProvider for Esplora {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        crypto == "bitcoin"
+    }
+
+    fn plan(&self, client: &Client, _crypto: &str, address: &str) -> Vec<Planned> {
+        self.requests(client, address).into_iter().map(|(_, request)| Planned::Http(Box::new(request))).collect()
+    }
+
+    async fn history(&self, client: &Client, _crypto: &str, address: &str, verbose: bool) -> Result<AddressHistory, CryptoError> {
+        let [(txs_url, txs), (tip_url, tip)] = self.requests(client, address);
+        let (txs, tip) = try_join(self.get(&txs_url, txs, verbose), self.get(&tip_url, tip, verbose)).await?;
+        let txs: Vec<Tx> = serde_json::from_str(&txs)
+            .map_err(|e| CryptoError::api(NAME, format!("unexpected response from {}: {}", txs_url, e)))?;
+        let tip = tip
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| CryptoError::api(NAME, format!("unexpected block height from {}", tip_url)))?;
+        let transactions = txs
+            .iter()
+            .map(|tx| AddressTx { txid: tx.txid.clone(), net: net(tx, address), height: tx.status.block_height })
+            .collect();
+        Ok(AddressHistory { transactions, tip })
+    }
+
+    fn plan_recent(&self, client: &Client, _crypto: &str) -> Planned {
+        Planned::Http(Box::new(self.recent_request(client).1))
+    }
+
+    async fn recent(&self, client: &Client, _crypto: &str, verbose: bool) -> Result<Vec<MempoolTx>, CryptoError> {
+        let (url, request) = self.recent_request(client);
+        let body = self.get(&url, request, verbose).await?;
+        let recent: Vec<Recent> = serde_json::from_str(&body)
+            .map_err(|e| CryptoError::api(NAME, format!("unexpected response from {}: {}", url, e)))?;
+        Ok(recent.into_iter().map(|tx| MempoolTx { txid: tx.txid, value: tx.value }).collect())
+    }
+}
+
+// GET /fee-estimates: sat/vB by confirmation target in blocks, keyed by the target as a string
+#[async_trait]
+impl FeeProvider for Esplora {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        crypto == "bitcoin"
+    }
+
+    fn plan_fees(&self, client: &Client, _crypto: &str) -> Planned {
+        Planned::Http(Box::new(self.fees_request(client).1))
+    }
+
+    async fn fees(&self, client: &Client, _crypto: &str, verbose: bool) -> Result<Fees, CryptoError> {
+        let (url, request) = self.fees_request(client);
+        let body = self.get(&url, request, verbose).await?;
+        let estimates: HashMap<String, f64> =
+            serde_json::from_str(&body).map_err(|e| CryptoError::api(NAME, format!("unexpected response from {}: {}", url, e)))?;
+        let tiers: Vec<FeeTier> = FEE_TARGETS
+            .iter()
+            .filter_map(|(name, blocks)| estimates.get(&blocks.to_string()).map(|rate| FeeTier { name, rate: *rate, blocks: Some(*blocks) }))
+            .collect();
+        if tiers.is_empty() {
+            return Err(CryptoError::NotFound { provider: NAME.to_string(), what: "fee estimates".to_string() });
+        }
+        Ok(Fees { unit: "sat/vB", tiers })
+    }
+}
+
+// Today's confirmed balance, less what the transactions since `at` moved, paging back through
+// them newest first until one is older
+#[async_trait]
+impl PastBalanceProvider for Esplora {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        crypto == "bitcoin"
+    }
+
+    fn plan_balance_at(&self, client: &Client, _crypto: &str, address: &str, _at: &AsOf) -> Vec<Planned> {
+        [self.address_request(client, address), self.chain_request(client, address, None)]
+            .into_iter()
+            .map(|(_, request)| Planned::Http(Box::new(request)))
+            .collect()
+    }
+
+    async fn balance_at(&self, client: &Client, _crypto: &str, address: &str, at: &AsOf, verbose: bool) -> Result<f64, CryptoError> {
+        let (url, request) = self.address_request(client, address);
+        let Address { chain_stats } = self.get_json(&url, request, verbose).await?;
+        let mut balance = chain_stats.funded_txo_sum as i64 - chain_stats.spent_txo_sum as i64;
+        let after = |status: &Status| match at {
+            AsOf::Height(height) => status.block_height.is_some_and(|block| block > *height),
+            AsOf::Time(time) => status.block_time.is_some_and(|block| block > time.timestamp()),
+        };
+        let mut last_seen: Option<String> = None;
+        loop {
+            let (url, request) = self.chain_request(client, address, last_seen.as_deref());
+            let page: Vec<Tx> = self.get_json(&url, request, verbose).await?;
+            let mut reached = page.len() < CHAIN_PAGE;
+            for tx in &page {
+                if !after(&tx.status) {
+                    reached = true;
+                    break;
+                }
+                balance -= net(tx, address);
+            }
+            if reached {
+                break;
+            }
+            last_seen = page.last().map(|tx| tx.txid.clone());
+        }
+        Ok(balance as f64 / 100_000_000.0)
+    }
+}
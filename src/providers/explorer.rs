@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::{BalanceProvider, Planned, Transport};
+use crate::CryptoError;
+use crate::config::ChainConfig;
+
+// A [[chains]] entry backed by a block explorer: GET the URL template with the address filled
+// in and read the balance, in the chain's smallest unit, from `balance_field`
+pub struct Explorer {
+    chain: String,
+    url: String,
+    // `balance_field` split on dots
+    field: Vec<String>,
+    decimals: i32,
+    transport: Arc<Transport>,
+}
+
+impl Explorer {
+    pub fn new(chain: &ChainConfig, url: &str, transport: Arc<Transport>) -> Self {
+        Explorer {
+            chain: chain.name.clone(),
+            url: url.to_string(),
+            field: chain.balance_field.split('.').map(str::to_string).collect(),
+            decimals: chain.decimals,
+            transport,
+        }
+    }
+
+    fn url(&self, address: &str) -> String {
+        self.url.replace("{address}", address)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl BalanceProvider for Explorer {
+    fn name(&self) -> &str {
+        &self.chain
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        crypto == self.chain
+    }
+
+    fn plan(&self, client: &Client, _crypto: &str, address: &str) -> Planned {
+        Planned::Http(Box::new(client.get(self.url(address))))
+    }
+
+    async fn balance(&self, client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError> {
+        let resp = self.transport.send(self.name(), client.get(self.url(address))).await?;
+        let body = resp.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+
+        // The template may hold a key, so errors name the field rather than the URL
+        let field = self.field.join(".");
+        let json: Value = serde_json::from_str(&body)
+            .map_err(|e| CryptoError::api(self.name(), format!("response isn't JSON: {}", e)))?;
+        let value = self.field.iter().try_fold(&json, |value, key| match value {
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => value.get(key),
+        });
+        // Explorers send big balances as strings to keep their precision
+        let raw = match value {
+            Some(Value::Number(n)) => n.as_f64(),
+            Some(Value::String(s)) => s.trim().parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| CryptoError::api(self.name(), format!("no numeric '{}' in the response", field)))?;
+
+        let balance = raw / 10f64.powi(self.decimals);
+        if verbose {
+            eprintln!("Fetched balance from {}: {} {}", self.name(), balance, crypto.to_uppercase());
+        }
+        Ok(balance)
+    }
+}
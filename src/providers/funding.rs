@@ -0,0 +1,211 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::try_join;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+
+use super::exchange::BinanceError;
+use super::{Funding, FundingProvider, Planned, Transport, decode};
+use crate::CryptoError;
+
+// GET /fapi/v1/premiumIndex: lastFundingRate is the rate for the coming settlement, as it
+// stands now
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinancePremium {
+    last_funding_rate: String,
+    next_funding_time: i64,
+}
+
+// GET /fapi/v1/fundingRate: settled rates, oldest first
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceSettled {
+    funding_rate: String,
+    funding_time: i64,
+}
+
+// Binance USD-M futures, on their own host
+pub struct BinanceFutures {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl BinanceFutures {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        BinanceFutures { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, path: &str, params: &[(&str, &str)]) -> (String, RequestBuilder) {
+        let url = format!("{}{}", self.base_url, path);
+        let request = client.get(&url).query(params);
+        (url, request)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, client: &Client, path: &str, params: &[(&str, &str)], verbose: bool) -> Result<T, CryptoError> {
+        let (url, request) = self.request(client, path, params);
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        decode::<_, BinanceError>(self.name(), &url, &body)
+    }
+}
+
+#[async_trait]
+impl FundingProvider for BinanceFutures {
+    fn name(&self) -> &str {
+        "Binance"
+    }
+
+    fn plan(&self, client: &Client, symbol: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, "/fapi/v1/premiumIndex", &[("symbol", symbol)]).1))
+    }
+
+    async fn funding(&self, client: &Client, symbol: &str, verbose: bool) -> Result<Funding, CryptoError> {
+        let (premium, settled) = try_join(
+            self.get::<BinancePremium>(client, "/fapi/v1/premiumIndex", &[("symbol", symbol)], verbose),
+            self.get::<Vec<BinanceSettled>>(client, "/fapi/v1/fundingRate", &[("symbol", symbol), ("limit", "1")], verbose),
+        )
+        .await?;
+        let last = settled.last().ok_or_else(|| CryptoError::NotFound {
+            provider: self.name().to_string(),
+            what: format!("funding history for {}", symbol),
+        })?;
+        Ok(Funding {
+            last_rate: rate(self.name(), &last.funding_rate)?,
+            last_time: millis(self.name(), last.funding_time)?,
+            predicted_rate: rate(self.name(), &premium.last_funding_rate)?,
+            next_time: millis(self.name(), premium.next_funding_time)?,
+        })
+    }
+}
+
+// Bybit's v5 replies: retCode 0 with `result`, or an error code and message
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitReply<T> {
+    ret_code: i64,
+    ret_msg: String,
+    result: Option<BybitList<T>>,
+}
+
+// Empty, without a list, on errors
+#[derive(Deserialize)]
+struct BybitList<T> {
+    #[serde(default = "Vec::new")]
+    list: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct BybitError {
+    #[serde(rename = "retMsg")]
+    message: String,
+}
+
+impl fmt::Display for BybitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// GET /v5/market/tickers: fundingRate is the coming settlement's rate. Times are strings of
+// milliseconds.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitTicker {
+    funding_rate: String,
+    next_funding_time: String,
+}
+
+// GET /v5/market/funding/history: settled rates, newest first
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitSettled {
+    funding_rate: String,
+    funding_rate_timestamp: String,
+}
+
+// Bybit's linear (USDT and USDC margined) perpetuals
+pub struct Bybit {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl Bybit {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        Bybit { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, path: &str, symbol: &str, limit: Option<&str>) -> (String, RequestBuilder) {
+        let url = format!("{}{}", self.base_url, path);
+        let mut request = client.get(&url).query(&[("category", "linear"), ("symbol", symbol)]);
+        if let Some(limit) = limit {
+            request = request.query(&[("limit", limit)]);
+        }
+        (url, request)
+    }
+
+    // The first entry of a list reply
+    async fn first<T: serde::de::DeserializeOwned>(
+        &self,
+        client: &Client,
+        path: &str,
+        symbol: &str,
+        limit: Option<&str>,
+        verbose: bool,
+    ) -> Result<T, CryptoError> {
+        let (url, request) = self.request(client, path, symbol, limit);
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let reply: BybitReply<T> = decode::<_, BybitError>(self.name(), &url, &body)?;
+        if reply.ret_code != 0 {
+            return Err(CryptoError::api(self.name(), format!("{} (code {})", reply.ret_msg, reply.ret_code)));
+        }
+        reply.result.and_then(|result| result.list.into_iter().next()).ok_or_else(|| CryptoError::NotFound {
+            provider: self.name().to_string(),
+            what: format!("a {} perpetual", symbol),
+        })
+    }
+}
+
+#[async_trait]
+impl FundingProvider for Bybit {
+    fn name(&self) -> &str {
+        "Bybit"
+    }
+
+    fn plan(&self, client: &Client, symbol: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, "/v5/market/tickers", symbol, None).1))
+    }
+
+    async fn funding(&self, client: &Client, symbol: &str, verbose: bool) -> Result<Funding, CryptoError> {
+        let (ticker, last) = try_join(
+            self.first::<BybitTicker>(client, "/v5/market/tickers", symbol, None, verbose),
+            self.first::<BybitSettled>(client, "/v5/market/funding/history", symbol, Some("1"), verbose),
+        )
+        .await?;
+        let time = |value: &str| {
+            let ms = value.parse().map_err(|_| CryptoError::api(self.name(), format!("'{}' isn't a time", value)))?;
+            millis(self.name(), ms)
+        };
+        Ok(Funding {
+            last_rate: rate(self.name(), &last.funding_rate)?,
+            last_time: time(&last.funding_rate_timestamp)?,
+            predicted_rate: rate(self.name(), &ticker.funding_rate)?,
+            next_time: time(&ticker.next_funding_time)?,
+        })
+    }
+}
+
+fn rate(provider: &str, value: &str) -> Result<f64, CryptoError> {
+    value.parse().map_err(|_| CryptoError::api(provider, format!("'{}' isn't a funding rate", value)))
+}
+
+fn millis(provider: &str, ms: i64) -> Result<DateTime<Utc>, CryptoError> {
+    DateTime::from_timestamp_millis(ms).ok_or_else(|| CryptoError::api(provider, format!("{} isn't a time", ms)))
+}
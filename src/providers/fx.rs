@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{FxProvider, Planned, Transport, decode};
+use crate::CryptoError;
+
+// GET /latest?from=USD&to=EUR: one unit of `from` in each of `to`
+#[derive(Deserialize)]
+struct Latest {
+    rates: HashMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// Frankfurter, which republishes the ECB's daily reference rates without a key
+pub struct Frankfurter {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl Frankfurter {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        Frankfurter { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, from: &str, to: &str) -> (String, RequestBuilder) {
+        let url = format!("{}/latest", self.base_url);
+        let request = client.get(&url).query(&[("from", from.to_uppercase()), ("to", to.to_uppercase())]);
+        (url, request)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl FxProvider for Frankfurter {
+    fn name(&self) -> &str {
+        "Frankfurter"
+    }
+
+    fn plan(&self, client: &Client, from: &str, to: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, from, to).1))
+    }
+
+    async fn rate(&self, client: &Client, from: &str, to: &str, verbose: bool) -> Result<f64, CryptoError> {
+        let (url, request) = self.request(client, from, to);
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let latest: Latest = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        latest.rates.get(&to.to_uppercase()).copied().ok_or_else(|| CryptoError::NotFound {
+            provider: self.name().to_string(),
+            what: format!("a {}/{} rate", to.to_uppercase(), from.to_uppercase()),
+        })
+    }
+}
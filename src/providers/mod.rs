@@ -0,0 +1,1271 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+#[cfg(target_arch = "wasm32")]
+use reqwest::header::RETRY_AFTER;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::CryptoError;
+use crate::cache::Validators;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::cache::RequestCounts;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audit::AuditLog;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::cassette::Cassette;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::har::HarLog;
+use crate::config::Config;
+use crate::keys::{KeyPlacement, KeyRing, KeyUsage, redact};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::PluginCapability;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::quota::Quotas;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::ratelimit::RateLimits;
+
+mod blockchair;
+mod blockcypher;
+#[cfg(not(target_arch = "wasm32"))]
+mod chain;
+mod coingecko;
+mod covalent;
+#[cfg(not(target_arch = "wasm32"))]
+mod esplora;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod exchange;
+mod explorer;
+#[cfg(not(target_arch = "wasm32"))]
+mod funding;
+mod fx;
+mod rpc;
+#[cfg(not(target_arch = "wasm32"))]
+mod sentiment;
+#[cfg(not(target_arch = "wasm32"))]
+mod ticker;
+#[cfg(not(target_arch = "wasm32"))]
+mod utxo;
+mod zerox;
+#[cfg(not(target_arch = "wasm32"))]
+mod plugin;
+
+// How provider requests go out: straight to the network, or through a record/replay cassette
+pub struct Transport {
+    // Extra headers per provider, keyed by lowercase name
+    headers: HashMap<String, HeaderMap>,
+    // API keys per provider, likewise lowercase
+    keys: HashMap<String, KeyRing>,
+    // Every key, to keep them out of error messages
+    secrets: Vec<String>,
+    // Requests per second each provider (lowercase) is held to, when it has a known allowance
+    #[cfg(not(target_arch = "wasm32"))]
+    pacing: HashMap<String, f64>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cassette: Option<Cassette>,
+    #[cfg(not(target_arch = "wasm32"))]
+    har: Option<HarLog>,
+    #[cfg(not(target_arch = "wasm32"))]
+    audit: Option<AuditLog>,
+    #[cfg(not(target_arch = "wasm32"))]
+    limits: RateLimits,
+    #[cfg(not(target_arch = "wasm32"))]
+    quotas: Quotas,
+    // This run's calls by provider, until the cache takes them to keep
+    #[cfg(not(target_arch = "wasm32"))]
+    requests: dashmap::DashMap<String, RequestCounts>,
+}
+
+impl Transport {
+    // `credentials` are secrets beyond the API keys, like exchange API secrets
+    fn from_config(config: &Config, credentials: &[String]) -> Result<Self, CryptoError> {
+        let mut keys = HashMap::new();
+        for (provider, api_keys) in &config.api_keys {
+            #[cfg(not(target_arch = "wasm32"))]
+            let values = match api_keys.keychain {
+                true => [api_keys.keys.clone(), crate::secrets::get(provider)?].concat(),
+                false => api_keys.keys.clone(),
+            };
+            #[cfg(target_arch = "wasm32")]
+            let values = {
+                if api_keys.keychain {
+                    eprintln!("Warning: api_keys.{}.keychain ignored, the browser has no OS keychain", provider);
+                }
+                api_keys.keys.clone()
+            };
+            if values.is_empty() {
+                continue;
+            }
+            let placement = match &api_keys.header {
+                Some(name) => KeyPlacement::Header(HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                    CryptoError::ConfigError(format!("Invalid header name '{}' for {}'s API keys", name, provider))
+                })?),
+                None => key_placement(provider).ok_or_else(|| {
+                    CryptoError::ConfigError(format!(
+                        "Don't know how {} takes an API key; set api_keys.{}.header",
+                        provider, provider
+                    ))
+                })?,
+            };
+            keys.insert(provider.to_lowercase(), KeyRing::new(&values, api_keys.rotation, placement));
+        }
+        let mut secrets: Vec<String> = keys.values().flat_map(KeyRing::secrets).map(str::to_string).collect();
+        let chain_urls = config.chains.iter().flat_map(|chain| chain.rpc.iter().chain(&chain.explorer));
+        secrets.extend(config.rpc.values().chain(chain_urls).flat_map(|url| url_secrets(url)));
+        secrets.extend(credentials.iter().cloned());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut pacing: HashMap<String, f64> = ANONYMOUS_RATES
+            .iter()
+            .filter(|(provider, _)| !keys.contains_key(*provider))
+            .map(|(provider, rate)| (provider.to_string(), *rate))
+            .collect();
+        #[cfg(not(target_arch = "wasm32"))]
+        for (provider, api_keys) in &config.api_keys {
+            match api_keys.requests_per_second {
+                Some(rate) if rate > 0.0 => {
+                    pacing.insert(provider.to_lowercase(), rate);
+                }
+                Some(_) => {
+                    return Err(CryptoError::ConfigError(format!("api_keys.{}.requests_per_second must be positive", provider)));
+                }
+                None => {}
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let cassette = match (&config.record, &config.replay) {
+            (Some(_), Some(_)) => return Err(CryptoError::ConfigError("Can't record and replay at the same time".to_string())),
+            (Some(path), None) => Some(Cassette::record(path, secrets.clone())),
+            (None, Some(path)) => Some(Cassette::replay(path, secrets.clone())?),
+            (None, None) => None,
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let har = config.debug_http.as_deref().map(|path| HarLog::new(path, secrets.clone(), &config.network.user_agent));
+        #[cfg(not(target_arch = "wasm32"))]
+        let audit = config.audit_log.as_deref().map(|path| AuditLog::open(path, secrets.clone())).transpose()?;
+        let mut headers = HashMap::new();
+        for (provider, values) in &config.headers {
+            let mut map = HeaderMap::new();
+            for (name, value) in values {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| CryptoError::ConfigError(format!("Invalid header name '{}' for {}", name, provider)))?;
+                let value = HeaderValue::from_str(value)
+                    .map_err(|_| CryptoError::ConfigError(format!("Invalid value for header '{}' for {}", name, provider)))?;
+                map.insert(name, value);
+            }
+            headers.insert(provider.to_lowercase(), map);
+        }
+        Ok(Transport {
+            headers,
+            keys,
+            secrets,
+            #[cfg(not(target_arch = "wasm32"))]
+            pacing,
+            #[cfg(not(target_arch = "wasm32"))]
+            cassette,
+            #[cfg(not(target_arch = "wasm32"))]
+            har,
+            #[cfg(not(target_arch = "wasm32"))]
+            audit,
+            #[cfg(not(target_arch = "wasm32"))]
+            limits: RateLimits::new(&config.network),
+            #[cfg(not(target_arch = "wasm32"))]
+            quotas: Quotas::new(config)?,
+            #[cfg(not(target_arch = "wasm32"))]
+            requests: dashmap::DashMap::new(),
+        })
+    }
+
+    // Sends `request` and sorts out failures that aren't about the response body: 429 becomes
+    // RateLimited (after moving on to another API key, or waiting out and retrying short ones)
+    // and 5xx ProviderUnavailable, so callers can tell them from bad responses. A provider at
+    // its [quotas] cap is ProviderUnavailable without a request.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn send(&self, provider: &str, request: RequestBuilder) -> Result<Response, CryptoError> {
+        let ring = self.key_ring(provider);
+        let mut request = self.with_headers(provider, request);
+        let mut attempt = 0;
+        // Other keys tried after a 429 before waiting on one
+        let mut rotations = 0;
+        loop {
+            self.quotas.check(provider)?;
+            let key = ring.map(|ring| ring.pick(|i| self.limits.is_blocked(&quota(provider, Some(i)))));
+            let quota = quota(provider, key);
+            self.limits.wait(provider, &quota).await?;
+            // Each key is paced separately, since each has the plan's allowance
+            if let Some(rate) = self.pacing.get(&provider.to_lowercase()) {
+                self.limits.pace(&quota, *rate).await;
+            }
+            let retry = request.try_clone();
+            let keyed = match (ring, key) {
+                (Some(ring), Some(index)) => ring.apply(index, request),
+                _ => request,
+            };
+            let started = std::time::Instant::now();
+            let resp = match &self.audit {
+                Some(audit) => audit.capture(provider, keyed, |request| self.logged(request)).await,
+                None => self.logged(keyed).await,
+            };
+            self.count(provider, started.elapsed(), resp.as_ref().ok().map(Response::status));
+            let resp = resp?;
+            let retry_after = self.limits.observe(&quota, resp.status(), resp.headers(), attempt);
+            if let (Some(_), Some(ring), Some(index)) = (retry_after, ring, key) {
+                ring.rate_limited(index);
+            }
+            let spare_keys = ring.map_or(0, |ring| ring.len() - 1);
+            match (retry_after, retry) {
+                (Some(_), Some(next)) if rotations < spare_keys => {
+                    request = next;
+                    rotations += 1;
+                }
+                (Some(wait), Some(next)) if attempt < self.limits.retries && self.limits.can_wait(wait) => {
+                    request = next;
+                    attempt += 1;
+                }
+                (Some(retry_after), _) => {
+                    return Err(CryptoError::RateLimited { provider: provider.to_string(), retry_after: Some(retry_after) });
+                }
+                (None, _) => return self.check_unavailable(provider, resp),
+            }
+        }
+    }
+
+    // `status` is None when no response came back
+    #[cfg(not(target_arch = "wasm32"))]
+    fn count(&self, provider: &str, latency: Duration, status: Option<StatusCode>) {
+        let mut counts = self.requests.entry(provider.to_string()).or_default();
+        counts.requests += 1;
+        counts.total_latency_ms += latency.as_secs_f64() * 1000.0;
+        if status.is_some() {
+            self.quotas.record(provider);
+        }
+        match status {
+            None => counts.errors += 1,
+            Some(StatusCode::TOO_MANY_REQUESTS) => counts.rate_limited += 1,
+            Some(status) if status.is_server_error() => counts.errors += 1,
+            Some(_) => {}
+        }
+    }
+
+    // The calls counted since the last take, for the cache to add to its totals
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn take_requests(&self) -> Vec<(String, RequestCounts)> {
+        let providers: Vec<String> = self.requests.iter().map(|e| e.key().clone()).collect();
+        providers.into_iter().filter_map(|provider| self.requests.remove(&provider)).collect()
+    }
+
+    // Through the --debug-http log, when there is one
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn logged(&self, request: RequestBuilder) -> Result<Response, CryptoError> {
+        match &self.har {
+            Some(har) => har.capture(request, |request| self.dispatch(request)).await,
+            None => self.dispatch(request).await,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn dispatch(&self, request: RequestBuilder) -> Result<Response, CryptoError> {
+        match &self.cassette {
+            Some(cassette) => cassette.send(request).await,
+            None => request.send().await.map_err(CryptoError::from),
+        }
+        .map_err(|e| self.redact(e))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn send(&self, provider: &str, request: RequestBuilder) -> Result<Response, CryptoError> {
+        let ring = self.key_ring(provider);
+        let key = ring.map(|ring| ring.pick(|_| false));
+        let request = self.with_headers(provider, request);
+        let request = match (ring, key) {
+            (Some(ring), Some(index)) => ring.apply(index, request),
+            _ => request,
+        };
+        let resp = request.send().await.map_err(|e| self.redact(e.into()))?;
+        if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+            if let (Some(ring), Some(index)) = (ring, key) {
+                ring.rate_limited(index);
+            }
+            let retry_after = resp
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok())
+                .map(Duration::from_secs);
+            return Err(CryptoError::RateLimited { provider: provider.to_string(), retry_after });
+        }
+        self.check_unavailable(provider, resp)
+    }
+
+    fn key_ring(&self, provider: &str) -> Option<&KeyRing> {
+        self.keys.get(&provider.to_lowercase())
+    }
+
+    // Per-key request and 429 counts for `status`; empty without API keys
+    pub fn key_usage(&self, provider: &str) -> Vec<KeyUsage> {
+        self.key_ring(provider).map(KeyRing::usage).unwrap_or_default()
+    }
+
+    // How much of its [quotas] the provider has used, for `status`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn quota_usage(&self, provider: &str) -> Option<String> {
+        self.quotas.usage(provider)
+    }
+
+    // `request` as `provider` would send it with key number `key`, for pinging with `status`
+    pub fn prepare(&self, provider: &str, key: Option<usize>, request: RequestBuilder) -> RequestBuilder {
+        let request = self.with_headers(provider, request);
+        match (self.key_ring(provider), key) {
+            (Some(ring), Some(index)) => ring.apply(index, request),
+            _ => request,
+        }
+    }
+
+    // `request` as it would go out for `provider`, with any API key blanked, for --dry-run.
+    // Client-wide headers like the User-Agent are added when sending and aren't listed.
+    pub fn describe(&self, provider: &str, request: RequestBuilder) -> String {
+        let key = self.key_ring(provider).map(|_| 0);
+        let request = match self.prepare(provider, key, request).build() {
+            Ok(request) => request,
+            Err(e) => return format!("invalid request: {}", e.without_url()),
+        };
+        let mut text = format!("{} {}", request.method(), request.url());
+        for (name, value) in request.headers() {
+            text.push_str(&format!("\n    {}: {}", name, String::from_utf8_lossy(value.as_bytes())));
+        }
+        if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+            text.push_str(&format!("\n    body: {}", String::from_utf8_lossy(body)));
+        }
+        redact(&self.secrets, &text)
+    }
+
+    fn check_unavailable(&self, provider: &str, resp: Response) -> Result<Response, CryptoError> {
+        if resp.status().is_server_error() {
+            return Err(CryptoError::ProviderUnavailable {
+                provider: provider.to_string(),
+                reason: redact(&self.secrets, &format!("HTTP {} from {}", resp.status(), resp.url())),
+            });
+        }
+        Ok(resp)
+    }
+
+    // reqwest errors name the URL, which may carry a key as a query parameter
+    fn redact(&self, error: CryptoError) -> CryptoError {
+        match error {
+            CryptoError::HttpError(mut e) => {
+                if let Some(url) = e.url_mut()
+                    && let Ok(redacted) = redact(&self.secrets, url.as_str()).parse()
+                {
+                    *url = redacted;
+                }
+                CryptoError::HttpError(e)
+            }
+            other => other,
+        }
+    }
+
+    fn with_headers(&self, provider: &str, request: RequestBuilder) -> RequestBuilder {
+        match self.headers.get(&provider.to_lowercase()) {
+            Some(headers) => request.headers(headers.clone()),
+            None => request,
+        }
+    }
+
+    // Writes out a cassette being recorded, the --debug-http log and the quota counts
+    pub fn save(&self) -> Result<(), CryptoError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.quotas.save()?;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cassette) = &self.cassette {
+            cassette.save()?;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(har) = &self.har {
+            har.save()?;
+        }
+        Ok(())
+    }
+}
+
+// Rate limit bucket for a provider, or for one of its API keys
+#[cfg(not(target_arch = "wasm32"))]
+fn quota(provider: &str, key: Option<usize>) -> String {
+    match key {
+        Some(index) => format!("{} key {}", provider, index + 1),
+        None => provider.to_string(),
+    }
+}
+
+// Parts of an RPC endpoint URL that look like an account key: path segments and query values
+// of 16 characters or more, as in .../v2/<key> or ?apikey=<key>
+fn url_secrets(url: &str) -> Vec<String> {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return Vec::new();
+    };
+    let segments = url.path().split('/').map(str::to_string);
+    let values = url.query_pairs().map(|(_, value)| value.into_owned());
+    segments.chain(values).filter(|part| part.len() >= 16).collect()
+}
+
+// Documented limits for requests without an API key, as provider and requests per second
+#[cfg(not(target_arch = "wasm32"))]
+const ANONYMOUS_RATES: &[(&str, f64)] = &[("blockcypher", 3.0)];
+
+// Where each built-in provider takes an API key
+fn key_placement(provider: &str) -> Option<KeyPlacement> {
+    match provider.to_lowercase().as_str() {
+        "coingecko" => Some(KeyPlacement::Header(HeaderName::from_static("x-cg-demo-api-key"))),
+        "blockcypher" => Some(KeyPlacement::Query("token")),
+        "blockchair" => Some(KeyPlacement::Query("key")),
+        "covalent" => Some(KeyPlacement::Query("key")),
+        "0x" => Some(KeyPlacement::Header(HeaderName::from_static("0x-api-key"))),
+        _ => None,
+    }
+}
+
+// Decodes a response body into the endpoint's model `T`. A body that doesn't fit is checked
+// against the provider's error payload `E`, so errors the provider sent aren't reported as a
+// parse failure; otherwise serde's message names the missing or mistyped field.
+fn decode<T: DeserializeOwned, E: DeserializeOwned + Display>(provider: &str, url: &str, body: &str) -> Result<T, CryptoError> {
+    serde_json::from_str(body).map_err(|e| match serde_json::from_str::<E>(body) {
+        Ok(error) => CryptoError::api(provider, format!("{} (from {})", error, url)),
+        Err(_) => CryptoError::api(provider, format!("unexpected response from {}: {}", url, e)),
+    })
+}
+
+// What a provider would send for a lookup, printed by --dry-run
+pub enum Planned {
+    Http(Box<RequestBuilder>),
+    // A plugin: the command line and the request written to its stdin
+    Command { command: String, stdin: String },
+}
+
+// An ERC-20 contract, as found in a token list
+pub struct Token {
+    pub symbol: String,
+    pub address: String,
+    pub decimals: i32,
+}
+
+// Stands in for a chain's gas coin where DEX aggregators expect a contract address
+const NATIVE_ADDRESS: &str = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE";
+
+impl Token {
+    pub fn native(symbol: &str) -> Self {
+        Token { symbol: symbol.to_uppercase(), address: NATIVE_ADDRESS.to_string(), decimals: 18 }
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.address.eq_ignore_ascii_case(NATIVE_ADDRESS)
+    }
+}
+
+// A token an address holds, and how much of it
+pub struct Holding {
+    pub token: Token,
+    pub balance: f64,
+}
+
+// A token's price plus how much it's really worth and traded, to tell real tokens from spam
+pub struct TokenQuote {
+    pub price: f64,
+    pub market_cap: Option<f64>,
+    pub volume_24h: Option<f64>,
+}
+
+// The whole market in one fiat, as a price aggregator totals it. Dominance is each coin's
+// share of the total market cap, in percent.
+pub struct GlobalMarket {
+    pub total_market_cap: f64,
+    pub total_volume_24h: f64,
+    pub btc_dominance: f64,
+    pub eth_dominance: f64,
+    pub active_coins: u64,
+    pub updated_at: std::time::SystemTime,
+}
+
+// One coin the provider prices, as kept in the local coin list
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct CoinListing {
+    pub id: String,
+    pub symbol: String,
+    pub name: String,
+}
+
+// A coin people are searching for, as the provider ranks them
+pub struct TrendingCoin {
+    pub id: String,
+    pub symbol: String,
+    pub name: String,
+    pub market_cap_rank: Option<u64>,
+    // In USD, where the provider has them
+    pub price: Option<f64>,
+    pub change_24h: Option<f64>,
+}
+
+// A day's closing price. The last one is today's price so far.
+pub struct DailyClose {
+    pub date: chrono::NaiveDate,
+    pub price: f64,
+}
+
+// A day's trading volume, as the 24 hours up to its close. The last one is the 24 hours up to now.
+pub struct DailyVolume {
+    pub date: chrono::NaiveDate,
+    pub volume: f64,
+}
+
+// What selling one token for another would return, as a DEX aggregator quotes it
+pub struct SwapQuote {
+    pub buy_amount: f64,
+    // Gas units the trade is estimated to take
+    pub gas: Option<f64>,
+    // The same, in the chain's gas coin
+    pub network_fee: Option<f64>,
+}
+
+// The top of an exchange's order book
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Ticker {
+    // The market quoted, as BASE/QUOTE, when it stands in for the one asked for
+    pub market: String,
+    pub bid: f64,
+    pub ask: f64,
+}
+
+// The top `depth` levels of each side of an order book, as (price, size), best first
+#[cfg(not(target_arch = "wasm32"))]
+pub struct OrderBook {
+    pub market: String,
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+}
+
+// A perpetual future's funding: the last settled rate and the coming one as it stands now,
+// as fractions per settlement
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Funding {
+    pub last_rate: f64,
+    pub last_time: chrono::DateTime<chrono::Utc>,
+    pub predicted_rate: f64,
+    pub next_time: chrono::DateTime<chrono::Utc>,
+}
+
+// An unspent output, in satoshis; `height` is None while unconfirmed
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Utxo {
+    pub value: u64,
+    pub height: Option<u64>,
+}
+
+// An address's unspent outputs with the chain's height and suggested fee rate in sat/vB.
+// `utxos` can be fewer than `total` for addresses with more than the provider lists.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct UtxoSet {
+    pub utxos: Vec<Utxo>,
+    pub total: usize,
+    pub tip: u64,
+    pub fee_rate: f64,
+}
+
+// A transaction touching an address: what it paid the address less what it spent from it, in
+// the coin's smallest unit. `height` is None while in the mempool.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AddressTx {
+    pub txid: String,
+    pub net: i64,
+    pub height: Option<u64>,
+}
+
+// A fee rate to pay for a speed: fast, normal or slow, confirming within `blocks` where the
+// provider says
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(serde::Serialize)]
+pub struct FeeTier {
+    pub name: &'static str,
+    pub rate: f64,
+    pub blocks: Option<u32>,
+}
+
+// Recommended fee rates, all in `unit`: sat/vB, sat/B or gwei
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Fees {
+    pub unit: &'static str,
+    pub tiers: Vec<FeeTier>,
+}
+
+// The newest block of a chain
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ChainTip {
+    pub height: u64,
+    pub hash: String,
+    pub time: chrono::DateTime<chrono::Utc>,
+}
+
+// `block` takes either
+#[cfg(not(target_arch = "wasm32"))]
+pub enum BlockId {
+    Height(u64),
+    Hash(String),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Display for BlockId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockId::Height(height) => write!(f, "{}", height),
+            BlockId::Hash(hash) => f.write_str(hash),
+        }
+    }
+}
+
+// When `address-balance --at/--height` reads a balance: at the end of a block, or as of a time
+#[cfg(not(target_arch = "wasm32"))]
+pub enum AsOf {
+    Height(u64),
+    Time(chrono::DateTime<chrono::Utc>),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Display for AsOf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsOf::Height(height) => write!(f, "block {}", height),
+            AsOf::Time(time) => write!(f, "{}", crate::timezone::iso(*time)),
+        }
+    }
+}
+
+// Hashrate in hashes per second over the last day; the next difficulty and when it takes
+// effect are estimates, for chains that retarget in periods
+#[cfg(not(target_arch = "wasm32"))]
+pub struct NetworkStats {
+    pub height: u64,
+    pub difficulty: f64,
+    pub hashrate: f64,
+    pub next_difficulty: Option<f64>,
+    pub next_retarget: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Size in bytes; fees in the coin
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Block {
+    pub height: u64,
+    pub hash: String,
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub size: u64,
+    pub transactions: u64,
+    pub fees: f64,
+}
+
+// A transaction just seen in the mempool, with what its outputs add up to in the coin's smallest
+// unit
+#[cfg(not(target_arch = "wasm32"))]
+pub struct MempoolTx {
+    pub txid: String,
+    pub value: u64,
+}
+
+// An address's latest transactions, unconfirmed ones first, with the chain's height
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AddressHistory {
+    pub transactions: Vec<AddressTx>,
+    pub tip: u64,
+}
+
+pub enum PriceQuote {
+    Fresh { price: f64, validators: Validators },
+    // 304 to a conditional request: the cached value is still current
+    NotModified,
+}
+
+// reqwest's fetch-based futures aren't Send on wasm32, so the traits drop that bound there
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait PriceProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Cheap endpoint pinged by `status`; None for providers without one
+    fn status_url(&self) -> Option<String> {
+        None
+    }
+
+    fn supports(&self, _crypto: &str) -> bool {
+        true
+    }
+
+    fn plan(&self, client: &Client, crypto: &str, fiat: &str, validators: Option<&Validators>) -> Planned;
+
+    // `validators` come from a stale cache entry and may be sent as a conditional request
+    async fn price(
+        &self,
+        client: &Client,
+        crypto: &str,
+        fiat: &str,
+        validators: Option<&Validators>,
+        verbose: bool,
+    ) -> Result<PriceQuote, CryptoError>;
+
+    // Prices of ERC-20 contracts on `crypto`'s chain, keyed by lowercase contract address.
+    // Contracts the provider has no price for are left out.
+    async fn token_prices(
+        &self,
+        _client: &Client,
+        _crypto: &str,
+        _contracts: &[String],
+        _fiat: &str,
+    ) -> Result<HashMap<String, TokenQuote>, CryptoError> {
+        Err(CryptoError::api(self.name(), "no token prices"))
+    }
+
+    // Most coins one `prices` call takes; 1 for providers without a batch endpoint
+    fn batch_limit(&self) -> usize {
+        1
+    }
+
+    fn plan_batch(&self, _client: &Client, _cryptos: &[String], _fiat: &str) -> Option<Planned> {
+        None
+    }
+
+    // Up to `batch_limit` coins in a single request, keyed by coin. Coins the provider has no
+    // price for are left out of the result.
+    async fn prices(&self, _client: &Client, _cryptos: &[String], _fiat: &str, _verbose: bool) -> Result<HashMap<String, f64>, CryptoError> {
+        Err(CryptoError::api(self.name(), "no batch endpoint"))
+    }
+
+    // None for providers without price history
+    fn plan_closes(&self, _client: &Client, _crypto: &str, _fiat: &str, _days: u32) -> Option<Planned> {
+        None
+    }
+
+    // Daily closes for the last `days` days and today, oldest first
+    async fn closes(&self, _client: &Client, _crypto: &str, _fiat: &str, _days: u32, _verbose: bool) -> Result<Vec<DailyClose>, CryptoError> {
+        Err(CryptoError::api(self.name(), "no price history"))
+    }
+
+    // None for providers without volume history
+    fn plan_volumes(&self, _client: &Client, _crypto: &str, _fiat: &str, _days: u32) -> Option<Planned> {
+        None
+    }
+
+    // Daily volumes for the last `days` days and today, oldest first
+    async fn volumes(&self, _client: &Client, _crypto: &str, _fiat: &str, _days: u32, _verbose: bool) -> Result<Vec<DailyVolume>, CryptoError> {
+        Err(CryptoError::api(self.name(), "no volume history"))
+    }
+
+    // None for providers without market-wide totals
+    fn plan_global(&self, _client: &Client, _fiat: &str) -> Option<Planned> {
+        None
+    }
+
+    async fn global(&self, _client: &Client, _fiat: &str, _verbose: bool) -> Result<GlobalMarket, CryptoError> {
+        Err(CryptoError::api(self.name(), "no global market data"))
+    }
+
+    // Likewise for trending coins, hottest first
+    fn plan_trending(&self, _client: &Client) -> Option<Planned> {
+        None
+    }
+
+    async fn trending(&self, _client: &Client, _verbose: bool) -> Result<Vec<TrendingCoin>, CryptoError> {
+        Err(CryptoError::api(self.name(), "no trending coins"))
+    }
+
+    // Likewise for the list of every coin id the provider prices
+    fn plan_coin_list(&self, _client: &Client) -> Option<Planned> {
+        None
+    }
+
+    async fn coin_list(&self, _client: &Client, _verbose: bool) -> Result<Vec<CoinListing>, CryptoError> {
+        Err(CryptoError::api(self.name(), "no coin list"))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait BalanceProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Cheap endpoint pinged by `status`; None for providers without one
+    fn status_url(&self) -> Option<String> {
+        None
+    }
+
+    fn supports(&self, crypto: &str) -> bool;
+
+    // For providers that don't index every kind of address on a chain they support
+    fn supports_address(&self, crypto: &str, _address: &str) -> bool {
+        self.supports(crypto)
+    }
+
+    fn plan(&self, client: &Client, crypto: &str, address: &str) -> Planned;
+
+    async fn balance(&self, client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError>;
+
+    // Most addresses one `balances` call takes; 1 for providers without a batch endpoint
+    fn batch_limit(&self) -> usize {
+        1
+    }
+
+    fn plan_batch(&self, _client: &Client, _crypto: &str, _addresses: &[String]) -> Option<Planned> {
+        None
+    }
+
+    // Up to `batch_limit` addresses of one coin in a single request. Addresses the provider
+    // doesn't know are left out of the result.
+    async fn balances(
+        &self,
+        _client: &Client,
+        _crypto: &str,
+        _addresses: &[String],
+        _verbose: bool,
+    ) -> Result<HashMap<String, f64>, CryptoError> {
+        Err(CryptoError::api(self.name(), "no batch endpoint"))
+    }
+
+    // Providers that can read ERC-20 balances on `crypto`'s chain
+    fn supports_tokens(&self, _crypto: &str) -> bool {
+        false
+    }
+
+    fn plan_token(&self, _client: &Client, _crypto: &str, _address: &str, _token: &Token) -> Option<Planned> {
+        None
+    }
+
+    async fn token_balance(
+        &self,
+        _client: &Client,
+        _crypto: &str,
+        _address: &str,
+        _token: &Token,
+        _verbose: bool,
+    ) -> Result<f64, CryptoError> {
+        Err(CryptoError::api(self.name(), "no token balances"))
+    }
+
+    // Providers that can list every token an address holds
+    fn discovers_tokens(&self, _crypto: &str) -> bool {
+        false
+    }
+
+    async fn holdings(&self, _client: &Client, _crypto: &str, _address: &str, _verbose: bool) -> Result<Vec<Holding>, CryptoError> {
+        Err(CryptoError::api(self.name(), "can't list tokens"))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait SwapProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn supports(&self, chain_id: u64) -> bool;
+
+    fn plan(&self, client: &Client, chain_id: u64, sell: &Token, buy: &Token, amount: f64) -> Planned;
+
+    // Selling `amount` of `sell` for `buy` on the chain with EIP-155 id `chain_id`
+    async fn quote(
+        &self,
+        client: &Client,
+        chain_id: u64,
+        sell: &Token,
+        buy: &Token,
+        amount: f64,
+        verbose: bool,
+    ) -> Result<SwapQuote, CryptoError>;
+}
+
+// Reference exchange rates between fiat currencies
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait FxProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn plan(&self, client: &Client, from: &str, to: &str) -> Planned;
+
+    // How many of `to` one `from` buys
+    async fn rate(&self, client: &Client, from: &str, to: &str, verbose: bool) -> Result<f64, CryptoError>;
+}
+
+// A custodial exchange account, read through its signed REST API
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait ExchangeProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn plan(&self, client: &Client) -> Planned;
+
+    // Nonzero balances by the exchange's ticker for the asset (BTC, ETH, USD, ...)
+    async fn holdings(&self, client: &Client, verbose: bool) -> Result<Vec<(String, f64)>, CryptoError>;
+
+    // The first request of a history import
+    fn plan_history(&self, client: &Client) -> Planned;
+
+    // Trades, deposits and withdrawals, in no particular order
+    async fn history(&self, client: &Client, verbose: bool) -> Result<Vec<crate::transactions::Transaction>, CryptoError>;
+}
+
+// An exchange's public market data, which needs no account
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait TickerProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn plan(&self, client: &Client, base: &str, quote: &str) -> Planned;
+
+    // `base` and `quote` are uppercase tickers, like BTC and USD
+    async fn ticker(&self, client: &Client, base: &str, quote: &str, verbose: bool) -> Result<Ticker, CryptoError>;
+
+    fn plan_book(&self, client: &Client, base: &str, quote: &str, depth: usize) -> Planned;
+
+    async fn book(&self, client: &Client, base: &str, quote: &str, depth: usize, verbose: bool) -> Result<OrderBook, CryptoError>;
+}
+
+// A derivatives exchange's public funding rates
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait FundingProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn plan(&self, client: &Client, symbol: &str) -> Planned;
+
+    // `symbol` is the exchange's perpetual, like BTCUSDT
+    async fn funding(&self, client: &Client, symbol: &str, verbose: bool) -> Result<Funding, CryptoError>;
+}
+
+// Fee recommendations, for `fees`
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait FeeProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn supports(&self, crypto: &str) -> bool;
+
+    fn plan_fees(&self, client: &Client, crypto: &str) -> Planned;
+
+    async fn fees(&self, client: &Client, crypto: &str, verbose: bool) -> Result<Fees, CryptoError>;
+}
+
+// Chain tips, blocks and mining stats, for `tip`, `block`, `halving` and `network`
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait ChainProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn supports(&self, crypto: &str) -> bool;
+
+    fn plan_tip(&self, client: &Client, crypto: &str) -> Planned;
+
+    async fn tip(&self, client: &Client, crypto: &str, verbose: bool) -> Result<ChainTip, CryptoError>;
+
+    fn plan_network(&self, client: &Client, crypto: &str) -> Planned;
+
+    async fn network(&self, client: &Client, crypto: &str, verbose: bool) -> Result<NetworkStats, CryptoError>;
+
+    fn plan_block(&self, client: &Client, crypto: &str, block: &BlockId) -> Planned;
+
+    async fn block(&self, client: &Client, crypto: &str, block: &BlockId, verbose: bool) -> Result<Block, CryptoError>;
+}
+
+// An address's latest transactions, for `watch-address`, and the chain's, for `whales`
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait HistoryProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn supports(&self, crypto: &str) -> bool;
+
+    // The transaction listing and anything else needed to count confirmations
+    fn plan(&self, client: &Client, crypto: &str, address: &str) -> Vec<Planned>;
+
+    async fn history(&self, client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<AddressHistory, CryptoError>;
+
+    fn plan_recent(&self, client: &Client, crypto: &str) -> Planned;
+
+    // The transactions that most recently reached the mempool
+    async fn recent(&self, client: &Client, crypto: &str, verbose: bool) -> Result<Vec<MempoolTx>, CryptoError>;
+}
+
+// Balances as they stood in the past, for `address-balance --at/--height`
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait PastBalanceProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn supports(&self, crypto: &str) -> bool;
+
+    // The first requests; the rest depend on what those return
+    fn plan_balance_at(&self, client: &Client, crypto: &str, address: &str, at: &AsOf) -> Vec<Planned>;
+
+    async fn balance_at(&self, client: &Client, crypto: &str, address: &str, at: &AsOf, verbose: bool) -> Result<f64, CryptoError>;
+}
+
+// An address's unspent outputs, for `utxo-report`
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait UtxoProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn supports(&self, crypto: &str) -> bool;
+
+    // The address listing and anything else the report needs, e.g. a fee suggestion
+    fn plan(&self, client: &Client, crypto: &str, address: &str) -> Vec<Planned>;
+
+    async fn utxos(&self, client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<UtxoSet, CryptoError>;
+}
+
+// A market sentiment index, one value a day
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+pub trait SentimentProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn plan(&self, client: &Client, days: usize) -> Planned;
+
+    // The last `days` values, today's first
+    async fn history(&self, client: &Client, days: usize, verbose: bool) -> Result<Vec<f64>, CryptoError>;
+}
+
+struct Endpoints {
+    coingecko: String,
+    blockcypher: String,
+    blockchair: String,
+    covalent: String,
+    esplora: String,
+    frankfurter: String,
+    zerox: String,
+    binance: String,
+    binance_futures: String,
+    bybit: String,
+    alternative: String,
+    kraken: String,
+    coinbase: String,
+}
+
+impl Endpoints {
+    fn new(config: &Config) -> Self {
+        let mut endpoints = Endpoints {
+            coingecko: "https://api.coingecko.com/api/v3".to_string(),
+            blockcypher: "https://api.blockcypher.com/v1".to_string(),
+            blockchair: "https://api.blockchair.com".to_string(),
+            covalent: "https://api.covalenthq.com/v1".to_string(),
+            esplora: "https://blockstream.info/api".to_string(),
+            frankfurter: "https://api.frankfurter.app".to_string(),
+            zerox: "https://api.0x.org".to_string(),
+            binance: "https://api.binance.com".to_string(),
+            binance_futures: "https://fapi.binance.com".to_string(),
+            bybit: "https://api.bybit.com".to_string(),
+            alternative: "https://api.alternative.me".to_string(),
+            kraken: "https://api.kraken.com".to_string(),
+            coinbase: "https://api.coinbase.com".to_string(),
+        };
+        if config.tor.enabled {
+            for (provider, url) in &config.tor.onion {
+                endpoints.set(provider, url, "tor.onion");
+            }
+        }
+        // Explicit overrides go last so they win over .onion addresses
+        for (provider, url) in &config.endpoints {
+            endpoints.set(provider, url, "endpoints");
+        }
+        endpoints
+    }
+
+    fn set(&mut self, provider: &str, url: &str, section: &str) {
+        let url = url.trim_end_matches('/').to_string();
+        match provider {
+            "coingecko" => self.coingecko = url,
+            "blockcypher" => self.blockcypher = url,
+            "blockchair" => self.blockchair = url,
+            "covalent" => self.covalent = url,
+            "esplora" => self.esplora = url,
+            "frankfurter" => self.frankfurter = url,
+            "0x" => self.zerox = url,
+            "binance" => self.binance = url,
+            "binance-futures" => self.binance_futures = url,
+            "bybit" => self.bybit = url,
+            "alternative" => self.alternative = url,
+            "kraken" => self.kraken = url,
+            "coinbase" => self.coinbase = url,
+            _ => eprintln!("Warning: unknown provider '{}' in {}, ignoring", provider, section),
+        }
+    }
+}
+
+// Providers in the order they're tried; plugins go first for the coins they declare
+pub struct Providers {
+    pub price: Vec<Box<dyn PriceProvider>>,
+    pub balance: Vec<Box<dyn BalanceProvider>>,
+    pub swap: Vec<Box<dyn SwapProvider>>,
+    pub fx: Vec<Box<dyn FxProvider>>,
+    // One per [exchanges] table
+    #[cfg(not(target_arch = "wasm32"))]
+    pub exchanges: Vec<Box<dyn ExchangeProvider>>,
+    // Every exchange, with or without credentials
+    #[cfg(not(target_arch = "wasm32"))]
+    pub tickers: Vec<Box<dyn TickerProvider>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub funding: Vec<Box<dyn FundingProvider>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub sentiment: Vec<Box<dyn SentimentProvider>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub utxo: Vec<Box<dyn UtxoProvider>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub history: Vec<Box<dyn HistoryProvider>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub chain: Vec<Box<dyn ChainProvider>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fees: Vec<Box<dyn FeeProvider>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub past: Vec<Box<dyn PastBalanceProvider>>,
+    // Shared by the HTTP providers above
+    pub transport: Arc<Transport>,
+}
+
+impl Providers {
+    pub fn from_config(config: &Config) -> Result<Self, CryptoError> {
+        let endpoints = Endpoints::new(config);
+        #[cfg(not(target_arch = "wasm32"))]
+        let credentials = exchange::credentials(config)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let secrets: Vec<String> = credentials.iter().flat_map(|c| [c.key.clone(), c.secret.clone()]).collect();
+        #[cfg(target_arch = "wasm32")]
+        let secrets = Vec::new();
+        let transport = Arc::new(Transport::from_config(config, &secrets)?);
+        let mut price: Vec<Box<dyn PriceProvider>> = Vec::new();
+        let mut balance: Vec<Box<dyn BalanceProvider>> = Vec::new();
+
+        // Plugins are subprocesses, which the browser doesn't have
+        #[cfg(not(target_arch = "wasm32"))]
+        for plugin in &config.plugins {
+            let provider = plugin::Plugin::new(plugin, config.network.timeout_secs);
+            if plugin.provides.contains(&PluginCapability::Price) {
+                price.push(Box::new(provider.clone()));
+            }
+            if plugin.provides.contains(&PluginCapability::Balance) {
+                balance.push(Box::new(provider));
+            }
+        }
+
+        price.push(Box::new(coingecko::CoinGecko::new(endpoints.coingecko, transport.clone())));
+        // The user's own endpoints and chains go ahead of the public explorers for the coins
+        // they cover
+        let mut rpc_endpoints: HashMap<String, rpc::Endpoint> = config
+            .rpc
+            .iter()
+            .map(|(coin, url)| (coin.clone(), rpc::Endpoint { url: url.clone(), decimals: 18 }))
+            .collect();
+        let mut explorers: Vec<Box<dyn BalanceProvider>> = Vec::new();
+        for chain in &config.chains {
+            match (&chain.rpc, &chain.explorer) {
+                (Some(url), None) => {
+                    rpc_endpoints.insert(chain.name.clone(), rpc::Endpoint { url: url.clone(), decimals: chain.decimals });
+                }
+                (None, Some(url)) => explorers.push(Box::new(explorer::Explorer::new(chain, url, transport.clone()))),
+                _ => {
+                    return Err(CryptoError::ConfigError(format!(
+                        "Chain '{}' needs exactly one of `rpc` and `explorer`",
+                        chain.name
+                    )));
+                }
+            }
+        }
+        // The user's nodes also go first for gas prices
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut fees: Vec<Box<dyn FeeProvider>> = Vec::new();
+        // Past balances need the node's archive state, so only the user's own nodes are asked
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut past: Vec<Box<dyn PastBalanceProvider>> = Vec::new();
+        if !rpc_endpoints.is_empty() {
+            let rpc = rpc::Rpc::new(rpc_endpoints, transport.clone());
+            #[cfg(not(target_arch = "wasm32"))]
+            fees.push(Box::new(rpc.clone()));
+            #[cfg(not(target_arch = "wasm32"))]
+            past.push(Box::new(rpc.clone()));
+            balance.push(Box::new(rpc));
+        }
+        balance.extend(explorers);
+        // Covalent has no anonymous tier; with a key it goes first for the EVM chains it covers
+        if transport.key_ring("covalent").is_some() {
+            balance.push(Box::new(covalent::Covalent::new(endpoints.covalent, transport.clone())));
+        }
+        balance.push(Box::new(blockcypher::BlockCypher::new(endpoints.blockcypher, transport.clone())));
+        balance.push(Box::new(blockchair::Blockchair::new(endpoints.blockchair.clone(), transport.clone())));
+
+        // 0x has no anonymous tier either
+        let mut swap: Vec<Box<dyn SwapProvider>> = Vec::new();
+        if transport.key_ring("0x").is_some() {
+            swap.push(Box::new(zerox::ZeroEx::new(endpoints.zerox, transport.clone())));
+        }
+        let fx: Vec<Box<dyn FxProvider>> = vec![Box::new(fx::Frankfurter::new(endpoints.frankfurter, transport.clone()))];
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let tickers: Vec<Box<dyn TickerProvider>> = vec![
+            Box::new(ticker::BinanceTicker::new(endpoints.binance.clone(), transport.clone())),
+            Box::new(ticker::KrakenTicker::new(endpoints.kraken.clone(), transport.clone())),
+            Box::new(ticker::CoinbaseTicker::new(endpoints.coinbase.clone(), transport.clone())),
+        ];
+        #[cfg(not(target_arch = "wasm32"))]
+        let funding: Vec<Box<dyn FundingProvider>> = vec![
+            Box::new(funding::BinanceFutures::new(endpoints.binance_futures.clone(), transport.clone())),
+            Box::new(funding::Bybit::new(endpoints.bybit.clone(), transport.clone())),
+        ];
+        #[cfg(not(target_arch = "wasm32"))]
+        let sentiment: Vec<Box<dyn SentimentProvider>> =
+            vec![Box::new(sentiment::AlternativeMe::new(endpoints.alternative.clone(), transport.clone()))];
+        #[cfg(not(target_arch = "wasm32"))]
+        let utxo: Vec<Box<dyn UtxoProvider>> = vec![Box::new(utxo::BlockchairUtxos::new(endpoints.blockchair.clone(), transport.clone()))];
+        #[cfg(not(target_arch = "wasm32"))]
+        let history: Vec<Box<dyn HistoryProvider>> = vec![Box::new(esplora::Esplora::new(endpoints.esplora.clone(), transport.clone()))];
+        #[cfg(not(target_arch = "wasm32"))]
+        let chain: Vec<Box<dyn ChainProvider>> = vec![Box::new(chain::BlockchairChain::new(endpoints.blockchair.clone(), transport.clone()))];
+        #[cfg(not(target_arch = "wasm32"))]
+        fees.extend([
+            Box::new(esplora::Esplora::new(endpoints.esplora.clone(), transport.clone())) as Box<dyn FeeProvider>,
+            Box::new(chain::BlockchairChain::new(endpoints.blockchair.clone(), transport.clone())),
+        ]);
+        #[cfg(not(target_arch = "wasm32"))]
+        past.push(Box::new(esplora::Esplora::new(endpoints.esplora.clone(), transport.clone())));
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut exchanges: Vec<Box<dyn ExchangeProvider>> = Vec::new();
+        #[cfg(not(target_arch = "wasm32"))]
+        for credentials in credentials {
+            let transport = transport.clone();
+            match credentials.exchange.as_str() {
+                "binance" => exchanges.push(Box::new(exchange::Binance::new(endpoints.binance.clone(), credentials, transport))),
+                "kraken" => exchanges.push(Box::new(exchange::Kraken::new(endpoints.kraken.clone(), credentials, transport)?)),
+                _ => exchanges.push(Box::new(exchange::Coinbase::new(endpoints.coinbase.clone(), credentials, transport))),
+            }
+        }
+
+        Ok(Providers {
+            price,
+            balance,
+            swap,
+            fx,
+            #[cfg(not(target_arch = "wasm32"))]
+            exchanges,
+            #[cfg(not(target_arch = "wasm32"))]
+            tickers,
+            #[cfg(not(target_arch = "wasm32"))]
+            funding,
+            #[cfg(not(target_arch = "wasm32"))]
+            sentiment,
+            #[cfg(not(target_arch = "wasm32"))]
+            utxo,
+            #[cfg(not(target_arch = "wasm32"))]
+            history,
+            #[cfg(not(target_arch = "wasm32"))]
+            chain,
+            #[cfg(not(target_arch = "wasm32"))]
+            fees,
+            #[cfg(not(target_arch = "wasm32"))]
+            past,
+            transport,
+        })
+    }
+}
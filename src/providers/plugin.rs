@@ -0,0 +1,155 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{Value, json};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::{BalanceProvider, Planned, PriceProvider, PriceQuote};
+use crate::CryptoError;
+use crate::cache::Validators;
+use crate::config::PluginConfig;
+
+// External provider speaking a one-shot JSON protocol: coinwagon spawns the command,
+// writes a single request object to stdin and reads a single response object from stdout.
+//
+//   -> {"method": "price", "crypto": "mycoin", "fiat": "usd"}
+//   <- {"price": 1.23}
+//   -> {"method": "balance", "crypto": "mycoin", "address": "..."}
+//   <- {"balance": 4.56}
+//   <- {"error": "message"}          (either method, on failure)
+//
+// Plugins do their own networking; coinwagon's proxy/Tor settings don't apply to them.
+#[derive(Clone)]
+pub struct Plugin {
+    config: PluginConfig,
+    timeout: Duration,
+}
+
+impl Plugin {
+    pub fn new(config: &PluginConfig, timeout_secs: u64) -> Self {
+        Plugin {
+            config: config.clone(),
+            timeout: Duration::from_secs(timeout_secs),
+        }
+    }
+
+    fn handles(&self, crypto: &str) -> bool {
+        self.config.coins.iter().any(|coin| coin == crypto)
+    }
+
+    fn planned(&self, request: Value) -> Planned {
+        Planned::Command {
+            command: std::iter::once(&self.config.command).chain(&self.config.args).cloned().collect::<Vec<_>>().join(" "),
+            stdin: request.to_string(),
+        }
+    }
+
+    async fn call(&self, request: Value, field: &str, verbose: bool) -> Result<f64, CryptoError> {
+        let name = &self.config.name;
+        if verbose {
+            eprintln!("Calling plugin {}: {}", name, request);
+        }
+        let mut child = Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| CryptoError::api(name, format!("failed to start: {}", e)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(format!("{}\n", request).as_bytes())
+                .await
+                .map_err(|e| CryptoError::api(name, format!("failed to write request: {}", e)))?;
+        }
+
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .map_err(|_| CryptoError::ProviderUnavailable {
+                provider: name.clone(),
+                reason: format!("timed out after {:?}", self.timeout),
+            })?
+            .map_err(|e| CryptoError::api(name, format!("failed: {}", e)))?;
+        if !output.status.success() {
+            return Err(CryptoError::api(
+                name,
+                format!(
+                    "exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+
+        let response: Value = serde_json::from_slice(&output.stdout)
+            .map_err(|e| CryptoError::api(name, format!("returned invalid JSON: {}", e)))?;
+        if let Some(error) = response.get("error").and_then(|e| e.as_str()) {
+            return Err(CryptoError::api(name, error));
+        }
+        response
+            .get(field)
+            .and_then(|v| v.as_f64())
+            .ok_or_else(|| CryptoError::api(name, format!("response is missing \"{}\"", field)))
+    }
+}
+
+#[async_trait]
+impl PriceProvider for Plugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        self.handles(crypto)
+    }
+
+    fn plan(&self, _client: &Client, crypto: &str, fiat: &str, _validators: Option<&Validators>) -> Planned {
+        self.planned(price_request(crypto, fiat))
+    }
+
+    async fn price(
+        &self,
+        _client: &Client,
+        crypto: &str,
+        fiat: &str,
+        _validators: Option<&Validators>,
+        verbose: bool,
+    ) -> Result<PriceQuote, CryptoError> {
+        let price = self.call(price_request(crypto, fiat), "price", verbose).await?;
+        Ok(PriceQuote::Fresh {
+            price,
+            validators: Validators::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl BalanceProvider for Plugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        self.handles(crypto)
+    }
+
+    fn plan(&self, _client: &Client, crypto: &str, address: &str) -> Planned {
+        self.planned(balance_request(crypto, address))
+    }
+
+    async fn balance(&self, _client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError> {
+        self.call(balance_request(crypto, address), "balance", verbose).await
+    }
+}
+
+fn price_request(crypto: &str, fiat: &str) -> Value {
+    json!({"method": "price", "crypto": crypto, "fiat": fiat})
+}
+
+fn balance_request(crypto: &str, address: &str) -> Value {
+    json!({"method": "balance", "crypto": crypto, "address": address})
+}
@@ -0,0 +1,276 @@
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::{AsOf, FeeProvider, FeeTier, Fees, PastBalanceProvider};
+use super::{BalanceProvider, Planned, Token, Transport, decode};
+use crate::CryptoError;
+
+const NAME: &str = "RPC";
+
+#[derive(Deserialize)]
+struct Reply<T> {
+    result: T,
+}
+
+// eth_feeHistory: a base fee per block plus the next block's, and the tips paid at each of the
+// asked-for percentiles, all as hex wei
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeHistory {
+    base_fee_per_gas: Vec<String>,
+    reward: Vec<Vec<String>>,
+}
+
+// Blocks and tip percentiles behind the slow, normal and fast gas prices
+#[cfg(not(target_arch = "wasm32"))]
+const FEE_BLOCKS: u32 = 20;
+#[cfg(not(target_arch = "wasm32"))]
+const FEE_PERCENTILES: [(&str, u32); 3] = [("fast", 90), ("normal", 50), ("slow", 10)];
+
+// eth_getBlockByNumber without the transactions; null past the chain's head
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Deserialize)]
+struct BlockHeader {
+    timestamp: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: RpcError,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.error.message, self.error.code)
+    }
+}
+
+#[derive(Clone)]
+pub struct Endpoint {
+    pub url: String,
+    // Of the chain's gas coin; 18 on every EVM chain coinwagon knows
+    pub decimals: i32,
+}
+
+// JSON-RPC against the user's own node or node-provider endpoint (Alchemy, Infura, Ankr, ...),
+// one URL per EVM coin from the [rpc] table or an `rpc` chain in [[chains]]. Those URLs
+// usually carry the account's key in their path, so they're treated as secrets.
+#[derive(Clone)]
+pub struct Rpc {
+    endpoints: HashMap<String, Endpoint>,
+    transport: Arc<Transport>,
+}
+
+impl Rpc {
+    pub fn new(endpoints: HashMap<String, Endpoint>, transport: Arc<Transport>) -> Self {
+        Rpc { endpoints, transport }
+    }
+
+    fn request(&self, client: &Client, crypto: &str, method: &str, params: Value) -> RequestBuilder {
+        let url = self.endpoints.get(crypto).map(|endpoint| endpoint.url.as_str()).unwrap_or_default();
+        client.post(url).json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+    }
+
+    // Runs `method` on `crypto`'s endpoint, e.g. eth_getBalance or eth_call, and returns its
+    // result, hex-encoded for those two
+    async fn call<T: DeserializeOwned>(&self, client: &Client, crypto: &str, method: &str, params: Value) -> Result<T, CryptoError> {
+        let resp = self.transport.send(NAME, self.request(client, crypto, method, params)).await?;
+        // Named rather than quoted, since the URL holds the key
+        let endpoint = format!("the {} endpoint", crypto);
+        let Reply { result } = decode::<_, ErrorBody>(NAME, &endpoint, &resp.text().await?)?;
+        Ok(result)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl BalanceProvider for Rpc {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        self.endpoints.contains_key(crypto)
+    }
+
+    fn plan(&self, client: &Client, crypto: &str, address: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, crypto, "eth_getBalance", json!([address, "latest"]))))
+    }
+
+    async fn balance(&self, client: &Client, crypto: &str, address: &str, verbose: bool) -> Result<f64, CryptoError> {
+        let balance = self.balance_in(client, crypto, address, "latest").await?;
+        if verbose {
+            eprintln!("Fetched balance from RPC: {} {}", balance, crypto.to_uppercase());
+        }
+        Ok(balance)
+    }
+
+    fn supports_tokens(&self, crypto: &str) -> bool {
+        self.endpoints.contains_key(crypto)
+    }
+
+    fn plan_token(&self, client: &Client, crypto: &str, address: &str, token: &Token) -> Option<Planned> {
+        Some(Planned::Http(Box::new(self.request(client, crypto, "eth_call", balance_of(address, token)))))
+    }
+
+    async fn token_balance(&self, client: &Client, crypto: &str, address: &str, token: &Token, verbose: bool) -> Result<f64, CryptoError> {
+        let result: String = self.call(client, crypto, "eth_call", balance_of(address, token)).await?;
+        let balance = self.quantity("balanceOf", &result)? / 10f64.powi(token.decimals);
+        if verbose {
+            eprintln!("Fetched {} balance from RPC: {}", token.symbol, balance);
+        }
+        Ok(balance)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn fee_history_params() -> Value {
+    json!([format!("0x{:x}", FEE_BLOCKS), "latest", FEE_PERCENTILES.iter().rev().map(|(_, p)| p).collect::<Vec<_>>()])
+}
+
+// The next block's base fee plus the average tip at each percentile, in gwei
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl FeeProvider for Rpc {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        self.endpoints.contains_key(crypto)
+    }
+
+    fn plan_fees(&self, client: &Client, crypto: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, crypto, "eth_feeHistory", fee_history_params())))
+    }
+
+    async fn fees(&self, client: &Client, crypto: &str, _verbose: bool) -> Result<Fees, CryptoError> {
+        let history: FeeHistory = self.call(client, crypto, "eth_feeHistory", fee_history_params()).await?;
+        let base = match history.base_fee_per_gas.last() {
+            Some(hex) => self.quantity("eth_feeHistory", hex)?,
+            None => return Err(CryptoError::api(NAME, "eth_feeHistory returned no base fees")),
+        };
+        let mut tiers = Vec::new();
+        // Percentiles were asked for from lowest to highest
+        for (i, (name, _)) in FEE_PERCENTILES.iter().rev().enumerate() {
+            let tips = history.reward.iter().filter_map(|block| block.get(i)).map(|hex| self.quantity("eth_feeHistory", hex));
+            let tips = tips.collect::<Result<Vec<f64>, _>>()?;
+            let tip = if tips.is_empty() { 0.0 } else { tips.iter().sum::<f64>() / tips.len() as f64 };
+            tiers.push(FeeTier { name, rate: crate::tidy((base + tip) / 1e9), blocks: None });
+        }
+        tiers.reverse();
+        Ok(Fees { unit: "gwei", tiers })
+    }
+}
+
+// eth_getBalance at a past block, which needs an archive node. A time is taken to mean the last
+// block mined by then, found by bisecting block timestamps.
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl PastBalanceProvider for Rpc {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        self.endpoints.contains_key(crypto)
+    }
+
+    fn plan_balance_at(&self, client: &Client, crypto: &str, address: &str, at: &AsOf) -> Vec<Planned> {
+        let request = match at {
+            AsOf::Height(height) => self.request(client, crypto, "eth_getBalance", json!([address, format!("0x{:x}", height)])),
+            AsOf::Time(_) => self.request(client, crypto, "eth_blockNumber", json!([])),
+        };
+        vec![Planned::Http(Box::new(request))]
+    }
+
+    async fn balance_at(&self, client: &Client, crypto: &str, address: &str, at: &AsOf, verbose: bool) -> Result<f64, CryptoError> {
+        let height = match at {
+            AsOf::Height(height) => *height,
+            AsOf::Time(time) => {
+                let Some(height) = self.block_at(client, crypto, time.timestamp() as f64).await? else {
+                    // Before the first block, nothing was held
+                    return Ok(0.0);
+                };
+                if verbose {
+                    eprintln!("Block {} is the last one mined by {}", height, at);
+                }
+                height
+            }
+        };
+        self.balance_in(client, crypto, address, &format!("0x{:x}", height)).await
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Rpc {
+    async fn timestamp(&self, client: &Client, crypto: &str, height: u64) -> Result<f64, CryptoError> {
+        let header: Option<BlockHeader> = self.call(client, crypto, "eth_getBlockByNumber", json!([format!("0x{:x}", height), false])).await?;
+        let header = header.ok_or_else(|| CryptoError::NotFound { provider: NAME.to_string(), what: format!("{} block {}", crypto, height) })?;
+        self.quantity("eth_getBlockByNumber", &header.timestamp)
+    }
+
+    // The newest block with a timestamp no later than `time`; None when even the first is later
+    async fn block_at(&self, client: &Client, crypto: &str, time: f64) -> Result<Option<u64>, CryptoError> {
+        let head: String = self.call(client, crypto, "eth_blockNumber", json!([])).await?;
+        let head = self.quantity("eth_blockNumber", &head)? as u64;
+        if self.timestamp(client, crypto, head).await? <= time {
+            return Ok(Some(head));
+        }
+        if self.timestamp(client, crypto, 0).await? > time {
+            return Ok(None);
+        }
+        // Block `low` was mined by `time` and block `high` after it
+        let (mut low, mut high) = (0, head);
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            if self.timestamp(client, crypto, middle).await? <= time {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        Ok(Some(low))
+    }
+}
+
+impl Rpc {
+    // The gas coin held at `block`, a block number in hex or a tag like "latest"
+    async fn balance_in(&self, client: &Client, crypto: &str, address: &str, block: &str) -> Result<f64, CryptoError> {
+        let result: String = self.call(client, crypto, "eth_getBalance", json!([address, block])).await?;
+        let wei = self.quantity("eth_getBalance", &result)?;
+        let decimals = self.endpoints.get(crypto).map_or(18, |endpoint| endpoint.decimals);
+        Ok(wei / 10f64.powi(decimals))
+    }
+
+    // Hex quantities and uint256 return values; either may be past u128, so they're read
+    // straight into a float
+    fn quantity(&self, method: &str, hex: &str) -> Result<f64, CryptoError> {
+        let digits = hex.trim_start_matches("0x");
+        digits
+            .chars()
+            .try_fold(0.0, |value, c| Some(value * 16.0 + c.to_digit(16)? as f64))
+            .filter(|_| !digits.is_empty())
+            .ok_or_else(|| CryptoError::api(NAME, format!("{} returned '{}', not a number", method, hex)))
+    }
+}
+
+// eth_call params for ERC-20 balanceOf(address): the selector, then the address padded to 32 bytes
+fn balance_of(address: &str, token: &Token) -> Value {
+    let data = format!("0x70a08231{:0>64}", address.trim_start_matches("0x").to_lowercase());
+    json!([{ "to": token.address, "data": data }, "latest"])
+}
@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{Planned, SentimentProvider, Transport, decode};
+use crate::CryptoError;
+
+// GET /fng/: newest first, one value a day, as strings. metadata.error is set instead of a
+// status code when a request is malformed.
+#[derive(Deserialize)]
+struct FearGreed {
+    data: Vec<FearGreedDay>,
+}
+
+#[derive(Deserialize)]
+struct FearGreedDay {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    metadata: ErrorMetadata,
+}
+
+#[derive(Deserialize)]
+struct ErrorMetadata {
+    error: String,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.metadata.error)
+    }
+}
+
+// alternative.me's Crypto Fear & Greed Index: 0 is extreme fear, 100 extreme greed
+pub struct AlternativeMe {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl AlternativeMe {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        AlternativeMe { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, days: usize) -> (String, RequestBuilder) {
+        let url = format!("{}/fng/", self.base_url);
+        let request = client.get(&url).query(&[("limit", days.to_string())]);
+        (url, request)
+    }
+}
+
+#[async_trait]
+impl SentimentProvider for AlternativeMe {
+    fn name(&self) -> &str {
+        "alternative.me"
+    }
+
+    fn plan(&self, client: &Client, days: usize) -> Planned {
+        Planned::Http(Box::new(self.request(client, days).1))
+    }
+
+    async fn history(&self, client: &Client, days: usize, verbose: bool) -> Result<Vec<f64>, CryptoError> {
+        let (url, request) = self.request(client, days);
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let index: FearGreed = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        let values = index
+            .data
+            .iter()
+            .take(days)
+            .map(|day| day.value.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| CryptoError::api(self.name(), format!("unexpected index values from {}", url)))?;
+        if values.is_empty() {
+            return Err(CryptoError::NotFound { provider: self.name().to_string(), what: "a Fear & Greed value".to_string() });
+        }
+        Ok(values)
+    }
+}
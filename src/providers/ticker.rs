@@ -0,0 +1,309 @@
+use async_trait::async_trait;
+use futures::future::try_join;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::exchange::{BinanceError, CoinbaseError, KrakenReply};
+use super::{OrderBook, Planned, Ticker, TickerProvider, Transport, decode};
+use crate::CryptoError;
+
+// GET /api/v3/ticker/bookTicker: the top of one market's order book
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceBook {
+    bid_price: String,
+    ask_price: String,
+}
+
+// GET /api/v3/depth: levels as [price, quantity]
+#[derive(Deserialize)]
+struct BinanceDepth {
+    bids: Vec<(String, String)>,
+    asks: Vec<(String, String)>,
+}
+
+// Binance's public market data. It has no USD markets, so USD is quoted in USDT.
+pub struct BinanceTicker {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl BinanceTicker {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        BinanceTicker { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, base: &str, quote: &str) -> (String, RequestBuilder) {
+        let url = format!("{}/api/v3/ticker/bookTicker", self.base_url);
+        let request = client.get(&url).query(&[("symbol", format!("{}{}", base, binance_quote(quote)))]);
+        (url, request)
+    }
+
+    fn book_request(&self, client: &Client, base: &str, quote: &str, depth: usize) -> (String, RequestBuilder) {
+        let url = format!("{}/api/v3/depth", self.base_url);
+        let symbol = format!("{}{}", base, binance_quote(quote));
+        let request = client.get(&url).query(&[("symbol", symbol), ("limit", depth.to_string())]);
+        (url, request)
+    }
+}
+
+fn binance_quote(quote: &str) -> &str {
+    if quote == "USD" { "USDT" } else { quote }
+}
+
+#[async_trait]
+impl TickerProvider for BinanceTicker {
+    fn name(&self) -> &str {
+        "Binance"
+    }
+
+    fn plan(&self, client: &Client, base: &str, quote: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, base, quote).1))
+    }
+
+    async fn ticker(&self, client: &Client, base: &str, quote: &str, verbose: bool) -> Result<Ticker, CryptoError> {
+        let (url, request) = self.request(client, base, quote);
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let book: BinanceBook = decode::<_, BinanceError>(self.name(), &url, &body)?;
+        Ok(Ticker {
+            market: format!("{}/{}", base, binance_quote(quote)),
+            bid: price(self.name(), &book.bid_price)?,
+            ask: price(self.name(), &book.ask_price)?,
+        })
+    }
+
+    fn plan_book(&self, client: &Client, base: &str, quote: &str, depth: usize) -> Planned {
+        Planned::Http(Box::new(self.book_request(client, base, quote, depth).1))
+    }
+
+    async fn book(&self, client: &Client, base: &str, quote: &str, depth: usize, verbose: bool) -> Result<OrderBook, CryptoError> {
+        let (url, request) = self.book_request(client, base, quote, depth);
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let book: BinanceDepth = decode::<_, BinanceError>(self.name(), &url, &body)?;
+        Ok(OrderBook {
+            market: format!("{}/{}", base, binance_quote(quote)),
+            bids: levels(self.name(), book.bids.iter().map(|(price, size)| (price, size)), depth)?,
+            asks: levels(self.name(), book.asks.iter().map(|(price, size)| (price, size)), depth)?,
+        })
+    }
+}
+
+// GET /0/public/Ticker: keyed by Kraken's own pair name, with `a` and `b` as [price, whole lot
+// volume, lot volume]
+#[derive(Deserialize)]
+struct KrakenTick {
+    a: Vec<String>,
+    b: Vec<String>,
+}
+
+// GET /0/public/Depth: keyed by pair name like the ticker, levels as [price, volume, timestamp]
+#[derive(Deserialize)]
+struct KrakenDepth {
+    bids: Vec<(String, String, i64)>,
+    asks: Vec<(String, String, i64)>,
+}
+
+pub struct KrakenTicker {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl KrakenTicker {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        KrakenTicker { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, base: &str, quote: &str) -> (String, RequestBuilder) {
+        let url = format!("{}/0/public/Ticker", self.base_url);
+        let request = client.get(&url).query(&[("pair", format!("{}{}", kraken_code(base), quote))]);
+        (url, request)
+    }
+
+    fn book_request(&self, client: &Client, base: &str, quote: &str, depth: usize) -> (String, RequestBuilder) {
+        let url = format!("{}/0/public/Depth", self.base_url);
+        let pair = format!("{}{}", kraken_code(base), quote);
+        let request = client.get(&url).query(&[("pair", pair), ("count", depth.to_string())]);
+        (url, request)
+    }
+
+    // The one pair in a public reply
+    async fn get<T: serde::de::DeserializeOwned>(&self, request: RequestBuilder, url: &str, market: &str, verbose: bool) -> Result<T, CryptoError> {
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let reply: KrakenReply<HashMap<String, T>> = serde_json::from_str(&body)
+            .map_err(|e| CryptoError::api(self.name(), format!("unexpected response from {}: {}", url, e)))?;
+        if !reply.error.is_empty() {
+            return Err(CryptoError::api(self.name(), reply.error.join(", ")));
+        }
+        reply.result.and_then(|result| result.into_values().next()).ok_or_else(|| CryptoError::NotFound {
+            provider: self.name().to_string(),
+            what: format!("a {} market", market),
+        })
+    }
+}
+
+// Kraken still calls bitcoin and dogecoin by their old codes
+fn kraken_code(ticker: &str) -> &str {
+    match ticker {
+        "BTC" => "XBT",
+        "DOGE" => "XDG",
+        _ => ticker,
+    }
+}
+
+#[async_trait]
+impl TickerProvider for KrakenTicker {
+    fn name(&self) -> &str {
+        "Kraken"
+    }
+
+    fn plan(&self, client: &Client, base: &str, quote: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, base, quote).1))
+    }
+
+    async fn ticker(&self, client: &Client, base: &str, quote: &str, verbose: bool) -> Result<Ticker, CryptoError> {
+        let (url, request) = self.request(client, base, quote);
+        let market = format!("{}/{}", base, quote);
+        let tick: KrakenTick = self.get(request, &url, &market, verbose).await?;
+        let top = |side: &[String]| price(self.name(), side.first().map_or("", String::as_str));
+        Ok(Ticker { market, bid: top(&tick.b)?, ask: top(&tick.a)? })
+    }
+
+    fn plan_book(&self, client: &Client, base: &str, quote: &str, depth: usize) -> Planned {
+        Planned::Http(Box::new(self.book_request(client, base, quote, depth).1))
+    }
+
+    async fn book(&self, client: &Client, base: &str, quote: &str, depth: usize, verbose: bool) -> Result<OrderBook, CryptoError> {
+        let (url, request) = self.book_request(client, base, quote, depth);
+        let market = format!("{}/{}", base, quote);
+        let book: KrakenDepth = self.get(request, &url, &market, verbose).await?;
+        Ok(OrderBook {
+            market,
+            bids: levels(self.name(), book.bids.iter().map(|(price, size, _)| (price, size)), depth)?,
+            asks: levels(self.name(), book.asks.iter().map(|(price, size, _)| (price, size)), depth)?,
+        })
+    }
+}
+
+// GET /v2/prices/:pair/buy and /sell: what Coinbase charges to buy and pays to sell, which
+// stand in for the ask and bid
+#[derive(Deserialize)]
+struct CoinbasePrice {
+    data: CoinbaseAmount,
+}
+
+#[derive(Deserialize)]
+struct CoinbaseAmount {
+    amount: String,
+}
+
+// GET /api/v3/brokerage/market/product_book: Advanced Trade's public book
+#[derive(Deserialize)]
+struct CoinbaseBook {
+    pricebook: CoinbasePriceBook,
+}
+
+#[derive(Deserialize)]
+struct CoinbasePriceBook {
+    bids: Vec<CoinbaseLevel>,
+    asks: Vec<CoinbaseLevel>,
+}
+
+#[derive(Deserialize)]
+struct CoinbaseLevel {
+    price: String,
+    size: String,
+}
+
+pub struct CoinbaseTicker {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl CoinbaseTicker {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        CoinbaseTicker { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, base: &str, quote: &str, side: &str) -> (String, RequestBuilder) {
+        let url = format!("{}/v2/prices/{}-{}/{}", self.base_url, base, quote, side);
+        let request = client.get(&url);
+        (url, request)
+    }
+
+    fn book_request(&self, client: &Client, base: &str, quote: &str, depth: usize) -> (String, RequestBuilder) {
+        let url = format!("{}/api/v3/brokerage/market/product_book", self.base_url);
+        let request = client.get(&url).query(&[("product_id", format!("{}-{}", base, quote)), ("limit", depth.to_string())]);
+        (url, request)
+    }
+
+    async fn side(&self, client: &Client, base: &str, quote: &str, side: &str, verbose: bool) -> Result<f64, CryptoError> {
+        let (url, request) = self.request(client, base, quote, side);
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let reply: CoinbasePrice = decode::<_, CoinbaseError>(self.name(), &url, &body)?;
+        price(self.name(), &reply.data.amount)
+    }
+}
+
+#[async_trait]
+impl TickerProvider for CoinbaseTicker {
+    fn name(&self) -> &str {
+        "Coinbase"
+    }
+
+    fn plan(&self, client: &Client, base: &str, quote: &str) -> Planned {
+        Planned::Http(Box::new(self.request(client, base, quote, "buy").1))
+    }
+
+    async fn ticker(&self, client: &Client, base: &str, quote: &str, verbose: bool) -> Result<Ticker, CryptoError> {
+        let (ask, bid) =
+            try_join(self.side(client, base, quote, "buy", verbose), self.side(client, base, quote, "sell", verbose)).await?;
+        Ok(Ticker { market: format!("{}/{}", base, quote), bid, ask })
+    }
+
+    fn plan_book(&self, client: &Client, base: &str, quote: &str, depth: usize) -> Planned {
+        Planned::Http(Box::new(self.book_request(client, base, quote, depth).1))
+    }
+
+    async fn book(&self, client: &Client, base: &str, quote: &str, depth: usize, verbose: bool) -> Result<OrderBook, CryptoError> {
+        let (url, request) = self.book_request(client, base, quote, depth);
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let book: CoinbaseBook = decode::<_, CoinbaseError>(self.name(), &url, &body)?;
+        let side = |levels: &[CoinbaseLevel]| self::levels(self.name(), levels.iter().map(|l| (&l.price, &l.size)), depth);
+        Ok(OrderBook { market: format!("{}/{}", base, quote), bids: side(&book.pricebook.bids)?, asks: side(&book.pricebook.asks)? })
+    }
+}
+
+// The first `depth` (price, size) levels, for exchanges that send more than asked for
+fn levels<'a>(provider: &str, side: impl Iterator<Item = (&'a String, &'a String)>, depth: usize) -> Result<Vec<(f64, f64)>, CryptoError> {
+    side.take(depth)
+        .map(|(value, size)| {
+            let size = size.parse::<f64>().map_err(|_| CryptoError::api(provider, format!("'{}' isn't a size", size)))?;
+            Ok((price(provider, value)?, size))
+        })
+        .collect()
+}
+
+fn price(provider: &str, value: &str) -> Result<f64, CryptoError> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|price| *price > 0.0)
+        .ok_or_else(|| CryptoError::api(provider, format!("'{}' isn't a price", value)))
+}
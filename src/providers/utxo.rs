@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use futures::future::try_join;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::blockchair::ErrorBody;
+use super::{Planned, Transport, Utxo, UtxoProvider, UtxoSet, decode};
+use crate::CryptoError;
+
+// Most UTXOs one address dashboard lists
+const UTXO_LIMIT: usize = 1000;
+
+// /bitcoin/dashboards/address/{address}: the UTXOs come after the address, newest first, and
+// the context has the chain's height
+#[derive(Deserialize)]
+struct Dashboard {
+    data: HashMap<String, AddressDashboard>,
+    context: Context,
+}
+
+#[derive(Deserialize)]
+struct AddressDashboard {
+    address: AddressInfo,
+    utxo: Vec<Output>,
+}
+
+#[derive(Deserialize)]
+struct AddressInfo {
+    unspent_output_count: usize,
+}
+
+// block_id is -1 while unconfirmed
+#[derive(Deserialize)]
+struct Output {
+    block_id: i64,
+    value: u64,
+}
+
+#[derive(Deserialize)]
+struct Context {
+    state: u64,
+}
+
+// /bitcoin/stats
+#[derive(Deserialize)]
+struct Stats {
+    data: StatsData,
+}
+
+#[derive(Deserialize)]
+struct StatsData {
+    suggested_transaction_fee_per_byte_sat: f64,
+}
+
+// Blockchair's UTXO listing and fee suggestion for bitcoin
+pub struct BlockchairUtxos {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl BlockchairUtxos {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        BlockchairUtxos { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, address: &str) -> (String, RequestBuilder) {
+        let url = format!("{}/bitcoin/dashboards/address/{}", self.base_url, address);
+        // Transactions, then UTXOs
+        let request = client.get(&url).query(&[("limit", format!("1,{}", UTXO_LIMIT))]);
+        (url, request)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, url: &str, request: RequestBuilder, verbose: bool) -> Result<T, CryptoError> {
+        let body = self.transport.send(self.name(), request).await?.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        decode::<_, ErrorBody>(self.name(), url, &body)
+    }
+}
+
+#[async_trait]
+impl UtxoProvider for BlockchairUtxos {
+    fn name(&self) -> &str {
+        "Blockchair"
+    }
+
+    fn supports(&self, crypto: &str) -> bool {
+        crypto == "bitcoin"
+    }
+
+    fn plan(&self, client: &Client, _crypto: &str, address: &str) -> Vec<Planned> {
+        let stats = client.get(format!("{}/bitcoin/stats", self.base_url));
+        vec![Planned::Http(Box::new(self.request(client, address).1)), Planned::Http(Box::new(stats))]
+    }
+
+    async fn utxos(&self, client: &Client, _crypto: &str, address: &str, verbose: bool) -> Result<UtxoSet, CryptoError> {
+        let (url, request) = self.request(client, address);
+        let stats_url = format!("{}/bitcoin/stats", self.base_url);
+        let (mut dashboard, stats) = try_join(
+            self.get::<Dashboard>(&url, request, verbose),
+            self.get::<Stats>(&stats_url, client.get(&stats_url), verbose),
+        )
+        .await?;
+        let found = dashboard.data.remove(address).ok_or_else(|| CryptoError::NotFound {
+            provider: self.name().to_string(),
+            what: format!("address {}", address),
+        })?;
+        Ok(UtxoSet {
+            utxos: found
+                .utxo
+                .iter()
+                .map(|output| Utxo { value: output.value, height: u64::try_from(output.block_id).ok() })
+                .collect(),
+            total: found.address.unspent_output_count,
+            tip: dashboard.context.state,
+            fee_rate: stats.data.suggested_transaction_fee_per_byte_sat,
+        })
+    }
+}
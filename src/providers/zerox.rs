@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{Planned, SwapProvider, SwapQuote, Token, Transport, decode};
+use crate::CryptoError;
+
+// /swap/permit2/price: an indicative quote. Amounts are in the tokens' smallest units and
+// `gas`/`totalNetworkFee` are null when 0x couldn't simulate the trade.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Price {
+    liquidity_available: bool,
+    buy_amount: Option<String>,
+    gas: Option<String>,
+    total_network_fee: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    name: String,
+    message: String,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.name)
+    }
+}
+
+// Chain ids the 0x Swap API trades on
+const CHAINS: &[u64] = &[1, 10, 56, 137, 8453, 42161, 43114, 59144, 81457, 534352];
+
+// The 0x Swap API, which routes across DEXes for the best price. Every request needs an API
+// key, so it's only set up when api_keys.0x has one.
+pub struct ZeroEx {
+    base_url: String,
+    transport: Arc<Transport>,
+}
+
+impl ZeroEx {
+    pub fn new(base_url: String, transport: Arc<Transport>) -> Self {
+        ZeroEx { base_url, transport }
+    }
+
+    fn request(&self, client: &Client, chain_id: u64, sell: &Token, buy: &Token, amount: f64) -> (String, RequestBuilder) {
+        let url = format!("{}/swap/permit2/price", self.base_url);
+        let request = client
+            .get(&url)
+            .header("0x-version", "v2")
+            .query(&[
+                ("chainId", chain_id.to_string()),
+                ("sellToken", sell.address.clone()),
+                ("buyToken", buy.address.clone()),
+                ("sellAmount", format!("{:.0}", amount * 10f64.powi(sell.decimals))),
+            ]);
+        (url, request)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl SwapProvider for ZeroEx {
+    fn name(&self) -> &str {
+        "0x"
+    }
+
+    fn supports(&self, chain_id: u64) -> bool {
+        CHAINS.contains(&chain_id)
+    }
+
+    fn plan(&self, client: &Client, chain_id: u64, sell: &Token, buy: &Token, amount: f64) -> Planned {
+        Planned::Http(Box::new(self.request(client, chain_id, sell, buy, amount).1))
+    }
+
+    async fn quote(
+        &self,
+        client: &Client,
+        chain_id: u64,
+        sell: &Token,
+        buy: &Token,
+        amount: f64,
+        verbose: bool,
+    ) -> Result<SwapQuote, CryptoError> {
+        let (url, request) = self.request(client, chain_id, sell, buy, amount);
+        let resp = self.transport.send(self.name(), request).await?;
+        let body = resp.text().await?;
+        if verbose {
+            eprintln!("API Response: {}", body);
+        }
+        let price: Price = decode::<_, ErrorBody>(self.name(), &url, &body)?;
+        let buy_amount = match (price.liquidity_available, price.buy_amount) {
+            (true, Some(amount)) => amount,
+            _ => {
+                return Err(CryptoError::NotFound {
+                    provider: self.name().to_string(),
+                    what: format!("liquidity for {} -> {}", sell.symbol, buy.symbol),
+                });
+            }
+        };
+        let units = |value: &str| {
+            value
+                .parse::<f64>()
+                .map_err(|_| CryptoError::api(self.name(), format!("'{}' from {} isn't a number", value, url)))
+        };
+        Ok(SwapQuote {
+            buy_amount: units(&buy_amount)? / 10f64.powi(buy.decimals),
+            gas: price.gas.as_deref().map(units).transpose()?,
+            // Paid in the chain's gas coin, which has 18 decimals on every chain 0x trades on
+            network_fee: price.total_network_fee.as_deref().map(units).transpose()?.map(|wei| wei / 1e18),
+        })
+    }
+}
@@ -0,0 +1,664 @@
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyString};
+use pyo3_async_runtimes::tokio::{future_into_py, get_runtime};
+use pyo3_stub_gen::create_exception;
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyfunction, gen_stub_pymethods};
+use reqwest::Client;
+use std::sync::{Arc, Mutex};
+
+use crate::commands::run_on;
+use crate::config::Config;
+use crate::providers::GlobalMarket;
+use crate::{Cli, ClientSettings, CryptoError, CryptoTool, Lookup, Output, WalletLine, load_config, render, using_tool};
+
+fn parse_args(command: String, args: Vec<String>) -> PyResult<Cli> {
+    Cli::try_parse_from(std::iter::once("coinwagon".to_string())
+        .chain(std::iter::once(command))
+        .chain(args))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid arguments: {}", e)))
+}
+
+// Subclasses RuntimeError so code written against the old plain RuntimeError keeps working
+create_exception!(coinwagon, CoinWagonError, PyRuntimeError, "Base class for coinwagon errors.");
+create_exception!(coinwagon, ApiError, CoinWagonError, "A provider failed or returned an unusable response.");
+create_exception!(coinwagon, RateLimited, ApiError, "A provider is throttling requests.");
+create_exception!(coinwagon, NotFound, ApiError, "A provider doesn't know the coin, fiat or address.");
+create_exception!(coinwagon, ProviderUnavailable, ApiError, "A provider is down or failing on its side.");
+create_exception!(coinwagon, NetworkError, CoinWagonError, "The HTTP request itself failed.");
+create_exception!(coinwagon, InvalidInput, CoinWagonError, "Bad arguments, wallet file or missing offline data.");
+
+// Errors from a provider carry `provider`, and throttling `retry_after` in seconds (or None),
+// so callers can back off without parsing the message
+fn to_py_err(e: CryptoError) -> PyErr {
+    let message = e.to_string();
+    let provider = e.provider().map(str::to_string);
+    let retry_after = e.retry_after().map(|d| d.as_secs_f64());
+    let err = match e {
+        CryptoError::ApiError { .. } => ApiError::new_err(message),
+        CryptoError::RateLimited { .. } => RateLimited::new_err(message),
+        CryptoError::NotFound { .. } => NotFound::new_err(message),
+        CryptoError::ProviderUnavailable { .. } => ProviderUnavailable::new_err(message),
+        CryptoError::HttpError(_) => NetworkError::new_err(message),
+        CryptoError::InvalidInput(_) => InvalidInput::new_err(message),
+        CryptoError::SystemTimeError(_) | CryptoError::ConfigError(_) | CryptoError::RuntimeError(_) => {
+            CoinWagonError::new_err(message)
+        }
+    };
+    Python::attach(|py| {
+        let value = err.value(py);
+        // Setting attributes on a fresh exception instance can't fail in practice
+        let _ = value.setattr("provider", provider);
+        let _ = value.setattr("retry_after", retry_after);
+    });
+    err
+}
+
+fn native_config(config: Option<&str>, offline: bool) -> PyResult<Config> {
+    let mut config = Config::load(config).map_err(to_py_err)?;
+    config.offline |= offline;
+    Ok(config)
+}
+
+// Module-level calls share the background runtime and one HTTP client, so connections are
+// reused between calls. The client is only rebuilt when the network/TLS settings change.
+static SHARED_CLIENT: Mutex<Option<(ClientSettings, Client)>> = Mutex::new(None);
+
+fn shared_tool(config: &Config) -> PyResult<CryptoTool> {
+    let settings = ClientSettings::from_config(config);
+    let mut shared = SHARED_CLIENT.lock().unwrap();
+    let client = match &*shared {
+        Some((current, client)) if *current == settings => client.clone(),
+        _ => {
+            let client = settings.build().map_err(to_py_err)?;
+            *shared = Some((settings, client.clone()));
+            client
+        }
+    };
+    CryptoTool::with_client(config, client).map_err(to_py_err)
+}
+
+// Runs `f` with a tool for `config` on the shared runtime, with the GIL released
+fn block_on<T: Send>(
+    py: Python<'_>,
+    config: &Config,
+    f: impl AsyncFnOnce(&CryptoTool) -> Result<T, CryptoError> + Send,
+) -> PyResult<T> {
+    block_on_tool(py, &shared_tool(config)?, f)
+}
+
+// `block_on` with a tool of the caller's, as a `CoinWagon` keeps
+fn block_on_tool<T: Send>(
+    py: Python<'_>,
+    tool: &CryptoTool,
+    f: impl AsyncFnOnce(&CryptoTool) -> Result<T, CryptoError> + Send,
+) -> PyResult<T> {
+    let (result, _) = py
+        .detach(|| get_runtime().block_on(using_tool(tool, f)))
+        .map_err(to_py_err)?;
+    Ok(result)
+}
+
+#[gen_stub_pyclass]
+#[pyclass(frozen, get_all, skip_from_py_object, module = "coinwagon")]
+#[derive(Clone)]
+pub struct Price {
+    crypto: String,
+    fiat: String,
+    price: f64,
+    timestamp: DateTime<Utc>,
+    source: String,
+    cached: bool,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl Price {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("crypto", &self.crypto)?;
+        dict.set_item("fiat", &self.fiat)?;
+        dict.set_item("price", self.price)?;
+        dict.set_item("timestamp", self.timestamp)?;
+        dict.set_item("source", &self.source)?;
+        dict.set_item("cached", self.cached)?;
+        Ok(dict)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Price(crypto={:?}, fiat={:?}, price={}, timestamp='{}', source={:?}, cached={})",
+            self.crypto, self.fiat, self.price, self.timestamp.to_rfc3339(), self.source, py_bool(self.cached)
+        )
+    }
+}
+
+#[gen_stub_pyclass]
+#[pyclass(frozen, get_all, skip_from_py_object, module = "coinwagon")]
+#[derive(Clone)]
+pub struct Balance {
+    crypto: String,
+    address: String,
+    balance: f64,
+    timestamp: DateTime<Utc>,
+    source: String,
+    cached: bool,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl Balance {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("crypto", &self.crypto)?;
+        dict.set_item("address", &self.address)?;
+        dict.set_item("balance", self.balance)?;
+        dict.set_item("timestamp", self.timestamp)?;
+        dict.set_item("source", &self.source)?;
+        dict.set_item("cached", self.cached)?;
+        Ok(dict)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Balance(crypto={:?}, address={:?}, balance={}, timestamp='{}', source={:?}, cached={})",
+            self.crypto, self.address, self.balance, self.timestamp.to_rfc3339(), self.source, py_bool(self.cached)
+        )
+    }
+}
+
+#[gen_stub_pyclass]
+#[pyclass(frozen, get_all, skip_from_py_object, module = "coinwagon")]
+#[derive(Clone)]
+pub struct WalletEntry {
+    crypto: String,
+    address: String,
+    // The address's name in the address book
+    name: Option<String>,
+    balance: f64,
+    price: f64,
+    fiat_value: f64,
+    fiat: String,
+    // The older of the balance and price timestamps
+    timestamp: DateTime<Utc>,
+    // Balance provider; the price provider is in `price_source`
+    source: String,
+    price_source: String,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl WalletEntry {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("crypto", &self.crypto)?;
+        dict.set_item("address", &self.address)?;
+        dict.set_item("name", &self.name)?;
+        dict.set_item("balance", self.balance)?;
+        dict.set_item("price", self.price)?;
+        dict.set_item("fiat_value", self.fiat_value)?;
+        dict.set_item("fiat", &self.fiat)?;
+        dict.set_item("timestamp", self.timestamp)?;
+        dict.set_item("source", &self.source)?;
+        dict.set_item("price_source", &self.price_source)?;
+        Ok(dict)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "WalletEntry(crypto={:?}, address={:?}, balance={}, price={}, fiat_value={}, fiat={:?})",
+            self.crypto, self.address, self.balance, self.price, self.fiat_value, self.fiat
+        )
+    }
+}
+
+#[gen_stub_pyclass]
+#[pyclass(frozen, get_all, skip_from_py_object, module = "coinwagon")]
+#[derive(Clone)]
+pub struct Wallet {
+    fiat: String,
+    entries: Vec<WalletEntry>,
+    total: f64,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl Wallet {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("fiat", &self.fiat)?;
+        let entries = self.entries.iter().map(|e| e.to_dict(py)).collect::<PyResult<Vec<_>>>()?;
+        dict.set_item("entries", entries)?;
+        dict.set_item("total", self.total)?;
+        Ok(dict)
+    }
+
+    fn __len__(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Wallet(fiat={:?}, entries={}, total={})", self.fiat, self.entries.len(), self.total)
+    }
+}
+
+#[gen_stub_pyclass]
+#[pyclass(frozen, get_all, skip_from_py_object, module = "coinwagon")]
+#[derive(Clone)]
+pub struct Global {
+    fiat: String,
+    total_market_cap: f64,
+    total_volume_24h: f64,
+    // Percent of the total market cap
+    btc_dominance: f64,
+    eth_dominance: f64,
+    active_coins: u64,
+    // When the provider last totalled the market
+    timestamp: DateTime<Utc>,
+    source: String,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl Global {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("fiat", &self.fiat)?;
+        dict.set_item("total_market_cap", self.total_market_cap)?;
+        dict.set_item("total_volume_24h", self.total_volume_24h)?;
+        dict.set_item("btc_dominance", self.btc_dominance)?;
+        dict.set_item("eth_dominance", self.eth_dominance)?;
+        dict.set_item("active_coins", self.active_coins)?;
+        dict.set_item("timestamp", self.timestamp)?;
+        dict.set_item("source", &self.source)?;
+        Ok(dict)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Global(fiat={:?}, total_market_cap={}, total_volume_24h={}, btc_dominance={}, eth_dominance={}, active_coins={})",
+            self.fiat, self.total_market_cap, self.total_volume_24h, self.btc_dominance, self.eth_dominance, self.active_coins
+        )
+    }
+}
+
+fn py_bool(value: bool) -> &'static str {
+    if value { "True" } else { "False" }
+}
+
+impl Price {
+    pub fn new(crypto: String, fiat: String, quote: Lookup) -> Self {
+        Price {
+            crypto,
+            fiat,
+            price: quote.value,
+            timestamp: quote.fetched_at.into(),
+            source: quote.source,
+            cached: quote.cached,
+        }
+    }
+}
+
+impl Balance {
+    pub fn new(crypto: String, address: String, quote: Lookup) -> Self {
+        Balance {
+            crypto,
+            address,
+            balance: quote.value,
+            timestamp: quote.fetched_at.into(),
+            source: quote.source,
+            cached: quote.cached,
+        }
+    }
+}
+
+impl Wallet {
+    pub fn new(fiat: String, lines: Vec<WalletLine>) -> Self {
+        let entries: Vec<WalletEntry> = lines
+            .into_iter()
+            .map(|line| WalletEntry {
+                fiat_value: line.fiat_value(),
+                crypto: line.crypto,
+                address: line.address,
+                name: line.name,
+                balance: line.balance.value,
+                price: line.price.value,
+                fiat: fiat.clone(),
+                timestamp: line.balance.fetched_at.min(line.price.fetched_at).into(),
+                source: line.balance.source,
+                price_source: line.price.source,
+            })
+            .collect();
+        Wallet {
+            total: entries.iter().map(|e| e.fiat_value).sum(),
+            fiat,
+            entries,
+        }
+    }
+}
+
+impl Global {
+    pub fn new(fiat: String, market: GlobalMarket, source: String) -> Self {
+        Global {
+            fiat,
+            total_market_cap: market.total_market_cap,
+            total_volume_24h: market.total_volume_24h,
+            btc_dominance: market.btc_dominance,
+            eth_dominance: market.eth_dominance,
+            active_coins: market.active_coins,
+            timestamp: market.updated_at.into(),
+            source,
+        }
+    }
+}
+
+fn output_to_py(py: Python<'_>, output: Output) -> PyResult<Py<PyAny>> {
+    Ok(match output {
+        Output::Price { crypto, fiat, quote } => Py::new(py, Price::new(crypto, fiat, quote))?.into_any(),
+        Output::Balance { crypto, address, quote } => Py::new(py, Balance::new(crypto, address, quote))?.into_any(),
+        Output::Wallet { fiat, lines } => Py::new(py, Wallet::new(fiat, lines))?.into_any(),
+        Output::Global { fiat, market, source } => Py::new(py, Global::new(fiat, market, source))?.into_any(),
+        Output::Check { held, .. } => PyBool::new(py, held).to_owned().into_any().unbind(),
+        Output::Text(text) => PyString::new(py, &text).into_any().unbind(),
+    })
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn run_command(py: Python<'_>, command: String, args: Vec<String>) -> PyResult<String> {
+    let cli = parse_args(command, args)?;
+    let tool = shared_tool(&load_config(&cli).map_err(to_py_err)?)?;
+    py.detach(|| get_runtime().block_on(run_on(&tool, cli.command)))
+        .map(render)
+        .map_err(to_py_err)
+}
+
+// Same commands as run_command, but returns Price/Balance/Wallet/Global objects where the
+// command produces data, check-price's answer as a bool, and plain strings for the rest
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[gen_stub(override_return_type(type_repr = "typing.Union[Price, Balance, Wallet, Global, bool, str]", imports = ("typing")))]
+fn query(py: Python<'_>, command: String, args: Vec<String>) -> PyResult<Py<PyAny>> {
+    let cli = parse_args(command, args)?;
+    let tool = shared_tool(&load_config(&cli).map_err(to_py_err)?)?;
+    let (output, _) = py
+        .detach(|| get_runtime().block_on(run_on(&tool, cli.command)))
+        .map_err(to_py_err)?;
+    output_to_py(py, output)
+}
+
+// Native-argument equivalents of the CLI commands; `config` is a config file path
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (crypto, fiat = "usd", *, verbose = false, offline = false, config = None))]
+fn get_price(
+    py: Python<'_>,
+    crypto: &str,
+    fiat: &str,
+    verbose: bool,
+    offline: bool,
+    config: Option<&str>,
+) -> PyResult<Price> {
+    let config = native_config(config, offline)?;
+    let quote = block_on(py, &config, async |tool| tool.lookup_price(crypto, fiat, verbose).await)?;
+    Ok(Price::new(crypto.to_string(), fiat.to_string(), quote))
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (crypto, address, *, verbose = false, offline = false, config = None))]
+fn get_address_balance(
+    py: Python<'_>,
+    crypto: &str,
+    address: &str,
+    verbose: bool,
+    offline: bool,
+    config: Option<&str>,
+) -> PyResult<Balance> {
+    let config = native_config(config, offline)?;
+    let quote = block_on(py, &config, async |tool| tool.lookup_balance(crypto, address, verbose).await)?;
+    Ok(Balance::new(crypto.to_string(), address.to_string(), quote))
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (path, fiat = "usd", *, verbose = false, offline = false, config = None))]
+fn get_wallet_balance(
+    py: Python<'_>,
+    path: &str,
+    fiat: &str,
+    verbose: bool,
+    offline: bool,
+    config: Option<&str>,
+) -> PyResult<Wallet> {
+    let config = native_config(config, offline)?;
+    let lines = block_on(py, &config, async |tool| tool.get_wallet_balance(path, fiat, verbose).await)?;
+    Ok(Wallet::new(fiat.to_string(), lines))
+}
+
+// Awaitable versions of the functions above
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[gen_stub(override_return_type(type_repr = "typing.Awaitable[Price]", imports = ("typing")))]
+#[pyo3(signature = (crypto, fiat = "usd".to_string(), *, verbose = false, offline = false, config = None))]
+fn get_price_async(
+    py: Python<'_>,
+    crypto: String,
+    fiat: String,
+    verbose: bool,
+    offline: bool,
+    config: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let tool = shared_tool(&native_config(config.as_deref(), offline)?)?;
+    future_into_py(py, async move {
+        let (quote, _) = using_tool(&tool, async |tool| tool.lookup_price(&crypto, &fiat, verbose).await)
+            .await
+            .map_err(to_py_err)?;
+        Ok(Price::new(crypto, fiat, quote))
+    })
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[gen_stub(override_return_type(type_repr = "typing.Awaitable[Balance]", imports = ("typing")))]
+#[pyo3(signature = (crypto, address, *, verbose = false, offline = false, config = None))]
+fn get_address_balance_async(
+    py: Python<'_>,
+    crypto: String,
+    address: String,
+    verbose: bool,
+    offline: bool,
+    config: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let tool = shared_tool(&native_config(config.as_deref(), offline)?)?;
+    future_into_py(py, async move {
+        let (quote, _) = using_tool(&tool, async |tool| tool.lookup_balance(&crypto, &address, verbose).await)
+            .await
+            .map_err(to_py_err)?;
+        Ok(Balance::new(crypto, address, quote))
+    })
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[gen_stub(override_return_type(type_repr = "typing.Awaitable[Wallet]", imports = ("typing")))]
+#[pyo3(signature = (path, fiat = "usd".to_string(), *, verbose = false, offline = false, config = None))]
+fn get_wallet_balance_async(
+    py: Python<'_>,
+    path: String,
+    fiat: String,
+    verbose: bool,
+    offline: bool,
+    config: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let tool = shared_tool(&native_config(config.as_deref(), offline)?)?;
+    future_into_py(py, async move {
+        let (lines, _) = using_tool(&tool, async |tool| tool.get_wallet_balance(&path, &fiat, verbose).await)
+            .await
+            .map_err(to_py_err)?;
+        Ok(Wallet::new(fiat, lines))
+    })
+}
+
+// Wallet as a pyarrow.RecordBatch, e.g. `.to_pandas()` or `polars.from_arrow(...)`
+#[cfg(feature = "arrow")]
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[gen_stub(override_return_type(type_repr = "pyarrow.RecordBatch", imports = ("pyarrow")))]
+#[pyo3(signature = (path, fiat = "usd", *, verbose = false, offline = false, config = None))]
+fn get_wallet_balance_arrow<'py>(
+    py: Python<'py>,
+    path: &str,
+    fiat: &str,
+    verbose: bool,
+    offline: bool,
+    config: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let config = native_config(config, offline)?;
+    let lines = block_on(py, &config, async |tool| tool.get_wallet_balance(path, fiat, verbose).await)?;
+    let batch = crate::arrow::wallet_record_batch(&lines, fiat)
+        .map_err(|e| CoinWagonError::new_err(format!("Failed to build Arrow batch: {}", e)))?;
+    pyo3_arrow::PyRecordBatch::new(batch).into_pyarrow(py)
+}
+
+// Long-lived client: keeps one HTTP connection pool and in-memory cache across calls
+// instead of rebuilding them for every function call. Blocking and async methods share
+// the background runtime, so pooled connections stay usable from both. Like the module
+// functions, each call then saves the cache, request stats and any cassette.
+#[gen_stub_pyclass]
+#[pyclass(frozen, module = "coinwagon")]
+pub struct CoinWagon {
+    tool: Arc<CryptoTool>,
+}
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl CoinWagon {
+    #[new]
+    #[pyo3(signature = (config = None, *, offline = false))]
+    fn new(config: Option<&str>, offline: bool) -> PyResult<Self> {
+        let config = native_config(config, offline)?;
+        Ok(CoinWagon {
+            tool: Arc::new(shared_tool(&config)?),
+        })
+    }
+
+    #[pyo3(signature = (crypto, fiat = "usd", *, verbose = false))]
+    fn get_price(&self, py: Python<'_>, crypto: &str, fiat: &str, verbose: bool) -> PyResult<Price> {
+        let quote = block_on_tool(py, &self.tool, async |tool| tool.lookup_price(crypto, fiat, verbose).await)?;
+        Ok(Price::new(crypto.to_string(), fiat.to_string(), quote))
+    }
+
+    #[pyo3(signature = (crypto, address, *, verbose = false))]
+    fn get_address_balance(&self, py: Python<'_>, crypto: &str, address: &str, verbose: bool) -> PyResult<Balance> {
+        let quote = block_on_tool(py, &self.tool, async |tool| tool.lookup_balance(crypto, address, verbose).await)?;
+        Ok(Balance::new(crypto.to_string(), address.to_string(), quote))
+    }
+
+    #[pyo3(signature = (path, fiat = "usd", *, verbose = false))]
+    fn get_wallet_balance(&self, py: Python<'_>, path: &str, fiat: &str, verbose: bool) -> PyResult<Wallet> {
+        let lines = block_on_tool(py, &self.tool, async |tool| tool.get_wallet_balance(path, fiat, verbose).await)?;
+        Ok(Wallet::new(fiat.to_string(), lines))
+    }
+
+    #[gen_stub(override_return_type(type_repr = "typing.Awaitable[Price]", imports = ("typing")))]
+    #[pyo3(signature = (crypto, fiat = "usd".to_string(), *, verbose = false))]
+    fn get_price_async<'py>(
+        &self,
+        py: Python<'py>,
+        crypto: String,
+        fiat: String,
+        verbose: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let tool = self.tool.clone();
+        future_into_py(py, async move {
+            let (quote, _) = using_tool(&tool, async |tool| tool.lookup_price(&crypto, &fiat, verbose).await)
+                .await
+                .map_err(to_py_err)?;
+            Ok(Price::new(crypto, fiat, quote))
+        })
+    }
+
+    #[gen_stub(override_return_type(type_repr = "typing.Awaitable[Balance]", imports = ("typing")))]
+    #[pyo3(signature = (crypto, address, *, verbose = false))]
+    fn get_address_balance_async<'py>(
+        &self,
+        py: Python<'py>,
+        crypto: String,
+        address: String,
+        verbose: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let tool = self.tool.clone();
+        future_into_py(py, async move {
+            let (quote, _) = using_tool(&tool, async |tool| tool.lookup_balance(&crypto, &address, verbose).await)
+                .await
+                .map_err(to_py_err)?;
+            Ok(Balance::new(crypto, address, quote))
+        })
+    }
+
+    #[gen_stub(override_return_type(type_repr = "typing.Awaitable[Wallet]", imports = ("typing")))]
+    #[pyo3(signature = (path, fiat = "usd".to_string(), *, verbose = false))]
+    fn get_wallet_balance_async<'py>(
+        &self,
+        py: Python<'py>,
+        path: String,
+        fiat: String,
+        verbose: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let tool = self.tool.clone();
+        future_into_py(py, async move {
+            let (lines, _) = using_tool(&tool, async |tool| tool.get_wallet_balance(&path, &fiat, verbose).await)
+                .await
+                .map_err(to_py_err)?;
+            Ok(Wallet::new(fiat, lines))
+        })
+    }
+
+    // Writes the cache file now; each call also saves it
+    fn save_cache(&self) -> PyResult<()> {
+        self.tool.cache.save().map_err(to_py_err)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CoinWagon(offline={})", py_bool(self.tool.offline))
+    }
+}
+
+impl Drop for CoinWagon {
+    fn drop(&mut self) {
+        if let Err(e) = self.tool.cache.save() {
+            eprintln!("Warning: {}", e);
+        }
+    }
+}
+
+#[pymodule]
+fn coinwagon(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(run_command, m)?)?;
+    m.add_function(wrap_pyfunction!(query, m)?)?;
+    m.add_function(wrap_pyfunction!(get_price, m)?)?;
+    m.add_function(wrap_pyfunction!(get_address_balance, m)?)?;
+    m.add_function(wrap_pyfunction!(get_wallet_balance, m)?)?;
+    #[cfg(feature = "arrow")]
+    m.add_function(wrap_pyfunction!(get_wallet_balance_arrow, m)?)?;
+    m.add_function(wrap_pyfunction!(get_price_async, m)?)?;
+    m.add_function(wrap_pyfunction!(get_address_balance_async, m)?)?;
+    m.add_function(wrap_pyfunction!(get_wallet_balance_async, m)?)?;
+    m.add("CoinWagonError", m.py().get_type::<CoinWagonError>())?;
+    m.add("ApiError", m.py().get_type::<ApiError>())?;
+    m.add("RateLimited", m.py().get_type::<RateLimited>())?;
+    m.add("NotFound", m.py().get_type::<NotFound>())?;
+    m.add("ProviderUnavailable", m.py().get_type::<ProviderUnavailable>())?;
+    m.add("NetworkError", m.py().get_type::<NetworkError>())?;
+    m.add("InvalidInput", m.py().get_type::<InvalidInput>())?;
+    m.add_class::<CoinWagon>()?;
+    m.add_class::<Price>()?;
+    m.add_class::<Balance>()?;
+    m.add_class::<WalletEntry>()?;
+    m.add_class::<Wallet>()?;
+    m.add_class::<Global>()?;
+    Ok(())
+}
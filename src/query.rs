@@ -0,0 +1,106 @@
+use chrono::SecondsFormat;
+use rusqlite::types::Value;
+use rusqlite::{Connection, params_from_iter};
+
+use crate::CryptoError;
+use crate::export::{Table, Values};
+
+// `query`: one read-only SQL statement, run by SQLite over an in-memory database holding
+// `tables`. Numbers are REAL columns and everything else TEXT, times as ISO 8601 UTC
+// ('2024-06-01T12:00:00Z') and dates as YYYY-MM-DD, so `time >= '2024-01-01'` does what it
+// looks like. A table that couldn't be loaded is left out, and a query naming it gets the reason.
+pub fn run(sql: &str, tables: Vec<(&str, Result<Table, CryptoError>)>) -> Result<Table, CryptoError> {
+    let db = Connection::open_in_memory().map_err(sqlite)?;
+    let mut missing = Vec::new();
+    for (name, table) in tables {
+        match table {
+            Ok(table) => load(&db, name, &table).map_err(sqlite)?,
+            Err(e) => missing.push((name, e)),
+        }
+    }
+    db.pragma_update(None, "query_only", true).map_err(sqlite)?;
+
+    let mut statement = match db.prepare(sql) {
+        Ok(statement) => statement,
+        Err(e) => {
+            // SQLite's "no such table: prices" doesn't say why
+            let reason = e.to_string();
+            let table = reason.strip_prefix("no such table: ").unwrap_or_default();
+            return Err(match missing.into_iter().find(|(name, _)| name.eq_ignore_ascii_case(table)) {
+                Some((_, e)) => e,
+                None => sqlite(e),
+            });
+        }
+    };
+    if !statement.readonly() {
+        return Err(CryptoError::InvalidInput("Only SELECT queries can be run; the data is read-only".to_string()));
+    }
+
+    let names: Vec<String> = statement.column_names().into_iter().map(String::from).collect();
+    let mut cells: Vec<Vec<Value>> = vec![Vec::new(); names.len()];
+    let mut rows = statement.query([]).map_err(sqlite)?;
+    while let Some(row) = rows.next().map_err(sqlite)? {
+        for (i, column) in cells.iter_mut().enumerate() {
+            column.push(row.get(i).map_err(sqlite)?);
+        }
+    }
+    Ok(Table { columns: names.into_iter().zip(cells.into_iter().map(values)).collect() })
+}
+
+fn load(db: &Connection, name: &str, table: &Table) -> rusqlite::Result<()> {
+    let columns: Vec<String> = table
+        .columns
+        .iter()
+        .map(|(column, values)| format!("\"{}\" {}", column, if matches!(values, Values::Number(_)) { "REAL" } else { "TEXT" }))
+        .collect();
+    let load = db.unchecked_transaction()?;
+    load.execute(&format!("CREATE TABLE \"{}\" ({})", name, columns.join(", ")), [])?;
+    let mut insert = load.prepare(&format!("INSERT INTO \"{}\" VALUES ({})", name, vec!["?"; columns.len()].join(", ")))?;
+    for row in 0..table.rows() {
+        insert.execute(params_from_iter(table.columns.iter().map(|(_, values)| cell(values, row))))?;
+    }
+    drop(insert);
+    load.commit()
+}
+
+fn cell(values: &Values, row: usize) -> Value {
+    let value = match values {
+        Values::Text(column) => column[row].clone().map(Value::Text),
+        Values::Number(column) => column[row].map(Value::Real),
+        Values::Time(column) => column[row].map(|time| Value::Text(time.to_rfc3339_opts(SecondsFormat::Secs, true))),
+        Values::Date(column) => column[row].map(|date| Value::Text(date.to_string())),
+    };
+    value.unwrap_or(Value::Null)
+}
+
+// A result column of numbers when every value in it is one, otherwise of text
+fn values(cells: Vec<Value>) -> Values {
+    if cells.iter().all(|cell| matches!(cell, Value::Null | Value::Integer(_) | Value::Real(_))) {
+        return Values::Number(
+            cells
+                .into_iter()
+                .map(|cell| match cell {
+                    Value::Integer(n) => Some(n as f64),
+                    Value::Real(n) => Some(n),
+                    _ => None,
+                })
+                .collect(),
+        );
+    }
+    Values::Text(
+        cells
+            .into_iter()
+            .map(|cell| match cell {
+                Value::Null => None,
+                Value::Integer(n) => Some(n.to_string()),
+                Value::Real(n) => Some(n.to_string()),
+                Value::Text(text) => Some(text),
+                Value::Blob(bytes) => Some(bytes.iter().map(|byte| format!("{:02x}", byte)).collect()),
+            })
+            .collect(),
+    )
+}
+
+fn sqlite(e: rusqlite::Error) -> CryptoError {
+    CryptoError::InvalidInput(format!("Query failed: {}", e))
+}
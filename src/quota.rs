@@ -0,0 +1,183 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::CryptoError;
+use crate::config::{Config, QuotaConfig};
+
+// Requests a provider answered on `day` and in the month up to it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Usage {
+    day: NaiveDate,
+    today: u64,
+    this_month: u64,
+}
+
+impl Usage {
+    fn none(day: NaiveDate) -> Self {
+        Usage { day, today: 0, this_month: 0 }
+    }
+
+    // As of `day`: counts from an earlier day, or an earlier month, are over
+    fn on(self, day: NaiveDate) -> Self {
+        match self.day == day {
+            true => self,
+            false if (self.day.year(), self.day.month()) == (day.year(), day.month()) => Usage { day, today: 0, ..self },
+            false => Usage::none(day),
+        }
+    }
+
+    fn add(self, other: Usage) -> Self {
+        let day = self.day.max(other.day);
+        let (this, other) = (self.on(day), other.on(day));
+        Usage { day, today: this.today + other.today, this_month: this.this_month + other.this_month }
+    }
+}
+
+// [quotas]: how much of each provider's daily and monthly allowance has gone, across runs.
+// Counting only happens for providers with a quota; the file is re-read before writing, under
+// a lock on quota.lock next to it, so that runs at the same time add up rather than overwrite
+// each other.
+pub struct Quotas {
+    limits: HashMap<String, QuotaConfig>,
+    path: Option<PathBuf>,
+    // As of the last load or save
+    saved: DashMap<String, Usage>,
+    // Answered since, and not in the file yet
+    counted: DashMap<String, Usage>,
+    // "<provider> <period>" already warned about this run
+    warned: DashSet<String>,
+}
+
+impl Quotas {
+    pub fn new(config: &Config) -> Result<Self, CryptoError> {
+        let mut limits = HashMap::new();
+        for (provider, quota) in &config.quotas {
+            for (name, percent) in [("warn_at", quota.warn_at), ("switch_at", quota.switch_at)] {
+                if !(percent > 0.0 && percent <= 100.0) {
+                    return Err(CryptoError::ConfigError(format!("quotas.{}.{} must be a percentage above 0, up to 100", provider, name)));
+                }
+            }
+            limits.insert(provider.to_lowercase(), quota.clone());
+        }
+        let path = match limits.is_empty() {
+            true => None,
+            false => config.cache.resolved_path().and_then(|path| Some(path.parent()?.join("quota.json"))),
+        };
+        let quotas = Quotas { limits, path, saved: DashMap::new(), counted: DashMap::new(), warned: DashSet::new() };
+        for (provider, usage) in quotas.read() {
+            quotas.saved.insert(provider, usage);
+        }
+        Ok(quotas)
+    }
+
+    // A missing or unreadable file means nothing counted yet
+    fn read(&self) -> HashMap<String, Usage> {
+        let Some(body) = self.path.as_ref().and_then(|path| std::fs::read_to_string(path).ok()) else {
+            return HashMap::new();
+        };
+        serde_json::from_str(&body).unwrap_or_default()
+    }
+
+    fn used(&self, provider: &str) -> Option<(Usage, &QuotaConfig)> {
+        let quota = self.limits.get(provider)?;
+        let today = Utc::now().date_naive();
+        let usage = [self.saved.get(provider).map(|u| *u), self.counted.get(provider).map(|u| *u)]
+            .into_iter()
+            .flatten()
+            .fold(Usage::none(today), Usage::add);
+        Some((usage.on(today), quota))
+    }
+
+    // (limit, used, "today" or "this month") for each quota the provider has
+    fn periods(&self, provider: &str) -> Vec<(u64, u64, &'static str)> {
+        let Some((usage, quota)) = self.used(&provider.to_lowercase()) else {
+            return Vec::new();
+        };
+        [(quota.daily, usage.today, "today"), (quota.monthly, usage.this_month, "this month")]
+            .into_iter()
+            .filter_map(|(limit, used, period)| Some((limit?, used, period)))
+            .collect()
+    }
+
+    // Refuses a request once the provider is at switch_at of a quota, so the lookup moves on
+    // to the next provider before this one starts answering 429
+    pub fn check(&self, provider: &str) -> Result<(), CryptoError> {
+        let switch_at = self.limits.get(&provider.to_lowercase()).map_or(100.0, |quota| quota.switch_at);
+        for (limit, used, period) in self.periods(provider) {
+            if used as f64 >= limit as f64 * switch_at / 100.0 {
+                let state = if used >= limit { "out of quota" } else { "near its quota" };
+                return Err(CryptoError::ProviderUnavailable {
+                    provider: provider.to_string(),
+                    reason: format!("{} ({} of {} requests {})", state, used, limit, period),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Counts a request the provider answered, warning the first time a quota passes warn_at
+    pub fn record(&self, provider: &str) {
+        let key = provider.to_lowercase();
+        let Some(quota) = self.limits.get(&key) else {
+            return;
+        };
+        let today = Utc::now().date_naive();
+        let mut counted = self.counted.entry(key.clone()).or_insert(Usage::none(today));
+        *counted = counted.on(today).add(Usage { day: today, today: 1, this_month: 1 });
+        drop(counted);
+        for (limit, used, period) in self.periods(provider) {
+            if used as f64 >= limit as f64 * quota.warn_at / 100.0 && self.warned.insert(format!("{} {}", key, period)) {
+                eprintln!("Warning: {} has used {} of its {} requests {}", provider, used, limit, period);
+            }
+        }
+    }
+
+    // "812/10000 today, 20412/300000 this month" for `status`; None without a quota
+    pub fn usage(&self, provider: &str) -> Option<String> {
+        let periods = self.periods(provider);
+        if periods.is_empty() {
+            return None;
+        }
+        Some(periods.iter().map(|(limit, used, period)| format!("{}/{} {}", used, limit, period)).collect::<Vec<_>>().join(", "))
+    }
+
+    pub fn save(&self) -> Result<(), CryptoError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.counted.is_empty() {
+            return Ok(());
+        }
+        let io_error = |e: std::io::Error| CryptoError::RuntimeError(format!("Failed to write quota file {}: {}", path.display(), e));
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        // Held from the read to the rename (and released on drop), so another run saving
+        // at the same time can't overwrite what this one adds
+        let lock = std::fs::File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_extension("lock"))
+            .map_err(io_error)?;
+        lock.lock().map_err(io_error)?;
+
+        let mut usage = self.read();
+        for (provider, counted) in self.counted.iter().map(|e| (e.key().clone(), *e.value())) {
+            let merged = usage.get(&provider).map_or(counted, |saved| saved.add(counted));
+            usage.insert(provider, merged);
+        }
+        let json = serde_json::to_string(&usage).map_err(|e| CryptoError::RuntimeError(format!("Failed to serialize quotas: {}", e)))?;
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        std::fs::write(&tmp, json).and_then(|()| std::fs::rename(&tmp, path)).map_err(io_error)?;
+        self.saved.clear();
+        for (provider, usage) in usage {
+            self.saved.insert(provider, usage);
+        }
+        self.counted.clear();
+        Ok(())
+    }
+}
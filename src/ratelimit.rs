@@ -0,0 +1,111 @@
+use chrono::DateTime;
+use dashmap::DashMap;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::CryptoError;
+use crate::config::NetworkConfig;
+
+// Fallback pause after a 429 that doesn't say how long to wait
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+
+// Remembers when each provider said its quota comes back, from 429s and from X-RateLimit-*
+// headers on successful responses. Requests to a provider wait that long when it's within
+// `max_wait`, and otherwise fail straight away instead of hammering the API. A quota is the
+// provider's name, or the name and key number when it has several API keys.
+pub struct RateLimits {
+    blocked_until: DashMap<String, Instant>,
+    // Earliest time the next request of a paced quota may go out
+    next_slot: DashMap<String, Instant>,
+    max_wait: Duration,
+    pub retries: u32,
+}
+
+impl RateLimits {
+    pub fn new(config: &NetworkConfig) -> Self {
+        RateLimits {
+            blocked_until: DashMap::new(),
+            next_slot: DashMap::new(),
+            max_wait: Duration::from_secs(config.max_retry_wait_secs),
+            retries: config.rate_limit_retries,
+        }
+    }
+
+    pub fn can_wait(&self, wait: Duration) -> bool {
+        wait <= self.max_wait
+    }
+
+    pub fn is_blocked(&self, quota: &str) -> bool {
+        self.blocked_until.get(quota).is_some_and(|until| *until > Instant::now())
+    }
+
+    pub async fn wait(&self, provider: &str, quota: &str) -> Result<(), CryptoError> {
+        let Some(until) = self.blocked_until.get(quota).map(|until| *until) else {
+            return Ok(());
+        };
+        let remaining = until.saturating_duration_since(Instant::now());
+        if remaining > self.max_wait {
+            return Err(CryptoError::RateLimited { provider: provider.to_string(), retry_after: Some(remaining) });
+        }
+        tokio::time::sleep(remaining).await;
+        self.blocked_until.remove_if(quota, |_, until| *until <= Instant::now());
+        Ok(())
+    }
+
+    // Spaces requests of `quota` at least 1/`per_second` apart, waiting for this one's turn
+    pub async fn pace(&self, quota: &str, per_second: f64) {
+        let interval = Duration::from_secs_f64(1.0 / per_second);
+        let slot = {
+            let now = Instant::now();
+            let mut next = self.next_slot.entry(quota.to_string()).or_insert(now);
+            let slot = (*next).max(now);
+            *next = slot + interval;
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+
+    // Records what a response says about the provider's quota; for a 429, returns how long to
+    // wait before trying again
+    pub fn observe(&self, quota: &str, status: StatusCode, headers: &HeaderMap, attempt: u32) -> Option<Duration> {
+        let limited = status == StatusCode::TOO_MANY_REQUESTS;
+        let wait = if limited {
+            // Doubles with each retry when the provider doesn't say
+            Some(retry_after(headers).unwrap_or(DEFAULT_BACKOFF * 2u32.saturating_pow(attempt)))
+        } else if header(headers, "x-ratelimit-remaining").is_some_and(|v| v.trim() == "0") {
+            reset_after(headers)
+        } else {
+            None
+        };
+        if let Some(wait) = wait {
+            self.blocked_until.insert(quota.to_string(), Instant::now() + wait);
+        }
+        wait.filter(|_| limited)
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+// Retry-After is either delay-seconds or an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let Some(value) = header(headers, RETRY_AFTER.as_str()).map(str::trim) else {
+        return reset_after(headers);
+    };
+    match value.parse() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => {
+            let at = DateTime::parse_from_rfc2822(value).ok()?;
+            Some(SystemTime::from(at).duration_since(SystemTime::now()).unwrap_or_default())
+        }
+    }
+}
+
+// X-RateLimit-Reset is seconds until the reset on some APIs and a Unix timestamp on others
+fn reset_after(headers: &HeaderMap) -> Option<Duration> {
+    let value: u64 = header(headers, "x-ratelimit-reset")?.trim().parse().ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(if value > now / 2 { value.saturating_sub(now) } else { value }))
+}
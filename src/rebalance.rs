@@ -0,0 +1,42 @@
+use crate::tidy;
+
+// A coin of the portfolio or the target, valued at its current price
+pub struct Position {
+    pub coin: String,
+    pub value: f64,
+    pub price: f64,
+    // Share of the total it should have, as a fraction; 0 for a coin held but not targeted
+    pub target: f64,
+}
+
+impl Position {
+    fn weight(&self, total: f64) -> f64 {
+        self.value / total
+    }
+}
+
+// Once any coin's weight is more than `band` percentage points off its target, every coin is
+// traded back to its target, so the sales pay for the buys
+pub fn render(wallet: &str, fiat: &str, band: f64, positions: &[Position]) -> String {
+    let fiat = fiat.to_uppercase();
+    let total: f64 = positions.iter().map(|p| p.value).sum();
+    let drift = |p: &Position| (p.weight(total) - p.target) * 100.0;
+    let outside = positions.iter().any(|p| drift(p).abs() > band);
+    let mut lines = vec![format!("Rebalance of {}, {} {} (band {} points):", wallet, tidy(total), fiat, tidy(band))];
+    for p in positions {
+        let weights = format!("{:.2}% now, {:.2}% target", p.weight(total) * 100.0, p.target * 100.0);
+        let (trade, unit) = (p.target * total - p.value, p.coin.to_uppercase());
+        // Rounding leaves dust on a coin already at its target
+        let dust = total * 1e-9;
+        lines.push(match trade {
+            _ if !outside => format!("  {}: {}, within band", p.coin, weights),
+            trade if trade > dust => format!("  {}: {}, buy {} {} ({} {})", p.coin, weights, tidy(trade / p.price), unit, tidy(trade), fiat),
+            trade if trade < -dust => format!("  {}: {}, sell {} {} ({} {})", p.coin, weights, tidy(-trade / p.price), unit, tidy(-trade), fiat),
+            _ => format!("  {}: {}, on target", p.coin, weights),
+        });
+    }
+    if !outside {
+        lines.push(format!("Every coin is within {} points of its target; nothing to trade", tidy(band)));
+    }
+    lines.join("\n")
+}
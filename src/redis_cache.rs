@@ -0,0 +1,92 @@
+use redis::Commands;
+use std::sync::Mutex;
+
+use crate::cache::CacheEntry;
+
+// Shared second-level cache so several processes/containers see the same prices.
+// Failures are reported and otherwise ignored: the cache is an optimisation, never a requirement.
+pub struct RedisCache {
+    connection: Mutex<redis::Connection>,
+    prefix: String,
+}
+
+impl RedisCache {
+    pub fn connect(url: &str, prefix: &str) -> Result<Self, redis::RedisError> {
+        let connection = redis::Client::open(url)?.get_connection()?;
+        Ok(RedisCache {
+            connection: Mutex::new(connection),
+            prefix: prefix.to_string(),
+        })
+    }
+
+    fn redis_key(&self, key: &str) -> String {
+        format!("{}:{}", self.prefix, key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        let result: redis::RedisResult<Option<String>> = self.connection.lock().unwrap().get(self.redis_key(key));
+        match result {
+            Ok(Some(json)) => serde_json::from_str(&json).ok(),
+            Ok(None) => None,
+            Err(e) => {
+                eprintln!("Warning: Redis GET failed: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn set(&self, key: &str, entry: &CacheEntry) {
+        let Ok(json) = serde_json::to_string(entry) else {
+            return;
+        };
+        let result: redis::RedisResult<()> = self.connection.lock().unwrap().set(self.redis_key(key), json);
+        if let Err(e) = result {
+            eprintln!("Warning: Redis SET failed: {}", e);
+        }
+    }
+
+    pub fn list(&self) -> Vec<(String, CacheEntry)> {
+        let keys = self.keys("*");
+        keys.into_iter()
+            .filter_map(|key| {
+                let entry = self.get(&key)?;
+                Some((key, entry))
+            })
+            .collect()
+    }
+
+    // `coin` limits deletion to "<kind>:<coin>:*" keys
+    pub fn clear(&self, coin: Option<&str>) -> usize {
+        let pattern = match coin {
+            Some(coin) => format!("*:{}:*", coin),
+            None => "*".to_string(),
+        };
+        let keys: Vec<String> = self.keys(&pattern).iter().map(|k| self.redis_key(k)).collect();
+        if keys.is_empty() {
+            return 0;
+        }
+        let result: redis::RedisResult<usize> = self.connection.lock().unwrap().del(&keys);
+        result.unwrap_or_else(|e| {
+            eprintln!("Warning: Redis DEL failed: {}", e);
+            0
+        })
+    }
+
+    // Cache keys (without our prefix) matching `pattern`
+    fn keys(&self, pattern: &str) -> Vec<String> {
+        let mut connection = self.connection.lock().unwrap();
+        let full_prefix = format!("{}:", self.prefix);
+        let result: redis::RedisResult<redis::Iter<String>> =
+            connection.scan_match(format!("{}{}", full_prefix, pattern));
+        match result {
+            Ok(iter) => iter
+                .filter_map(|key| key.ok())
+                .filter_map(|key| key.strip_prefix(&full_prefix).map(str::to_string))
+                .collect(),
+            Err(e) => {
+                eprintln!("Warning: Redis SCAN failed: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+
+use crate::coins::{infer_chain, resolve_symbol};
+use crate::{CryptoError, CryptoTool, format_wallet};
+
+const HELP: &str = "Commands:
+  price <coin> [fiat]         Current price (e.g. price btc eur)
+  balance [coin] <address>    Address balance; coin is inferred from the address if omitted
+  portfolio [wallet-file]     Value the wallet file
+  fiat <code>                 Change the default fiat currency
+  verbose on|off              Toggle diagnostics
+  help                        Show this message
+  quit                        Leave the REPL";
+
+pub async fn run(tool: &CryptoTool, mut wallet: Option<String>, mut fiat: String) -> Result<String, CryptoError> {
+    let mut editor = DefaultEditor::new().map_err(|e| CryptoError::RuntimeError(format!("Failed to start REPL: {}", e)))?;
+    let history = dirs::data_dir().map(|dir| dir.join("coinwagon").join("history.txt"));
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+
+    let mut verbose = false;
+    println!("coinwagon REPL - type `help` for commands, `quit` to exit");
+    loop {
+        let line = match editor.readline("coinwagon> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(CryptoError::RuntimeError(format!("Failed to read input: {}", e))),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line.as_str());
+
+        let result = match words.as_slice() {
+            ["quit"] | ["exit"] => break,
+            ["help"] => Ok(HELP.to_string()),
+            ["price", coin] => price(tool, coin, &fiat, verbose).await,
+            ["price", coin, quote] => price(tool, coin, quote, verbose).await,
+            ["balance", address] => match infer_chain(address) {
+                Some(coin) => balance(tool, coin, address, verbose).await,
+                None => Err(CryptoError::InvalidInput(format!(
+                    "Can't tell which chain {} belongs to, use `balance <coin> <address>`",
+                    address
+                ))),
+            },
+            ["balance", coin, address] => balance(tool, &resolve_symbol(coin), address, verbose).await,
+            ["portfolio"] => match &wallet {
+                Some(path) => portfolio(tool, path, &fiat, verbose).await,
+                None => Err(CryptoError::InvalidInput(
+                    "No wallet file, use `portfolio <wallet-file>` or start with --wallet".to_string(),
+                )),
+            },
+            ["portfolio", path] => {
+                wallet = Some(path.to_string());
+                portfolio(tool, path, &fiat, verbose).await
+            }
+            ["fiat", code] => {
+                fiat = code.to_lowercase();
+                Ok(format!("Default fiat set to {}", fiat.to_uppercase()))
+            }
+            ["verbose", "on"] => {
+                verbose = true;
+                Ok("Verbose output on".to_string())
+            }
+            ["verbose", "off"] => {
+                verbose = false;
+                Ok("Verbose output off".to_string())
+            }
+            _ => Err(CryptoError::InvalidInput(format!("Unknown command: {} (try `help`)", line.trim()))),
+        };
+
+        match result {
+            Ok(output) => println!("{}", output),
+            Err(e) => eprintln!("{}", crate::error_message(&e)),
+        }
+        // Persist after every command so the warm cache survives a crash or Ctrl-C
+        if let Err(e) = tool.cache.save() {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    if let Some(history) = &history {
+        if let Some(dir) = history.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = editor.save_history(history);
+    }
+    Ok(String::new())
+}
+
+async fn price(tool: &CryptoTool, coin: &str, fiat: &str, verbose: bool) -> Result<String, CryptoError> {
+    let price = tool.get_current_price(&resolve_symbol(coin), &fiat.to_lowercase(), verbose).await?;
+    Ok(format!("{} {}", price, fiat.to_uppercase()))
+}
+
+async fn balance(tool: &CryptoTool, coin: &str, address: &str, verbose: bool) -> Result<String, CryptoError> {
+    let balance = tool.get_address_balance(coin, address, verbose).await?;
+    Ok(format!("{} {}", balance, coin.to_uppercase()))
+}
+
+async fn portfolio(tool: &CryptoTool, wallet: &str, fiat: &str, verbose: bool) -> Result<String, CryptoError> {
+    let results = tool.get_wallet_balance(wallet, fiat, verbose).await?;
+    Ok(format_wallet(&results, fiat))
+}
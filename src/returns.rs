@@ -0,0 +1,184 @@
+use chrono::{Days, NaiveDate};
+use std::collections::HashMap;
+
+use crate::transactions::{Kind, Transaction};
+use crate::{CryptoError, coins};
+
+// Daily closes in the report's fiat, by coin id and then date
+pub type Prices = HashMap<String, HashMap<NaiveDate, f64>>;
+
+// Holdings smaller than this are rounding left over from trades, not a balance
+const DUST: f64 = 1e-9;
+
+fn id(asset: &str) -> String {
+    coins::resolve_symbol(asset)
+}
+
+// What `transaction` adds to each holding, negative for what it takes away
+fn changes(transaction: &Transaction) -> Vec<(String, f64)> {
+    let mut changes = match transaction.kind {
+        Kind::Deposit => vec![(id(&transaction.asset), transaction.amount)],
+        Kind::Withdrawal => vec![(id(&transaction.asset), -transaction.amount)],
+        Kind::Trade => vec![(id(&transaction.asset), transaction.amount)],
+    };
+    changes.extend(transaction.counter.iter().map(|counter| (id(&counter.asset), -counter.amount)));
+    changes.extend(transaction.fee.iter().map(|fee| (id(&fee.asset), -fee.amount)));
+    changes
+}
+
+// Every coin the transactions up to the end of `to` hold at some point
+pub fn assets(transactions: &[Transaction], to: NaiveDate) -> Vec<String> {
+    let mut assets: Vec<String> = Vec::new();
+    for transaction in transactions.iter().filter(|t| t.time.date_naive() <= to) {
+        for (asset, _) in changes(transaction) {
+            if !assets.contains(&asset) {
+                assets.push(asset);
+            }
+        }
+    }
+    assets
+}
+
+// Money in minus money out, valued at each day's close. Deposits and withdrawals are taken to
+// happen at the close, after the day's price move.
+pub struct Returns {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub start_value: f64,
+    pub end_value: f64,
+    pub net_deposits: f64,
+    // Both over the whole range, not annualized
+    pub time_weighted: f64,
+    // None when no rate balances the cash flows
+    pub money_weighted: Option<f64>,
+}
+
+impl Returns {
+    pub fn compute(transactions: &[Transaction], prices: &Prices, fiat: &str, from: NaiveDate, to: NaiveDate) -> Result<Self, CryptoError> {
+        let start = from - Days::new(1);
+        let price = |asset: &str, date: NaiveDate| -> Result<f64, CryptoError> {
+            if asset == fiat {
+                return Ok(1.0);
+            }
+            prices.get(asset).and_then(|closes| closes.get(&date)).copied().ok_or_else(|| {
+                CryptoError::InvalidInput(format!("No {} close for {} on {}", fiat, asset, date))
+            })
+        };
+        let value = |holdings: &HashMap<String, f64>, date: NaiveDate| -> Result<f64, CryptoError> {
+            let mut total = 0.0;
+            for (asset, amount) in holdings {
+                if amount.abs() > DUST {
+                    total += amount * price(asset, date)?;
+                }
+            }
+            Ok(total)
+        };
+        let mut holdings: HashMap<String, f64> = HashMap::new();
+        let mut pending = transactions.iter().filter(|t| t.time.date_naive() <= to).peekable();
+        // Applies the transactions of every day up to `date` and returns their net deposits
+        let mut settle = |holdings: &mut HashMap<String, f64>, date: NaiveDate| -> Result<f64, CryptoError> {
+            let mut flow = 0.0;
+            while let Some(transaction) = pending.next_if(|t| t.time.date_naive() <= date) {
+                for (asset, change) in changes(transaction) {
+                    *holdings.entry(asset).or_default() += change;
+                }
+                let sign = match transaction.kind {
+                    Kind::Deposit => 1.0,
+                    Kind::Withdrawal => -1.0,
+                    Kind::Trade => 0.0,
+                };
+                if sign != 0.0 {
+                    flow += sign * transaction.amount * price(&id(&transaction.asset), transaction.time.date_naive())?;
+                }
+            }
+            if let Some((asset, amount)) = holdings.iter().find(|(_, amount)| **amount < -DUST) {
+                return Err(CryptoError::InvalidInput(format!(
+                    "The imported transactions take {} {} more than they bring in by {}; import the account's full history",
+                    -amount, asset, date
+                )));
+            }
+            Ok(flow)
+        };
+
+        // Flows before the range make up its starting value rather than count as deposits
+        settle(&mut holdings, start)?;
+        let start_value = value(&holdings, start)?;
+        // Days after the start and the investor's cash flow then, out negative
+        let mut cash = vec![(0.0, -start_value)];
+        let (mut previous, mut growth, mut net_deposits) = (start_value, 1.0, 0.0);
+        let mut date = from;
+        while date <= to {
+            let flow = settle(&mut holdings, date)?;
+            let current = value(&holdings, date)?;
+            if previous > DUST {
+                growth *= (current - flow) / previous;
+            }
+            if flow != 0.0 {
+                cash.push(((date - start).num_days() as f64, -flow));
+            }
+            net_deposits += flow;
+            previous = current;
+            date = date + Days::new(1);
+        }
+        if start_value <= DUST && net_deposits == 0.0 {
+            return Err(CryptoError::InvalidInput(format!("Nothing was held or deposited from {} to {}", from, to)));
+        }
+        let days = (to - start).num_days() as f64;
+        cash.push((days, previous));
+        Ok(Returns {
+            from,
+            to,
+            start_value,
+            end_value: previous,
+            net_deposits,
+            time_weighted: growth - 1.0,
+            money_weighted: irr(&cash, days),
+        })
+    }
+
+    pub fn render(&self, fiat: &str, sources: &str, benchmark: Option<(&str, f64)>) -> String {
+        let fiat = fiat.to_uppercase();
+        let days = (self.to - self.from).num_days() + 1;
+        let percent = |rate: f64| {
+            // Annualizing a range shorter than a year would blow up a short run of luck
+            match days >= 365 {
+                true => format!("{:.2}% ({:.2}% a year)", rate * 100.0, ((1.0 + rate).powf(365.0 / days as f64) - 1.0) * 100.0),
+                false => format!("{:.2}%", rate * 100.0),
+            }
+        };
+        let mut lines = vec![
+            format!("Returns in {}, {} to {} ({}):", fiat, self.from, self.to, sources),
+            format!("  Start value: {:.2} {}", self.start_value, fiat),
+            format!("  End value: {:.2} {}", self.end_value, fiat),
+            format!("  Net deposits: {:.2} {}", self.net_deposits, fiat),
+            format!("  Time-weighted return: {}", percent(self.time_weighted)),
+        ];
+        lines.push(match self.money_weighted {
+            Some(rate) => format!("  Money-weighted return (IRR): {}", percent(rate)),
+            None => "  Money-weighted return (IRR): no rate balances these cash flows".to_string(),
+        });
+        if let Some((coin, change)) = benchmark {
+            lines.push(format!("  Holding {} instead: {}", coin, percent(change)));
+        }
+        lines.join("\n")
+    }
+}
+
+// The rate over `days` at which the cash flows (days in, amount) are worth nothing today,
+// bisected on the log of the growth since net present value falls as it rises
+fn irr(cash: &[(f64, f64)], days: f64) -> Option<f64> {
+    let npv = |log: f64| cash.iter().map(|(day, amount)| amount * (-log * day / days).exp()).sum::<f64>();
+    let (mut low, mut high) = (-20.0, 20.0);
+    if npv(low).signum() == npv(high).signum() {
+        return None;
+    }
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if npv(middle).signum() == npv(low).signum() {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Some(((low + high) / 2.0).exp() - 1.0)
+}
@@ -0,0 +1,126 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::providers::DailyClose;
+
+// Crypto trades every day of the year
+const DAYS_PER_YEAR: f64 = 365.0;
+
+// A holding's current value and its daily closes
+pub struct Holding {
+    pub coin: String,
+    pub value: f64,
+    pub closes: Vec<DailyClose>,
+}
+
+pub struct AssetRisk {
+    pub coin: String,
+    pub weight: f64,
+    // Annualized, as fractions
+    pub volatility: f64,
+    // Share of the portfolio's variance
+    pub contribution: f64,
+}
+
+pub struct Risk {
+    pub value: f64,
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+    pub returns: usize,
+    // Annualized, as fractions
+    pub mean_return: f64,
+    pub volatility: f64,
+    pub risk_free: f64,
+    pub assets: Vec<AssetRisk>,
+}
+
+impl Risk {
+    // Daily returns over the dates every holding has a close for, weighted by today's values.
+    // None without two returns to take a variance of.
+    pub fn estimate(holdings: &[Holding], risk_free: f64) -> Option<Self> {
+        let value: f64 = holdings.iter().map(|h| h.value).sum();
+        if value <= 0.0 {
+            return None;
+        }
+        let prices: Vec<HashMap<NaiveDate, f64>> =
+            holdings.iter().map(|h| h.closes.iter().map(|close| (close.date, close.price)).collect()).collect();
+        let mut dates: BTreeSet<NaiveDate> = holdings.first()?.closes.iter().map(|close| close.date).collect();
+        dates.retain(|date| prices.iter().all(|closes| closes.contains_key(date)));
+        let dates: Vec<NaiveDate> = dates.into_iter().collect();
+        if dates.len() < 3 {
+            return None;
+        }
+        let returns: Vec<Vec<f64>> = prices
+            .iter()
+            .map(|closes| dates.windows(2).map(|pair| closes[&pair[1]] / closes[&pair[0]] - 1.0).collect())
+            .collect();
+        let weights: Vec<f64> = holdings.iter().map(|h| h.value / value).collect();
+        let means: Vec<f64> = returns.iter().map(|r| mean(r)).collect();
+        let covariance: Vec<Vec<f64>> = (0..returns.len())
+            .map(|i| (0..returns.len()).map(|j| covariance(&returns[i], means[i], &returns[j], means[j])).collect())
+            .collect();
+        // Each asset's covariance with the portfolio; weighted, they add up to its variance
+        let with_portfolio: Vec<f64> = covariance.iter().map(|row| row.iter().zip(&weights).map(|(c, w)| c * w).sum()).collect();
+        let variance: f64 = weights.iter().zip(&with_portfolio).map(|(w, c)| w * c).sum();
+        let assets = holdings
+            .iter()
+            .enumerate()
+            .map(|(i, h)| AssetRisk {
+                coin: h.coin.clone(),
+                weight: weights[i],
+                volatility: (covariance[i][i] * DAYS_PER_YEAR).sqrt(),
+                contribution: if variance > 0.0 { weights[i] * with_portfolio[i] / variance } else { 0.0 },
+            })
+            .collect();
+        Some(Risk {
+            value,
+            first: dates[0],
+            last: dates[dates.len() - 1],
+            returns: dates.len() - 1,
+            mean_return: weights.iter().zip(&means).map(|(w, m)| w * m).sum::<f64>() * DAYS_PER_YEAR,
+            volatility: (variance * DAYS_PER_YEAR).sqrt(),
+            risk_free,
+            assets,
+        })
+    }
+
+    // Excess return per unit of volatility; None for a portfolio that doesn't move
+    pub fn sharpe(&self) -> Option<f64> {
+        (self.volatility > 0.0).then(|| (self.mean_return - self.risk_free) / self.volatility)
+    }
+
+    pub fn render(&self, wallet: &str, fiat: &str, source: &str) -> String {
+        let fiat = fiat.to_uppercase();
+        let sharpe = match self.sharpe() {
+            Some(sharpe) => format!("{:.2}", sharpe),
+            None => "n/a, no volatility".to_string(),
+        };
+        let mut lines = vec![
+            format!("Risk of {} from {} daily returns, {} to {} ({}):", wallet, self.returns, self.first, self.last, source),
+            format!("  Value: {:.2} {}", self.value, fiat),
+            format!("  Annualized return: {:+.2}%", self.mean_return * 100.0),
+            format!("  Annualized volatility: {:.2}%", self.volatility * 100.0),
+            format!("  Sharpe ratio: {} (risk-free rate {:.2}%)", sharpe, self.risk_free * 100.0),
+            "Contribution to risk:".to_string(),
+        ];
+        for asset in &self.assets {
+            lines.push(format!(
+                "  {}: {:.2}% of value, {:.2}% of risk, volatility {:.2}%",
+                asset.coin,
+                asset.weight * 100.0,
+                asset.contribution * 100.0,
+                asset.volatility * 100.0
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Sample covariance of two series of the same length
+fn covariance(a: &[f64], mean_a: f64, b: &[f64], mean_b: f64) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - mean_a) * (b - mean_b)).sum::<f64>() / (a.len() - 1) as f64
+}
@@ -0,0 +1,137 @@
+#[cfg(feature = "keychain")]
+use dashmap::DashMap;
+#[cfg(feature = "keychain")]
+use std::io::{BufRead, IsTerminal};
+#[cfg(feature = "keychain")]
+use std::sync::LazyLock;
+
+use crate::{CryptoError, SecretsCommand};
+
+// API keys live in the OS keychain (macOS Keychain, Windows Credential Manager, or the
+// Secret Service on Linux) under this service, one entry per provider holding its keys
+// comma-separated
+#[cfg(feature = "keychain")]
+const SERVICE: &str = "coinwagon";
+
+// Keys read so far; tools are built per call from Python and each read can mean a D-Bus round
+// trip or a Keychain prompt
+#[cfg(feature = "keychain")]
+static LOADED: LazyLock<DashMap<String, Vec<String>>> = LazyLock::new(DashMap::new);
+
+pub fn run(action: SecretsCommand) -> Result<String, CryptoError> {
+    match action {
+        SecretsCommand::Set { name } => {
+            let value = read_secret(&name)?;
+            let count = split(&value).len();
+            if count == 0 {
+                return Err(CryptoError::InvalidInput("No key given".to_string()));
+            }
+            set(&name, &value)?;
+            Ok(format!(
+                "Stored {} key{} for {} in the OS keychain; set api_keys.{}.keychain = true to use {}",
+                count,
+                if count == 1 { "" } else { "s" },
+                name,
+                name,
+                if count == 1 { "it" } else { "them" }
+            ))
+        }
+        SecretsCommand::Delete { name } => {
+            delete(&name)?;
+            Ok(format!("Removed the keys for {} from the OS keychain", name))
+        }
+    }
+}
+
+// The keys stored for `name` by `coinwagon secrets set`
+pub fn get(name: &str) -> Result<Vec<String>, CryptoError> {
+    #[cfg(feature = "keychain")]
+    {
+        if let Some(keys) = LOADED.get(name) {
+            return Ok(keys.clone());
+        }
+        let keys = match entry(name)?.get_password() {
+            Ok(value) => split(&value),
+            Err(keyring::Error::NoEntry) => Vec::new(),
+            Err(e) => return Err(keychain_error("read", name, e)),
+        };
+        if keys.is_empty() {
+            return Err(CryptoError::ConfigError(format!(
+                "No API key for {} in the OS keychain; add one with `coinwagon secrets set {}`",
+                name, name
+            )));
+        }
+        LOADED.insert(name.to_string(), keys.clone());
+        Ok(keys)
+    }
+    #[cfg(not(feature = "keychain"))]
+    Err(unsupported(name))
+}
+
+fn set(name: &str, value: &str) -> Result<(), CryptoError> {
+    #[cfg(feature = "keychain")]
+    {
+        entry(name)?.set_password(value).map_err(|e| keychain_error("store", name, e))?;
+        LOADED.insert(name.to_string(), split(value));
+        Ok(())
+    }
+    #[cfg(not(feature = "keychain"))]
+    {
+        let _ = value;
+        Err(unsupported(name))
+    }
+}
+
+fn delete(name: &str) -> Result<(), CryptoError> {
+    #[cfg(feature = "keychain")]
+    {
+        LOADED.remove(name);
+        match entry(name)?.delete_credential() {
+            Ok(()) => Ok(()),
+            Err(keyring::Error::NoEntry) => Err(CryptoError::InvalidInput(format!("No keys stored for {}", name))),
+            Err(e) => Err(keychain_error("remove", name, e)),
+        }
+    }
+    #[cfg(not(feature = "keychain"))]
+    Err(unsupported(name))
+}
+
+// Prompts without echo on a terminal; otherwise takes the first line of stdin, so keys can be
+// piped in rather than typed into the shell history
+#[cfg(feature = "keychain")]
+fn read_secret(name: &str) -> Result<String, CryptoError> {
+    let read_error = |e: std::io::Error| CryptoError::InvalidInput(format!("Couldn't read the key: {}", e));
+    if std::io::stdin().is_terminal() {
+        return rpassword::prompt_password(format!("API key(s) for {}, comma-separated: ", name)).map_err(read_error);
+    }
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line).map_err(read_error)?;
+    Ok(line)
+}
+
+#[cfg(not(feature = "keychain"))]
+fn read_secret(name: &str) -> Result<String, CryptoError> {
+    Err(unsupported(name))
+}
+
+fn split(value: &str) -> Vec<String> {
+    value.split(',').map(str::trim).filter(|key| !key.is_empty()).map(str::to_string).collect()
+}
+
+#[cfg(feature = "keychain")]
+fn entry(name: &str) -> Result<keyring::Entry, CryptoError> {
+    keyring::Entry::new(SERVICE, name).map_err(|e| keychain_error("open", name, e))
+}
+
+#[cfg(feature = "keychain")]
+fn keychain_error(action: &str, name: &str, e: keyring::Error) -> CryptoError {
+    CryptoError::ConfigError(format!("Couldn't {} the keys for {} in the OS keychain: {}", action, name, e))
+}
+
+#[cfg(not(feature = "keychain"))]
+fn unsupported(name: &str) -> CryptoError {
+    CryptoError::ConfigError(format!(
+        "Can't reach the OS keychain for {}: coinwagon was built without the `keychain` feature",
+        name
+    ))
+}
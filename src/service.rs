@@ -0,0 +1,188 @@
+use clap::Parser;
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo, ServiceStartType,
+    ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+use crate::{Cli, Commands, CryptoError, ServiceCommand, daemon};
+
+// What `service run` was asked to run, for the service's main function to pick up
+static SERVICE: OnceLock<(String, Vec<String>)> = OnceLock::new();
+// Set once the service is registered; unset when not running as one
+static STATUS: OnceLock<ServiceStatusHandle> = OnceLock::new();
+
+// The SCM's errors for a service that isn't running, and for a process it didn't start
+const ERROR_SERVICE_NOT_ACTIVE: i32 = 1062;
+const ERROR_FAILED_SERVICE_CONTROLLER_CONNECT: i32 = 1063;
+
+define_windows_service!(ffi_service_main, service_main);
+
+// `service`: the alerts daemon as a native Windows service. `install` registers this binary
+// with the Service Control Manager to start with the machine, running `service run` with the
+// alerts arguments; `run` is what the SCM starts, and hands them to the daemon, whose stop
+// and reload come from the SCM instead of Ctrl-C.
+pub fn run(action: ServiceCommand, config_path: Option<&Path>) -> Result<String, CryptoError> {
+    match action {
+        ServiceCommand::Install { name, args } => install(&name, args, config_path),
+        ServiceCommand::Uninstall { name } => uninstall(&name),
+        ServiceCommand::Run { name, args } => dispatch(name, args),
+    }
+}
+
+// The daemon's arguments, checked now rather than when the SCM first starts it. The service
+// runs as LocalSystem, whose default config path isn't the user's, so the config file this
+// was run with is passed on unless the arguments name one.
+fn service_args(args: Vec<String>, config_path: Option<&Path>) -> Result<Vec<String>, CryptoError> {
+    let mut args = if args.is_empty() { vec!["alerts".to_string()] } else { args };
+    let cli = parse(&args)?;
+    if !matches!(cli.command, Commands::Alerts { .. }) {
+        return Err(CryptoError::InvalidInput("A service runs the alerts daemon: give `alerts` and its options after --".to_string()));
+    }
+    if cli.config.is_none()
+        && let Some(path) = config_path
+    {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        args = ["--config".to_string(), path.display().to_string()].into_iter().chain(args).collect();
+    }
+    Ok(args)
+}
+
+fn parse(args: &[String]) -> Result<Cli, CryptoError> {
+    Cli::try_parse_from(std::iter::once("coinwagon").chain(args.iter().map(String::as_str)))
+        .map_err(|e| CryptoError::InvalidInput(format!("Bad service arguments: {}", e)))
+}
+
+fn install(name: &str, args: Vec<String>, config_path: Option<&Path>) -> Result<String, CryptoError> {
+    let args = service_args(args, config_path)?;
+    let executable_path = std::env::current_exe().map_err(|e| CryptoError::RuntimeError(format!("Can't find coinwagon's own path: {}", e)))?;
+    let launch_arguments = ["service", "run", "--name", name, "--"].into_iter().map(OsString::from).chain(args.iter().map(OsString::from)).collect();
+    let info = ServiceInfo {
+        name: name.into(),
+        display_name: format!("coinwagon alerts ({})", name).into(),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path,
+        launch_arguments,
+        dependencies: Vec::new(),
+        // LocalSystem
+        account_name: None,
+        account_password: None,
+    };
+    let manager = manager(ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)?;
+    let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG).map_err(|e| failed("create the service", e))?;
+    // Only cosmetic, so a failure isn't worth undoing the install over
+    let _ = service.set_description("Watches prices and the portfolio and sends the [[alerts.rules]] that fire");
+    Ok(format!("Installed service {} running `coinwagon {}`; start it with `sc start {}`", name, args.join(" "), name))
+}
+
+fn uninstall(name: &str) -> Result<String, CryptoError> {
+    let manager = manager(ServiceManagerAccess::CONNECT)?;
+    let access = ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE;
+    let service = manager.open_service(name, access).map_err(|e| failed("open the service", e))?;
+    let stopped = match service.stop() {
+        Ok(_) => true,
+        Err(windows_service::Error::Winapi(e)) if e.raw_os_error() == Some(ERROR_SERVICE_NOT_ACTIVE) => false,
+        Err(e) => return Err(failed("stop the service", e)),
+    };
+    service.delete().map_err(|e| failed("remove the service", e))?;
+    Ok(match stopped {
+        true => format!("Stopped and removed service {}", name),
+        false => format!("Removed service {}", name),
+    })
+}
+
+// Blocks until the service stops, handing the SCM's calls to `service_main` and `control`
+fn dispatch(name: String, args: Vec<String>) -> Result<String, CryptoError> {
+    // Parsed up front so bad arguments fail here, where they can still be seen
+    parse(&args)?;
+    let _ = SERVICE.set((name.clone(), args));
+    match service_dispatcher::start(&name, ffi_service_main) {
+        Ok(()) => Ok(String::new()),
+        Err(windows_service::Error::Winapi(e)) if e.raw_os_error() == Some(ERROR_FAILED_SERVICE_CONTROLLER_CONNECT) => Err(CryptoError::InvalidInput(
+            "service run is started by the Service Control Manager; run `coinwagon alerts` to watch in a console".to_string(),
+        )),
+        Err(e) => Err(failed("start the service dispatcher", e)),
+    }
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    let Some((name, args)) = SERVICE.get() else {
+        return;
+    };
+    let Ok(handle) = service_control_handler::register(name, control) else {
+        return;
+    };
+    let _ = STATUS.set(handle);
+    set_status(ServiceState::StartPending, ServiceExitCode::Win32(0));
+    // The daemon reports running through daemon::notify once its first poll is done
+    match parse(args).and_then(crate::execute) {
+        Ok(_) => set_status(ServiceState::Stopped, ServiceExitCode::Win32(0)),
+        Err(e) => {
+            eprintln!("{}", crate::error_message(&e));
+            set_status(ServiceState::Stopped, ServiceExitCode::ServiceSpecific(1));
+        }
+    }
+}
+
+fn control(control: ServiceControl) -> ServiceControlHandlerResult {
+    match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            set_status(ServiceState::StopPending, ServiceExitCode::Win32(0));
+            daemon::SERVICE_STOP.notify_one();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::ParamChange => {
+            daemon::SERVICE_RELOAD.notify_one();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    }
+}
+
+// daemon::notify's states, as the SCM's service states; nothing outside a service
+pub fn report(state: &str) {
+    match state {
+        "READY=1" => set_status(ServiceState::Running, ServiceExitCode::Win32(0)),
+        "STOPPING=1" => set_status(ServiceState::StopPending, ServiceExitCode::Win32(0)),
+        _ => {}
+    }
+}
+
+fn set_status(current_state: ServiceState, exit_code: ServiceExitCode) {
+    let Some(handle) = STATUS.get() else {
+        return;
+    };
+    let controls_accepted = match current_state {
+        ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN | ServiceControlAccept::PARAM_CHANGE,
+        _ => ServiceControlAccept::empty(),
+    };
+    // Stopping waits for the poll in progress
+    let wait_hint = match current_state {
+        ServiceState::StartPending | ServiceState::StopPending => Duration::from_secs(30),
+        _ => Duration::ZERO,
+    };
+    let status = ServiceStatus { service_type: ServiceType::OWN_PROCESS, current_state, controls_accepted, exit_code, checkpoint: 0, wait_hint, process_id: None };
+    let _ = handle.set_service_status(status);
+}
+
+fn manager(access: ServiceManagerAccess) -> Result<ServiceManager, CryptoError> {
+    ServiceManager::local_computer(None::<&str>, access).map_err(|e| failed("open the Service Control Manager (run as Administrator)", e))
+}
+
+// With the OS's own error, where the crate's says only "IO error in winapi call"
+fn failed(what: &str, e: windows_service::Error) -> CryptoError {
+    let reason = match e {
+        windows_service::Error::Winapi(e) => e.to_string(),
+        e => e.to_string(),
+    };
+    CryptoError::RuntimeError(format!("Can't {}: {}", what, reason))
+}
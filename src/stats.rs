@@ -0,0 +1,36 @@
+use crate::cache::{Cache, RequestCounts};
+
+// What the persistent cache has counted since it was last cleared: its own hit ratio, then one
+// line per provider with its requests, how many failed or were rate limited, and how long they took
+pub fn run(cache: &Cache) -> String {
+    let stats = cache.stats();
+    let hit_ratio = stats.hit_ratio().map_or("n/a".to_string(), |ratio| format!("{:.1}%", ratio * 100.0));
+    let mut output = format!(
+        "Cache: {} entries, {} hits, {} misses, hit ratio {}\n\n",
+        stats.entries, stats.hits, stats.misses, hit_ratio
+    );
+
+    let requests = cache.requests();
+    if requests.is_empty() {
+        output.push_str("No provider requests counted yet");
+        return output;
+    }
+    let width = requests.iter().map(|(provider, _)| provider.len()).max().unwrap_or(0).max("PROVIDER".len());
+    output.push_str(&format!("{:width$}  {:>8}  {:>14}  {:>12}  {:>11}\n", "PROVIDER", "REQUESTS", "ERRORS", "RATE LIMITED", "AVG LATENCY"));
+    for (provider, counts) in &requests {
+        output.push_str(&format!(
+            "{:width$}  {:>8}  {:>14}  {:>12}  {:>11}\n",
+            provider,
+            counts.requests,
+            format!("{} ({})", counts.errors, percent(counts.errors, counts)),
+            format!("{} ({})", counts.rate_limited, percent(counts.rate_limited, counts)),
+            format!("{:.0} ms", counts.total_latency_ms / counts.requests as f64),
+        ));
+    }
+    output.trim_end().to_string()
+}
+
+// Of the provider's requests; none are counted without at least one
+fn percent(count: u64, counts: &RequestCounts) -> String {
+    format!("{:.1}%", count as f64 / counts.requests as f64 * 100.0)
+}
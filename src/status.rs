@@ -0,0 +1,122 @@
+use futures::future::join_all;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, StatusCode};
+use std::time::{Duration, Instant};
+
+use crate::keys::KeyUsage;
+use crate::providers::{Providers, Transport};
+
+struct Probe {
+    provider: String,
+    kind: &'static str,
+    status: String,
+    latency: Option<Duration>,
+    headroom: Option<String>,
+    // Requests and 429s this session, for providers with API keys
+    used: Option<String>,
+    // Of its [quotas], across runs
+    quota: Option<String>,
+}
+
+// Pings every provider's status endpoint at once and reports one line per provider and kind,
+// or per API key for providers that have several
+pub async fn run(client: &Client, providers: &Providers) -> String {
+    let transport = &providers.transport;
+    let targets = providers
+        .price
+        .iter()
+        .map(|p| ("price", p.name(), p.status_url()))
+        .chain(providers.balance.iter().map(|p| ("balance", p.name(), p.status_url())));
+    let targets = targets.flat_map(|(kind, name, url)| {
+        let usage = transport.key_usage(name);
+        if usage.is_empty() {
+            return vec![(kind, name, url, None)];
+        }
+        usage.into_iter().enumerate().map(|(i, usage)| (kind, name, url.clone(), Some((i, usage)))).collect()
+    });
+    let probes = join_all(targets.map(|(kind, name, url, key)| probe(client, transport, kind, name, url, key))).await;
+
+    let width = probes.iter().map(|p| p.provider.len()).max().unwrap_or(0).max("PROVIDER".len());
+    let quota_width = probes.iter().filter_map(|p| p.quota.as_ref()).map(|q| q.chars().count()).max().unwrap_or(0).max("QUOTA".len());
+    let mut output = format!(
+        "{:width$}  {:7}  {:>8}  {:14}  {:16}  {:quota_width$}  STATUS\n",
+        "PROVIDER", "KIND", "LATENCY", "RATE LIMIT", "USED", "QUOTA"
+    );
+    for probe in probes {
+        output.push_str(&format!(
+            "{:width$}  {:7}  {:>8}  {:14}  {:16}  {:quota_width$}  {}\n",
+            probe.provider,
+            probe.kind,
+            probe.latency.map_or("-".to_string(), |d| format!("{} ms", d.as_millis())),
+            probe.headroom.as_deref().unwrap_or("-"),
+            probe.used.as_deref().unwrap_or("-"),
+            probe.quota.as_deref().unwrap_or("-"),
+            probe.status,
+        ));
+    }
+    output.trim_end().to_string()
+}
+
+async fn probe(
+    client: &Client,
+    transport: &Transport,
+    kind: &'static str,
+    provider: &str,
+    url: Option<String>,
+    key: Option<(usize, KeyUsage)>,
+) -> Probe {
+    let mut probe = Probe {
+        provider: provider.to_string(),
+        kind,
+        status: String::new(),
+        latency: None,
+        headroom: None,
+        used: None,
+        quota: transport.quota_usage(provider),
+    };
+    if let Some((_, usage)) = &key {
+        probe.provider = format!("{} {}", provider, usage.label);
+        probe.used = Some(match usage.limited {
+            0 => format!("{} req", usage.used),
+            limited => format!("{} req, {} limited", usage.used, limited),
+        });
+    }
+    // Plugins have no endpoint of their own to ping
+    let Some(url) = url else {
+        probe.status = "not pinged (plugin)".to_string();
+        return probe;
+    };
+
+    let started = Instant::now();
+    let request = transport.prepare(provider, key.map(|(index, _)| index), client.get(&url));
+    match request.send().await {
+        Ok(resp) => {
+            probe.latency = Some(started.elapsed());
+            probe.headroom = headroom(resp.headers());
+            probe.status = match resp.status() {
+                status if status.is_success() => "ok".to_string(),
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                    format!("credentials rejected (HTTP {})", resp.status().as_u16())
+                }
+                StatusCode::TOO_MANY_REQUESTS => "rate limited".to_string(),
+                status => format!("HTTP {}", status),
+            };
+        }
+        Err(e) => probe.status = format!("unreachable: {}", e.without_url()),
+    }
+    probe
+}
+
+// Remaining quota, under whichever header name the provider uses
+fn headroom(headers: &HeaderMap) -> Option<String> {
+    let header = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()).map(str::to_string))
+    };
+    let remaining = header(&["x-ratelimit-remaining", "x-rate-limit-remaining", "ratelimit-remaining"])?;
+    Some(match header(&["x-ratelimit-limit", "x-rate-limit-limit", "ratelimit-limit"]) {
+        Some(limit) => format!("{}/{} left", remaining, limit),
+        None => format!("{} left", remaining),
+    })
+}
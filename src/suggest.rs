@@ -0,0 +1,51 @@
+// Most suggestions given for one misspelling
+const MAX_SUGGESTIONS: usize = 3;
+
+// Edits (insertions, deletions, substitutions) between `a` and `b`
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// The candidates close enough to `word` to be what was meant, closest first; none when `word`
+// is a candidate itself, since it's spelt right. Short words allow fewer edits, so `eth`
+// doesn't suggest every three-letter ticker.
+pub fn nearest<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let word = word.to_lowercase();
+    let allowed = match word.chars().count() {
+        0..=4 => 1,
+        5..=8 => 2,
+        _ => 3,
+    };
+    let mut close: Vec<(usize, &str)> = Vec::new();
+    for candidate in candidates {
+        let distance = levenshtein(&word, candidate);
+        if distance == 0 {
+            return Vec::new();
+        }
+        if distance <= allowed {
+            close.push((distance, candidate));
+        }
+    }
+    close.sort();
+    close.dedup_by(|a, b| a.1 == b.1);
+    close.into_iter().take(MAX_SUGGESTIONS).map(|(_, candidate)| candidate).collect()
+}
+
+// "; did you mean x, y or z?", or nothing without suggestions
+pub fn did_you_mean(suggestions: &[&str]) -> String {
+    match suggestions {
+        [] => String::new(),
+        [only] => format!("; did you mean {}?", only),
+        [rest @ .., last] => format!("; did you mean {} or {}?", rest.join(", "), last),
+    }
+}
@@ -0,0 +1,46 @@
+use handlebars::{Handlebars, no_escape};
+use serde_json::Value;
+
+use crate::CryptoError;
+
+// `--template`: a Handlebars template, filled from a command's result as JSON. Names that aren't
+// there render as nothing, and nothing is HTML-escaped, since reports are Markdown or text as
+// often as HTML.
+pub struct Template {
+    registry: Handlebars<'static>,
+}
+
+// The one template's name in the registry
+const NAME: &str = "template";
+
+impl Template {
+    pub fn load(path: &str) -> Result<Self, CryptoError> {
+        let source = std::fs::read_to_string(path).map_err(|e| CryptoError::InvalidInput(format!("Can't read template {}: {}", path, e)))?;
+        let mut registry = Handlebars::new();
+        registry.register_escape_fn(no_escape);
+        registry.register_template_string(NAME, source).map_err(|e| {
+            let at = e.pos().map_or(String::new(), |(line, column)| format!(" line {}, column {}:", line, column));
+            CryptoError::InvalidInput(format!("Template {}:{} {}", path, at, e.reason()))
+        })?;
+        Ok(Template { registry })
+    }
+
+    pub fn render(&self, context: &Value) -> Result<String, CryptoError> {
+        self.registry
+            .render(NAME, &whole(context.clone()))
+            .map_err(|e| CryptoError::InvalidInput(format!("Template failed: {}", e)))
+    }
+}
+
+// Whole floats as integers, so 7500.0 renders as 7500 the way the text output has it
+fn whole(value: Value) -> Value {
+    match value {
+        Value::Number(number) => match number.as_f64() {
+            Some(float) if number.is_f64() && float.fract() == 0.0 && float.abs() < i64::MAX as f64 => Value::from(float as i64),
+            _ => Value::Number(number),
+        },
+        Value::Array(items) => Value::Array(items.into_iter().map(whole).collect()),
+        Value::Object(fields) => Value::Object(fields.into_iter().map(|(name, value)| (name, whole(value))).collect()),
+        value => value,
+    }
+}
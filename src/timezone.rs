@@ -0,0 +1,42 @@
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::sync::RwLock;
+
+use crate::CryptoError;
+
+// The zone times are shown in: None for the machine's local zone, the default
+static ZONE: RwLock<Option<Tz>> = RwLock::new(None);
+
+// The zone of every later timestamp: `name` (--timezone or the config's timezone) is "local"
+// or an IANA name like "UTC" or "Europe/Berlin"
+pub fn select(name: Option<&str>) -> Result<(), CryptoError> {
+    let zone = match name.map(str::trim) {
+        None => None,
+        Some(name) if name.eq_ignore_ascii_case("local") => None,
+        Some(name) => Some(name.parse::<Tz>().map_err(|_| {
+            CryptoError::ConfigError(format!("Unknown timezone '{}'; give 'local' or an IANA name like Europe/Berlin", name))
+        })?),
+    };
+    *ZONE.write().unwrap() = zone;
+    Ok(())
+}
+
+// ISO 8601 in the selected zone to the second, like 2024-06-01T14:00:00+02:00; UTC ends in Z
+pub fn iso(time: impl Into<DateTime<Utc>>) -> String {
+    let time = time.into();
+    match *ZONE.read().unwrap() {
+        Some(zone) => time.with_timezone(&zone).to_rfc3339_opts(SecondsFormat::Secs, true),
+        None => time.with_timezone(&Local).to_rfc3339_opts(SecondsFormat::Secs, true),
+    }
+}
+
+// The last second of `date` in the selected zone, for --at dates
+pub fn end_of_day(date: NaiveDate) -> DateTime<Utc> {
+    let end = date.and_hms_opt(23, 59, 59).unwrap_or_default();
+    // Where a DST change repeats that second the earlier one counts, and where it skips it UTC's
+    let zoned = match *ZONE.read().unwrap() {
+        Some(zone) => zone.from_local_datetime(&end).earliest().map(|time| time.with_timezone(&Utc)),
+        None => Local.from_local_datetime(&end).earliest().map(|time| time.with_timezone(&Utc)),
+    };
+    zoned.unwrap_or_else(|| end.and_utc())
+}
@@ -0,0 +1,216 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::ClientBuilder;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::CryptoError;
+use crate::config::TlsConfig;
+
+pub fn apply(mut builder: ClientBuilder, config: &TlsConfig) -> Result<ClientBuilder, CryptoError> {
+    let extra_roots = match &config.ca_bundle {
+        Some(path) => load_pem(path)?,
+        None => Vec::new(),
+    };
+
+    // Without pins the stock verifier is enough; just extend its trust store
+    if config.pins.is_empty() {
+        for der in extra_roots {
+            let cert = reqwest::Certificate::from_der(&der)?;
+            builder = builder.add_root_certificate(cert);
+        }
+        return Ok(builder);
+    }
+
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
+    }));
+    for der in extra_roots {
+        roots
+            .add(&Certificate(der))
+            .map_err(|e| CryptoError::ConfigError(format!("Invalid certificate in CA bundle: {}", e)))?;
+    }
+
+    let pins = config
+        .pins
+        .iter()
+        .map(|(host, pins)| (host.to_ascii_lowercase(), pins.iter().map(|p| normalize_pin(p)).collect()))
+        .collect();
+    let verifier = PinningVerifier {
+        inner: WebPkiVerifier::new(roots, None),
+        pins,
+    };
+    let tls = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(builder.use_preconfigured_tls(tls))
+}
+
+fn load_pem(path: &str) -> Result<Vec<Vec<u8>>, CryptoError> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| CryptoError::ConfigError(format!("Failed to open CA bundle {}: {}", path, e)))?;
+    let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+        .map_err(|e| CryptoError::ConfigError(format!("Failed to parse CA bundle {}: {}", path, e)))?;
+    if certs.is_empty() {
+        return Err(CryptoError::ConfigError(format!("No certificates found in CA bundle {}", path)));
+    }
+    Ok(certs)
+}
+
+// Pins are compared as base64; a 64-digit hex SHA-256 (what `openssl dgst -sha256` prints) is
+// converted, and the "sha256/" prefix is optional either way
+fn normalize_pin(pin: &str) -> String {
+    let pin = pin.trim();
+    let pin = pin.strip_prefix("sha256/").unwrap_or(pin);
+    if pin.len() == 64 && pin.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let bytes: Vec<u8> = (0..64)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&pin[i..i + 2], 16).unwrap_or_default())
+            .collect();
+        return STANDARD.encode(bytes);
+    }
+    pin.to_string()
+}
+
+struct PinningVerifier {
+    inner: WebPkiVerifier,
+    pins: HashMap<String, Vec<String>>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self
+            .inner
+            .verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)?;
+
+        let ServerName::DnsName(host) = server_name else {
+            return Ok(verified);
+        };
+        let Some(pins) = self.pins.get(&host.as_ref().to_ascii_lowercase()) else {
+            return Ok(verified);
+        };
+
+        let (_, cert) = x509_parser::parse_x509_certificate(&end_entity.0)
+            .map_err(|e| rustls::Error::General(format!("Failed to parse server certificate: {}", e)))?;
+        let spki_hash = STANDARD.encode(Sha256::digest(cert.tbs_certificate.subject_pki.raw));
+        if pins.contains(&spki_hash) {
+            Ok(verified)
+        } else {
+            Err(rustls::Error::General(format!(
+                "Certificate pin mismatch for {} (got sha256/{})",
+                host.as_ref(),
+                spki_hash
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // tests/fixtures/tls_leaf.pem is issued by tls_ca.pem for api.example.com and other.example.com
+    const CA: &str = include_str!("../tests/fixtures/tls_ca.pem");
+    const LEAF: &str = include_str!("../tests/fixtures/tls_leaf.pem");
+    const LEAF_PIN: &str = "2FsDg16QPkQzBLKqHcL6fkQ9+XRw+dvMXUnHtroRSJA=";
+    const LEAF_PIN_HEX: &str = "d85b03835e903e443304b2aa1dc2fa7e443df97470f9dbcc5d49c7b6ba114890";
+
+    fn der(pem: &str) -> Certificate {
+        Certificate(rustls_pemfile::certs(&mut pem.as_bytes()).unwrap().remove(0))
+    }
+
+    fn verifier(pins: &[(&str, &str)]) -> PinningVerifier {
+        let mut roots = RootCertStore::empty();
+        roots.add(&der(CA)).unwrap();
+        PinningVerifier {
+            inner: WebPkiVerifier::new(roots, None),
+            pins: pins.iter().map(|(host, pin)| (host.to_string(), vec![normalize_pin(pin)])).collect(),
+        }
+    }
+
+    fn verify(verifier: &PinningVerifier, cert: &Certificate, host: &str) -> Result<ServerCertVerified, rustls::Error> {
+        let name = ServerName::try_from(host).unwrap();
+        // Inside the certificates' validity window
+        let now = UNIX_EPOCH + Duration::from_secs(1_830_000_000);
+        verifier.verify_server_cert(cert, &[], &name, &mut std::iter::empty(), &[], now)
+    }
+
+    #[test]
+    fn matching_pin_is_accepted() {
+        let verifier = verifier(&[("api.example.com", LEAF_PIN)]);
+        assert!(verify(&verifier, &der(LEAF), "api.example.com").is_ok());
+    }
+
+    #[test]
+    fn mismatched_pin_is_rejected() {
+        let verifier = verifier(&[("api.example.com", "sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")]);
+        let err = verify(&verifier, &der(LEAF), "api.example.com").unwrap_err();
+        assert_eq!(
+            err,
+            rustls::Error::General(format!("Certificate pin mismatch for api.example.com (got sha256/{})", LEAF_PIN))
+        );
+    }
+
+    #[test]
+    fn pins_are_matched_per_host() {
+        let verifier = verifier(&[("other.example.com", "sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")]);
+        assert!(verify(&verifier, &der(LEAF), "api.example.com").is_ok());
+        assert!(verify(&verifier, &der(LEAF), "other.example.com").is_err());
+    }
+
+    #[test]
+    fn unpinned_host_falls_back_to_webpki() {
+        let verifier = verifier(&[]);
+        assert!(verify(&verifier, &der(LEAF), "api.example.com").is_ok());
+        // The chain is still checked: a name the certificate doesn't cover, and an issuer
+        // outside the trust store, are both refused
+        assert!(verify(&verifier, &der(LEAF), "wrong.example.com").is_err());
+        let untrusted = PinningVerifier {
+            inner: WebPkiVerifier::new(RootCertStore::empty(), None),
+            pins: HashMap::new(),
+        };
+        assert!(verify(&untrusted, &der(LEAF), "api.example.com").is_err());
+    }
+
+    #[test]
+    fn pin_does_not_bypass_webpki() {
+        let verifier = PinningVerifier {
+            inner: WebPkiVerifier::new(RootCertStore::empty(), None),
+            pins: HashMap::from([("api.example.com".to_string(), vec![LEAF_PIN.to_string()])]),
+        };
+        assert!(verify(&verifier, &der(LEAF), "api.example.com").is_err());
+    }
+
+    #[test]
+    fn hex_and_unprefixed_pins_are_accepted() {
+        for pin in [LEAF_PIN_HEX, &format!("sha256/{}", LEAF_PIN_HEX.to_uppercase()), LEAF_PIN] {
+            let verifier = verifier(&[("api.example.com", pin)]);
+            assert!(verify(&verifier, &der(LEAF), "api.example.com").is_ok(), "{}", pin);
+        }
+    }
+
+    #[test]
+    fn normalizes_pins_to_base64() {
+        assert_eq!(normalize_pin(&format!("sha256/{}", LEAF_PIN)), LEAF_PIN);
+        assert_eq!(normalize_pin(LEAF_PIN), LEAF_PIN);
+        assert_eq!(normalize_pin(LEAF_PIN_HEX), LEAF_PIN);
+        assert_eq!(normalize_pin(&format!(" sha256/{} ", LEAF_PIN_HEX)), LEAF_PIN);
+        // Not 64 hex digits: left for the base64 comparison, which it will never match
+        assert_eq!(normalize_pin("sha256/d85b"), "d85b");
+    }
+}
@@ -0,0 +1,230 @@
+use reqwest::Client;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tokio::sync::OnceCell;
+
+use crate::CryptoError;
+use crate::config::TokensConfig;
+use crate::providers::{Token, TokenQuote};
+
+// The parts of the Uniswap token list format (https://tokenlists.org) needed to find a token
+#[derive(Deserialize)]
+struct TokenList {
+    tokens: Vec<ListedToken>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListedToken {
+    chain_id: u64,
+    address: String,
+    symbol: String,
+    decimals: i32,
+}
+
+// Token lists, loaded on first use and shared by every lookup. Lists given by URL are kept on
+// disk and only downloaded again once `refresh_hours` old; if that download fails the old copy
+// is used.
+pub struct TokenLists {
+    sources: Vec<String>,
+    // Where downloaded lists are kept; None with a non-persistent cache
+    dir: Option<PathBuf>,
+    max_age: Duration,
+    loaded: OnceCell<Vec<ListedToken>>,
+}
+
+impl TokenLists {
+    pub fn new(config: &TokensConfig, cache_path: Option<PathBuf>) -> Self {
+        TokenLists {
+            sources: config.lists.clone(),
+            dir: cache_path.and_then(|path| Some(path.parent()?.join("token-lists"))),
+            max_age: Duration::from_secs(config.refresh_hours * 3600),
+            loaded: OnceCell::new(),
+        }
+    }
+
+    // `token` is a symbol like LINK or a contract address
+    pub async fn resolve(&self, client: &Client, chain: &str, chain_id: u64, token: &str, verbose: bool) -> Result<Token, CryptoError> {
+        let tokens = self.loaded.get_or_try_init(|| self.load(client, verbose)).await?;
+        find(tokens, chain, chain_id, token)
+    }
+
+    // Same, from local lists and downloaded copies only, for --dry-run
+    pub fn resolve_offline(&self, chain: &str, chain_id: u64, token: &str) -> Option<Token> {
+        let tokens: Vec<ListedToken> = self
+            .sources
+            .iter()
+            .filter_map(|source| std::fs::read_to_string(self.local_path(source)?).ok())
+            .filter_map(|body| serde_json::from_str::<TokenList>(&body).ok())
+            .flat_map(|list| list.tokens)
+            .collect();
+        find(&tokens, chain, chain_id, token).ok()
+    }
+
+    // Lists that would be downloaded before a lookup, for --dry-run
+    pub fn stale_urls(&self) -> Vec<&str> {
+        self.sources
+            .iter()
+            .filter(|source| is_url(source) && self.fresh_copy(source).is_none())
+            .map(String::as_str)
+            .collect()
+    }
+
+    async fn load(&self, client: &Client, verbose: bool) -> Result<Vec<ListedToken>, CryptoError> {
+        let mut tokens = Vec::new();
+        let mut last_error = None;
+        for source in &self.sources {
+            let list = self
+                .read(client, source, verbose)
+                .await
+                .and_then(|body| {
+                    serde_json::from_str::<TokenList>(&body)
+                        .map_err(|e| CryptoError::InvalidInput(format!("Token list {} isn't valid: {}", source, e)))
+                });
+            match list {
+                Ok(list) => tokens.extend(list.tokens),
+                Err(e) => {
+                    eprintln!("Warning: skipping token list {}: {}", source, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match (tokens.is_empty(), last_error) {
+            (true, Some(e)) => Err(e),
+            _ => Ok(tokens),
+        }
+    }
+
+    async fn read(&self, client: &Client, source: &str, verbose: bool) -> Result<String, CryptoError> {
+        if !is_url(source) {
+            return std::fs::read_to_string(source)
+                .map_err(|e| CryptoError::InvalidInput(format!("Failed to read token list {}: {}", source, e)));
+        }
+        if let Some(path) = self.fresh_copy(source) {
+            return std::fs::read_to_string(path).map_err(|e| CryptoError::RuntimeError(e.to_string()));
+        }
+        if verbose {
+            eprintln!("Downloading token list {}", source);
+        }
+        let download = async { client.get(source).send().await?.error_for_status()?.text().await };
+        match download.await {
+            Ok(body) => {
+                if let Some(path) = self.local_path(source) {
+                    let saved = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|_| std::fs::write(&path, &body));
+                    if let Err(e) = saved {
+                        eprintln!("Warning: couldn't save token list to {}: {}", path.display(), e);
+                    }
+                }
+                Ok(body)
+            }
+            Err(e) => match self.local_path(source).and_then(|path| std::fs::read_to_string(path).ok()) {
+                Some(body) => {
+                    eprintln!("Warning: couldn't refresh token list {} ({}), using the saved copy", source, e);
+                    Ok(body)
+                }
+                None => Err(e.into()),
+            },
+        }
+    }
+
+    // Where a list lives on disk: the file itself, or the saved copy of a URL
+    fn local_path(&self, source: &str) -> Option<PathBuf> {
+        if !is_url(source) {
+            return Some(PathBuf::from(source));
+        }
+        let name: String = source
+            .split_once("://")
+            .map_or(source, |(_, rest)| rest)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+            .collect();
+        Some(self.dir.as_ref()?.join(format!("{}.json", name)))
+    }
+
+    fn fresh_copy(&self, source: &str) -> Option<PathBuf> {
+        let path = self.local_path(source)?;
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        (age < self.max_age).then_some(path)
+    }
+}
+
+// Which discovered tokens count. Airdropped scam tokens are the bulk of most addresses'
+// holdings, so anything without a price, on a deny list, or below the configured market cap or
+// trading volume is left out unless --include-unverified.
+pub struct TokenFilter {
+    // Lowercase contract addresses and symbols
+    deny: Vec<String>,
+    min_market_cap: f64,
+    min_volume_24h: f64,
+}
+
+impl TokenFilter {
+    pub fn new(config: &TokensConfig) -> Result<Self, CryptoError> {
+        let mut deny: Vec<String> = config.deny.iter().map(|entry| entry.trim().to_lowercase()).collect();
+        for path in &config.deny_lists {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| CryptoError::ConfigError(format!("Failed to read deny list {}: {}", path, e)))?;
+            deny.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_lowercase),
+            );
+        }
+        Ok(TokenFilter { deny, min_market_cap: config.min_market_cap, min_volume_24h: config.min_volume_24h })
+    }
+
+    // Why `token` is left out, or None if it counts
+    pub fn reject(&self, token: &Token, quote: Option<&TokenQuote>) -> Option<String> {
+        if self.deny.contains(&token.address.to_lowercase()) || self.deny.contains(&token.symbol.to_lowercase()) {
+            return Some("on a deny list".to_string());
+        }
+        let Some(quote) = quote else {
+            return Some("no price".to_string());
+        };
+        if self.min_market_cap > 0.0 && quote.market_cap.is_none_or(|cap| cap < self.min_market_cap) {
+            return Some(format!("market cap under {}", self.min_market_cap));
+        }
+        if self.min_volume_24h > 0.0 && quote.volume_24h.is_none_or(|volume| volume < self.min_volume_24h) {
+            return Some(format!("24h volume under {}", self.min_volume_24h));
+        }
+        None
+    }
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+fn find(tokens: &[ListedToken], chain: &str, chain_id: u64, token: &str) -> Result<Token, CryptoError> {
+    let by_address = token.starts_with("0x");
+    let mut matches: Vec<&ListedToken> = tokens
+        .iter()
+        .filter(|t| t.chain_id == chain_id)
+        .filter(|t| match by_address {
+            true => t.address.eq_ignore_ascii_case(token),
+            false => t.symbol.eq_ignore_ascii_case(token),
+        })
+        .collect();
+    // The same contract in several lists is one token
+    let mut seen = Vec::new();
+    matches.retain(|t| {
+        let address = t.address.to_lowercase();
+        let new = !seen.contains(&address);
+        seen.push(address);
+        new
+    });
+    match matches.as_slice() {
+        [t] => Ok(Token { symbol: t.symbol.clone(), address: t.address.clone(), decimals: t.decimals }),
+        [] => Err(CryptoError::InvalidInput(format!("No token {} on {} in the token lists", token, chain))),
+        several => Err(CryptoError::InvalidInput(format!(
+            "{} is ambiguous on {}: {}; pass the contract address instead",
+            token,
+            chain,
+            several.iter().map(|t| t.address.as_str()).collect::<Vec<_>>().join(", ")
+        ))),
+    }
+}
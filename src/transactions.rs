@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::CryptoError;
+use crate::config::TransactionsConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Trade,
+    Deposit,
+    Withdrawal,
+}
+
+impl Kind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Trade => "trade",
+            Kind::Deposit => "deposit",
+            Kind::Withdrawal => "withdrawal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Amount {
+    pub asset: String,
+    pub amount: f64,
+}
+
+// A trade, deposit or withdrawal on an exchange account. A trade received `amount` of `asset`
+// and paid `counter` for it, so a sale has the coin sold as the counter; deposits and
+// withdrawals have no counter and a positive amount either way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transaction {
+    // Exchange, lowercase, and its own id for the transaction; together they're unique
+    pub source: String,
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub kind: Kind,
+    pub asset: String,
+    pub amount: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<Amount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<Amount>,
+}
+
+impl Transaction {
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "{} {} {} {} {}",
+            crate::timezone::iso(self.time),
+            self.source,
+            self.kind.label(),
+            self.amount,
+            self.asset
+        );
+        if let Some(counter) = &self.counter {
+            text.push_str(&format!(" for {} {}", counter.amount, counter.asset));
+        }
+        if let Some(fee) = &self.fee {
+            text.push_str(&format!(" (fee {} {})", fee.amount, fee.asset));
+        }
+        text
+    }
+}
+
+// Transactions imported from exchanges, oldest first, in a JSON file under the data directory
+// so cost basis and tax reports don't need the exchanges' CSV exports
+pub struct TransactionStore {
+    path: PathBuf,
+    transactions: Vec<Transaction>,
+}
+
+impl TransactionStore {
+    // Defaults to ~/.local/share/coinwagon/transactions.json
+    pub fn path(config: &TransactionsConfig) -> Option<PathBuf> {
+        match &config.path {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::data_dir().map(|dir| dir.join("coinwagon").join("transactions.json")),
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self, CryptoError> {
+        let transactions = match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| {
+                CryptoError::RuntimeError(format!("Transaction store {} is corrupt: {}", path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(CryptoError::RuntimeError(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        Ok(TransactionStore { path: path.to_path_buf(), transactions })
+    }
+
+    pub fn all(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    // Adds the transactions not stored yet and returns how many that was
+    pub fn add(&mut self, transactions: Vec<Transaction>) -> usize {
+        let before = self.transactions.len();
+        for transaction in transactions {
+            let stored = self.transactions.iter().any(|t| t.source == transaction.source && t.id == transaction.id);
+            if !stored {
+                self.transactions.push(transaction);
+            }
+        }
+        self.transactions.sort_by_key(|t| t.time);
+        self.transactions.len() - before
+    }
+
+    pub fn save(&self) -> Result<(), CryptoError> {
+        let json = serde_json::to_string_pretty(&self.transactions).map_err(|e| CryptoError::RuntimeError(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| CryptoError::RuntimeError(e.to_string()))?;
+        }
+        std::fs::write(&self.path, json)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
@@ -0,0 +1,41 @@
+use crate::CryptoError;
+use crate::coins;
+
+// Moves the decimal point of `amount` by `places` (right when positive), exactly, so 1 ETH in wei
+// doesn't come out as 999999999999999900
+fn shift(amount: &str, places: i64) -> Option<String> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty() || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let digits = format!("{}{}", whole, fraction);
+    let point = whole.len() as i64 + places;
+    let (whole, fraction) = if point <= 0 {
+        (String::new(), format!("{}{}", "0".repeat(point.unsigned_abs() as usize), digits))
+    } else if point as usize >= digits.len() {
+        (format!("{}{}", digits, "0".repeat(point as usize - digits.len())), String::new())
+    } else {
+        let (whole, fraction) = digits.split_at(point as usize);
+        (whole.to_string(), fraction.to_string())
+    };
+    let whole = whole.trim_start_matches('0');
+    let whole = if whole.is_empty() { "0" } else { whole };
+    let fraction = fraction.trim_end_matches('0');
+    Some(if fraction.is_empty() { whole.to_string() } else { format!("{}.{}", whole, fraction) })
+}
+
+// `amount` of one denomination in another of the same coin, e.g. sat to BTC
+pub fn convert(amount: &str, from: &str, to: &str) -> Result<String, CryptoError> {
+    let lookup = |name: &str| coins::unit(name).ok_or_else(|| CryptoError::InvalidInput(format!("Unknown unit '{}'", name)));
+    let (from_name, from_coin, from_power) = lookup(from)?;
+    let (to_name, to_coin, to_power) = lookup(to)?;
+    if from_coin != to_coin {
+        return Err(CryptoError::InvalidInput(format!(
+            "{} is a unit of {} and {} of {}; units only convert within a coin",
+            from_name, from_coin, to_name, to_coin
+        )));
+    }
+    let converted = shift(amount, from_power as i64 - to_power as i64)
+        .ok_or_else(|| CryptoError::InvalidInput(format!("Amount must be a plain decimal number, got '{}'", amount)))?;
+    Ok(format!("{} {}", converted, to_name))
+}
@@ -0,0 +1,81 @@
+use crate::coins;
+use crate::providers::UtxoSet;
+
+// Blocks come about every ten minutes
+const AGE_BUCKETS: &[(u64, &str)] = &[(144, "under a day"), (1008, "under a week"), (4320, "under a month"), (52560, "under a year")];
+
+// What spending from an address type adds to a transaction, in vbytes
+struct Sizes {
+    overhead: f64,
+    input: f64,
+    output: f64,
+}
+
+fn sizes(address: &str) -> Sizes {
+    let lower = address.to_lowercase();
+    let segwit = lower.starts_with("bc1") || lower.starts_with("tb1") || lower.starts_with("bcrt1");
+    match address {
+        _ if coins::is_taproot(address) => Sizes { overhead: 10.5, input: 57.5, output: 43.0 },
+        // P2WSH, taken to be a 2-of-3 multisig
+        _ if segwit && address.len() > 50 => Sizes { overhead: 10.5, input: 104.5, output: 43.0 },
+        _ if segwit => Sizes { overhead: 10.5, input: 68.0, output: 31.0 },
+        // P2SH, taken to wrap P2WPKH
+        _ if address.starts_with('3') || address.starts_with('2') => Sizes { overhead: 10.5, input: 91.0, output: 32.0 },
+        _ => Sizes { overhead: 10.0, input: 148.0, output: 34.0 },
+    }
+}
+
+fn btc(sats: u64) -> String {
+    format!("{:.8} BTC", sats as f64 / 100_000_000.0)
+}
+
+// Dust is what costs more to spend at `fee_rate` than it holds. `label` is what was asked
+// about, and `address` one of its addresses, whose type sizes the inputs.
+pub fn render(label: &str, address: &str, set: &UtxoSet, fee_rate: f64, source: &str) -> String {
+    let sizes = sizes(address);
+    let spend_cost = (sizes.input * fee_rate).ceil() as u64;
+    let held: u64 = set.utxos.iter().map(|utxo| utxo.value).sum();
+    let mut lines = vec![
+        format!("UTXOs of {} ({}, {} sat/vB):", label, source, fee_rate),
+        format!("  Outputs: {} holding {}", set.utxos.len(), btc(held)),
+    ];
+    if set.utxos.len() < set.total {
+        lines.push(format!("  Only the newest {} of {} are listed; the rest aren't counted", set.utxos.len(), set.total));
+    }
+    let dust: Vec<u64> = set.utxos.iter().map(|utxo| utxo.value).filter(|value| *value <= spend_cost).collect();
+    lines.push(format!(
+        "  Dust: {} output{} holding {} sats, each worth no more than the {} sats spending it costs",
+        dust.len(),
+        if dust.len() == 1 { "" } else { "s" },
+        dust.iter().sum::<u64>(),
+        spend_cost
+    ));
+    let mut ages = vec![0usize; AGE_BUCKETS.len() + 1];
+    let mut unconfirmed = 0;
+    for utxo in &set.utxos {
+        let Some(height) = utxo.height else {
+            unconfirmed += 1;
+            continue;
+        };
+        let blocks = set.tip.saturating_sub(height) + 1;
+        ages[AGE_BUCKETS.iter().position(|(limit, _)| blocks < *limit).unwrap_or(AGE_BUCKETS.len())] += 1;
+    }
+    let mut buckets: Vec<String> = Vec::new();
+    if unconfirmed > 0 {
+        buckets.push(format!("unconfirmed {}", unconfirmed));
+    }
+    let names = AGE_BUCKETS.iter().map(|(_, name)| *name).chain(["a year or more"]);
+    buckets.extend(names.zip(&ages).filter(|(_, count)| **count > 0).map(|(name, count)| format!("{} {}", name, count)));
+    if !buckets.is_empty() {
+        lines.push(format!("  Age: {}", buckets.join(", ")));
+    }
+    match set.utxos.len() {
+        0 | 1 => lines.push("  Nothing to consolidate".to_string()),
+        count => {
+            let vsize = sizes.overhead + count as f64 * sizes.input + sizes.output;
+            let fee = (vsize * fee_rate).ceil() as u64;
+            lines.push(format!("  Consolidating into one output: {} vB, {} sats ({})", vsize, fee, btc(fee)));
+        }
+    }
+    lines.join("\n")
+}
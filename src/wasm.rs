@@ -0,0 +1,63 @@
+use reqwest::Client;
+use wasm_bindgen::prelude::*;
+
+use crate::CryptoError;
+use crate::coins::resolve_symbol;
+use crate::config::Config;
+use crate::providers::{PriceQuote, Providers};
+
+// Browser API over the same providers and fallback order as the native tool, minus the
+// cache, proxy/Tor and plugins. Requests go through fetch, so the browser's CORS rules apply.
+
+fn js_error(e: CryptoError) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+async fn lookup_price(client: &Client, crypto: &str, fiat: &str) -> Result<f64, CryptoError> {
+    let providers = Providers::from_config(&Config::default())?;
+    let mut last_error = None;
+    for provider in providers.price.iter().filter(|p| p.supports(crypto)) {
+        match provider.price(client, crypto, fiat, None, false).await {
+            Ok(PriceQuote::Fresh { price, .. }) => return Ok(price),
+            // Only sent in reply to conditional requests, which we never make here
+            Ok(PriceQuote::NotModified) => {}
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| CryptoError::InvalidInput(format!("No price provider supports {}", crypto))))
+}
+
+async fn lookup_balance(client: &Client, crypto: &str, address: &str) -> Result<f64, CryptoError> {
+    let providers = Providers::from_config(&Config::default())?;
+    let mut last_error = None;
+    for provider in providers.balance.iter().filter(|p| p.supports(crypto)) {
+        match provider.balance(client, crypto, address, false).await {
+            Ok(balance) => return Ok(balance),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| CryptoError::InvalidInput(format!("No balance provider supports {}", crypto))))
+}
+
+#[wasm_bindgen(js_name = getPrice)]
+pub async fn get_price(crypto: String, fiat: String) -> Result<f64, JsError> {
+    lookup_price(&Client::new(), &resolve_symbol(&crypto), &fiat.to_lowercase())
+        .await
+        .map_err(js_error)
+}
+
+#[wasm_bindgen(js_name = getAddressBalance)]
+pub async fn get_address_balance(crypto: String, address: String) -> Result<f64, JsError> {
+    lookup_balance(&Client::new(), &resolve_symbol(&crypto), &address)
+        .await
+        .map_err(js_error)
+}
+
+// Value of `amount` coins in `fiat`
+#[wasm_bindgen]
+pub async fn convert(amount: f64, crypto: String, fiat: String) -> Result<f64, JsError> {
+    let price = lookup_price(&Client::new(), &resolve_symbol(&crypto), &fiat.to_lowercase())
+        .await
+        .map_err(js_error)?;
+    Ok(amount * price)
+}
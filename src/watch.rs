@@ -0,0 +1,146 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::providers::{AddressHistory, AddressTx, MempoolTx};
+use crate::{CryptoError, CryptoTool, coins, tidy};
+
+fn confirmations(tx: &AddressTx, tip: u64) -> u64 {
+    tx.height.map_or(0, |height| tip.saturating_sub(height) + 1)
+}
+
+fn plural(count: u64) -> &'static str {
+    if count == 1 { "" } else { "s" }
+}
+
+// One line per transaction that's new since the last poll or has gained a confirmation it's
+// still waited on for, oldest first
+fn changes(seen: &mut HashMap<String, u64>, history: &AddressHistory, crypto: &str, address: &str, wanted: u64) -> Vec<String> {
+    let mut lines = Vec::new();
+    for tx in history.transactions.iter().rev() {
+        let count = confirmations(tx, history.tip);
+        match seen.insert(tx.txid.clone(), count) {
+            None => {
+                let (direction, preposition) = if tx.net < 0 { ("Outgoing", "from") } else { ("Incoming", "to") };
+                let amount = tidy(tx.net.unsigned_abs() as f64 / 100_000_000.0);
+                lines.push(format!(
+                    "{} {} {} {} {}: {}, {} confirmation{}",
+                    direction,
+                    amount,
+                    crypto.to_uppercase(),
+                    preposition,
+                    address,
+                    tx.txid,
+                    count,
+                    plural(count)
+                ));
+            }
+            Some(before) if before < wanted && count > before => {
+                let settled = if count >= wanted { ", confirmed" } else { "" };
+                lines.push(format!("{}: {} confirmation{}{}", tx.txid, count, plural(count), settled));
+            }
+            Some(_) => {}
+        }
+    }
+    lines
+}
+
+// Calls `poll` every `interval` after the caller's first poll, until interrupted or `polls` have
+// been made in all
+async fn keep_polling(interval: Duration, polls: Option<u32>, mut poll: impl AsyncFnMut()) {
+    let mut done = 1;
+    while polls.is_none_or(|polls| done < polls) {
+        tokio::time::sleep(interval).await;
+        done += 1;
+        poll().await;
+    }
+}
+
+// Polls every `interval` and prints what changed. Transactions from before the first poll are
+// only counted.
+pub async fn run(
+    tool: &CryptoTool,
+    crypto: &str,
+    address: &str,
+    interval: Duration,
+    wanted: u64,
+    polls: Option<u32>,
+    verbose: bool,
+) -> Result<String, CryptoError> {
+    let (history, source) = tool.address_history(crypto, address, verbose).await?;
+    let mut seen: HashMap<String, u64> = history.transactions.iter().map(|tx| (tx.txid.clone(), confirmations(tx, history.tip))).collect();
+    println!(
+        "Watching {} address {} on {} every {}s ({} earlier transaction{})",
+        crypto,
+        address,
+        source,
+        interval.as_secs(),
+        seen.len(),
+        plural(seen.len() as u64)
+    );
+    keep_polling(interval, polls, async || match tool.address_history(crypto, address, verbose).await {
+        Ok((history, _)) => {
+            for line in changes(&mut seen, &history, crypto, address, wanted) {
+                println!("{}", line);
+            }
+        }
+        // A daemon outlives a provider's hiccup; the next poll tries again
+        Err(e) => eprintln!("Warning: poll failed: {}", e),
+    })
+    .await;
+    Ok(String::new())
+}
+
+// A chain's mempool as seen by the last poll, to tell which transactions are new
+pub struct Mempool {
+    crypto: String,
+    // Of the chain's smallest units to the coin, which mempool values are counted in
+    scale: f64,
+    // Only the last poll's, since a transaction doesn't come back once it's dropped off the list
+    seen: HashSet<String>,
+}
+
+impl Mempool {
+    pub fn new(crypto: &str) -> Result<Self, CryptoError> {
+        let (.., power) = coins::unit(crypto)
+            .filter(|(_, coin, _)| *coin == crypto)
+            .ok_or_else(|| CryptoError::InvalidInput(format!("Don't know how {} amounts divide; can't watch its mempool", crypto)))?;
+        Ok(Mempool { crypto: crypto.to_string(), scale: 10f64.powi(power as i32), seen: HashSet::new() })
+    }
+
+    // A transaction's value in the coin
+    pub fn amount(&self, tx: &MempoolTx) -> f64 {
+        tx.value as f64 / self.scale
+    }
+
+    // The transactions this poll lists that the last one didn't, with at least `min` of the
+    // coin, oldest first
+    pub fn whales(&mut self, recent: Vec<MempoolTx>, min: f64) -> Vec<MempoolTx> {
+        let seen = std::mem::take(&mut self.seen);
+        self.seen = recent.iter().map(|tx| tx.txid.clone()).collect();
+        recent.into_iter().rev().filter(|tx| self.amount(tx) >= min && !seen.contains(&tx.txid)).collect()
+    }
+
+    pub fn describe(&self, tx: &MempoolTx) -> String {
+        format!("{} {} moved in {}", tidy(self.amount(tx)), self.crypto.to_uppercase(), tx.txid)
+    }
+}
+
+// Prints each transaction reaching the mempool that moves at least `min` of the coin, polling
+// every `interval`. Providers only list the latest few, so longer intervals miss more.
+pub async fn whales(tool: &CryptoTool, crypto: &str, min: f64, interval: Duration, polls: Option<u32>, verbose: bool) -> Result<String, CryptoError> {
+    let mut mempool = Mempool::new(crypto)?;
+    let mut report = |recent: Vec<MempoolTx>| {
+        for tx in mempool.whales(recent, min) {
+            println!("Whale: {}", mempool.describe(&tx));
+        }
+    };
+    let (recent, source) = tool.recent_transactions(crypto, verbose).await?;
+    println!("Watching the {} mempool on {} every {}s for {} {} or more", crypto, source, interval.as_secs(), tidy(min), crypto.to_uppercase());
+    report(recent);
+    keep_polling(interval, polls, async || match tool.recent_transactions(crypto, verbose).await {
+        Ok((recent, _)) => report(recent),
+        Err(e) => eprintln!("Warning: poll failed: {}", e),
+    })
+    .await;
+    Ok(String::new())
+}
@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+
+use crate::CryptoError;
+use crate::config::WatchlistConfig;
+
+// Coins `current-price --watchlist` quotes, as CoinGecko ids in the order they were added, in
+// a JSON file under the data directory
+pub struct Watchlist {
+    path: PathBuf,
+    coins: Vec<String>,
+}
+
+impl Watchlist {
+    // Defaults to ~/.local/share/coinwagon/watchlist.json
+    pub fn path(config: &WatchlistConfig) -> Option<PathBuf> {
+        match &config.path {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::data_dir().map(|dir| dir.join("coinwagon").join("watchlist.json")),
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self, CryptoError> {
+        let coins = match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| CryptoError::RuntimeError(format!("Watchlist {} is corrupt: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(CryptoError::RuntimeError(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        Ok(Watchlist { path: path.to_path_buf(), coins })
+    }
+
+    pub fn coins(&self) -> &[String] {
+        &self.coins
+    }
+
+    // Adds the coins not on the list yet and returns how many that was
+    pub fn add(&mut self, coins: &[String]) -> usize {
+        let before = self.coins.len();
+        for coin in coins {
+            if !self.coins.contains(coin) {
+                self.coins.push(coin.clone());
+            }
+        }
+        self.coins.len() - before
+    }
+
+    // Returns how many of `coins` were on the list
+    pub fn remove(&mut self, coins: &[String]) -> usize {
+        let before = self.coins.len();
+        self.coins.retain(|coin| !coins.contains(coin));
+        before - self.coins.len()
+    }
+
+    pub fn save(&self) -> Result<(), CryptoError> {
+        let json = serde_json::to_string_pretty(&self.coins).map_err(|e| CryptoError::RuntimeError(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| CryptoError::RuntimeError(e.to_string()))?;
+        }
+        std::fs::write(&self.path, json)
+            .map_err(|e| CryptoError::RuntimeError(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
@@ -0,0 +1,279 @@
+// The alerts daemon: [[alerts.rules]] checked against each poll's snapshot of the [alerts] wallet and prices
+mod common;
+
+use common::{coinwagon_with_config, route, serve, stderr, stdout, temp_file};
+// Only the Unix daemon tests start it as a child process
+#[cfg(unix)]
+use common::{coinwagon_command, config_file};
+use serde_json::Value;
+
+const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+fn read_snapshots(path: &std::path::Path) -> Vec<Value> {
+    std::fs::read_to_string(path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[test]
+fn drift_alert_fires_once_while_a_weight_is_off_target() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_bitcoin_ethereum_prices.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", ADDRESS));
+    let snapshots = temp_file("snapshots.jsonl", "");
+    let (wallet, snapshots_path) = (wallet.to_str().unwrap(), snapshots.to_str().unwrap());
+    let config = format!(
+        "[alerts]\nwallet = \"{}\"\nsnapshots = \"{}\"\n\n[[alerts.rules]]\nkind = \"drift\"\ntarget = \"btc=60,eth=40\"\npoints = 5\n",
+        wallet, snapshots_path
+    );
+    let output = coinwagon_with_config(&server, &config, &["alerts", "--interval", "1", "--polls", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "Watching 1 alert rule every 1s\nAlert: {} drifted more than 5 points: bitcoin 100.00% (target 60.00%, +40.00 points), ethereum 0.00% (target 40.00%, -40.00 points)",
+            wallet
+        )
+    );
+
+    let snapshots = read_snapshots(&snapshots);
+    assert_eq!(snapshots.len(), 2, "{:?}", snapshots);
+    assert_eq!(snapshots[0]["fiat"], "usd");
+    assert_eq!(snapshots[0]["total"], 7500.0);
+    assert_eq!(snapshots[0]["holdings"]["bitcoin"], 7500.0);
+}
+
+#[test]
+fn portfolio_rules_need_a_wallet() {
+    let server = serve(vec![]);
+    let config = "[[alerts.rules]]\nkind = \"drift\"\ntarget = \"btc=60,eth=40\"\npoints = 5\n";
+    let output = coinwagon_with_config(&server, config, &["alerts", "--polls", "1"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Alerts on the portfolio need [alerts] wallet set to a wallet file"), "{}", stderr(&output));
+}
+
+#[test]
+fn value_alerts_read_live_prices_and_earlier_snapshots() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_bitcoin_ethereum_prices.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", ADDRESS));
+    // Worth 10000 USD yesterday; the higher totals are from before the window or in another fiat
+    let yesterday = (chrono::Utc::now() - chrono::Duration::days(1)).to_rfc3339();
+    let long_ago = (chrono::Utc::now() - chrono::Duration::days(40)).to_rfc3339();
+    let earlier = [(long_ago.as_str(), "usd", 20000), (yesterday.as_str(), "usd", 10000), (yesterday.as_str(), "eur", 30000)]
+        .map(|(time, fiat, total)| format!("{{\"time\":\"{}\",\"fiat\":\"{}\",\"total\":{},\"holdings\":{{\"bitcoin\":{}}}}}\n", time, fiat, total, total))
+        .concat();
+    let snapshots = temp_file("snapshots.jsonl", &earlier);
+    let config = format!(
+        "[alerts]\nwallet = \"{}\"\nsnapshots = \"{}\"\n\n[[alerts.rules]]\nkind = \"value\"\nbelow = 8000\n\n[[alerts.rules]]\nkind = \"drawdown\"\npercent = 10\n",
+        wallet.to_str().unwrap(),
+        snapshots.to_str().unwrap()
+    );
+    let output = coinwagon_with_config(&server, &config, &["alerts", "--polls", "1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let wallet = wallet.to_str().unwrap();
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "Watching 2 alert rules every 60s\nAlert: {0} is worth 7500 USD, below 8000 USD\nAlert: {0} is 25.00% below its 30-day high of 10000 USD, at 7500 USD",
+            wallet
+        )
+    );
+    assert_eq!(read_snapshots(&snapshots).len(), 4);
+}
+
+#[test]
+fn move_alert_compares_prices_polled_within_the_window() {
+    // 60000 on the first poll, 67234.5 on the second; ttl_secs = 0 lands under [cache] so each poll refetches
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_bitcoin_ethereum_prices.json").once(),
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+    ]);
+    let config = "ttl_secs = 0\n\n[[alerts.rules]]\nkind = \"move\"\ncoin = \"btc\"\npercent = 5\n";
+    let output = coinwagon_with_config(&server, config, &["alerts", "--interval", "1", "--polls", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Watching 1 alert rule every 1s\nAlert: bitcoin moved +12.06% within 60 minutes: 60000 -> 67234.5 USD"
+    );
+}
+
+#[test]
+fn move_alert_refetches_prices_the_cache_still_holds() {
+    // A one-minute window under the default five-minute TTL: the second poll still sees the new price
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_bitcoin_ethereum_prices.json").once(),
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+    ]);
+    let config = "[[alerts.rules]]\nkind = \"move\"\ncoin = \"btc\"\npercent = 5\nminutes = 1\n";
+    let output = coinwagon_with_config(&server, config, &["alerts", "--interval", "1", "--polls", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Watching 1 alert rule every 1s\nAlert: bitcoin moved +12.06% within 1 minute: 60000 -> 67234.5 USD"
+    );
+}
+
+#[test]
+fn volume_alert_compares_the_last_24_hours_to_the_daily_average() {
+    // 13M in the last 24 hours against 9.75M a day over the 8 days before
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let config = "[[alerts.rules]]\nkind = \"volume\"\ncoin = \"btc\"\nmultiple = 1.3\n\n[[alerts.rules]]\nkind = \"volume\"\ncoin = \"bitcoin\"\nmultiple = 1.5\ndays = 3\n";
+    let output = coinwagon_with_config(&server, config, &["alerts", "--polls", "1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Watching 2 alert rules every 60s\nAlert: bitcoin's 24h volume is 13000000 USD, 1.33x its 8-day average of 9750000 USD"
+    );
+}
+
+#[test]
+fn cross_alert_fires_on_the_latest_close_crossing_an_indicator() {
+    // Closes of 9, 9 then 13: up through the 3-day SMA, and RSI3 up into overbought, but not below the 5-day SMA
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let config = "[[alerts.rules]]\nkind = \"cross\"\ncoin = \"btc\"\nindicator = \"sma3\"\ndirection = \"above\"\n\n[[alerts.rules]]\nkind = \"cross\"\ncoin = \"btc\"\nindicator = \"sma5\"\ndirection = \"below\"\n\n[[alerts.rules]]\nkind = \"cross\"\ncoin = \"btc\"\nindicator = \"rsi3\"\ndirection = \"above\"\n";
+    let output = coinwagon_with_config(&server, config, &["alerts", "--polls", "1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Watching 3 alert rules every 60s\nAlert: bitcoin crossed above its SMA3: 13 USD against 10.33333333 USD\nAlert: bitcoin's RSI3 crossed above 70, at 90.00"
+    );
+
+    let config = "[[alerts.rules]]\nkind = \"cross\"\ncoin = \"btc\"\nindicator = \"bollinger\"\ndirection = \"above\"\n";
+    let output = coinwagon_with_config(&server, config, &["alerts", "--polls", "1"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Cross alert: Unknown indicator 'bollinger'"), "{}", stderr(&output));
+}
+
+#[test]
+fn whale_alert_fires_for_each_large_transaction_new_to_the_mempool() {
+    let server = serve(vec![
+        route("/mempool/recent", 200, "esplora_recent.json").once(),
+        route("/mempool/recent", 200, "esplora_recent_next.json"),
+    ]);
+    let config = "[[alerts.rules]]\nkind = \"whale\"\ncoin = \"btc\"\nmin = 100\n";
+    let output = coinwagon_with_config(&server, config, &["alerts", "--interval", "1", "--polls", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Watching 1 alert rule every 1s\n\
+         Alert: Whale in the bitcoin mempool: 150.12345678 BITCOIN moved in a7f2e5b8c1d4a9f6e3b0c7d2a5f8e1b4c9d6a3f0e7b2c5d8a1f4e9b6c3d0a7f2\n\
+         Alert: Whale in the bitcoin mempool: 250 BITCOIN moved in f1d3b5a7c9e2f4a6b8d0c1e3f5a7b9d2c4e6f8a0b1d3c5e7f9a2b4d6c8e0f1a3"
+    );
+
+    let config = "[[alerts.rules]]\nkind = \"whale\"\ncoin = \"bogus\"\nmin = 100\n";
+    let output = coinwagon_with_config(&server, config, &["alerts", "--polls", "1"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Whale alert: Don't know how bogus amounts divide"), "{}", stderr(&output));
+}
+
+#[cfg(unix)]
+#[test]
+fn daemon_notifies_systemd_reloads_on_sighup_and_stops_on_sigterm() {
+    use std::os::unix::net::UnixDatagram;
+    use std::process::Stdio;
+
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    let rule = "[[alerts.rules]]\nkind = \"move\"\ncoin = \"btc\"\npercent = 50\n";
+    let config = config_file(rule);
+    let pid_file = temp_file("alerts.pid", "");
+    let socket_path = temp_file("notify.sock", "");
+    std::fs::remove_file(&socket_path).unwrap();
+    let socket = UnixDatagram::bind(&socket_path).unwrap();
+    socket.set_read_timeout(Some(std::time::Duration::from_secs(20))).unwrap();
+    let notified = || {
+        let mut buffer = [0; 256];
+        let length = socket.recv(&mut buffer).unwrap();
+        String::from_utf8_lossy(&buffer[..length]).to_string()
+    };
+    let signal = |name: &str, pid: u32| {
+        assert!(std::process::Command::new("kill").args([name, &pid.to_string()]).status().unwrap().success());
+    };
+
+    let child = coinwagon_command(&server, &config, &["alerts", "--interval", "1", "--pid-file", pid_file.to_str().unwrap()])
+        .env("NOTIFY_SOCKET", &socket_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    assert_eq!(notified(), "READY=1");
+    assert_eq!(std::fs::read_to_string(&pid_file).unwrap(), format!("{}\n", child.id()));
+
+    std::fs::write(&config, format!("[cache]\npersistent = false\n{}\n{}", rule, rule.replace("50", "60"))).unwrap();
+    signal("-HUP", child.id());
+    assert_eq!(notified(), "RELOADING=1");
+    assert_eq!(notified(), "READY=1");
+
+    signal("-TERM", child.id());
+    assert_eq!(notified(), "STOPPING=1");
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Watching 1 alert rule every 1s\nReloaded: Watching 2 alert rules every 1s");
+    assert!(!pid_file.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn readyz_turns_unavailable_when_a_poll_fails() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::process::Stdio;
+
+    // The first poll gets a price; the ones after it get CoinGecko errors, since nothing is cached
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json").once(),
+        route("/simple/price", 500, "coingecko_error.json"),
+    ]);
+    let config = config_file("ttl_secs = 0\n\n[[alerts.rules]]\nkind = \"move\"\ncoin = \"btc\"\npercent = 50\n");
+    let mut child = coinwagon_command(&server, &config, &["alerts", "--interval", "1", "--listen", "127.0.0.1:0"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    assert_eq!(lines.next().unwrap().unwrap(), "Watching 1 alert rule every 1s");
+    let listening = lines.next().unwrap().unwrap();
+    let address = listening.strip_prefix("Health checks on http://").unwrap().strip_suffix("/healthz and /readyz").unwrap().to_string();
+    let get = |path: &str| {
+        let mut stream = std::net::TcpStream::connect(&address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: coinwagon\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), serde_json::from_str::<Value>(body).unwrap())
+    };
+
+    // A client that never sends its request doesn't hold up a probe behind it
+    let _silent = std::net::TcpStream::connect(&address).unwrap();
+    let started = std::time::Instant::now();
+    let (status, body) = get("/healthz");
+    assert!(started.elapsed() < std::time::Duration::from_secs(2), "{:?}", started.elapsed());
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(body["status"], "ok");
+    let (status, _) = get("/metrics");
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+    // Ready after the first poll, until a later one fails
+    let mut seen = Vec::new();
+    for _ in 0..100 {
+        let (status, body) = get("/readyz");
+        if seen.last() != Some(&status) {
+            seen.push(status.clone());
+        }
+        if status.contains("503") && body["polls"].as_u64() > Some(1) {
+            assert_eq!(body["ready"], false);
+            assert!(body["last_poll"].as_str().unwrap().contains("CoinGecko"), "{}", body);
+            assert_eq!(body["providers"]["CoinGecko"], "up");
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    assert_eq!(seen.last().map(String::as_str), Some("HTTP/1.1 503 Service Unavailable"), "{:?}", seen);
+    assert!(seen.iter().any(|status| status == "HTTP/1.1 200 OK"), "{:?}", seen);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
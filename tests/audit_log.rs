@@ -0,0 +1,41 @@
+// --audit-log appends a JSON line for each provider call
+mod common;
+
+use common::{coinwagon_with_config, route, serve, stderr, temp_file};
+use serde_json::Value;
+
+const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+fn read_log(path: &std::path::Path) -> Vec<Value> {
+    std::fs::read_to_string(path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[test]
+fn every_call_is_appended_with_keys_redacted() {
+    let path = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![route(path, 200, "coingecko_simple_price.json")]);
+    let log = temp_file("audit.jsonl", "");
+    let config = "[api_keys.blockcypher]\nkeys = [\"bcy-secret-token\"]\n";
+    let args = ["address-balance", "bitcoin", ADDRESS, "--audit-log", log.to_str().unwrap()];
+    let output = coinwagon_with_config(&server, config, &args);
+    assert!(!output.status.success());
+
+    let contents = std::fs::read_to_string(&log).unwrap();
+    assert!(!contents.contains("bcy-secret-token"), "{}", contents);
+    let entries = read_log(&log);
+    // BlockCypher's odd response, then the Blockchair fallback's 404
+    assert_eq!(entries.len(), 2, "{}", contents);
+    assert_eq!(entries[0]["provider"], "BlockCypher");
+    assert_eq!(entries[0]["method"], "GET");
+    assert_eq!(entries[0]["host"], "127.0.0.1");
+    assert_eq!(entries[0]["path"], format!("/btc/main/addrs/{}/balance?token=REDACTED", ADDRESS));
+    assert_eq!(entries[0]["status"], 200);
+    assert!(entries[0]["latency_ms"].as_f64().unwrap() >= 0.0, "{}", contents);
+    assert!(entries[0]["timestamp"].as_str().unwrap().ends_with('Z'), "{}", contents);
+    assert_eq!(entries[1]["status"], 404);
+
+    // Later runs add to the log rather than replace it
+    let output = coinwagon_with_config(&server, config, &args);
+    assert!(!output.status.success(), "{}", stderr(&output));
+    assert_eq!(read_log(&log).len(), 4);
+}
@@ -0,0 +1,74 @@
+// The persistent cache file as the cache subcommands and --offline read it
+mod common;
+
+use common::{coinwagon_command, serve, stderr, stdout, temp_file};
+use std::path::PathBuf;
+
+// 2024-06-01T09:30:00Z
+const FETCHED_AT: u64 = 1_717_234_200;
+
+// A config using a cache file seeded with `entries` (key, value) fetched at FETCHED_AT
+fn seeded_config(entries: &[(&str, f64)]) -> PathBuf {
+    let entries: serde_json::Map<String, serde_json::Value> = entries
+        .iter()
+        .map(|(key, value)| {
+            let entry = serde_json::json!({
+                "value": value,
+                "fetched_at": { "secs_since_epoch": FETCHED_AT, "nanos_since_epoch": 0 },
+                "validators": { "etag": null, "last_modified": null },
+                "source": "CoinGecko",
+            });
+            (key.to_string(), entry)
+        })
+        .collect();
+    let file = serde_json::json!({ "entries": entries, "hits": 3, "misses": 1 });
+    let cache = temp_file("cache.json", &file.to_string());
+    temp_file("config.toml", &format!("[cache]\npersistent = true\npath = \"{}\"\n", cache.to_str().unwrap()))
+}
+
+#[test]
+fn offline_answers_from_the_cache_with_its_age() {
+    // Nothing is routed, so any request would fail
+    let server = serve(vec![]);
+    let config = seeded_config(&[("price:bitcoin:usd", 67234.5)]);
+    let run = |args: &[&str]| coinwagon_command(&server, &config, args).output().unwrap();
+
+    let output = run(&["current-price", "bitcoin", "usd", "--offline"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "67234.5 USD (stale as of 2024-06-01T09:30:00Z)");
+
+    let output = run(&["current-price", "ethereum", "usd", "--offline"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("No cached price for ethereum/usd available in offline mode"), "{}", stderr(&output));
+}
+
+#[test]
+fn cache_lists_counts_and_clears_the_seeded_entries() {
+    let server = serve(vec![]);
+    let config = seeded_config(&[("price:ethereum:usd", 3000.0), ("price:bitcoin:usd", 67234.5), ("balance:bitcoin:bc1qexample", 0.5)]);
+    let run = |args: &[&str]| {
+        let output = coinwagon_command(&server, &config, args).output().unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+    };
+
+    assert_eq!(
+        run(&["cache", "list"]),
+        "balance:bitcoin:bc1qexample = 0.5 (fetched 2024-06-01T09:30:00Z)\n\
+         price:bitcoin:usd = 67234.5 (fetched 2024-06-01T09:30:00Z)\n\
+         price:ethereum:usd = 3000 (fetched 2024-06-01T09:30:00Z)"
+    );
+    assert_eq!(
+        run(&["cache", "stats"]),
+        "Entries: 3\nHits: 3\nMisses: 1\nHit ratio: 75.0%\nAge: <5m: 0, 5m-1h: 0, 1h-24h: 0, >24h: 3"
+    );
+
+    assert_eq!(run(&["cache", "clear", "--coin", "bitcoin"]), "Removed 2 cached entries for bitcoin");
+    assert_eq!(run(&["cache", "list"]), "price:ethereum:usd = 3000 (fetched 2024-06-01T09:30:00Z)");
+    // Clearing one coin keeps the counters
+    assert!(run(&["cache", "stats"]).starts_with("Entries: 1\nHits: 3\nMisses: 1"));
+
+    assert_eq!(run(&["cache", "clear"]), "Removed 1 cached entries");
+    assert_eq!(run(&["cache", "list"]), "Cache is empty");
+    assert_eq!(run(&["cache", "stats"]), "Entries: 0\nHits: 0\nMisses: 0\nHit ratio: n/a\nAge: <5m: 0, 5m-1h: 0, 1h-24h: 0, >24h: 0");
+}
@@ -0,0 +1,42 @@
+// --record/--replay round trips through the binary
+mod common;
+
+use common::{coinwagon, coinwagon_live, fixture, route, serve, stderr, stdout, temp_file};
+
+#[test]
+fn replay_answers_without_the_network() {
+    let cassette = fixture("cassette_bitcoin_price.json");
+    let output = coinwagon_live(&["current-price", "bitcoin", "usd", "--replay", cassette.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "67234.5 USD");
+}
+
+#[test]
+fn replay_rejects_unrecorded_requests() {
+    let cassette = fixture("cassette_bitcoin_price.json");
+    let output = coinwagon_live(&["current-price", "bitcoin", "eur", "--replay", cassette.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("has no recorded response for GET"), "{}", stderr(&output));
+}
+
+#[test]
+fn recorded_failure_replays_the_same_way() {
+    let server = serve(vec![route("/simple/price", 200, "blockcypher_balance.json")]);
+    let cassette = temp_file("cassette.json", "");
+    let cassette = cassette.to_str().unwrap();
+
+    let recorded = coinwagon(&server, &["current-price", "bitcoin", "usd", "--record", cassette]);
+    assert!(!recorded.status.success());
+    let contents = std::fs::read_to_string(cassette).unwrap();
+    assert!(contents.contains("/simple/price?ids=bitcoin&vs_currencies=usd"), "{}", contents);
+
+    let replayed = coinwagon(&server, &["current-price", "bitcoin", "usd", "--replay", cassette]);
+    assert_eq!(stderr(&replayed), stderr(&recorded));
+}
+
+#[test]
+fn record_and_replay_are_exclusive() {
+    let output = coinwagon_live(&["current-price", "bitcoin", "usd", "--record", "a.json", "--replay", "b.json"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("cannot be used with"), "{}", stderr(&output));
+}
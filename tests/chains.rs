@@ -0,0 +1,43 @@
+// Chains declared in [[chains]] rather than built in
+mod common;
+
+use common::{coinwagon_with_config, route, serve, stderr, stdout, temp_file};
+
+const KASPA: &str = "kaspa:qr0lr4ml9fn3chekrqmjdkergxl93l4wrk3dankcgvjq776s9wn9jkdskewva";
+
+fn explorer_chain(server: &str) -> String {
+    format!(
+        "[[chains]]\nname = \"kaspa\"\nexplorer = \"{}/addresses/{{address}}/balance\"\nbalance_field = \"result.balance\"\ndecimals = 8\naddress_format = {{ prefix = \"kaspa:\", length = 67 }}\n",
+        server
+    )
+}
+
+#[test]
+fn explorer_chain_balance() {
+    let server = serve(vec![route(format!("/addresses/{}/balance", KASPA), 200, "explorer_address.json")]);
+    let output = coinwagon_with_config(&server, &explorer_chain(&server), &["address-balance", "kaspa", KASPA]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "12345 KASPA");
+}
+
+#[test]
+fn rpc_chain_uses_its_decimals() {
+    let server = serve(vec![route("/rpc", 200, "rpc_balance.json")]);
+    let config = format!("[[chains]]\nname = \"mychain\"\nrpc = \"{}/rpc\"\ndecimals = 9\n", server);
+    let output = coinwagon_with_config(&server, &config, &["address-balance", "mychain", "0x742d35Cc6634C0532925a3b8D4Fddfac9e2C4cb7"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1500000000 MYCHAIN");
+}
+
+#[test]
+fn malformed_wallet_address_is_rejected_up_front() {
+    // Nothing is served: the bad line must fail before any request goes out
+    let server = serve(vec![]);
+    let wallet = temp_file("wallet.txt", &format!("kaspa,{}\nkaspa,qr0lr4ml9fn3\n", KASPA));
+    let output = coinwagon_with_config(&server, &explorer_chain(&server), &["wallet-balance", wallet.to_str().unwrap(), "usd"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "Error: Invalid input: qr0lr4ml9fn3 doesn't look like a kaspa address (expected one starting with kaspa: and 67 characters long)"
+    );
+}
@@ -0,0 +1,182 @@
+// Each test crate uses a different subset of these helpers
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// A canned response: requests whose path (without query) is `path` get `fixture` with `status`.
+// A `*` segment in `path` matches any one segment.
+pub struct Route {
+    path: String,
+    status: u16,
+    fixture: &'static str,
+    headers: Vec<(&'static str, String)>,
+    // Served this many times, then the next matching route takes over
+    times: Option<usize>,
+    // Request headers (lowercase "name: value") without which the route answers 401
+    required_headers: Vec<String>,
+    // Only requests carrying this header (lowercase "name: value") match the route
+    only_with: Option<String>,
+}
+
+pub fn route(path: impl Into<String>, status: u16, fixture: &'static str) -> Route {
+    Route { path: path.into(), status, fixture, headers: Vec::new(), times: None, required_headers: Vec::new(), only_with: None }
+}
+
+impl Route {
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub fn require_header(mut self, name: &str, value: &str) -> Self {
+        self.required_headers.push(format!("{}: {}", name.to_lowercase(), value));
+        self
+    }
+
+    pub fn when_header(mut self, name: &str, value: &str) -> Self {
+        self.only_with = Some(format!("{}: {}", name.to_lowercase(), value));
+        self
+    }
+
+    pub fn once(mut self) -> Self {
+        self.times = Some(1);
+        self
+    }
+}
+
+// Local HTTP server answering from tests/fixtures, for pointing providers at with
+// --endpoint-override. Unknown paths get a 404; the server lives until the test exits.
+pub fn serve(mut routes: Vec<Route>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+            let mut request_headers = Vec::new();
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|n| n > 2) {
+                let (name, value) = header.trim_end().split_once(':').unwrap_or_default();
+                request_headers.push(format!("{}: {}", name.to_lowercase(), value.trim()));
+                header.clear();
+            }
+            // Read any body, e.g. a JSON-RPC call, so closing the connection doesn't reset it
+            let length = request_headers
+                .iter()
+                .find_map(|h| h.strip_prefix("content-length: ")?.parse().ok())
+                .unwrap_or(0);
+            let _ = reader.by_ref().take(length).read_to_end(&mut Vec::new());
+
+            let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+            let path = target.split('?').next().unwrap_or(target);
+            let (status, headers, body) = match routes.iter_mut().find(|r| {
+                matches(&r.path, path) && r.times != Some(0) && r.only_with.as_ref().is_none_or(|h| request_headers.contains(h))
+            }) {
+                Some(r) if r.required_headers.iter().any(|h| !request_headers.contains(h)) => {
+                    (401, String::new(), "{\"error\": \"missing credentials\"}".to_string())
+                }
+                Some(r) => {
+                    r.times = r.times.map(|n| n - 1);
+                    let headers: String = r.headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect();
+                    (r.status, headers, std::fs::read_to_string(fixture(r.fixture)).unwrap())
+                }
+                None => (404, String::new(), format!("{{\"error\": \"no fixture for {}\"}}", path)),
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {} Fixture\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                headers,
+                body
+            );
+        }
+    });
+    url
+}
+
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, path): (Vec<&str>, Vec<&str>) = (pattern.split('/').collect(), path.split('/').collect());
+    pattern.len() == path.len() && pattern.iter().zip(&path).all(|(want, got)| *want == "*" || want == got)
+}
+
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+// Scratch file unique to this test process and call
+pub fn temp_file(name: &str, contents: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!("coinwagon-test-{}-{}-{}", std::process::id(), n, name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+const CONFIG: &str = "[cache]\npersistent = false\n";
+
+// Runs the coinwagon binary with an in-memory cache and every provider sent to `server`
+pub fn coinwagon(server: &str, args: &[&str]) -> Output {
+    coinwagon_with_config(server, "", args)
+}
+
+// Same, with `extra` appended to the config file
+pub fn coinwagon_with_config(server: &str, extra: &str, args: &[&str]) -> Output {
+    let config = config_file(extra);
+    coinwagon_command(server, &config, args).output().unwrap()
+}
+
+// The same, unstarted and reading the config file at `config`, for a daemon the test signals
+pub fn coinwagon_command(server: &str, config: &Path, args: &[&str]) -> Command {
+    let overrides = ["coingecko", "blockcypher", "blockchair", "covalent", "esplora", "frankfurter", "0x", "binance", "kraken", "coinbase", "binance-futures", "bybit", "alternative"].map(|p| format!("--endpoint-override={}={}", p, server));
+    let overrides: Vec<&str> = overrides.iter().map(String::as_str).collect();
+    command(config, &[&overrides[..], args].concat())
+}
+
+// A config file with the in-memory cache and `extra` appended, as coinwagon_with_config writes
+pub fn config_file(extra: &str) -> PathBuf {
+    temp_file("config.toml", &format!("{}{}", CONFIG, extra))
+}
+
+// Against the real provider URLs
+pub fn coinwagon_live(args: &[&str]) -> Output {
+    command(&temp_file("config.toml", CONFIG), args).output().unwrap()
+}
+
+fn command(config: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_coinwagon"));
+    command
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .env_remove("HTTPS_PROXY")
+        .env_remove("HTTP_PROXY")
+        .env_remove("ALL_PROXY")
+        .env_remove("https_proxy")
+        .env_remove("http_proxy")
+        .env_remove("all_proxy")
+        // Messages in English whatever the machine's locale,
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES")
+        .env_remove("LANG")
+        // and times in UTC
+        .env("TZ", "UTC");
+    command
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).trim().to_string()
+}
@@ -0,0 +1,57 @@
+// Shell completion scripts, and the `complete-coins` call they make for coin ids
+mod common;
+
+use common::{coinwagon, coinwagon_with_config, route, serve, stderr, stdout, temp_file};
+
+#[test]
+fn scripts_generate_for_every_shell() {
+    let server = serve(vec![]);
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let output = coinwagon(&server, &["completions", shell]);
+        assert!(output.status.success(), "{}: {}", shell, stderr(&output));
+        // The hidden subcommand isn't offered
+        assert!(!stdout(&output).contains("-a \"complete-coins\""), "{}", shell);
+        assert!(!stdout(&output).contains("coinwagon,complete-coins)"), "{}", shell);
+    }
+}
+
+#[test]
+fn scripts_ask_the_binary_for_coins() {
+    let server = serve(vec![]);
+    let output = coinwagon(&server, &["completions", "bash"]);
+    let script = stdout(&output);
+    assert!(script.contains("--help $(coinwagon complete-coins 2>/dev/null)\""), "{}", script);
+    // Fiat codes are fixed, so they're written out
+    assert!(script.contains("eur"));
+    assert!(!script.contains("dogecoin"));
+
+    let output = coinwagon(&server, &["completions", "zsh"]);
+    let script = stdout(&output);
+    assert!(script.contains("coins=(${(f)\"$(coinwagon complete-coins 2>/dev/null)\"})"), "{}", script);
+    assert!(script.contains(":crypto -- Cryptocurrency symbol (e.g., bitcoin):_coinwagon_coins'"), "{}", script);
+
+    let output = coinwagon(&server, &["completions", "fish"]);
+    assert!(stdout(&output).contains(
+        "complete -c coinwagon -n \"__fish_coinwagon_using_subcommand current-price\" -f -a \"(coinwagon complete-coins)\""
+    ));
+}
+
+#[test]
+fn complete_coins_lists_known_and_saved_coins() {
+    let server = serve(vec![route("/coins/list", 200, "coingecko_coins_list.json").once()]);
+    let list = temp_file("coin-list.json", "");
+    let config = format!("[coin_list]\npath = {:?}\n", list.to_str().unwrap());
+
+    let output = coinwagon_with_config(&server, &config, &["complete-coins"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "bitcoin\nbitcoin-cash\ndogecoin\nethereum\nlitecoin");
+
+    // Picked up as soon as the coin list is saved, with no new script
+    let output = coinwagon_with_config(&server, &config, &["coin-list", "--refresh"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = coinwagon_with_config(&server, &config, &["complete-coins"]);
+    assert_eq!(
+        stdout(&output),
+        "bitcoin\nbitcoin-cash\ndogecoin\nethereum\nlitecoin\nshiba-inu\nunicorn-token\nuniswap"
+    );
+}
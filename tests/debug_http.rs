@@ -0,0 +1,47 @@
+// --debug-http writes provider traffic to a HAR file
+mod common;
+
+use common::{coinwagon, coinwagon_with_config, route, serve, stderr, temp_file};
+use serde_json::Value;
+
+const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+fn read_har(path: &std::path::Path) -> Value {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn unparseable_response_is_in_the_har() {
+    let path = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![route(path, 200, "coingecko_simple_price.json")]);
+    let har = temp_file("debug.har", "");
+    let output = coinwagon(&server, &["address-balance", "bitcoin", ADDRESS, "--debug-http", har.to_str().unwrap()]);
+    assert!(!output.status.success());
+
+    let har = read_har(&har);
+    let entries = har["log"]["entries"].as_array().unwrap();
+    // BlockCypher's odd response, then the Blockchair fallback's 404
+    assert_eq!(entries.len(), 2, "{}", har);
+    assert_eq!(entries[0]["response"]["status"], 200);
+    assert!(entries[0]["response"]["content"]["text"].as_str().unwrap().contains("67234.5"), "{}", har);
+    assert_eq!(entries[1]["response"]["status"], 404);
+    assert!(stderr(&output).contains("unexpected response"), "{}", stderr(&output));
+}
+
+#[test]
+fn keys_are_redacted() {
+    let path = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![route(path, 200, "blockcypher_balance.json")]);
+    let har_path = temp_file("debug.har", "");
+    let config = "[api_keys.blockcypher]\nkeys = [\"bcy-secret-token\"]\n\n[headers.blockcypher]\nAuthorization = \"Bearer hunter2\"\n";
+    let args = ["address-balance", "bitcoin", ADDRESS, "--debug-http", har_path.to_str().unwrap()];
+    let output = coinwagon_with_config(&server, config, &args);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let contents = std::fs::read_to_string(&har_path).unwrap();
+    assert!(!contents.contains("bcy-secret-token") && !contents.contains("hunter2"), "{}", contents);
+    let request = &read_har(&har_path)["log"]["entries"][0]["request"];
+    assert!(request["url"].as_str().unwrap().ends_with("/balance?token=REDACTED"), "{}", request);
+    let headers = request["headers"].as_array().unwrap();
+    assert!(headers.iter().any(|h| h["name"] == "authorization" && h["value"] == "REDACTED"), "{}", request);
+}
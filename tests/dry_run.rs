@@ -0,0 +1,59 @@
+// --dry-run lists provider requests instead of sending them
+mod common;
+
+use common::{coinwagon, coinwagon_with_config, serve, stderr, stdout, temp_file};
+
+#[test]
+fn wallet_scan_lists_each_lookup_once() {
+    let server = serve(vec![]);
+    let wallet = temp_file("wallet.txt", "bitcoin,bc1qfirst\nbitcoin,bc1qsecond\nbitcoin,bc1qfirst\n");
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd", "--dry-run"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.starts_with("Dry run, nothing sent."), "{}", stdout);
+    assert!(stdout.contains("Balances of 2 bitcoin addresses in one request"), "{}", stdout);
+    assert!(stdout.contains(&format!("Blockchair: GET {}/bitcoin/dashboards/addresses/bc1qfirst,bc1qsecond", server)), "{}", stdout);
+    assert!(!stdout.contains("Balance of bitcoin address"), "{}", stdout);
+    assert_eq!(stdout.matches("CoinGecko: GET").count(), 1, "{}", stdout);
+}
+
+#[test]
+fn tor_isolation_looks_up_addresses_separately() {
+    let server = serve(vec![]);
+    let wallet = temp_file("wallet.txt", "bitcoin,bc1qfirst\nbitcoin,bc1qsecond\n");
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd", "--dry-run", "--tor-isolate"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("a separate Tor circuit per address"), "{}", stdout);
+    assert!(stdout.contains(&format!("BlockCypher: GET {}/btc/main/addrs/bc1qsecond/balance", server)), "{}", stdout);
+    assert!(
+        stdout.contains(&format!("Blockchair, if the above fail: GET {}/bitcoin/dashboards/address/bc1qsecond", server)),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("/dashboards/addresses/"), "{}", stdout);
+}
+
+#[test]
+fn headers_are_listed_and_keys_blanked() {
+    let server = serve(vec![]);
+    let config = "[headers.coingecko]\nx-client = \"audit\"\n\n[api_keys.coingecko]\nkeys = [\"CG-secret-key-0001\"]\n";
+    let output = coinwagon_with_config(&server, config, &["current-price", "bitcoin", "usd", "--dry-run"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains(&format!("GET {}/simple/price?ids=bitcoin&vs_currencies=usd", server)), "{}", stdout);
+    assert!(stdout.contains("x-client: audit"), "{}", stdout);
+    assert!(stdout.contains("x-cg-demo-api-key: REDACTED"), "{}", stdout);
+    assert!(!stdout.contains("CG-secret-key"), "{}", stdout);
+}
+
+#[test]
+fn rpc_calls_show_their_body() {
+    let server = serve(vec![]);
+    let config = "[rpc]\nethereum = \"https://eth-mainnet.g.alchemy.com/v2/alchemy-test-key-0123456789\"\n";
+    let output = coinwagon_with_config(&server, config, &["address-balance", "ethereum", "0xabc", "--dry-run"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("RPC: POST https://eth-mainnet.g.alchemy.com/v2/REDACTED"), "{}", stdout);
+    assert!(stdout.contains(r#"body: {"id":1,"jsonrpc":"2.0","method":"eth_getBalance","params":["0xabc","latest"]}"#), "{}", stdout);
+}
@@ -0,0 +1,268 @@
+// Exchange accounts read with signed requests, on their own and in wallet totals, and the
+// exchanges' public market data
+mod common;
+
+use common::{coinwagon_with_config, route, serve, stderr, stdout, temp_file};
+
+const BINANCE: &str = "[exchanges.binance]\nkey = \"binance-test-key\"\nsecret = \"binance-test-secret\"\n";
+
+// Base64, as Kraken hands them out
+const KRAKEN: &str = "[exchanges.kraken]\nkey = \"kraken-test-key\"\nsecret = \"a3Jha2VuLXRlc3Qtc2VjcmV0\"\n";
+
+#[test]
+fn binance_holdings_are_valued() {
+    let server = serve(vec![
+        route("/api/v3/account", 200, "binance_account.json").require_header("x-mbx-apikey", "binance-test-key"),
+        route("/simple/price", 200, "coingecko_exchange_prices.json"),
+    ]);
+    let output = coinwagon_with_config(&server, BINANCE, &["exchange-balance", "binance"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "BITCOIN: 0.5 BITCOIN = 30000 USD\nTETHER: 250 TETHER = 250 USD\nTotal: 30250 USD");
+}
+
+#[test]
+fn exchange_errors_are_reported() {
+    let server = serve(vec![route("/api/v3/account", 400, "binance_error.json")]);
+    let output = coinwagon_with_config(&server, BINANCE, &["exchange-balance", "binance"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Signature for this request is not valid. (code -1022)"), "{}", stderr(&output));
+}
+
+#[test]
+fn kraken_asset_codes_become_tickers() {
+    let server = serve(vec![
+        route("/0/private/Balance", 200, "kraken_balance.json").require_header("api-key", "kraken-test-key"),
+        route("/simple/price", 200, "coingecko_exchange_prices.json"),
+    ]);
+    let output = coinwagon_with_config(&server, KRAKEN, &["exchange-balance", "kraken"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("BITCOIN: 0.25 BITCOIN = 15000 USD"), "{}", stdout);
+    // Staked ETH counts with the rest
+    assert!(stdout.contains("ETHEREUM: 1.5 ETHEREUM = 4500 USD"), "{}", stdout);
+    assert!(stdout.contains("USD: 120.5 USD = 120.5 USD"), "{}", stdout);
+    assert!(stdout.ends_with("Total: 19620.5 USD"), "{}", stdout);
+}
+
+#[test]
+fn coinbase_accounts_are_read_page_by_page() {
+    let server = serve(vec![
+        route("/v2/accounts", 200, "coinbase_accounts_page1.json").require_header("cb-access-key", "coinbase-test-key").once(),
+        route("/v2/accounts", 200, "coinbase_accounts_page2.json").require_header("cb-access-key", "coinbase-test-key"),
+        route("/simple/price", 200, "coingecko_exchange_prices.json"),
+    ]);
+    let config = "[exchanges.coinbase]\nkey = \"coinbase-test-key\"\nsecret = \"coinbase-test-secret\"\n";
+    let output = coinwagon_with_config(&server, config, &["exchange-balance", "coinbase"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "BITCOIN: 0.1 BITCOIN = 6000 USD\nETHEREUM: 2 ETHEREUM = 6000 USD\nTotal: 12000 USD");
+}
+
+#[test]
+fn wallet_total_includes_exchange_holdings() {
+    const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+    let server = serve(vec![
+        route(format!("/btc/main/addrs/{}/balance", ADDRESS), 200, "blockcypher_balance.json"),
+        route("/api/v3/account", 200, "binance_account.json"),
+        route("/simple/price", 200, "coingecko_exchange_prices.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", ADDRESS));
+    let output = coinwagon_with_config(&server, BINANCE, &["wallet-balance", wallet.to_str().unwrap(), "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).ends_with("Total: 37750 USD"), "{}", stdout(&output));
+}
+
+#[test]
+fn dry_run_keeps_the_key_out() {
+    let server = serve(vec![]);
+    let output = coinwagon_with_config(&server, BINANCE, &["exchange-balance", "binance", "--dry-run"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Holdings on Binance:"), "{}", stdout);
+    assert!(stdout.contains("x-mbx-apikey: REDACTED"), "{}", stdout);
+    assert!(!stdout.contains("binance-test"), "{}", stdout);
+}
+
+#[test]
+fn unconfigured_exchange_asks_for_credentials() {
+    let server = serve(vec![]);
+    let output = coinwagon_with_config(&server, "", &["exchange-balance", "kraken"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("add an [exchanges.kraken] table with a read-only API key"), "{}", stderr(&output));
+}
+
+// A config pointing the transaction store at a fresh file
+fn with_store(config: &str) -> String {
+    let store = temp_file("transactions.json", "[]");
+    format!("{}[transactions]\npath = {:?}\n", config, store.to_str().unwrap())
+}
+
+#[test]
+fn binance_history_is_imported_once() {
+    let server = serve(vec![
+        route("/sapi/v1/capital/deposit/hisrec", 200, "binance_deposits.json").require_header("x-mbx-apikey", "binance-test-key"),
+        route("/sapi/v1/capital/withdraw/history", 200, "binance_withdrawals.json"),
+        route("/api/v3/myTrades", 200, "binance_trades.json"),
+    ]);
+    let config = with_store(&format!("{}trade_pairs = [\"BTC/USDT\"]\n", BINANCE));
+    let output = coinwagon_with_config(&server, &config, &["import-history", "binance"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Imported 3 new transactions from Binance (3 stored)");
+
+    let output = coinwagon_with_config(&server, &config, &["import-history", "binance"]);
+    assert_eq!(stdout(&output), "Imported 0 new transactions from Binance (3 stored)");
+
+    let output = coinwagon_with_config(&server, &config, &["transactions"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "2023-11-14T22:13:20Z binance deposit 0.5 BTC\n\
+         2023-11-16T02:00:00Z binance trade 0.2 BTC for 7000 USDT (fee 0.0002 BTC)\n\
+         2023-11-20T10:00:00Z binance withdrawal 0.1 BTC (fee 0.0005 BTC)"
+    );
+}
+
+#[test]
+fn kraken_trades_are_paired_from_the_ledger() {
+    let server = serve(vec![route("/0/private/Ledgers", 200, "kraken_ledgers.json").require_header("api-key", "kraken-test-key")]);
+    let config = with_store(KRAKEN);
+    let output = coinwagon_with_config(&server, &config, &["import-history", "kraken"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    // Staking rewards aren't trades, deposits or withdrawals
+    assert_eq!(stdout(&output), "Imported 2 new transactions from Kraken (2 stored)");
+    let output = coinwagon_with_config(&server, &config, &["transactions", "--exchange", "kraken"]);
+    assert_eq!(
+        stdout(&output),
+        "2023-11-14T22:13:20Z kraken deposit 10000 USD\n2023-11-14T23:13:20Z kraken trade 0.1 BTC for 6000 USD (fee 9.6 USD)"
+    );
+}
+
+#[test]
+fn coinbase_history_skips_pending_transactions() {
+    let server = serve(vec![
+        route("/v2/accounts", 200, "coinbase_history_accounts.json"),
+        route("/v2/accounts/58542935-67b5-56e1-a3f9-42686e07fa40/transactions", 200, "coinbase_transactions.json")
+            .require_header("cb-access-key", "coinbase-test-key"),
+    ]);
+    let config = with_store("[exchanges.coinbase]\nkey = \"coinbase-test-key\"\nsecret = \"coinbase-test-secret\"\n");
+    let output = coinwagon_with_config(&server, &config, &["import-history", "coinbase"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = coinwagon_with_config(&server, &config, &["transactions"]);
+    assert_eq!(
+        stdout(&output),
+        "2024-01-10T12:00:00Z coinbase trade 0.1 BTC for 6000 USD\n2024-02-01T08:30:00Z coinbase withdrawal 0.05 BTC"
+    );
+}
+
+#[test]
+fn no_transactions_before_an_import() {
+    let server = serve(vec![]);
+    let output = coinwagon_with_config(&server, &with_store(""), &["transactions"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "No transactions imported; run import-history first");
+}
+
+#[test]
+fn spread_compares_every_exchange() {
+    let server = serve(vec![
+        route("/api/v3/ticker/bookTicker", 200, "binance_book_ticker.json"),
+        route("/0/public/Ticker", 200, "kraken_ticker.json"),
+        route("/v2/prices/BTC-USD/buy", 200, "coinbase_buy_price.json"),
+        route("/v2/prices/BTC-USD/sell", 200, "coinbase_sell_price.json"),
+    ]);
+    // No credentials needed
+    let output = coinwagon_with_config(&server, "", &["spread", "bitcoin", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "BTC/USD bid/ask by exchange:\n  \
+         Binance (BTC/USDT): bid 67010.5, ask 67011.2\n  \
+         Kraken: bid 67000.1, ask 67002.3\n  \
+         Coinbase: bid 66990, ask 67020\n\
+         Best spread: buy on Kraken at 67002.3, sell on Binance at 67010.5: 8.2 (0.01%)"
+    );
+}
+
+#[test]
+fn spread_lists_exchanges_that_fail() {
+    let server = serve(vec![
+        route("/api/v3/ticker/bookTicker", 200, "binance_book_ticker.json"),
+        route("/0/public/Ticker", 200, "kraken_ticker.json"),
+    ]);
+    let output = coinwagon_with_config(&server, "", &["spread", "btc", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("\n  Coinbase: unavailable ("), "{}", stdout);
+    assert!(stdout.ends_with("Best spread: buy on Kraken at 67002.3, sell on Binance at 67010.5: 8.2 (0.01%)"), "{}", stdout);
+}
+
+#[test]
+fn orderbook_shows_cumulative_depth() {
+    let server = serve(vec![route("/api/v3/depth", 200, "binance_depth.json")]);
+    let output = coinwagon_with_config(&server, "", &["orderbook", "binance", "BTC/USD"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "BTC/USDT on Binance\n\
+         Mid price: 67010.85, spread 0.7\n\
+         Bids (price, size, cumulative):\n  67010.5 1.25 1.25\n  67010 0.5 1.75\n\
+         Asks (price, size, cumulative):\n  67011.2 0.4 0.4\n  67012 2 2.4"
+    );
+}
+
+#[test]
+fn orderbook_keeps_to_the_depth_asked_for() {
+    let server = serve(vec![route("/0/public/Depth", 200, "kraken_depth.json")]);
+    let output = coinwagon_with_config(&server, "", &["orderbook", "kraken", "btc-usd", "--depth", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Bids (price, size, cumulative):\n  67000.1 2 2\n  66999.5 0.1 2.1\nAsks"), "{}", stdout);
+    assert!(stdout.ends_with("  67003 0.25 1.25"), "{}", stdout);
+}
+
+#[test]
+fn orderbook_prints_json() {
+    let server = serve(vec![route("/api/v3/brokerage/market/product_book", 200, "coinbase_product_book.json")]);
+    let output = coinwagon_with_config(&server, "", &["orderbook", "coinbase", "ETH/USD", "--json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let book: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(book["exchange"], "Coinbase");
+    assert_eq!(book["market"], "ETH/USD");
+    assert_eq!(book["mid_price"], 3000.2);
+    assert_eq!(book["asks"][1]["cumulative"], 3.5);
+    assert_eq!(book["bids"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn funding_reports_both_exchanges() {
+    let server = serve(vec![
+        route("/fapi/v1/premiumIndex", 200, "binance_premium_index.json"),
+        route("/fapi/v1/fundingRate", 200, "binance_funding_rate.json"),
+        route("/v5/market/tickers", 200, "bybit_tickers.json"),
+        route("/v5/market/funding/history", 200, "bybit_funding_history.json"),
+    ]);
+    let output = coinwagon_with_config(&server, "", &["funding", "bitcoin"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "BTCUSDT funding:\n  \
+         Binance: last 0.0100% (2023-11-14T16:00:00Z), next 0.0125% at 2023-11-15T00:00:00Z, 13.69% a year\n  \
+         Bybit: last 0.0080% (2023-11-14T16:00:00Z), next 0.0100% at 2023-11-15T00:00:00Z, 10.95% a year"
+    );
+}
+
+#[test]
+fn funding_fails_only_when_every_exchange_does() {
+    let server = serve(vec![
+        route("/fapi/v1/premiumIndex", 200, "binance_premium_index.json"),
+        route("/fapi/v1/fundingRate", 200, "binance_funding_rate.json"),
+        route("/v5/market/tickers", 200, "bybit_error.json"),
+        route("/v5/market/funding/history", 200, "bybit_error.json"),
+    ]);
+    let output = coinwagon_with_config(&server, "", &["funding", "BTCUSDT"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Bybit: unavailable (") && stdout(&output).contains("params error: symbol invalid (code 10001)"), "{}", stdout(&output));
+
+    let server = serve(vec![]);
+    let output = coinwagon_with_config(&server, "", &["funding", "BTCUSDT"]);
+    assert!(!output.status.success());
+}
@@ -0,0 +1,17 @@
+#!/bin/sh
+# Provider plugin for tests/plugins.rs, answering the way its first argument says
+read -r request
+case "$1" in
+  answer)
+    case "$request" in
+      '{"crypto":"mycoin","fiat":"usd","method":"price"}') echo '{"price": 1.25}' ;;
+      '{"address":"myc1qexample","crypto":"mycoin","method":"balance"}') echo '{"balance": 4.5}' ;;
+      *) echo "unexpected request: $request" >&2; exit 1 ;;
+    esac ;;
+  error) echo '{"error": "address not found"}' ;;
+  # exec, so the kill on timeout doesn't leave the sleep behind
+  slow) exec sleep 5 ;;
+  crash) echo "backend down" >&2; exit 3 ;;
+  garbage) echo "price: 1.25" ;;
+  missing) echo '{"value": 1.25}' ;;
+esac
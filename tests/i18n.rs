@@ -0,0 +1,70 @@
+// --lang translates labels and error messages
+mod common;
+
+use common::{coinwagon, coinwagon_command, config_file, route, serve, stderr, stdout, temp_file};
+
+const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+#[test]
+fn labels_and_errors_follow_the_chosen_language() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", ADDRESS));
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd", "--lang", "de"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).ends_with("Gesamt: 8404.3125 USD"), "{}", stdout(&output));
+
+    let output = coinwagon(&server, &["wallet-balance", "/nonexistent/wallet.txt", "usd", "--lang", "es_ES.UTF-8"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("Error: Entrada no válida: "), "{}", stderr(&output));
+
+    // English without --lang, whatever the test machine's locale
+    let output = coinwagon(&server, &["wallet-balance", "/nonexistent/wallet.txt", "usd"]);
+    assert!(stderr(&output).starts_with("Error: Invalid input: "), "{}", stderr(&output));
+
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd", "--lang", "xx"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("No translations for 'xx'; pick one of en, de, es"), "{}", stderr(&output));
+}
+
+#[test]
+fn repl_errors_follow_the_chosen_language() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let server = serve(vec![]);
+    // Keeps the REPL's history file out of the real data directory
+    let home = temp_file("home", "");
+    std::fs::remove_file(&home).unwrap();
+    std::fs::create_dir(&home).unwrap();
+    let mut child = coinwagon_command(&server, &config_file(""), &["repl", "--lang", "de"])
+        .env("HOME", &home)
+        .env("XDG_DATA_HOME", &home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"frobnicate\nquit\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("Fehler: Ungültige Eingabe: Unknown command: frobnicate (try `help`)"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+// The bundled locale files are parsed when the language is picked: a syntax error in one would
+// stop every run rather than show up as raw text
+#[test]
+fn every_bundled_locale_loads() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    for lang in ["en", "de", "es"] {
+        let output = coinwagon(&server, &["current-price", "bitcoin", "usd", "--lang", lang]);
+        assert!(output.status.success(), "{}: {}", lang, stderr(&output));
+    }
+}
@@ -0,0 +1,65 @@
+// Spreading provider requests over several API keys
+mod common;
+
+use common::{coinwagon_with_config, route, serve, stderr, stdout};
+
+const KEYS: &str = "[api_keys.coingecko]\nkeys = [\"CG-first-key-0001\", \"CG-second-key-0002\"]\n";
+
+#[test]
+fn rate_limited_key_hands_over_to_the_next() {
+    let server = serve(vec![
+        route("/simple/price", 429, "coingecko_rate_limited.json")
+            .header("Retry-After", "3600")
+            .when_header("x-cg-demo-api-key", "CG-first-key-0001"),
+        route("/simple/price", 200, "coingecko_simple_price.json").when_header("x-cg-demo-api-key", "CG-second-key-0002"),
+    ]);
+    let config = format!("{}rotation = \"on-rate-limit\"\n", KEYS);
+    let output = coinwagon_with_config(&server, &config, &["current-price", "bitcoin", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "67234.5 USD");
+}
+
+#[test]
+fn every_key_limited_is_rate_limited() {
+    let server = serve(vec![route("/simple/price", 429, "coingecko_rate_limited.json").header("Retry-After", "3600")]);
+    let output = coinwagon_with_config(&server, KEYS, &["current-price", "bitcoin", "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Rate limited by CoinGecko"), "{}", stderr(&output));
+}
+
+#[test]
+fn status_pings_each_key() {
+    let server = serve(vec![
+        route("/ping", 200, "coingecko_simple_price.json").when_header("x-cg-demo-api-key", "CG-first-key-0001"),
+        route("/ping", 401, "coingecko_error.json"),
+    ]);
+    let output = coinwagon_with_config(&server, KEYS, &["status"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    let line = |label: &str| stdout.lines().find(|l| l.starts_with(label)).unwrap_or_default().to_string();
+    assert!(line("CoinGecko key 1 (…0001)").ends_with("ok"), "{}", stdout);
+    assert!(line("CoinGecko key 2 (…0002)").ends_with("credentials rejected (HTTP 401)"), "{}", stdout);
+    assert!(!stdout.contains("CG-first-key"), "{}", stdout);
+}
+
+#[test]
+fn keys_in_urls_stay_out_of_errors() {
+    let address = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+    let server = serve(vec![route(format!("/btc/main/addrs/{}/balance", address), 503, "blockcypher_error.json")]);
+    let config = "[api_keys.blockcypher]\nkeys = [\"bcy-secret-token\"]\n";
+    let output = coinwagon_with_config(&server, config, &["address-balance", "bitcoin", address, "--verbose"]);
+    let stderr = stderr(&output);
+    assert!(stderr.contains("token=REDACTED"), "{}", stderr);
+    assert!(!stderr.contains("bcy-secret-token"), "{}", stderr);
+}
+
+#[test]
+fn keychain_failure_is_a_config_error() {
+    // Default builds have no keychain support, and test machines no stored coingecko keys
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    let config = "[api_keys.coingecko]\nkeychain = true\n";
+    let output = coinwagon_with_config(&server, config, &["current-price", "bitcoin", "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("Error: Config error:"), "{}", stderr(&output));
+    assert!(stderr(&output).contains("OS keychain"), "{}", stderr(&output));
+}
@@ -0,0 +1,109 @@
+// export --format parquet, read back with the parquet crate's reader: every column must come
+// back with its logical type and the values the CSV export has
+mod common;
+
+use chrono::{DateTime, NaiveDate};
+use common::{coinwagon, coinwagon_with_config, fixture, route, serve, stderr, stdout, temp_file};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::Field;
+
+// A column's name, then its values in CSV's form: times and dates as ISO 8601, None for a null
+type Column = (String, Vec<Option<String>>);
+
+// The file's row count and columns. The reader only hands back strings, timestamps and dates
+// for columns annotated STRING, TIMESTAMP(MICROS) and DATE, so this checks the types too.
+fn read(path: &std::path::Path) -> (i64, Vec<Column>) {
+    let reader = SerializedFileReader::new(std::fs::File::open(path).unwrap()).unwrap();
+    let meta = reader.metadata().file_metadata();
+    let mut columns: Vec<Column> = meta.schema_descr().columns().iter().map(|column| (column.name().to_string(), Vec::new())).collect();
+    for row in reader.get_row_iter(None).unwrap() {
+        for ((name, values), (_, field)) in columns.iter_mut().zip(row.unwrap().get_column_iter()) {
+            values.push(match field {
+                Field::Null => None,
+                Field::Str(text) => Some(text.clone()),
+                Field::Double(number) => Some(number.to_string()),
+                Field::TimestampMicros(micros) => {
+                    Some(DateTime::from_timestamp_micros(*micros).unwrap().format("%Y-%m-%dT%H:%M:%SZ").to_string())
+                }
+                Field::Date(days) => Some((NaiveDate::from_ymd_opt(1970, 1, 1).unwrap() + chrono::Duration::days(*days as i64)).to_string()),
+                other => panic!("column {} has an unexpected value {:?}", name, other),
+            });
+        }
+    }
+    (meta.num_rows(), columns)
+}
+
+// The CSV export's rows, split into cells, empty ones as None
+fn csv_rows(csv: &str) -> Vec<Vec<Option<String>>> {
+    csv.lines().map(|line| line.split(',').map(|cell| (!cell.is_empty()).then(|| cell.to_string())).collect()).collect()
+}
+
+fn assert_same_as_csv(path: &std::path::Path, csv: &str) {
+    let (rows, columns) = read(path);
+    let csv = csv_rows(csv);
+    assert_eq!(rows as usize, csv.len() - 1);
+    let names: Vec<Option<String>> = columns.iter().map(|(name, _)| Some(name.clone())).collect();
+    assert_eq!(names, csv[0]);
+    for (row, cells) in csv[1..].iter().enumerate() {
+        let values: Vec<Option<String>> = columns.iter().map(|(_, values)| values[row].clone()).collect();
+        assert_eq!(&values, cells, "row {}", row);
+    }
+}
+
+#[test]
+fn transactions_read_back_as_the_csv_has_them() {
+    let server = serve(vec![]);
+    let config = format!("[transactions]\npath = {:?}\n", fixture("transactions_8949.json").to_str().unwrap());
+    let csv = stdout(&coinwagon_with_config(&server, &config, &["export", "transactions"]));
+    let path = temp_file("transactions.parquet", "");
+    let output = coinwagon_with_config(&server, &config, &["export", "transactions", "--format", "parquet", "--output", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_same_as_csv(&path, &csv);
+
+    // Nulls where a trade has no fee
+    let (_, columns) = read(&path);
+    let fees = &columns.iter().find(|(name, _)| name == "fee_amount").unwrap().1;
+    assert_eq!(fees, &[Some("20".to_string()), None, Some("100".to_string()), None]);
+}
+
+#[test]
+fn history_dates_read_back_as_dates() {
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let csv = stdout(&coinwagon(&server, &["export", "history", "btc", "--days", "8"]));
+    let path = temp_file("history.parquet", "");
+    let output = coinwagon(&server, &["export", "history", "btc", "--days", "8", "--format", "parquet", "--output", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_same_as_csv(&path, &csv);
+    assert_eq!(read(&path).1[0].1[0].as_deref(), Some("2024-05-24"));
+}
+
+#[test]
+fn alert_snapshots_export_a_row_per_coin() {
+    let server = serve(vec![]);
+    let snapshots = temp_file(
+        "snapshots.jsonl",
+        "{\"time\":\"2024-06-01T12:00:00Z\",\"fiat\":\"usd\",\"total\":1500.0,\"holdings\":{\"bitcoin\":1000.0,\"ethereum\":500.0}}\n\
+         {\"time\":\"2024-06-01T12:01:00Z\",\"fiat\":\"usd\",\"total\":1020.0,\"holdings\":{\"bitcoin\":1020.0}}\n",
+    );
+    let config = format!("[alerts]\nsnapshots = {:?}\n", snapshots.to_str().unwrap());
+    let output = coinwagon_with_config(&server, &config, &["export", "snapshots"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let csv = stdout(&output);
+    assert_eq!(
+        csv,
+        "time,coin,value,fiat,total\n\
+         2024-06-01T12:00:00Z,bitcoin,1000,usd,1500\n\
+         2024-06-01T12:00:00Z,ethereum,500,usd,1500\n\
+         2024-06-01T12:01:00Z,bitcoin,1020,usd,1020"
+    );
+
+    let path = temp_file("snapshots.parquet", "");
+    let output = coinwagon_with_config(&server, &config, &["export", "snapshots", "--format", "parquet", "--output", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), format!("Wrote 3 rows to {}", path.display()));
+    assert_same_as_csv(&path, &csv);
+
+    let output = coinwagon(&server, &["export", "snapshots"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("No snapshots file to export; set [alerts] snapshots or pass --file"), "{}", stderr(&output));
+}
@@ -0,0 +1,93 @@
+// [[plugins]] providers: tests/fixtures/plugin.sh stands in for the external executable
+#![cfg(unix)]
+mod common;
+
+use common::{coinwagon_with_config, fixture, serve, stderr, stdout};
+
+// The fixture answers the way `mode` says
+fn plugin(mode: &str) -> String {
+    format!(
+        "[[plugins]]\nname = \"test-plugin\"\ncommand = \"sh\"\nargs = [{:?}, {:?}]\ncoins = [\"mycoin\"]\n",
+        fixture("plugin.sh").to_str().unwrap(),
+        mode
+    )
+}
+
+// Runs a price lookup the plugin fails, returning why it failed: the lookup then falls back
+// to the built-in providers, which have nothing for it either
+fn price_failure(mode: &str) -> String {
+    let server = serve(vec![]);
+    let output = coinwagon_with_config(&server, &plugin(mode), &["current-price", "mycoin", "usd", "--verbose", "--timeout", "1"]);
+    assert!(!output.status.success());
+    let stderr = stderr(&output);
+    stderr
+        .lines()
+        .find(|line| line.starts_with("Warning: test-plugin failed"))
+        .unwrap_or_else(|| panic!("no plugin failure in: {}", stderr))
+        .to_string()
+}
+
+#[test]
+fn price_from_a_plugin() {
+    let server = serve(vec![]);
+    let output = coinwagon_with_config(&server, &plugin("answer"), &["current-price", "mycoin", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1.25 USD");
+}
+
+#[test]
+fn balance_from_a_plugin() {
+    let server = serve(vec![]);
+    let output = coinwagon_with_config(&server, &plugin("answer"), &["address-balance", "mycoin", "myc1qexample"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "4.5 MYCOIN");
+}
+
+#[test]
+fn error_reply_is_an_api_error() {
+    assert_eq!(
+        price_failure("error"),
+        "Warning: test-plugin failed (API error from test-plugin: address not found), falling back to CoinGecko"
+    );
+}
+
+#[test]
+fn slow_plugin_times_out_as_unavailable() {
+    assert_eq!(
+        price_failure("slow"),
+        "Warning: test-plugin failed (test-plugin is unavailable: timed out after 1s), falling back to CoinGecko"
+    );
+}
+
+#[test]
+fn non_zero_exit_is_an_api_error_with_its_stderr() {
+    assert_eq!(
+        price_failure("crash"),
+        "Warning: test-plugin failed (API error from test-plugin: exited with exit status: 3: backend down), falling back to CoinGecko"
+    );
+}
+
+#[test]
+fn invalid_json_is_an_api_error() {
+    assert_eq!(
+        price_failure("garbage"),
+        "Warning: test-plugin failed (API error from test-plugin: returned invalid JSON: expected value at line 1 column 1), falling back to CoinGecko"
+    );
+    assert_eq!(
+        price_failure("missing"),
+        "Warning: test-plugin failed (API error from test-plugin: response is missing \"price\"), falling back to CoinGecko"
+    );
+}
+
+#[test]
+fn failed_balance_lookup_falls_back_too() {
+    let server = serve(vec![]);
+    let output =
+        coinwagon_with_config(&server, &plugin("error"), &["address-balance", "mycoin", "myc1qexample", "--verbose"]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Warning: test-plugin failed (API error from test-plugin: address not found), falling back to"),
+        "{}",
+        stderr(&output)
+    );
+}
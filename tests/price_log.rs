@@ -0,0 +1,38 @@
+// --log-prices appends a JSON line for each price fetched from a provider
+mod common;
+
+use common::{coinwagon, route, serve, stderr, temp_file};
+use serde_json::Value;
+
+const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+fn read_log(path: &std::path::Path) -> Vec<Value> {
+    std::fs::read_to_string(path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[test]
+fn fetched_prices_are_appended_once() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+    ]);
+    let log = temp_file("prices.jsonl", "");
+    // The second line's price comes from the first's lookup, so it isn't logged again
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{0}\nbitcoin,{0}\n", ADDRESS));
+    let args = ["wallet-balance", wallet.to_str().unwrap(), "usd", "--log-prices", log.to_str().unwrap()];
+    let output = coinwagon(&server, &args);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let entries = read_log(&log);
+    assert_eq!(entries.len(), 1, "{:?}", entries);
+    assert_eq!(entries[0]["coin"], "bitcoin");
+    assert_eq!(entries[0]["fiat"], "usd");
+    assert_eq!(entries[0]["price"], 67234.5);
+    assert_eq!(entries[0]["source"], "CoinGecko");
+    assert!(entries[0]["timestamp"].as_str().unwrap().ends_with('Z'), "{:?}", entries);
+
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usd", "--log-prices", log.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(read_log(&log).len(), 2);
+}
@@ -0,0 +1,1092 @@
+// End-to-end runs of the coinwagon binary against fixture responses served locally
+mod common;
+
+use common::{coinwagon, coinwagon_command, coinwagon_with_config, fixture, route, serve, stderr, stdout, temp_file};
+
+const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+#[test]
+fn current_price_from_coingecko() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    let output = coinwagon(&server, &["current-price", "btc", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "67234.5 USD");
+}
+
+#[test]
+fn unknown_coin_is_not_found() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_empty.json")]);
+    let output = coinwagon(&server, &["current-price", "btc", "usd"]);
+    assert!(!output.status.success());
+    assert_eq!(stderr(&output), "Error: Not found: CoinGecko has no usd price for bitcoin");
+}
+
+#[test]
+fn current_price_in_ounces_of_gold() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_xau_price.json")]);
+    let output = coinwagon(&server, &["current-price", "bitcoin", "--vs", "xau"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "28.71 XAU");
+}
+
+#[test]
+fn check_price_exits_by_whether_the_threshold_holds() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    let output = coinwagon(&server, &["check-price", "bitcoin", "usd", "--above", "60000"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "");
+
+    let output = coinwagon(&server, &["check-price", "btc", "usd", "--below", "60000", "--verbose"]);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "btc is 67234.5 USD, not below 60000 USD (CoinGecko)");
+
+    let server = serve(vec![route("/simple/price", 200, "coingecko_empty.json")]);
+    let output = coinwagon(&server, &["check-price", "bitcoin", "usd", "--above", "60000"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "Error: Not found: CoinGecko has no usd price for bitcoin");
+}
+
+#[test]
+fn missing_fiat_is_converted_from_usd() {
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_empty.json").once(),
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+        route("/latest", 200, "frankfurter_latest.json"),
+    ]);
+    let output = coinwagon(&server, &["current-price", "bitcoin", "eur", "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "62877.7044 EUR");
+    assert!(
+        stderr(&output).contains("Converted via USD: 67234.5 USD × 0.9352 EUR/USD (Frankfurter) = 62877.7044 EUR"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn price_in_another_coin_uses_the_direct_pair_or_both_usd_prices() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_ethereum_btc_price.json")]);
+    let output = coinwagon(&server, &["current-price", "eth", "btc"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.0512 BTC");
+
+    let server = serve(vec![route("/simple/price", 200, "coingecko_bitcoin_ethereum_prices.json")]);
+    let output = coinwagon(&server, &["current-price", "ethereum", "bitcoin", "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.05 BITCOIN");
+    assert!(stderr(&output).contains("Converted via USD: 3000 USD ÷ 60000 USD/BITCOIN (CoinGecko) = 0.05 BITCOIN"), "{}", stderr(&output));
+
+    let server = serve(vec![
+        route("/coins/ethereum/market_chart", 400, "coingecko_error.json").once(),
+        route("/coins/ethereum/market_chart", 200, "coingecko_ethereum_market_chart.json"),
+        route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json"),
+    ]);
+    let output = coinwagon(&server, &["export", "history", "ethereum", "--fiat", "bitcoin", "--days", "8"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.starts_with("date,coin,fiat,price,source\n2024-05-24,ethereum,bitcoin,10,CoinGecko\n"), "{}", stdout);
+    assert!(stdout.ends_with("\n2024-06-01,ethereum,bitcoin,9.23076923076923,CoinGecko"), "{}", stdout);
+}
+
+#[test]
+fn provider_error_payload_is_reported() {
+    let server = serve(vec![route("/simple/price", 401, "coingecko_error.json")]);
+    let output = coinwagon(&server, &["current-price", "btc", "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("API error from CoinGecko: API Key Missing"), "{}", stderr(&output));
+}
+
+#[test]
+fn address_balance_from_blockcypher() {
+    let path = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![route(path, 200, "blockcypher_balance.json")]);
+    let output = coinwagon(&server, &["address-balance", "bitcoin", ADDRESS]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.125 BITCOIN");
+}
+
+#[test]
+fn falls_back_to_blockchair_when_blockcypher_is_down() {
+    let blockcypher = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let blockchair = format!("/bitcoin/dashboards/address/{}", ADDRESS);
+    let server = serve(vec![
+        route(blockcypher, 503, "blockcypher_error.json"),
+        route(blockchair, 200, "blockchair_dashboard.json"),
+    ]);
+    let output = coinwagon(&server, &["address-balance", "bitcoin", ADDRESS, "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.125 BITCOIN");
+    assert!(stderr(&output).contains("BlockCypher failed"), "{}", stderr(&output));
+}
+
+#[test]
+fn wallet_balance_totals_every_line() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("# savings\nbitcoin,{0}\nbitcoin,{0}\n", ADDRESS));
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert_eq!(stdout.matches("BITCOIN: 0.125 BITCOIN = 8404.3125 USD").count(), 2, "{}", stdout);
+    assert!(stdout.ends_with("Total: 16808.625 USD"), "{}", stdout);
+}
+
+#[test]
+fn wallet_lines_sharing_a_coin_share_one_request() {
+    // A second request to either route would get a 404 and fail the wallet
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json").once(),
+        route(balance, 200, "blockcypher_balance.json").once(),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", ADDRESS).repeat(8));
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).matches("BITCOIN: 0.125 BITCOIN = 8404.3125 USD").count(), 8, "{}", stdout(&output));
+}
+
+#[test]
+fn address_book_names_stand_in_for_addresses() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+    ]);
+    let book = temp_file("addressbook.json", "{}");
+    let config = format!("[addressbook]\npath = {:?}\n", book.to_str().unwrap());
+    let run = |args: &[&str]| coinwagon_with_config(&server, &config, args);
+
+    assert_eq!(stdout(&run(&["addressbook", "add", "cold-storage", "btc", ADDRESS])), format!("Added @cold-storage: bitcoin {}", ADDRESS));
+    assert_eq!(stdout(&run(&["addressbook", "show"])), format!("@cold-storage: bitcoin {}", ADDRESS));
+
+    let output = run(&["address-balance", "@cold-storage"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.125 BITCOIN");
+
+    let wallet = temp_file("wallet.txt", &format!("@cold-storage\nbitcoin,{}\n", ADDRESS));
+    let output = run(&["wallet-balance", wallet.to_str().unwrap(), "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).matches("BITCOIN @cold-storage: 0.125 BITCOIN = 8404.3125 USD").count(), 2, "{}", stdout(&output));
+
+    assert_eq!(stdout(&run(&["addressbook", "remove", "@cold-storage"])), "Removed @cold-storage");
+    let output = run(&["address-balance", "@cold-storage"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("No @cold-storage in the address book"), "{}", stderr(&output));
+}
+
+#[test]
+fn addr_descriptors_are_read_as_their_address() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![route(balance, 200, "blockcypher_balance.json")]);
+    let output = coinwagon(&server, &["address-balance", "bitcoin", &format!("addr({})#a4v7yfky", ADDRESS)]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.125 BITCOIN");
+
+    let wallet = temp_file("wallet.txt", "bitcoin,wsh(sortedmulti(2,xpub6A,xpub6B))\n");
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("'xpub6A' isn't an extended public key or a hex public key"), "{}", stderr(&output));
+}
+
+// The BIP 44, 49 and 84 test vectors' account keys, of the "abandon ... about" mnemonic
+const XPUB: &str = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+const YPUB: &str = "ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP";
+const ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+// Their 2-of-3 sortedmulti at receive index 3, under wsh()
+const MULTISIG: &str = "bc1q8syvh0qqfk055u7jej4dt5lr7jmpwdsvmjsh03mj6skptem3cm0qmscnxe";
+
+#[test]
+fn key_descriptors_derive_their_address() {
+    let vectors = [
+        (format!("pkh({}/0/0)", XPUB), "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"),
+        (format!("sh(wpkh({}/0/0))", YPUB), "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"),
+        (format!("wpkh([73c5da0a/84h/0h/0h]{}/1/0)", ZPUB), "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"),
+        (format!("wsh(sortedmulti(2,{}/0/3,{}/0/3,{}/0/3))", ZPUB, XPUB, YPUB), MULTISIG),
+        (format!("sh(wsh(sortedmulti(2,{}/0/3,{}/0/3,{}/0/3)))", XPUB, YPUB, ZPUB), "3MDfBr3nYG7sdTPTyVewiD5j25WRma8xg8"),
+        (format!("sh(sortedmulti(2,{}/0/3,{}/0/3,{}/0/3))", XPUB, YPUB, ZPUB), "34pmsunLYeTiKFQ7SrsxmeRtpwTdUZr9Q2"),
+    ];
+    for (descriptor, address) in vectors {
+        let server = serve(vec![route(format!("/btc/main/addrs/{}/balance", address), 200, "blockcypher_balance.json")]);
+        let output = coinwagon(&server, &["address-balance", "bitcoin", &descriptor]);
+        assert!(output.status.success(), "{}: {}", descriptor, stderr(&output));
+        assert_eq!(stdout(&output), "0.125 BITCOIN");
+    }
+
+    let server = serve(vec![]);
+    for (descriptor, error) in [
+        (format!("wpkh({}/84h/0)", ZPUB), "Can't follow the hardened step 84h"),
+        (format!("tr({}/0/0)", XPUB), "tr() isn't supported"),
+        (format!("wsh(sortedmulti(3,{}/0/*,{}/0/*))", XPUB, YPUB), "sortedmulti() needs a threshold from 1 to its number of keys"),
+        ("wpkh(tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp/0/*)".to_string(), "tpub keys are for testnet"),
+    ] {
+        let output = coinwagon(&server, &["address-balance", "bitcoin", &descriptor]);
+        assert!(!output.status.success());
+        assert!(stderr(&output).contains(error), "{}", stderr(&output));
+    }
+}
+
+#[test]
+fn ranged_descriptors_are_scanned_to_the_gap_limit() {
+    // Receive index 3 has a transaction and nothing else does
+    let server = serve(vec![
+        route(format!("/address/{}/txs", MULTISIG), 200, "esplora_txs.json"),
+        route("/address/*/txs", 200, "esplora_txs_none.json"),
+        route("/blocks/tip/height", 200, "esplora_tip.txt"),
+        route(format!("/btc/main/addrs/{}/balance", MULTISIG), 200, "blockcypher_balance.json"),
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+    ]);
+    let descriptor = format!("wsh(sortedmulti(2,{0}/<0;1>/*,{1}/<0;1>/*,{2}/<0;1>/*))", XPUB, YPUB, ZPUB);
+    let output = coinwagon(&server, &["address-balance", "bitcoin", &descriptor, "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.125 BITCOIN");
+    // Receive addresses up to 20 past index 3, then 20 change addresses
+    assert!(stderr(&output).contains(&format!("Scanned 60 addresses of {}: 1 used", descriptor)), "{}", stderr(&output));
+
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", descriptor));
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "BITCOIN: 0.125 BITCOIN = 8404.3125 USD\nTotal: 8404.3125 USD");
+}
+
+#[test]
+fn taproot_addresses_skip_providers_that_dont_index_them() {
+    const TAPROOT: &str = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+    let server = serve(vec![
+        route(format!("/btc/main/addrs/{}/balance", TAPROOT), 200, "blockcypher_balance.json"),
+        route(format!("/bitcoin/dashboards/address/{}", TAPROOT), 200, "blockchair_taproot.json"),
+    ]);
+    let output = coinwagon(&server, &["address-balance", "bitcoin", TAPROOT]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.5 BITCOIN");
+
+    // The same program checksummed as bech32 rather than bech32m
+    let output = coinwagon(&server, &["address-balance", "bitcoin", "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("isn't a valid taproot address"), "{}", stderr(&output));
+}
+
+#[test]
+fn utxo_report_counts_dust_ages_and_consolidation_cost() {
+    let server = serve(vec![
+        route(format!("/bitcoin/dashboards/address/{}", ADDRESS), 200, "blockchair_utxos.json"),
+        route("/bitcoin/stats", 200, "blockchair_stats.json"),
+    ]);
+    let output = coinwagon(&server, &["utxo-report", ADDRESS]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "UTXOs of {} (Blockchair, 10 sat/vB):\n  Outputs: 6 holding 0.07630800 BTC\n  \
+             Dust: 2 outputs holding 800 sats, each worth no more than the 680 sats spending it costs\n  \
+             Age: unconfirmed 1, under a day 2, under a week 1, under a year 1, a year or more 1\n  \
+             Consolidating into one output: 449.5 vB, 4495 sats (0.00004495 BTC)",
+            ADDRESS
+        )
+    );
+
+    let output = coinwagon(&server, &["utxo-report", ADDRESS, "--fee-rate", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Dust: 0 outputs holding 0 sats"), "{}", stdout(&output));
+
+}
+
+#[test]
+fn utxo_report_of_an_account_key_adds_up_its_used_addresses() {
+    // The zpub's first receive address
+    const FIRST: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+    let server = serve(vec![
+        route(format!("/address/{}/txs", FIRST), 200, "esplora_txs.json"),
+        route("/address/*/txs", 200, "esplora_txs_none.json"),
+        route("/blocks/tip/height", 200, "esplora_tip.txt"),
+        route(format!("/bitcoin/dashboards/address/{}", FIRST), 200, "blockchair_utxos_zpub.json"),
+        route("/bitcoin/stats", 200, "blockchair_stats.json"),
+    ]);
+    let output = coinwagon(&server, &["utxo-report", ZPUB, "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    // Scanned in runs of 20: 40 receive addresses, then 20 change
+    assert!(stderr(&output).contains("Scanned 60 addresses"), "{}", stderr(&output));
+    let text = stdout(&output);
+    assert!(text.starts_with(&format!("UTXOs of {} (Blockchair, 10 sat/vB):\n  Outputs: 6 holding 0.07630800 BTC", ZPUB)), "{}", text);
+    assert!(text.contains("Consolidating into one output: 449.5 vB"), "{}", text);
+
+    // The same key with a multisig's version bytes
+    let zpub = "Zpub739WFCnqb8H6bozqRWNgL4NwrVvUUDaa5UodTovoPXuLnoVJTvkwKA6b5ioUif4ntuhU53ob9LUdZ66F3uNGoX8S6gzjGa1yvYFtkDRknR2";
+    let output = coinwagon(&server, &["utxo-report", zpub]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("A Zpub is one key of a multisig; give the wallet's descriptor"), "{}", stderr(&output));
+}
+
+#[test]
+fn watch_address_reports_new_transactions_and_confirmations() {
+    let txs = format!("/address/{}/txs", ADDRESS);
+    let server = serve(vec![
+        route(txs.clone(), 200, "esplora_txs.json").once(),
+        route(txs.clone(), 200, "esplora_txs_incoming.json").once(),
+        route(txs, 200, "esplora_txs_confirmed.json"),
+        route("/blocks/tip/height", 200, "esplora_tip.txt").once(),
+        route("/blocks/tip/height", 200, "esplora_tip.txt").once(),
+        route("/blocks/tip/height", 200, "esplora_tip_next.txt"),
+    ]);
+    let output = coinwagon(&server, &["watch-address", "bitcoin", ADDRESS, "--interval", "1", "--polls", "3"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let txid = "c9e1a3f5b7d9c1e3a5f7b9d1c3e5a7f9b1d3c5e7a9f1b3d5c7e9a1f3b5d7c9e1";
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "Watching bitcoin address {0} on Esplora every 1s (2 earlier transactions)\n\
+             Incoming 0.001 BITCOIN to {0}: {1}, 0 confirmations\n\
+             {1}: 1 confirmation, confirmed",
+            ADDRESS, txid
+        )
+    );
+}
+
+#[test]
+fn address_balance_at_a_past_date_or_height() {
+    let server = serve(vec![
+        route(format!("/address/{}", ADDRESS), 200, "esplora_address.json"),
+        route(format!("/address/{}/txs/chain", ADDRESS), 200, "esplora_txs.json"),
+    ]);
+    let output = coinwagon(&server, &["address-balance", "bitcoin", ADDRESS, "--at", "2024-06-15"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.0025 BITCOIN");
+
+    let output = coinwagon(&server, &["address-balance", "bitcoin", ADDRESS, "--height", "848999"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0 BITCOIN");
+
+    let output = coinwagon(&server, &["address-balance", "bitcoin", ADDRESS, "--at", "31/12/2023"]);
+    assert!(stderr(&output).contains("--at takes a date like 2023-12-31, got '31/12/2023'"), "{}", stderr(&output));
+}
+
+#[test]
+fn whales_reports_each_large_mempool_transaction_once() {
+    let server = serve(vec![
+        route("/mempool/recent", 200, "esplora_recent.json").once(),
+        route("/mempool/recent", 200, "esplora_recent_next.json"),
+    ]);
+    let output = coinwagon(&server, &["whales", "btc", "--min", "100", "--interval", "1", "--polls", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Watching the bitcoin mempool on Esplora every 1s for 100 BITCOIN or more\n\
+         Whale: 150.12345678 BITCOIN moved in a7f2e5b8c1d4a9f6e3b0c7d2a5f8e1b4c9d6a3f0e7b2c5d8a1f4e9b6c3d0a7f2\n\
+         Whale: 250 BITCOIN moved in f1d3b5a7c9e2f4a6b8d0c1e3f5a7b9d2c4e6f8a0b1d3c5e7f9a2b4d6c8e0f1a3"
+    );
+}
+
+#[test]
+fn tip_and_block_read_the_chain_from_blockchair() {
+    let server = serve(vec![
+        route("/bitcoin/stats", 200, "blockchair_stats.json"),
+        route("/bitcoin/dashboards/block/850000", 200, "blockchair_block.json"),
+    ]);
+    let output = coinwagon(&server, &["tip", "btc"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Tip of bitcoin (Blockchair):\n  Height: 850123\n  \
+         Hash: 00000000000000000001b4f3a8c2e9d7f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1\n  Time: 2024-06-22T10:15:07Z"
+    );
+
+    let output = coinwagon(&server, &["block", "bitcoin", "850000"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Block 850000 of bitcoin (Blockchair):\n  \
+         Hash: 00000000000000000002a7c4c1e48d76c5a37902165a270156b7a8d72728a054\n  Time: 2024-06-21T18:40:03Z\n  \
+         Size: 1582379 bytes\n  Transactions: 4237\n  Fees: 0.07967731 BITCOIN"
+    );
+
+    let output = coinwagon(&server, &["block", "bitcoin", "latest"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("neither a block height nor a block hash"), "{}", stderr(&output));
+}
+
+#[test]
+fn timestamps_are_shown_in_the_chosen_timezone() {
+    let server = serve(vec![route("/bitcoin/stats", 200, "blockchair_stats.json"), route("/global", 200, "coingecko_global.json")]);
+    let output = coinwagon(&server, &["tip", "btc", "--timezone", "Europe/Berlin"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).ends_with("Time: 2024-06-22T12:15:07+02:00"), "{}", stdout(&output));
+
+    let output = coinwagon(&server, &["global", "--json", "--timezone", "Asia/Kolkata"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["updated_at"], "2024-04-06T01:04:38+05:30");
+
+    let output = coinwagon(&server, &["tip", "btc", "--timezone", "Mars/Olympus_Mons"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown timezone 'Mars/Olympus_Mons'"), "{}", stderr(&output));
+}
+
+#[test]
+fn network_reports_difficulty_hashrate_and_the_next_adjustment() {
+    let server = serve(vec![route("/bitcoin/stats", 200, "blockchair_stats.json")]);
+    let output = coinwagon(&server, &["network", "bitcoin"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "bitcoin network at block 850123 (Blockchair):\n  Difficulty: 83.15 T\n  Hashrate (24h): 612.35 EH/s\n  \
+         Next adjustment: +2.31% to 85.07 T around 2024-06-26T20:48:12Z, in 629 blocks"
+    );
+}
+
+#[test]
+fn halving_counts_down_at_the_recent_block_time() {
+    let server = serve(vec![
+        route("/bitcoin/stats", 200, "blockchair_stats.json"),
+        route("/bitcoin/dashboards/block/848107", 200, "blockchair_block_848107.json"),
+    ]);
+    let output = coinwagon(&server, &["halving"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Next bitcoin halving (Blockchair):\n  At block 1050000, 199877 blocks after the tip at 850123\n  \
+         Block reward: 3.125 to 1.5625 BITCOIN\n  Average block time over the last 2016 blocks: 9.8 minutes\n  \
+         Estimated: 2028-03-18T07:52:17Z (in about 1364 days)"
+    );
+}
+
+#[test]
+fn configured_headers_reach_the_provider() {
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json")
+            .require_header("x-cg-demo-api-key", "demo-key")
+            .require_header("user-agent", "portfolio-bot/2.0"),
+    ]);
+    let config = "[network]\nuser_agent = \"portfolio-bot/2.0\"\n\n[headers.coingecko]\nx-cg-demo-api-key = \"demo-key\"\n";
+    let output = coinwagon_with_config(&server, config, &["current-price", "btc", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "67234.5 USD");
+}
+
+#[test]
+fn default_user_agent_names_coinwagon() {
+    let user_agent = concat!("coinwagon/", env!("CARGO_PKG_VERSION"));
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json").require_header("user-agent", user_agent)]);
+    let output = coinwagon(&server, &["current-price", "btc", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn zero_timeouts_are_rejected() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    for flag in ["--timeout", "--connect-timeout"] {
+        let output = coinwagon(&server, &["current-price", "btc", "usd", flag, "0"]);
+        assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+        assert!(stderr(&output).contains("0 is not in 1.."), "{}", stderr(&output));
+    }
+
+    let output = coinwagon_with_config(&server, "[network]\ntimeout_secs = 0\n", &["current-price", "btc", "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("timeout_secs and connect_timeout_secs must be at least 1"), "{}", stderr(&output));
+}
+
+#[test]
+fn wallet_addresses_are_batched_on_blockchair() {
+    const OTHER: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+    let batch = format!("/bitcoin/dashboards/addresses/{},{}", ADDRESS, OTHER);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+        route(batch, 200, "blockchair_addresses.json").once(),
+        // Left out of the batch answer, so looked up on its own
+        route(format!("/btc/main/addrs/{}/balance", OTHER), 200, "blockcypher_balance.json").once(),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\nbitcoin,{}\n", ADDRESS, OTHER));
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd", "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).ends_with("Total: 16808.625 USD"), "{}", stdout(&output));
+    assert!(stderr(&output).contains("Fetched 1 of 2 balances from Blockchair in one request"), "{}", stderr(&output));
+}
+
+#[test]
+fn ethereum_balance_from_covalent_with_a_key() {
+    const ETH_ADDRESS: &str = "0x742d35Cc6634C0532925a3b8D4Fddfac9e2C4cb7";
+    let path = format!("/eth-mainnet/address/{}/balances_v2/", ETH_ADDRESS);
+    let server = serve(vec![route(path, 200, "covalent_balances.json")]);
+    let config = "[api_keys.covalent]\nkeys = [\"cqt_test_key\"]\n";
+    let output = coinwagon_with_config(&server, config, &["address-balance", "ethereum", ETH_ADDRESS, "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1.5 ETHEREUM");
+    assert!(stderr(&output).contains("token: 2500 USDC"), "{}", stderr(&output));
+}
+
+#[test]
+fn ethereum_balance_from_a_configured_rpc_endpoint() {
+    const ETH_ADDRESS: &str = "0x742d35Cc6634C0532925a3b8D4Fddfac9e2C4cb7";
+    let server = serve(vec![route("/v2/alchemy-test-key-0123456789", 200, "rpc_balance.json")]);
+    let config = format!("[rpc]\nethereum = \"{}/v2/alchemy-test-key-0123456789\"\n", server);
+    let output = coinwagon_with_config(&server, &config, &["address-balance", "ethereum", ETH_ADDRESS]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1.5 ETHEREUM");
+}
+
+#[test]
+fn rpc_errors_keep_the_endpoint_key_out() {
+    let server = serve(vec![route("/v2/alchemy-test-key-0123456789", 200, "rpc_error.json")]);
+    let config = format!("[rpc]\nethereum = \"{}/v2/alchemy-test-key-0123456789\"\n", server);
+    let output = coinwagon_with_config(&server, &config, &["address-balance", "ethereum", "0xabc", "--verbose"]);
+    let stderr = stderr(&output);
+    assert!(stderr.contains("RPC failed (API error from RPC: invalid argument 0"), "{}", stderr);
+    assert!(!stderr.contains("alchemy-test-key"), "{}", stderr);
+}
+
+#[test]
+fn fees_come_in_each_chains_unit() {
+    let server = serve(vec![
+        route("/fee-estimates", 200, "esplora_fee_estimates.json"),
+        route("/litecoin/stats", 200, "blockchair_stats.json"),
+        route("/v2/alchemy-test-key-0123456789", 200, "rpc_fee_history.json"),
+    ]);
+    let output = coinwagon(&server, &["fees", "btc"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Fees on bitcoin (Esplora):\n  fast: 25.4 sat/vB (next block)\n  normal: 12.2 sat/vB (within 6 blocks)\n  slow: 2.03 sat/vB (within 144 blocks)"
+    );
+
+    let output = coinwagon(&server, &["fees", "litecoin"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Fees on litecoin (Blockchair):\n  normal: 10 sat/vB");
+
+    // The next block's base fee plus the average tip at each percentile
+    let config = format!("[rpc]\nethereum = \"{}/v2/alchemy-test-key-0123456789\"\n", server);
+    let output = coinwagon_with_config(&server, &config, &["fees", "ethereum", "--json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["chain"], "ethereum");
+    assert_eq!(json["unit"], "gwei");
+    assert_eq!(json["source"], "RPC");
+    assert_eq!(
+        json["tiers"],
+        serde_json::json!([
+            { "name": "fast", "rate": 16.0, "blocks": null },
+            { "name": "normal", "rate": 14.25, "blocks": null },
+            { "name": "slow", "rate": 12.55, "blocks": null },
+        ])
+    );
+}
+
+#[test]
+fn sentiment_shows_the_index_and_its_trend() {
+    let server = serve(vec![route("/fng/", 200, "alternative_fng.json")]);
+    let output = coinwagon(&server, &["sentiment", "--days", "4"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[..2], ["Fear & Greed: 72 (Greed)", "Trend: rising (+27 over 3 days)"]);
+    assert_eq!(lines.len(), 6, "{}", stdout);
+    assert!(lines[2].ends_with(": 72 Greed") && lines[5].ends_with(": 45 Fear"), "{}", stdout);
+}
+
+#[test]
+fn global_reports_market_totals_and_dominance() {
+    let server = serve(vec![route("/global", 200, "coingecko_global.json")]);
+    let output = coinwagon(&server, &["global"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Total market cap: 2650000000000 USD\n24h volume: 98000000000 USD\nBTC dominance: 54.21%\nETH dominance: 16.90%\nActive coins: 13690"
+    );
+
+    let output = coinwagon(&server, &["global", "--fiat", "eur", "--json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["total_market_cap"], 2450123456789.5);
+    assert_eq!(json["btc_dominance"], 54.2137);
+    assert_eq!(json["updated_at"], "2024-04-05T19:34:38Z");
+    assert_eq!(json["source"], "CoinGecko");
+}
+
+#[test]
+fn peg_flags_stablecoins_off_their_peg() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_stablecoins.json")]);
+    let output = coinwagon(&server, &["peg"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Pegs (off peg beyond 0.5%):\n  USDT: 1.0002 USD (+0.02%) ok\n  USDC: 0.9999 USD (-0.01%) ok\n  DAI: 0.9921 USD (-0.79%) OFF PEG"
+    );
+
+    let output = coinwagon(&server, &["peg", "dai", "--threshold", "1", "--json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["threshold_percent"], 1.0);
+    assert_eq!(json["coins"][0]["deviation_percent"], -0.79);
+    assert_eq!(json["coins"][0]["off_peg"], false);
+
+    let output = coinwagon(&server, &["peg", "bitcoin"]);
+    assert!(!output.status.success());
+    assert_eq!(stderr(&output), "Error: Invalid input: bitcoin isn't a stablecoin coinwagon knows the peg of");
+}
+
+#[test]
+fn trending_lists_the_hot_coins() {
+    let server = serve(vec![route("/search/trending", 200, "coingecko_trending.json")]);
+    let output = coinwagon(&server, &["trending"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Trending on CoinGecko:\n  1. Pepe (PEPE): 0.0000123 USD, +12.35% 24h, rank #24 [pepe]\n  2. New Launch (NLA): 0.42 USD, -3.50% 24h [new-launch]"
+    );
+}
+
+#[test]
+fn watchlist_is_quoted_in_one_batched_request() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_exchange_prices.json").once()]);
+    let list = temp_file("watchlist.json", "[]");
+    let config = format!("[watchlist]\npath = {:?}\n", list.to_str().unwrap());
+    let run = |args: &[&str]| coinwagon_with_config(&server, &config, args);
+
+    assert_eq!(stdout(&run(&["watchlist", "add", "btc", "ethereum", "solana"])), "Added 3 coins (3 on the watchlist)");
+    assert_eq!(stdout(&run(&["watchlist", "add", "bitcoin"])), "Added 0 coins (3 on the watchlist)");
+    assert_eq!(stdout(&run(&["watchlist", "remove", "sol"])), "Removed 1 coin (2 on the watchlist)");
+    assert_eq!(stdout(&run(&["watchlist", "show"])), "bitcoin\nethereum");
+
+    let output = run(&["current-price", "--watchlist"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "bitcoin: 60000 USD\nethereum: 3000 USD");
+}
+
+#[test]
+fn dca_plan_splits_the_budget_from_one_batched_request() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_exchange_prices.json").once()]);
+    let output = coinwagon(&server, &["dca-plan", "btc=3,ethereum=1", "--budget", "500"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Buys for 500 USD:\n  bitcoin: 375 USD (75.00%) = 0.00625 at 60000 USD (CoinGecko)\n  ethereum: 125 USD (25.00%) = 0.04166667 at 3000 USD (CoinGecko)"
+    );
+
+    let output = coinwagon(&server, &["dca-plan", "bitcoin=60,ethereum", "--budget", "500"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Allocation entries look like bitcoin=60, got 'ethereum'"), "{}", stderr(&output));
+}
+
+#[test]
+fn stale_prices_are_revalidated_with_their_etag() {
+    // The unconditional route answers once; a second plain request would get a 404
+    let server = serve(vec![
+        route("/simple/price", 304, "not_modified.txt").when_header("if-none-match", "\"v1\""),
+        route("/simple/price", 200, "coingecko_simple_price.json").header("ETag", "\"v1\"").once(),
+    ]);
+    let cache = temp_file("cache.json", "");
+    let config = temp_file("config.toml", &format!("[cache]\npersistent = true\nttl_secs = 0\npath = \"{}\"\n", cache.to_str().unwrap()));
+    let run = |args: &[&str]| coinwagon_command(&server, &config, args).output().unwrap();
+
+    let output = run(&["current-price", "btc", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "67234.5 USD");
+
+    let output = run(&["current-price", "btc", "usd", "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "67234.5 USD");
+    assert!(stderr(&output).contains("CoinGecko price unchanged (304), refreshed cache for bitcoin/usd"), "{}", stderr(&output));
+}
+
+#[test]
+fn batched_prices_count_once_towards_the_hit_ratio() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_exchange_prices.json").once()]);
+    let cache = temp_file("cache.json", "");
+    let config = temp_file("config.toml", &format!("[cache]\npersistent = true\npath = \"{}\"\n", cache.to_str().unwrap()));
+    let run = |args: &[&str]| coinwagon_command(&server, &config, args).output().unwrap();
+
+    // Two misses answered by the batch, then two hits
+    for _ in 0..2 {
+        let output = run(&["dca-plan", "btc=3,ethereum=1", "--budget", "500"]);
+        assert!(output.status.success(), "{}", stderr(&output));
+    }
+    let output = run(&["stats"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().next(), Some("Cache: 2 entries, 2 hits, 2 misses, hit ratio 50.0%"));
+}
+
+#[test]
+fn units_convert_exactly_within_a_coin() {
+    let server = serve(vec![]);
+    let output = coinwagon(&server, &["units", "150000", "sats", "btc"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.0015 BTC");
+
+    let output = coinwagon(&server, &["units", "1.5", "ethereum", "wei"]);
+    assert_eq!(stdout(&output), "1500000000000000000 wei");
+
+    let output = coinwagon(&server, &["units", "21", "gwei", "eth"]);
+    assert_eq!(stdout(&output), "0.000000021 ETH");
+
+    let output = coinwagon(&server, &["units", "1", "sat", "gwei"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("sat is a unit of bitcoin and gwei of ethereum"), "{}", stderr(&output));
+}
+
+#[test]
+fn returns_separate_performance_from_deposits() {
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let config = format!("[transactions]\npath = {:?}\n", fixture("transactions_returns.json").to_str().unwrap());
+    let args = ["returns", "--from", "2024-05-27", "--to", "2024-06-01", "--benchmark", "btc"];
+    let output = coinwagon_with_config(&server, &config, &args);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Returns in USD, 2024-05-27 to 2024-06-01 (CoinGecko):\n  \
+         Start value: 1000.00 USD\n  \
+         End value: 1313.00 USD\n  \
+         Net deposits: 9.00 USD\n  \
+         Time-weighted return: 30.00%\n  \
+         Money-weighted return (IRR): 30.32%\n  \
+         Holding bitcoin instead: 30.00%"
+    );
+}
+
+#[test]
+fn gains_match_sales_to_lots_by_method() {
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let config = format!("[transactions]\npath = {:?}\nlot_method = \"lifo\"\n", fixture("transactions_gains.json").to_str().unwrap());
+    let output = coinwagon_with_config(&server, &config, &["gains", "--year", "2024"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Realized gains in USD, LIFO (CoinGecko):\n  \
+         2024-06-01 sold 5 bitcoin (acquired 2024-05-30, held 2 days): proceeds 65.00, cost 40.00, gain 25.00\n  \
+         2024-06-01 sold 3 bitcoin (acquired 2024-05-28, held 3 days): proceeds 39.00, cost 30.00, gain 9.00\n\
+         Total: proceeds 104.00, cost 70.00, gain 34.00 USD"
+    );
+
+    let output = coinwagon_with_config(&server, &config, &["gains", "--method", "fifo"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Realized gains in USD, FIFO (CoinGecko):\n  \
+         2024-06-01 sold 8 bitcoin (acquired 2024-05-25, held 7 days): proceeds 104.00, cost 80.80, gain 23.20\n\
+         Total: proceeds 104.00, cost 80.80, gain 23.20 USD"
+    );
+}
+
+#[test]
+fn export_8949_writes_a_years_sales_as_csv() {
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let config = format!("[transactions]\npath = {:?}\n", fixture("transactions_8949.json").to_str().unwrap());
+    let output = coinwagon_with_config(&server, &config, &["export-8949", "--year", "2024"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "Description,Date Acquired,Date Sold,Proceeds,Cost Basis,Gain or Loss,Term\n\
+         2 BTC,01/10/2023,06/01/2024,135920.00,34020.00,101900.00,long\n\
+         0.5 BTC,05/30/2024,06/01/2024,33980.00,34000.00,-20.00,short"
+    );
+}
+
+#[test]
+fn export_writes_transactions_and_history_as_csv_or_parquet() {
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let config = format!("[transactions]\npath = {:?}\n", fixture("transactions_8949.json").to_str().unwrap());
+    let output = coinwagon_with_config(&server, &config, &["export", "transactions"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let csv = stdout(&output);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 5, "{}", csv);
+    assert_eq!(lines[0], "time,source,id,kind,asset,amount,counter_asset,counter_amount,fee_asset,fee_amount");
+    assert_eq!(lines[2], "2024-05-30T15:00:00Z,coinbase,buy-2,trade,BTC,1,USD,68000,,");
+
+    let output = coinwagon(&server, &["export", "history", "btc", "--days", "8"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let csv = stdout(&output);
+    assert!(csv.starts_with("date,coin,fiat,price,source\n2024-05-24,bitcoin,usd,10,CoinGecko\n"), "{}", csv);
+
+    let path = temp_file("transactions.parquet", "");
+    let args = ["export", "transactions", "--format", "parquet", "--output", path.to_str().unwrap()];
+    let output = coinwagon_with_config(&server, &config, &args);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), format!("Wrote 4 rows to {}", path.display()));
+    let parquet = std::fs::read(&path).unwrap();
+    assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+    // The footer's length, then the footer: the schema names every column
+    let footer_len = u32::from_le_bytes(parquet[parquet.len() - 8..parquet.len() - 4].try_into().unwrap()) as usize;
+    let footer = &parquet[parquet.len() - 8 - footer_len..parquet.len() - 8];
+    assert!(footer.windows(14).any(|window| window == b"counter_amount"));
+
+    let output = coinwagon_with_config(&server, &config, &["export", "transactions", "--format", "parquet"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--format parquet needs --output FILE"), "{}", stderr(&output));
+}
+
+#[test]
+fn query_runs_read_only_sql_over_local_data() {
+    let server = serve(vec![]);
+    let log = temp_file(
+        "prices.jsonl",
+        "{\"coin\":\"bitcoin\",\"fiat\":\"usd\",\"price\":60000,\"source\":\"CoinGecko\",\"timestamp\":\"2024-06-01T12:00:00.000Z\"}\n\
+         {\"coin\":\"bitcoin\",\"fiat\":\"usd\",\"price\":62000,\"source\":\"CoinGecko\",\"timestamp\":\"2024-06-02T12:00:00.000Z\"}\n",
+    );
+    let config = format!("[transactions]\npath = {:?}\n", fixture("transactions_8949.json").to_str().unwrap());
+    let run = |args: &[&str]| coinwagon_with_config(&server, &config, args);
+
+    let output = run(&["query", "select asset, count(*) as trades, sum(amount) from transactions where time >= '2024' group by asset order by trades desc"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "asset  trades  sum(amount)\nUSD         2       220000\nBTC         1            1");
+
+    let output = run(&["query", "SELECT id, fee_amount FROM transactions WHERE id LIKE 'sell%' ORDER BY id DESC", "--format", "csv"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "id,fee_amount\nsell-2,\nsell-1,100");
+
+    let query = "SELECT coin, avg(price), max(time) FROM prices GROUP BY coin";
+    let output = run(&["query", query, "--format", "json", "--log-prices", log.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json, serde_json::json!([{ "coin": "bitcoin", "avg(price)": 61000.0, "max(time)": "2024-06-02T12:00:00Z" }]));
+
+    let output = run(&["query", "DELETE FROM transactions"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Only SELECT queries can be run"), "{}", stderr(&output));
+    let output = run(&["query", "SELECT nope FROM transactions"]);
+    assert!(stderr(&output).contains("Query failed: no such column: nope"), "{}", stderr(&output));
+    // Without a price log there's no prices table, and the error says how to get one
+    let output = run(&["query", "SELECT * FROM prices"]);
+    assert!(stderr(&output).contains("No price log to query; set log_prices or pass --log-prices"), "{}", stderr(&output));
+}
+
+#[test]
+fn query_runs_sqlite_sql_with_its_nulls() {
+    let server = serve(vec![]);
+    let snapshots = temp_file(
+        "query_snapshots.jsonl",
+        "{\"time\":\"2024-06-01T12:00:00Z\",\"fiat\":\"usd\",\"total\":70000,\"holdings\":{\"bitcoin\":60000,\"ethereum\":10000}}\n",
+    );
+    let config = format!(
+        "[transactions]\npath = {:?}\n\n[alerts]\nsnapshots = {:?}\n",
+        fixture("transactions_8949.json").to_str().unwrap(),
+        snapshots.to_str().unwrap()
+    );
+    let csv = |sql: &str| {
+        let output = coinwagon_with_config(&server, &config, &["query", sql, "--format", "csv"]);
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+    };
+
+    // Two of the trades have no fee: no comparison holds for them, not even = NULL
+    assert_eq!(csv("SELECT id FROM transactions WHERE fee_amount != 20"), "id\nsell-1");
+    assert_eq!(csv("SELECT id FROM transactions WHERE fee_amount < 1000"), "id\nbuy-1\nsell-1");
+    assert_eq!(csv("SELECT id FROM transactions WHERE fee_amount = NULL"), "id");
+    assert_eq!(csv("SELECT id FROM transactions WHERE fee_amount IS NULL"), "id\nbuy-2\nsell-2");
+    assert_eq!(csv("SELECT count(*), count(fee_amount), avg(fee_amount) FROM transactions"), "count(*),count(fee_amount),avg(fee_amount)\n4,2,60");
+
+    // Nulls sort first, and last when descending
+    assert_eq!(csv("SELECT id, fee_amount FROM transactions ORDER BY fee_amount, id"), "id,fee_amount\nbuy-2,\nsell-2,\nbuy-1,20\nsell-1,100");
+    assert_eq!(csv("SELECT id, fee_amount FROM transactions ORDER BY fee_amount DESC, id"), "id,fee_amount\nsell-1,100\nbuy-1,20\nbuy-2,\nsell-2,");
+    assert_eq!(csv("SELECT fee_asset, min(fee_amount) FROM transactions GROUP BY fee_asset ORDER BY fee_asset DESC"), "fee_asset,min(fee_amount)\nUSD,20\n,");
+
+    // The rest of SQLite's SQL: OR, DISTINCT, HAVING, functions, arithmetic, subqueries and joins
+    assert_eq!(csv("SELECT id FROM transactions WHERE asset = 'BTC' OR id = 'sell-2'"), "id\nbuy-1\nbuy-2\nsell-2");
+    assert_eq!(csv("SELECT DISTINCT lower(asset) AS asset FROM transactions ORDER BY asset"), "asset\nbtc\nusd");
+    assert_eq!(csv("SELECT asset FROM transactions GROUP BY asset HAVING sum(amount) > 100"), "asset\nUSD");
+    assert_eq!(csv("SELECT id, amount * 2 AS twice FROM transactions WHERE amount > (SELECT avg(amount) FROM transactions)"), "id,twice\nsell-1,340000");
+    assert_eq!(
+        csv("SELECT s.coin, s.value / s.total AS share FROM snapshots s JOIN snapshots t ON s.time = t.time AND t.coin = 'bitcoin' ORDER BY share DESC"),
+        "coin,share\nbitcoin,0.8571428571428571\nethereum,0.14285714285714285"
+    );
+    let output = coinwagon(&server, &["query", "--help"]);
+    assert!(stdout(&output).contains("The query is SQLite's SQL"), "{}", stdout(&output));
+}
+
+#[test]
+fn misspelt_coins_and_fiats_get_suggestions() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    let output = coinwagon(&server, &["current-price", "bitcoim", "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("CoinGecko has no usd price for bitcoim; did you mean bitcoin?"), "{}", stderr(&output));
+
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usdd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown fiat 'usdd': no provider quotes it; did you mean usd?"), "{}", stderr(&output));
+}
+
+#[test]
+fn coin_list_resolves_and_checks_coins() {
+    let server = serve(vec![
+        route("/coins/list", 200, "coingecko_coins_list.json").once(),
+        route("/simple/price", 200, "coingecko_shib_price.json"),
+    ]);
+    let list = temp_file("coin-list.json", "");
+    let config = format!("[coin_list]\npath = {:?}\n", list.to_str().unwrap());
+
+    // Downloaded on first use, then served from the saved copy
+    let output = coinwagon_with_config(&server, &config, &["current-price", "shib", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.0000171 USD");
+
+    let output = coinwagon_with_config(&server, &config, &["current-price", "uni", "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("'uni' could be any of uniswap, unicorn-token; give the coin id"), "{}", stderr(&output));
+
+    let output = coinwagon_with_config(&server, &config, &["current-price", "bitcoim", "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown coin 'bitcoim': not an id, symbol or name in the coin list; did you mean bitcoin?"), "{}", stderr(&output));
+
+    let output = coinwagon_with_config(&server, &config, &["coin-list", "bitcoin", "--offline"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "bitcoin (BTC) Bitcoin\nbitcoin-cash (BCH) Bitcoin Cash");
+}
+
+#[test]
+fn risk_measures_a_wallet_from_daily_returns() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+        route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", ADDRESS));
+    let path = wallet.to_str().unwrap();
+    let output = coinwagon_with_config(&server, "[risk]\nrisk_free_rate = 4\n", &["risk", path, "usd", "--days", "8"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.starts_with(&format!("Risk of {} from 8 daily returns, 2024-05-24 to 2024-06-01 (CoinGecko):", path)), "{}", stdout);
+    assert!(
+        stdout.ends_with("  Value: 8404.31 USD\n  Annualized return: +1571.53%\n  Annualized volatility: 316.99%\n  Sharpe ratio: 4.95 (risk-free rate 4.00%)\nContribution to risk:\n  bitcoin: 100.00% of value, 100.00% of risk, volatility 316.99%"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn compare_rebases_two_coins_to_100() {
+    let server = serve(vec![
+        route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json"),
+        route("/coins/ethereum/market_chart", 200, "coingecko_ethereum_market_chart.json"),
+    ]);
+    let output = coinwagon(&server, &["compare", "btc", "ethereum", "--days", "8", "--chart"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(
+        stdout.starts_with("bitcoin vs ethereum in USD over 9 daily closes, 2024-05-24 to 2024-06-01 (CoinGecko):\n  bitcoin: 100 -> 130.00 (+30.00%)\n  ethereum: 100 -> 120.00 (+20.00%)\n  bitcoin/ethereum: 0.1 -> 0.10833333 (+8.33%)\nbitcoin outperformed ethereum by 10.00 points\n\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.ends_with("* bitcoin  o ethereum"), "{}", stdout);
+
+    let output = coinwagon(&server, &["compare", "bitcoin", "btc"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Give two different coins to compare, not bitcoin twice"), "{}", stderr(&output));
+}
+
+#[test]
+fn baskets_are_quoted_and_charted_like_a_single_asset() {
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_bitcoin_ethereum_prices.json"),
+        route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json"),
+        route("/coins/ethereum/market_chart", 200, "coingecko_ethereum_market_chart.json"),
+    ]);
+    let config = "[baskets]\nmajors = { bitcoin = 0.5, eth = 2 }\n";
+    let output = coinwagon_with_config(&server, config, &["basket", "list"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "majors: 0.5 bitcoin + 2 ethereum");
+
+    let output = coinwagon_with_config(&server, config, &["basket", "quote", "majors"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "majors: 36000 USD (CoinGecko)\n  0.5 bitcoin × 60000 = 30000 USD (83.33%)\n  2 ethereum × 3000 = 6000 USD (16.67%)"
+    );
+
+    let output = coinwagon_with_config(&server, config, &["basket", "history", "majors", "--days", "8", "--chart"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(
+        stdout.starts_with("majors in USD over 9 daily closes, 2024-05-24 to 2024-06-01 (CoinGecko):\n  First: 205 USD\n  Last: 246.5 USD (+20.24%)\n  High: 246.5 USD on 2024-06-01\n  Low: 184.5 USD on 2024-05-30\n\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.ends_with("* majors"), "{}", stdout);
+
+    let output = coinwagon_with_config(&server, config, &["basket", "quote", "defi"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("No basket named 'defi'; the config has majors"), "{}", stderr(&output));
+}
+
+#[test]
+fn rebalance_trades_back_to_the_target_once_a_weight_leaves_its_band() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_bitcoin_ethereum_prices.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", ADDRESS));
+    let path = wallet.to_str().unwrap();
+    let output = coinwagon(&server, &["rebalance", path, "usd", "--target", "btc=60,eth=40"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "Rebalance of {}, 7500 USD (band 5 points):\n  bitcoin: 100.00% now, 60.00% target, sell 0.05 BITCOIN (3000 USD)\n  ethereum: 0.00% now, 40.00% target, buy 1 ETHEREUM (3000 USD)",
+            path
+        )
+    );
+
+    let output = coinwagon(&server, &["rebalance", path, "usd", "--target", "btc=60,eth=40", "--band", "50"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).ends_with("  ethereum: 0.00% now, 40.00% target, within band\nEvery coin is within 50 points of its target; nothing to trade"), "{}", stdout(&output));
+}
+
+#[test]
+fn indicators_are_computed_from_daily_closes() {
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let output = coinwagon(&server, &["indicators", "bitcoin", "usd", "--set", "sma3,sma5,rsi3,macd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "bitcoin in USD: 13 on 2024-06-01 (9 daily closes from CoinGecko)\n  SMA3: 10.33333333, price above\n  SMA5: 10.2, price above\n  RSI3: 90.00, overbought\n  MACD: not enough history (9 daily closes)\nSignals:\n  Golden cross: SMA3 crossed above SMA5\n  Price crossed above SMA3\n  Price crossed above SMA5\n  RSI3 turned overbought"
+    );
+
+    let output = coinwagon(&server, &["indicators", "bitcoin", "usd", "--set", "bollinger"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown indicator 'bollinger'"), "{}", stderr(&output));
+}
+
+#[test]
+fn backtest_dca_is_set_against_a_lump_sum() {
+    let server = serve(vec![route("/coins/bitcoin/market_chart", 200, "coingecko_market_chart.json")]);
+    let output = coinwagon(&server, &["backtest-dca", "bitcoin", "usd", "--amount", "10", "--interval", "weekly", "--from", "2024-05-24"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "DCA of 10 USD weekly into bitcoin from 2024-05-24 to 2024-06-01 (CoinGecko):\n  Buys: 2\n  Invested: 20 USD\n  Units: 2.11111111\n  Average cost: 9.47368421 USD\n  Value now: 27.44444444 USD (ROI +37.22%)\nLump sum on 2024-05-24: 2 units, value now 26 USD (ROI +30.00%)\nDCA beat the lump sum by 7.22 points"
+    );
+
+    let output = coinwagon(&server, &["backtest-dca", "bitcoin", "usd", "--from", "24/05/2024"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--from must be a date like 2021-01-01"), "{}", stderr(&output));
+}
+
+#[test]
+fn stats_add_up_provider_requests_across_runs() {
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_simple_price.json").once(),
+        route("/simple/price", 500, "coingecko_error.json"),
+    ]);
+    let cache = temp_file("cache.json", "");
+    let config = temp_file("config.toml", &format!("[cache]\npersistent = true\npath = \"{}\"\n", cache.to_str().unwrap()));
+    let run = |args: &[&str]| coinwagon_command(&server, &config, args).output().unwrap();
+
+    // A price, then a cache hit, then a 500
+    let output = run(&["current-price", "btc", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(run(&["current-price", "btc", "usd"]).status.success());
+    assert!(!run(&["current-price", "eth", "usd"]).status.success());
+
+    let output = run(&["stats"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let text = stdout(&output);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "Cache: 1 entries, 1 hits, 2 misses, hit ratio 33.3%");
+    assert_eq!(lines[2].split_whitespace().collect::<Vec<_>>(), ["PROVIDER", "REQUESTS", "ERRORS", "RATE", "LIMITED", "AVG", "LATENCY"]);
+    // The latency varies; the rest of the line doesn't
+    assert!(lines[3].starts_with("CoinGecko         2       1 (50.0%)      0 (0.0%)"), "{}", text);
+    assert_eq!(lines.len(), 4, "{}", text);
+
+    run(&["cache", "clear"]);
+    assert!(stdout(&run(&["stats"])).ends_with("No provider requests counted yet"));
+}
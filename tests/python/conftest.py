@@ -0,0 +1,81 @@
+# The Python module against canned provider responses: a local HTTP server answering from
+# tests/fixtures, and a config file pointing every provider at it. Build the module into the
+# environment first with `maturin develop`.
+import threading
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from pathlib import Path
+
+import pytest
+
+FIXTURES = Path(__file__).resolve().parent.parent / "fixtures"
+
+# Every provider with an [endpoints] override, so nothing falls through to the real APIs
+PROVIDERS = [
+    "coingecko", "blockcypher", "blockchair", "covalent", "esplora", "frankfurter", "0x",
+    "binance", "kraken", "coinbase", "binance-futures", "bybit", "alternative",
+]
+
+ADDRESS = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"
+
+
+def handler(routes):
+    class Handler(BaseHTTPRequestHandler):
+        def do_GET(self):
+            path = self.path.split("?")[0]
+            if path not in routes:
+                self.send_error(404)
+                return
+            status, fixture, *headers = routes[path]
+            body = (FIXTURES / fixture).read_bytes()
+            self.send_response(status)
+            for name, value in (headers[0] if headers else {}).items():
+                self.send_header(name, value)
+            self.send_header("Content-Type", "application/json")
+            self.send_header("Content-Length", str(len(body)))
+            self.end_headers()
+            self.wfile.write(body)
+
+        def log_message(self, *args):
+            pass
+
+    return Handler
+
+
+def config_file(path, url):
+    endpoints = "".join(f'"{provider}" = "{url}"\n' for provider in PROVIDERS)
+    path.write_text(f"[cache]\npersistent = false\n\n[endpoints]\n{endpoints}")
+    return str(path)
+
+
+@pytest.fixture
+def serve(tmp_path):
+    """Serves `routes` ({path: (status, fixture[, headers])}; anything else gets a 404) and
+    returns a config file pointing the providers at them."""
+    servers = []
+
+    def start(routes):
+        server = ThreadingHTTPServer(("127.0.0.1", 0), handler(routes))
+        threading.Thread(target=server.serve_forever, daemon=True).start()
+        servers.append(server)
+        host, port = server.server_address
+        return config_file(tmp_path / f"config-{len(servers)}.toml", f"http://{host}:{port}")
+
+    yield start
+    for server in servers:
+        server.shutdown()
+        server.server_close()
+
+
+@pytest.fixture
+def unreachable(tmp_path):
+    """A config file pointing the providers at a port nothing listens on."""
+    with ThreadingHTTPServer(("127.0.0.1", 0), BaseHTTPRequestHandler) as server:
+        host, port = server.server_address
+    return config_file(tmp_path / "config.toml", f"http://{host}:{port}")
+
+
+@pytest.fixture
+def wallet(tmp_path):
+    path = tmp_path / "wallet.txt"
+    path.write_text(f"# savings\nbitcoin,{ADDRESS}\n")
+    return str(path)
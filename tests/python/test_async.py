@@ -0,0 +1,101 @@
+# The `_async` variants: awaitables resolving to the same results, and raising the same
+# exceptions, as the blocking calls
+import asyncio
+
+import coinwagon
+import pytest
+
+ADDRESS = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"
+
+
+def run(awaitable):
+    async def main():
+        return await awaitable()
+
+    return asyncio.run(main())
+
+
+def test_price_async(serve):
+    config = serve({"/simple/price": (200, "coingecko_simple_price.json")})
+    price = run(lambda: coinwagon.get_price_async("bitcoin", "usd", config=config))
+    assert (price.crypto, price.price, price.source) == ("bitcoin", 67234.5, "CoinGecko")
+
+
+def test_address_balance_async(serve):
+    config = serve({f"/btc/main/addrs/{ADDRESS}/balance": (200, "blockcypher_balance.json")})
+    balance = run(lambda: coinwagon.get_address_balance_async("bitcoin", ADDRESS, config=config))
+    assert (balance.address, balance.balance, balance.source) == (ADDRESS, 0.125, "BlockCypher")
+
+
+def test_wallet_balance_async(serve, wallet):
+    config = serve({
+        "/simple/price": (200, "coingecko_simple_price.json"),
+        f"/btc/main/addrs/{ADDRESS}/balance": (200, "blockcypher_balance.json"),
+    })
+    result = run(lambda: coinwagon.get_wallet_balance_async(wallet, "usd", config=config))
+    assert len(result) == 1
+    assert result.total == 8404.3125
+
+
+def test_calls_run_concurrently(serve):
+    config = serve({
+        "/simple/price": (200, "coingecko_simple_price.json"),
+        f"/btc/main/addrs/{ADDRESS}/balance": (200, "blockcypher_balance.json"),
+    })
+
+    async def both():
+        return await asyncio.gather(
+            coinwagon.get_price_async("bitcoin", config=config),
+            coinwagon.get_address_balance_async("bitcoin", ADDRESS, config=config),
+        )
+
+    price, balance = run(both)
+    assert (price.price, balance.balance) == (67234.5, 0.125)
+
+
+def test_rate_limited_async(serve):
+    config = serve({"/simple/price": (429, "coingecko_rate_limited.json", {"Retry-After": "3600"})})
+    with pytest.raises(coinwagon.RateLimited) as raised:
+        run(lambda: coinwagon.get_price_async("bitcoin", config=config))
+    assert raised.value.provider == "CoinGecko"
+    assert raised.value.retry_after == 3600.0
+
+
+def test_not_found_async(serve):
+    config = serve({"/simple/price": (200, "coingecko_empty.json")})
+    with pytest.raises(coinwagon.NotFound) as raised:
+        run(lambda: coinwagon.get_price_async("bitcoin", config=config))
+    assert raised.value.provider == "CoinGecko"
+    assert raised.value.retry_after is None
+
+
+def test_network_error_async(unreachable):
+    with pytest.raises(coinwagon.NetworkError) as raised:
+        run(lambda: coinwagon.get_address_balance_async("bitcoin", ADDRESS, config=unreachable))
+    assert raised.value.provider is None
+    assert raised.value.retry_after is None
+
+
+def test_invalid_input_async(serve, tmp_path):
+    config = serve({})
+    with pytest.raises(coinwagon.InvalidInput):
+        run(lambda: coinwagon.get_wallet_balance_async(str(tmp_path / "missing.txt"), config=config))
+
+
+def test_client_async_methods(serve, wallet):
+    config = serve({
+        "/simple/price": (200, "coingecko_simple_price.json"),
+        f"/btc/main/addrs/{ADDRESS}/balance": (200, "blockcypher_balance.json"),
+    })
+    client = coinwagon.CoinWagon(config)
+    assert run(lambda: client.get_price_async("bitcoin")).price == 67234.5
+    assert run(lambda: client.get_address_balance_async("bitcoin", ADDRESS)).balance == 0.125
+    assert run(lambda: client.get_wallet_balance_async(wallet)).total == 8404.3125
+
+
+def test_client_async_errors(serve):
+    config = serve({"/simple/price": (503, "coingecko_error.json")})
+    client = coinwagon.CoinWagon(config)
+    with pytest.raises(coinwagon.ProviderUnavailable) as raised:
+        run(lambda: client.get_price_async("bitcoin"))
+    assert (raised.value.provider, raised.value.retry_after) == ("CoinGecko", None)
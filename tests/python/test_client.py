@@ -0,0 +1,35 @@
+# The long-lived CoinWagon client saves after each call what the module functions do
+import asyncio
+from pathlib import Path
+
+import coinwagon
+
+ADDRESS = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"
+
+
+def recording(config, cassette):
+    """`config` with every provider response recorded to `cassette`."""
+    path = Path(config)
+    path.write_text(f'record = "{cassette}"\n' + path.read_text())
+    return config
+
+
+def test_blocking_calls_write_the_cassette(serve, tmp_path):
+    cassette = tmp_path / "cassette.json"
+    config = recording(serve({"/simple/price": (200, "coingecko_simple_price.json")}), cassette)
+    client = coinwagon.CoinWagon(config)
+    assert client.get_price("bitcoin").price == 67234.5
+    # Written by the call itself, not when the client goes away
+    assert "/simple/price" in cassette.read_text()
+
+
+def test_async_calls_write_the_cassette(serve, tmp_path):
+    cassette = tmp_path / "cassette.json"
+    config = recording(serve({f"/btc/main/addrs/{ADDRESS}/balance": (200, "blockcypher_balance.json")}), cassette)
+    client = coinwagon.CoinWagon(config)
+
+    async def main():
+        return await client.get_address_balance_async("bitcoin", ADDRESS)
+
+    assert asyncio.run(main()).balance == 0.125
+    assert f"/btc/main/addrs/{ADDRESS}/balance" in cassette.read_text()
@@ -0,0 +1,106 @@
+# The exception classes the module raises, and the `provider` and `retry_after` they carry
+import coinwagon
+import pytest
+
+ADDRESS = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"
+
+
+def test_exception_hierarchy():
+    assert issubclass(coinwagon.CoinWagonError, RuntimeError)
+    for error in (coinwagon.ApiError, coinwagon.NetworkError, coinwagon.InvalidInput):
+        assert issubclass(error, coinwagon.CoinWagonError)
+    for error in (coinwagon.RateLimited, coinwagon.NotFound, coinwagon.ProviderUnavailable):
+        assert issubclass(error, coinwagon.ApiError)
+    assert not issubclass(coinwagon.NetworkError, coinwagon.ApiError)
+    assert not issubclass(coinwagon.InvalidInput, coinwagon.ApiError)
+
+
+def test_price_is_returned(serve):
+    config = serve({"/simple/price": (200, "coingecko_simple_price.json")})
+    price = coinwagon.get_price("bitcoin", "usd", config=config)
+    assert (price.crypto, price.fiat, price.price, price.source, price.cached) == ("bitcoin", "usd", 67234.5, "CoinGecko", False)
+
+
+def test_rate_limited_carries_retry_after(serve):
+    config = serve({"/simple/price": (429, "coingecko_rate_limited.json", {"Retry-After": "3600"})})
+    with pytest.raises(coinwagon.RateLimited) as raised:
+        coinwagon.get_price("bitcoin", config=config)
+    assert str(raised.value) == "Rate limited by CoinGecko, retry after 3600s"
+    assert raised.value.provider == "CoinGecko"
+    assert raised.value.retry_after == 3600.0
+
+
+def test_unknown_coin_is_not_found(serve):
+    config = serve({"/simple/price": (200, "coingecko_empty.json")})
+    with pytest.raises(coinwagon.NotFound) as raised:
+        coinwagon.get_price("bitcoin", config=config)
+    assert str(raised.value) == "Not found: CoinGecko has no usd price for bitcoin"
+    assert raised.value.provider == "CoinGecko"
+    assert raised.value.retry_after is None
+
+
+def test_provider_error_payload_is_an_api_error(serve):
+    config = serve({"/simple/price": (401, "coingecko_error.json")})
+    with pytest.raises(coinwagon.ApiError) as raised:
+        coinwagon.get_price("bitcoin", config=config)
+    assert type(raised.value) is coinwagon.ApiError
+    assert "API error from CoinGecko: API Key Missing" in str(raised.value)
+    assert raised.value.provider == "CoinGecko"
+    assert raised.value.retry_after is None
+
+
+def test_server_error_is_provider_unavailable(serve):
+    config = serve({"/simple/price": (503, "coingecko_error.json")})
+    with pytest.raises(coinwagon.ProviderUnavailable) as raised:
+        coinwagon.get_price("bitcoin", config=config)
+    assert str(raised.value).startswith("CoinGecko is unavailable: HTTP 503 Service Unavailable")
+    assert raised.value.provider == "CoinGecko"
+    assert raised.value.retry_after is None
+
+
+def test_refused_connection_is_a_network_error(unreachable):
+    with pytest.raises(coinwagon.NetworkError) as raised:
+        coinwagon.get_price("bitcoin", config=unreachable)
+    assert str(raised.value).startswith("HTTP request failed:")
+    assert raised.value.provider is None
+    assert raised.value.retry_after is None
+
+
+def test_missing_wallet_file_is_invalid_input(serve, tmp_path):
+    config = serve({})
+    with pytest.raises(coinwagon.InvalidInput) as raised:
+        coinwagon.get_wallet_balance(str(tmp_path / "missing.txt"), config=config)
+    assert str(raised.value).startswith("Invalid input: Failed to read wallet file:")
+    assert raised.value.provider is None
+    assert raised.value.retry_after is None
+
+
+def test_offline_without_a_cached_price_is_invalid_input(serve):
+    config = serve({})
+    with pytest.raises(coinwagon.InvalidInput, match="No cached price for bitcoin/usd available in offline mode"):
+        coinwagon.get_price("bitcoin", offline=True, config=config)
+
+
+def test_code_catching_runtime_error_still_catches_them(serve):
+    config = serve({"/simple/price": (200, "coingecko_empty.json")})
+    with pytest.raises(RuntimeError):
+        coinwagon.get_price("bitcoin", config=config)
+
+
+def test_client_methods_raise_the_same_classes(serve):
+    config = serve({
+        "/simple/price": (429, "coingecko_rate_limited.json", {"Retry-After": "3600"}),
+        f"/btc/main/addrs/{ADDRESS}/balance": (200, "blockcypher_balance.json"),
+    })
+    client = coinwagon.CoinWagon(config)
+    assert client.get_address_balance("bitcoin", ADDRESS).balance == 0.125
+    with pytest.raises(coinwagon.RateLimited) as raised:
+        client.get_price("bitcoin")
+    assert (raised.value.provider, raised.value.retry_after) == ("CoinGecko", 3600.0)
+
+
+def test_bad_arguments_raise_value_error():
+    with pytest.raises(ValueError, match="Invalid arguments: .*unexpected argument '--nope'"):
+        coinwagon.run_command("current-price", ["bitcoin", "--nope"])
+    with pytest.raises(ValueError, match="Invalid arguments:"):
+        coinwagon.query("no-such-command", [])
@@ -0,0 +1,87 @@
+// [quotas]: provider requests counted per day and month across runs, against each plan's cap
+mod common;
+
+use common::{coinwagon_command, route, serve, stderr, stdout, temp_file};
+
+const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+#[test]
+fn provider_at_its_daily_quota_is_skipped_for_the_fallback() {
+    let server = serve(vec![
+        route(format!("/btc/main/addrs/{}/balance", ADDRESS), 200, "blockcypher_balance.json"),
+        route(format!("/bitcoin/dashboards/address/{}", ADDRESS), 200, "blockchair_dashboard.json"),
+    ]);
+    // A directory of its own, for the quota.json kept next to the cache file
+    let state = temp_file("state", "");
+    std::fs::remove_file(&state).unwrap();
+    std::fs::create_dir(&state).unwrap();
+    let cache = state.join("cache.json");
+    let config = format!(
+        "[cache]\npersistent = true\nttl_secs = 0\npath = \"{}\"\n\n[quotas.blockcypher]\ndaily = 2\nmonthly = 1000\nwarn_at = 50\n",
+        cache.to_str().unwrap()
+    );
+    let config = temp_file("config.toml", &config);
+    let run = |args: &[&str]| coinwagon_command(&server, &config, args).output().unwrap();
+    let balance = ["address-balance", "bitcoin", ADDRESS, "--verbose"];
+
+    let output = run(&balance);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Warning: BlockCypher has used 1 of its 2 requests today"), "{}", stderr(&output));
+    assert!(run(&balance).status.success());
+
+    // The third run's request would go over, so Blockchair answers it
+    let output = run(&balance);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.125 BITCOIN");
+    assert!(stderr(&output).contains("out of quota (2 of 2 requests today)"), "{}", stderr(&output));
+
+    let output = run(&["status"]);
+    let text = stdout(&output);
+    let line = text.lines().find(|line| line.starts_with("BlockCypher")).unwrap_or_default();
+    assert!(line.contains("2/2 today, 2/1000 this month"), "{}", text);
+    assert!(text.lines().next().unwrap().contains("QUOTA"), "{}", text);
+}
+
+#[test]
+fn runs_at_the_same_time_all_count() {
+    use std::process::Stdio;
+
+    let server = serve(vec![route(format!("/btc/main/addrs/{}/balance", ADDRESS), 200, "blockcypher_balance.json")]);
+    let state = temp_file("state", "");
+    std::fs::remove_file(&state).unwrap();
+    std::fs::create_dir(&state).unwrap();
+    let config = format!(
+        "[cache]\npersistent = true\nttl_secs = 0\npath = \"{}\"\n\n[quotas.blockcypher]\ndaily = 1000\n",
+        state.join("cache.json").to_str().unwrap()
+    );
+    let config = temp_file("config.toml", &config);
+
+    // Each saves its one request while the others may be saving theirs
+    let runs: Vec<_> = (0..8)
+        .map(|_| {
+            coinwagon_command(&server, &config, &["address-balance", "bitcoin", ADDRESS])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for run in runs {
+        let output = run.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+    }
+
+    let output = coinwagon_command(&server, &config, &["status"]).output().unwrap();
+    let text = stdout(&output);
+    let line = text.lines().find(|line| line.starts_with("BlockCypher")).unwrap_or_default();
+    assert!(line.contains("8/1000 today"), "{}", text);
+}
+
+#[test]
+fn quota_percentages_are_checked() {
+    let server = serve(vec![]);
+    let config = temp_file("config.toml", "[cache]\npersistent = false\n\n[quotas.coingecko]\ndaily = 100\nswitch_at = 120\n");
+    let output = coinwagon_command(&server, &config, &["current-price", "btc", "usd"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("quotas.coingecko.switch_at must be a percentage above 0, up to 100"), "{}", stderr(&output));
+}
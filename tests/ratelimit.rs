@@ -0,0 +1,54 @@
+// Waiting out and retrying HTTP 429s, and staying under known allowances
+mod common;
+
+use common::{coinwagon, coinwagon_with_config, route, serve, stderr, stdout, temp_file};
+use std::time::{Duration, Instant};
+
+#[test]
+fn short_retry_after_is_waited_out() {
+    let server = serve(vec![
+        route("/simple/price", 429, "coingecko_rate_limited.json").header("Retry-After", "1").once(),
+        route("/simple/price", 200, "coingecko_simple_price.json"),
+    ]);
+    let started = Instant::now();
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "67234.5 USD");
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[test]
+fn long_retry_after_fails_without_waiting() {
+    let server = serve(vec![route("/simple/price", 429, "coingecko_rate_limited.json").header("Retry-After", "3600")]);
+    let started = Instant::now();
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usd"]);
+    assert!(!output.status.success());
+    assert_eq!(stderr(&output), "Error: Rate limited by CoinGecko, retry after 3600s");
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn gives_up_after_the_configured_retries() {
+    let server = serve(vec![route("/simple/price", 429, "coingecko_rate_limited.json").header("Retry-After", "0")]);
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usd"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("Error: Rate limited by CoinGecko"), "{}", stderr(&output));
+}
+
+#[test]
+fn requests_are_spaced_to_the_allowance() {
+    let addresses = ["bc1qone", "bc1qtwo", "bc1qthree", "bc1qfour"];
+    let mut routes = vec![route("/simple/price", 200, "coingecko_simple_price.json")];
+    routes.extend(addresses.iter().map(|a| route(format!("/btc/main/addrs/{}/balance", a), 200, "blockcypher_balance.json")));
+    let server = serve(routes);
+    let wallet: String = addresses.iter().map(|a| format!("bitcoin,{}\n", a)).collect();
+    let wallet = temp_file("wallet.txt", &wallet);
+    let config = "[api_keys.blockcypher]\nkeys = [\"bcy-token\"]\nrequests_per_second = 4.0\n";
+
+    // The Blockchair batch 404s, so each address goes to BlockCypher, a quarter second apart
+    let started = Instant::now();
+    let output = coinwagon_with_config(&server, config, &["wallet-balance", wallet.to_str().unwrap(), "usd"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).ends_with("Total: 33617.25 USD"), "{}", stdout(&output));
+    assert!(started.elapsed() >= Duration::from_millis(750), "{:?}", started.elapsed());
+}
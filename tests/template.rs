@@ -0,0 +1,77 @@
+// --template: results rendered through a Handlebars template instead of as text
+mod common;
+
+use common::{coinwagon, route, serve, stderr, stdout, temp_file};
+
+const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+#[test]
+fn wallet_report_from_a_template() {
+    let balance = format!("/btc/main/addrs/{}/balance", ADDRESS);
+    let server = serve(vec![
+        route("/simple/price", 200, "coingecko_bitcoin_ethereum_prices.json"),
+        route(balance, 200, "blockcypher_balance.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("bitcoin,{}\n", ADDRESS));
+    let template = temp_file(
+        "report.md",
+        "# Wallet in {{fiat}}\n{{! one row per address }}\n{{#each lines}}{{@index}}. {{crypto}}{{#if name}} @{{name}}{{else}} {{address}}{{/if}}: {{balance}} = {{value}} {{../fiat}} ({{price_quote.source}})\n{{/each}}\nTotal: {{total}}{{#if stale_as_of}} (stale){{/if}}\n",
+    );
+    let output = coinwagon(&server, &["wallet-balance", wallet.to_str().unwrap(), "usd", "--template", template.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    // Block tags and comments alone on their line take the line with them, as Handlebars has it
+    assert_eq!(stdout(&output), format!("# Wallet in usd\n0. bitcoin {}: 0.125 = 7500 usd (CoinGecko)\nTotal: 7500", ADDRESS));
+}
+
+#[test]
+fn price_template_sees_the_text_output_too() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    let template = temp_file("price.txt", "{{crypto}}/{{fiat}} = {{price}} [{{output}}]");
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usd", "--template", template.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "bitcoin/usd = 67234.5 [67234.5 USD]");
+}
+
+#[test]
+fn broken_template_fails_before_any_request() {
+    let server = serve(vec![]);
+    let template = temp_file("broken.txt", "Prices:\n{{#each lines}}{{this}}\n");
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usd", "--template", template.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("line 3, column 1: invalid handlebars syntax"), "{}", stderr(&output));
+}
+
+#[test]
+fn missing_names_render_as_nothing() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    let template = temp_file("missing.txt", "[{{nope}}][{{quote.nope.deeper}}][{{#each nope}}x{{/each}}][{{#if nope}}yes{{else}}no{{/if}}]");
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usd", "--template", template.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "[][][][no]");
+}
+
+#[test]
+fn handlebars_helpers_and_paths_work() {
+    let server = serve(vec![route("/simple/price", 200, "coingecko_simple_price.json")]);
+    let template = temp_file(
+        "helpers.txt",
+        "{{#unless stale_as_of}}fresh{{/unless}} {{#with quote}}{{source}} for {{../crypto}}{{/with}} {{lookup quote \"cached\"}} {{#if nope}}a{{else if price}}b{{/if}} {{{fiat}}}",
+    );
+    let output = coinwagon(&server, &["current-price", "bitcoin", "usd", "--template", template.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "fresh CoinGecko for bitcoin false b usd");
+}
+
+#[test]
+fn mismatched_blocks_are_named_before_any_request() {
+    let server = serve(vec![]);
+    for (source, error) in [
+        ("{{#if price}}a{{/each}}", "line 1, column 15: helper \"if\" was opened, but \"each\" is closing"),
+        ("{{price", "line 1, column 8: invalid handlebars syntax"),
+    ] {
+        let template = temp_file("mismatched.txt", source);
+        let output = coinwagon(&server, &["current-price", "bitcoin", "usd", "--template", template.to_str().unwrap()]);
+        assert!(!output.status.success(), "{}", source);
+        assert!(stderr(&output).contains(error), "{}: {}", source, stderr(&output));
+    }
+}
@@ -0,0 +1,153 @@
+// ERC-20 tokens: balances of tokens found in token lists, every token an address holds, and
+// swap quotes between them
+mod common;
+
+use common::{Route, coinwagon_with_config, fixture, route, serve, stderr, stdout, temp_file};
+
+const ADDRESS: &str = "0x742d35Cc6634C0532925a3b8D4Fddfac9e2C4cb7";
+
+const COVALENT: &str = "[api_keys.covalent]\nkeys = [\"cqt_test_key\"]\n";
+
+const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+fn covalent_route() -> Route {
+    route(format!("/eth-mainnet/address/{}/balances_v2/", ADDRESS), 200, "covalent_balances.json")
+}
+
+fn config(server: &str, list: &str) -> String {
+    format!("[rpc]\nethereum = \"{}/rpc\"\n\n[tokens]\nlists = [\"{}\"]\n", server, list)
+}
+
+#[test]
+fn symbol_resolves_to_the_contract_on_the_chain() {
+    let server = serve(vec![route("/rpc", 200, "rpc_token_balance.json")]);
+    let list = fixture("token_list.json");
+    let output = coinwagon_with_config(
+        &server,
+        &config(&server, list.to_str().unwrap()),
+        &["token-balance", "ethereum", ADDRESS, "--token", "link"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "2.5 LINK");
+}
+
+#[test]
+fn token_list_is_downloaded_from_a_url() {
+    let server = serve(vec![
+        route("/tokenlist.json", 200, "token_list.json").once(),
+        route("/rpc", 200, "rpc_token_balance.json"),
+    ]);
+    let list = format!("{}/tokenlist.json", server);
+    let output = coinwagon_with_config(&server, &config(&server, &list), &["token-balance", "ethereum", ADDRESS, "--token", "LINK"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "2.5 LINK");
+}
+
+#[test]
+fn ambiguous_symbol_asks_for_the_contract() {
+    let server = serve(vec![]);
+    let list = fixture("token_list.json");
+    let output = coinwagon_with_config(
+        &server,
+        &config(&server, list.to_str().unwrap()),
+        &["token-balance", "ethereum", ADDRESS, "--token", "USDC"],
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("USDC is ambiguous on ethereum"), "{}", stderr(&output));
+    assert!(stderr(&output).contains("pass the contract address instead"), "{}", stderr(&output));
+}
+
+#[test]
+fn dry_run_shows_the_balance_of_call() {
+    let server = serve(vec![]);
+    let list = fixture("token_list.json");
+    let output = coinwagon_with_config(
+        &server,
+        &config(&server, list.to_str().unwrap()),
+        &["token-balance", "ethereum", ADDRESS, "--token", "LINK", "--dry-run"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("LINK balance of ethereum address"), "{}", stdout);
+    assert!(stdout.contains("0x514910771AF9Ca656af840dff83E8264EcF986CA"), "{}", stdout);
+    assert!(stdout.contains("\"method\":\"eth_call\""), "{}", stdout);
+}
+
+#[test]
+fn tokens_lists_and_values_every_holding() {
+    let server = serve(vec![covalent_route(), route("/simple/token_price/ethereum", 200, "coingecko_token_price.json")]);
+    let output = coinwagon_with_config(&server, COVALENT, &["tokens", ADDRESS, "--chain", "ethereum"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "USDC: 2500 USDC = 2500 USD\nTotal: 2500 USD");
+}
+
+#[test]
+fn wallet_total_counts_tokens_when_asked() {
+    let server = serve(vec![
+        covalent_route(),
+        route("/simple/token_price/ethereum", 200, "coingecko_token_price.json"),
+        route("/simple/price", 200, "coingecko_ethereum_price.json"),
+    ]);
+    let wallet = temp_file("wallet.txt", &format!("ethereum,{}\n", ADDRESS));
+    let output = coinwagon_with_config(&server, COVALENT, &["wallet-balance", wallet.to_str().unwrap(), "usd", "--tokens"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("ETHEREUM: 1.5 ETHEREUM = 4500 USD"), "{}", stdout);
+    assert!(stdout.contains("USDC: 2500 USDC = 2500 USD"), "{}", stdout);
+    assert!(stdout.ends_with("Total: 7000 USD"), "{}", stdout);
+}
+
+#[test]
+fn unpriced_tokens_are_left_out_unless_asked_for() {
+    let server = serve(vec![covalent_route(), route("/simple/token_price/ethereum", 200, "coingecko_token_price.json")]);
+    let output = coinwagon_with_config(&server, COVALENT, &["tokens", ADDRESS]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!stdout(&output).contains("VISIT-ETH.COM"), "{}", stdout(&output));
+    assert!(stderr(&output).contains("Left out 1 token of"), "{}", stderr(&output));
+
+    let output = coinwagon_with_config(&server, COVALENT, &["tokens", ADDRESS, "--include-unverified"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("VISIT-ETH.COM: 1000000 VISIT-ETH.COM = 0 USD"), "{}", stdout(&output));
+}
+
+#[test]
+fn deny_list_and_market_cap_floor_filter_priced_tokens() {
+    let server = serve(vec![covalent_route(), route("/simple/token_price/ethereum", 200, "coingecko_token_price.json")]);
+    let deny = temp_file("deny.txt", "# known scams\n0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48\n");
+    let config = format!("{}\n[tokens]\ndeny_lists = [\"{}\"]\n", COVALENT, deny.display());
+    let output = coinwagon_with_config(&server, &config, &["tokens", ADDRESS, "--verbose"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Total: 0 USD");
+    assert!(stderr(&output).contains("Left out USDC (0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48): on a deny list"), "{}", stderr(&output));
+
+    let config = format!("{}\n[tokens]\nmin_market_cap = 50000000000\n", COVALENT);
+    let output = coinwagon_with_config(&server, &config, &["tokens", ADDRESS, "--verbose"]);
+    assert!(stderr(&output).contains("market cap under 50000000000"), "{}", stderr(&output));
+}
+
+#[test]
+fn swap_quote_shows_output_price_impact_and_gas() {
+    let server = serve(vec![
+        route("/swap/permit2/price", 200, "zerox_price.json").require_header("0x-api-key", "zerox-test-key"),
+        route("/simple/price", 200, "coingecko_ethereum_price.json"),
+        route("/simple/token_price/ethereum", 200, "coingecko_token_price.json"),
+    ]);
+    let list = fixture("token_list.json");
+    let config = format!("[api_keys.0x]\nkeys = [\"zerox-test-key\"]\n\n[tokens]\nlists = [\"{}\"]\n", list.display());
+    let output = coinwagon_with_config(&server, &config, &["quote-swap", "1", "ETH", USDC]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "1 ETH -> 2994 USDC (quoted by 0x)\nRate: 2994 USDC per ETH\nPrice impact: 0.20% against market prices\nGas: 150000 units, about 0.0015 ETHEREUM"
+    );
+}
+
+#[test]
+fn swap_quote_needs_a_0x_key() {
+    let server = serve(vec![]);
+    let list = fixture("token_list.json");
+    let config = format!("[tokens]\nlists = [\"{}\"]\n", list.display());
+    let output = coinwagon_with_config(&server, &config, &["quote-swap", "1", "ETH", "LINK"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("No swap provider quotes on ethereum; add a 0x API key"), "{}", stderr(&output));
+}